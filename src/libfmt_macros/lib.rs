@@ -185,6 +185,12 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Returns the byte offset in the input of the next character to be
+    /// parsed, e.g. where the next piece starts.
+    pub fn offset(&self) -> usize {
+        self.cur.clone().next().map_or(self.input.len(), |(pos, _)| pos)
+    }
+
     /// Notifies of an error. The message doesn't actually need to be of type
     /// String, but I think it does when this eventually uses conditions so it
     /// might as well start using it now.
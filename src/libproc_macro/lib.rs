@@ -676,6 +676,7 @@ impl TokenTree {
             Underscore => op!('_'),
 
             Ident(ident) | Lifetime(ident) => TokenNode::Term(Term(ident.name)),
+            Placeholder(name) => TokenNode::Term(Term(name)),
             Literal(..) | DocComment(..) => TokenNode::Literal(self::Literal(token)),
 
             Interpolated(_) => {
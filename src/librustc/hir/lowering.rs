@@ -1646,6 +1646,9 @@ impl<'a> LoweringContext<'a> {
                         add_bounds.get(&ty_param.id).map_or(&[][..], |x| &x)
                     ))
                 }
+                GenericParam::Const(ref const_param) => {
+                    self.sess.span_fatal(const_param.span, "const generics are not supported")
+                }
             })
             .collect()
     }
@@ -2020,6 +2023,8 @@ impl<'a> LoweringContext<'a> {
                                     self.lower_bounds(bounds, ImplTraitContext::Disallowed))
             }
             ItemKind::MacroDef(..) | ItemKind::Mac(..) => panic!("Shouldn't still be around"),
+            ItemKind::Extension(..) => panic!("parser extension items cannot be lowered"),
+            ItemKind::Err => panic!("erroneous items are not lowered"),
         }
 
         // [1] `defaultness.has_value()` is never called for an `impl`, always `true` in order to
@@ -2073,7 +2078,7 @@ impl<'a> LoweringContext<'a> {
                 }, ParamMode::Explicit, true));
                 hir::ItemUse(path, hir::UseKind::Glob)
             }
-            UseTreeKind::Nested(ref trees) => {
+            UseTreeKind::Nested(ref trees, _) => {
                 let prefix = Path {
                     segments: prefix.segments
                         .iter()
@@ -2304,7 +2309,7 @@ impl<'a> LoweringContext<'a> {
                 self.lower_item_id_use_tree(use_tree, &mut vec);
                 return vec;
             }
-            ItemKind::MacroDef(..) => return SmallVector::new(),
+            ItemKind::MacroDef(..) | ItemKind::Err => return SmallVector::new(),
             _ => {}
         }
         SmallVector::one(hir::ItemId { id: i.id })
@@ -2312,7 +2317,7 @@ impl<'a> LoweringContext<'a> {
 
     fn lower_item_id_use_tree(&self, tree: &UseTree, vec: &mut SmallVector<hir::ItemId>) {
         match tree.kind {
-            UseTreeKind::Nested(ref nested_vec) => {
+            UseTreeKind::Nested(ref nested_vec, _) => {
                 for &(ref nested, id) in nested_vec {
                     vec.push(hir::ItemId { id, });
                     self.lower_item_id_use_tree(nested, vec);
@@ -2342,6 +2347,10 @@ impl<'a> LoweringContext<'a> {
             }
             return None;
         }
+        if let ItemKind::Err = i.node {
+            self.sess.delay_span_bug(i.span, "erroneous item without an error");
+            return None;
+        }
 
         let node = self.with_parent_def(i.id, |this| {
             this.lower_item_kind(i.id, &mut name, &attrs, &mut vis, &i.node)
@@ -2389,6 +2398,7 @@ impl<'a> LoweringContext<'a> {
                     ForeignItemKind::Ty => {
                         hir::ForeignItemType
                     }
+                    ForeignItemKind::Macro(..) => panic!("Shouldn't exist any more"),
                 },
                 vis: this.lower_visibility(&i.vis, None),
                 span: i.span,
@@ -2544,6 +2554,10 @@ impl<'a> LoweringContext<'a> {
                                 after.iter().map(|x| self.lower_pat(x)).collect())
                 }
                 PatKind::Mac(_) => panic!("Shouldn't exist here"),
+                PatKind::Err => {
+                    self.sess.delay_span_bug(p.span, "erroneous pattern without an error");
+                    hir::PatKind::Wild
+                }
             },
             span: p.span,
         })
@@ -2678,10 +2692,10 @@ impl<'a> LoweringContext<'a> {
                 let count = self.lower_body(None, |this| this.lower_expr(count));
                 hir::ExprRepeat(expr, count)
             }
-            ExprKind::Tup(ref elts) => {
+            ExprKind::Tup(ref elts, _) => {
                 hir::ExprTup(elts.iter().map(|x| self.lower_expr(x)).collect())
             }
-            ExprKind::Call(ref f, ref args) => {
+            ExprKind::Call(ref f, ref args, _) => {
                 let f = P(self.lower_expr(f));
                 hir::ExprCall(f, args.iter().map(|x| self.lower_expr(x)).collect())
             }
@@ -2773,7 +2787,8 @@ impl<'a> LoweringContext<'a> {
                                arms.iter().map(|x| self.lower_arm(x)).collect(),
                                hir::MatchSource::Normal)
             }
-            ExprKind::Closure(capture_clause, movability, ref decl, ref body, fn_decl_span) => {
+            ExprKind::Closure(capture_clause, movability, ref decl, ref body, spans) => {
+                let fn_decl_span = spans.decl;
                 self.with_new_scopes(|this| {
                     this.with_parent_def(e.id, |this| {
                         let mut is_generator = false;
@@ -2927,7 +2942,7 @@ impl<'a> LoweringContext<'a> {
                     asm.inputs.iter().map(|&(_, ref input)| self.lower_expr(input)).collect();
                 hir::ExprInlineAsm(P(hir_asm), outputs, inputs)
             }
-            ExprKind::Struct(ref path, ref fields, ref maybe_expr) => {
+            ExprKind::Struct(ref path, ref fields, ref maybe_expr, _) => {
                 hir::ExprStruct(self.lower_qpath(e.id, &None, path, ParamMode::Optional,
                                                  ImplTraitContext::Disallowed),
                                 fields.iter().map(|x| self.lower_field(x)).collect(),
@@ -3293,6 +3308,14 @@ impl<'a> LoweringContext<'a> {
             }
 
             ExprKind::Mac(_) => panic!("Shouldn't exist here"),
+            ExprKind::Unparsed(_) => span_bug!(e.span, "unparsed function body"),
+
+            // The parse error has been reported, lower to `()` so that the
+            // passes after lowering can still run.
+            ExprKind::Err => {
+                self.sess.delay_span_bug(e.span, "erroneous expression without an error");
+                hir::ExprTup(hir_vec![])
+            }
         };
 
         let LoweredNodeId { node_id, hir_id } = self.lower_node_id(e.id);
@@ -117,7 +117,8 @@ impl<'a> visit::Visitor<'a> for DefCollector<'a> {
                 DefPathData::ValueNs(i.ident.name.as_str()),
             ItemKind::MacroDef(..) => DefPathData::MacroDef(i.ident.name.as_str()),
             ItemKind::Mac(..) => return self.visit_macro_invoc(i.id, false),
-            ItemKind::GlobalAsm(..) => DefPathData::Misc,
+            ItemKind::GlobalAsm(..) | ItemKind::Extension(..) | ItemKind::Err =>
+                DefPathData::Misc,
             ItemKind::Use(..) => {
                 return visit::walk_item(self, i);
             }
@@ -198,6 +199,13 @@ impl<'a> visit::Visitor<'a> for DefCollector<'a> {
                     REGULAR_SPACE
                 );
             }
+            GenericParam::Const(ref const_param) => {
+                self.create_def(
+                    const_param.id,
+                    DefPathData::ValueNs(const_param.ident.name.as_str()),
+                    REGULAR_SPACE
+                );
+            }
         }
 
         visit::walk_generic_param(self, param);
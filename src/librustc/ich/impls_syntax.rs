@@ -143,16 +143,28 @@ impl_stable_hash_for!(enum ::syntax::ast::LitIntType {
     Unsuffixed
 });
 
+impl_stable_hash_for!(enum ::syntax::parse::token::Lit {
+    Byte(value),
+    Char(value),
+    Integer(value),
+    Float(value),
+    Str_(value),
+    StrRaw(value, n),
+    ByteStr(value),
+    ByteStrRaw(value, n)
+});
+
 impl_stable_hash_for_spanned!(::syntax::ast::LitKind);
 impl_stable_hash_for!(enum ::syntax::ast::LitKind {
     Str(value, style),
     ByteStr(value),
     Byte(value),
     Char(value),
-    Int(value, lit_int_type),
-    Float(value, float_ty),
-    FloatUnsuffixed(value),
-    Bool(value)
+    Int(value, lit_int_type, text),
+    Float(value, float_ty, text),
+    FloatUnsuffixed(value, text),
+    Bool(value),
+    UnknownSuffix(lit, suffix)
 });
 
 impl_stable_hash_for!(enum ::syntax::ast::IntTy { Isize, I8, I16, I32, I64, I128 });
@@ -324,6 +336,7 @@ fn hash_token<'gcx, W: StableHasherResult>(token: &token::Token,
         }
 
         token::Token::DocComment(val) |
+        token::Token::Placeholder(val) |
         token::Token::Shebang(val) => val.hash_stable(hcx, hasher),
     }
 }
@@ -401,6 +414,12 @@ impl<'gcx> HashStable<StableHashingContext<'gcx>> for FileMap {
             ref lines,
             ref multibyte_chars,
             ref non_narrow_chars,
+            // Only used while parsing.
+            syntax_variant: _,
+            frontmatter: _,
+            // Not encoded, so not available for imported files.
+            crlf_lines: _,
+            canonical_path: _,
         } = *self;
 
         (name_hash as u64).hash_stable(hcx, hasher);
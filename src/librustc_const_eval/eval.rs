@@ -113,28 +113,28 @@ fn eval_const_expr_partial<'a, 'tcx>(cx: &ConstContext<'a, 'tcx>,
             const I64_OVERFLOW: u128 = i64::min_value() as u64 as u128;
             const I128_OVERFLOW: u128 = i128::min_value() as u128;
             let negated = match (&lit.node, &ty.sty) {
-                (&LitKind::Int(I8_OVERFLOW, _), &ty::TyInt(IntTy::I8)) |
-                (&LitKind::Int(I8_OVERFLOW, Signed(IntTy::I8)), _) => {
+                (&LitKind::Int(I8_OVERFLOW, _, _), &ty::TyInt(IntTy::I8)) |
+                (&LitKind::Int(I8_OVERFLOW, Signed(IntTy::I8), _), _) => {
                     Some(I8(i8::min_value()))
                 },
-                (&LitKind::Int(I16_OVERFLOW, _), &ty::TyInt(IntTy::I16)) |
-                (&LitKind::Int(I16_OVERFLOW, Signed(IntTy::I16)), _) => {
+                (&LitKind::Int(I16_OVERFLOW, _, _), &ty::TyInt(IntTy::I16)) |
+                (&LitKind::Int(I16_OVERFLOW, Signed(IntTy::I16), _), _) => {
                     Some(I16(i16::min_value()))
                 },
-                (&LitKind::Int(I32_OVERFLOW, _), &ty::TyInt(IntTy::I32)) |
-                (&LitKind::Int(I32_OVERFLOW, Signed(IntTy::I32)), _) => {
+                (&LitKind::Int(I32_OVERFLOW, _, _), &ty::TyInt(IntTy::I32)) |
+                (&LitKind::Int(I32_OVERFLOW, Signed(IntTy::I32), _), _) => {
                     Some(I32(i32::min_value()))
                 },
-                (&LitKind::Int(I64_OVERFLOW, _), &ty::TyInt(IntTy::I64)) |
-                (&LitKind::Int(I64_OVERFLOW, Signed(IntTy::I64)), _) => {
+                (&LitKind::Int(I64_OVERFLOW, _, _), &ty::TyInt(IntTy::I64)) |
+                (&LitKind::Int(I64_OVERFLOW, Signed(IntTy::I64), _), _) => {
                     Some(I64(i64::min_value()))
                 },
-                (&LitKind::Int(I128_OVERFLOW, _), &ty::TyInt(IntTy::I128)) |
-                (&LitKind::Int(I128_OVERFLOW, Signed(IntTy::I128)), _) => {
+                (&LitKind::Int(I128_OVERFLOW, _, _), &ty::TyInt(IntTy::I128)) |
+                (&LitKind::Int(I128_OVERFLOW, Signed(IntTy::I128), _), _) => {
                     Some(I128(i128::min_value()))
                 },
-                (&LitKind::Int(n, _), &ty::TyInt(IntTy::Isize)) |
-                (&LitKind::Int(n, Signed(IntTy::Isize)), _) => {
+                (&LitKind::Int(n, _, _), &ty::TyInt(IntTy::Isize)) |
+                (&LitKind::Int(n, Signed(IntTy::Isize), _), _) => {
                     match tcx.sess.target.isize_ty {
                         IntTy::I16 => if n == I16_OVERFLOW {
                             Some(Isize(Is16(i16::min_value())))
@@ -597,7 +597,7 @@ fn lit_to_const<'a, 'tcx>(lit: &'tcx ast::LitKind,
         LitKind::Str(ref s, _) => Ok(Str(s.as_str())),
         LitKind::ByteStr(ref data) => Ok(ByteStr(ByteArray { data })),
         LitKind::Byte(n) => Ok(Integral(U8(n))),
-        LitKind::Int(n, hint) => {
+        LitKind::Int(n, hint, _) => {
             match (&ty.sty, hint) {
                 (&ty::TyInt(ity), _) |
                 (_, Signed(ity)) => {
@@ -612,10 +612,10 @@ fn lit_to_const<'a, 'tcx>(lit: &'tcx ast::LitKind,
                 _ => bug!()
             }
         }
-        LitKind::Float(n, fty) => {
+        LitKind::Float(n, fty, _) => {
             parse_float(&n.as_str(), fty).map(Float)
         }
-        LitKind::FloatUnsuffixed(n) => {
+        LitKind::FloatUnsuffixed(n, _) => {
             let fty = match ty.sty {
                 ty::TyFloat(fty) => fty,
                 _ => bug!()
@@ -624,6 +624,8 @@ fn lit_to_const<'a, 'tcx>(lit: &'tcx ast::LitKind,
         }
         LitKind::Bool(b) => Ok(Bool(b)),
         LitKind::Char(c) => Ok(Char(c)),
+        // Already reported by AST validation.
+        LitKind::UnknownSuffix(..) => Err(TypeckError),
     }
 }
 
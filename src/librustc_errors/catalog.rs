@@ -0,0 +1,58 @@
+// Copyright 2018 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Translatable diagnostic messages.
+//!
+//! A `Message` pairs a stable ID with its English template, in which the
+//! arguments are written as `{name}` and literal braces as `{{` and `}}`.
+//! Embedders can present translated messages by giving the `Handler` a
+//! `MessageLookup` with `Handler::set_message_lookup`.
+
+use std::rc::Rc;
+
+/// A diagnostic message which can be translated.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Message {
+    /// The ID of the message in message catalogs, e.g. `parse-expected-one-of`.
+    pub id: &'static str,
+    /// The English template of the message.
+    pub template: &'static str,
+}
+
+/// Returns the template for a message ID in the language of the embedder, or
+/// `None` to use the English template.
+pub type MessageLookup = Rc<Fn(&str) -> Option<String>>;
+
+/// Substitutes `args` into `template`. References to unknown arguments are
+/// kept as written.
+pub fn format_message(template: &str, args: &[(&str, &str)]) -> String {
+    let mut formatted = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(i) = rest.find(|c: char| c == '{' || c == '}') {
+        formatted.push_str(&rest[..i]);
+        let tail = &rest[i..];
+        if tail.starts_with("{{") || tail.starts_with("}}") {
+            formatted.push_str(&tail[..1]);
+            rest = &tail[2..];
+        } else if let (true, Some(end)) = (tail.starts_with('{'), tail.find('}')) {
+            let name = &tail[1..end];
+            match args.iter().find(|&&(arg, _)| arg == name) {
+                Some(&(_, value)) => formatted.push_str(value),
+                None => formatted.push_str(&tail[..end + 1]),
+            }
+            rest = &tail[end + 1..];
+        } else {
+            formatted.push_str(&tail[..1]);
+            rest = &tail[1..];
+        }
+    }
+    formatted.push_str(rest);
+    formatted
+}
@@ -8,6 +8,7 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use Applicability;
 use CodeSuggestion;
 use SubstitutionPart;
 use Substitution;
@@ -25,6 +26,22 @@ pub struct Diagnostic {
     pub span: MultiSpan,
     pub children: Vec<SubDiagnostic>,
     pub suggestions: Vec<CodeSuggestion>,
+    /// Machine-readable details of an "expected ..., found ..." syntax error,
+    /// for tools which phrase the error themselves or offer completions.
+    pub expected_found: Option<ExpectedFound>,
+}
+
+/// What the parser expected and found when raising a syntax error. Tokens are
+/// described by their kinds, like `Semi`, `OpenDelim(Brace)`, `Ident` or
+/// `Keyword(fn)`, rather than by their text.
+#[derive(Clone, Debug, PartialEq, Hash, RustcEncodable, RustcDecodable)]
+pub struct ExpectedFound {
+    /// The kinds of the tokens which would have been accepted, sorted.
+    pub expected: Vec<String>,
+    /// The kind of the token found instead.
+    pub found: String,
+    /// The construct being parsed, like "`match` expression", if known.
+    pub production: Option<String>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash, RustcEncodable, RustcDecodable)]
@@ -95,6 +112,7 @@ impl Diagnostic {
             span: MultiSpan::new(),
             children: vec![],
             suggestions: vec![],
+            expected_found: None,
         }
     }
 
@@ -222,6 +240,7 @@ impl Diagnostic {
             }],
             msg: msg.to_owned(),
             show_code_when_inline: false,
+            applicability: Applicability::Unspecified,
         });
         self
     }
@@ -252,6 +271,7 @@ impl Diagnostic {
             }],
             msg: msg.to_owned(),
             show_code_when_inline: true,
+            applicability: Applicability::Unspecified,
         });
         self
     }
@@ -267,6 +287,63 @@ impl Diagnostic {
             }).collect(),
             msg: msg.to_owned(),
             show_code_when_inline: true,
+            applicability: Applicability::Unspecified,
+        });
+        self
+    }
+
+    /// Like `span_suggestion`, also telling tools whether the suggestion can
+    /// be applied without a human looking at it, see `Applicability`.
+    pub fn span_suggestion_with_applicability(&mut self, sp: Span, msg: &str,
+                                              suggestion: String,
+                                              applicability: Applicability) -> &mut Self {
+        self.suggestions.push(CodeSuggestion {
+            substitutions: vec![Substitution {
+                parts: vec![SubstitutionPart {
+                    snippet: suggestion,
+                    span: sp,
+                }],
+            }],
+            msg: msg.to_owned(),
+            show_code_when_inline: true,
+            applicability,
+        });
+        self
+    }
+
+    /// Like `span_suggestions`, with the applicability of the suggestions.
+    pub fn span_suggestions_with_applicability(&mut self, sp: Span, msg: &str,
+                                               suggestions: Vec<String>,
+                                               applicability: Applicability) -> &mut Self {
+        self.suggestions.push(CodeSuggestion {
+            substitutions: suggestions.into_iter().map(|snippet| Substitution {
+                parts: vec![SubstitutionPart {
+                    snippet,
+                    span: sp,
+                }],
+            }).collect(),
+            msg: msg.to_owned(),
+            show_code_when_inline: true,
+            applicability,
+        });
+        self
+    }
+
+    /// Like `span_suggestion_short`, with the applicability of the suggestion.
+    pub fn span_suggestion_short_with_applicability(&mut self, sp: Span, msg: &str,
+                                                    suggestion: String,
+                                                    applicability: Applicability)
+                                                    -> &mut Self {
+        self.suggestions.push(CodeSuggestion {
+            substitutions: vec![Substitution {
+                parts: vec![SubstitutionPart {
+                    snippet: suggestion,
+                    span: sp,
+                }],
+            }],
+            msg: msg.to_owned(),
+            show_code_when_inline: false,
+            applicability,
         });
         self
     }
@@ -281,6 +358,11 @@ impl Diagnostic {
         self
     }
 
+    pub fn set_expected_found(&mut self, expected_found: ExpectedFound) -> &mut Self {
+        self.expected_found = Some(expected_found);
+        self
+    }
+
     pub fn get_code(&self) -> Option<DiagnosticId> {
         self.code.clone()
     }
@@ -8,9 +8,11 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use Applicability;
 use Diagnostic;
 use DiagnosticId;
 use DiagnosticStyledString;
+use ExpectedFound;
 
 use Level;
 use Handler;
@@ -130,7 +132,7 @@ impl<'a> DiagnosticBuilder<'a> {
     /// locally in whichever way makes the most sense.
     pub fn delay_as_bug(&mut self) {
         self.level = Level::Bug;
-        *self.handler.delayed_span_bug.borrow_mut() = Some(self.diagnostic.clone());
+        self.handler.delayed_span_bugs.borrow_mut().push(self.diagnostic.clone());
         self.cancel();
     }
 
@@ -186,8 +188,27 @@ impl<'a> DiagnosticBuilder<'a> {
                                      msg: &str,
                                      suggestions: Vec<String>)
                                      -> &mut Self);
+    forward!(pub fn span_suggestion_with_applicability(&mut self,
+                                                       sp: Span,
+                                                       msg: &str,
+                                                       suggestion: String,
+                                                       applicability: Applicability)
+                                                       -> &mut Self);
+    forward!(pub fn span_suggestions_with_applicability(&mut self,
+                                                        sp: Span,
+                                                        msg: &str,
+                                                        suggestions: Vec<String>,
+                                                        applicability: Applicability)
+                                                        -> &mut Self);
+    forward!(pub fn span_suggestion_short_with_applicability(&mut self,
+                                                             sp: Span,
+                                                             msg: &str,
+                                                             suggestion: String,
+                                                             applicability: Applicability)
+                                                             -> &mut Self);
     forward!(pub fn set_span<S: Into<MultiSpan>>(&mut self, sp: S) -> &mut Self);
     forward!(pub fn code(&mut self, s: DiagnosticId) -> &mut Self);
+    forward!(pub fn set_expected_found(&mut self, expected_found: ExpectedFound) -> &mut Self);
 
     /// Convenience function for internal use, clients should use one of the
     /// struct_* methods on Handler.
@@ -10,7 +10,7 @@
 
 use self::Destination::*;
 
-use syntax_pos::{DUMMY_SP, FileMap, Span, MultiSpan};
+use syntax_pos::{DUMMY_SP, BytePos, FileMap, Loc, NonNarrowChar, Span, MultiSpan};
 
 use {Level, CodeSuggestion, DiagnosticBuilder, SubDiagnostic, CodeMapper, DiagnosticId};
 use snippet::{Annotation, AnnotationType, Line, MultilineAnnotation, StyledString, Style};
@@ -102,10 +102,15 @@ impl ColorConfig {
     }
 }
 
+/// The number of columns a tab takes up in `Loc::col_display`.
+pub const DEFAULT_TAB_WIDTH: usize = 4;
+
 pub struct EmitterWriter {
     dst: Destination,
     cm: Option<Rc<CodeMapper>>,
     short_message: bool,
+    tab_width: usize,
+    replace_tabs: bool,
 }
 
 struct FileWithAnnotatedLines {
@@ -125,12 +130,16 @@ impl EmitterWriter {
                 dst,
                 cm: code_map,
                 short_message: short_message,
+                tab_width: DEFAULT_TAB_WIDTH,
+                replace_tabs: false,
             }
         } else {
             EmitterWriter {
                 dst: Raw(Box::new(io::stderr())),
                 cm: code_map,
                 short_message: short_message,
+                tab_width: DEFAULT_TAB_WIDTH,
+                replace_tabs: false,
             }
         }
     }
@@ -143,6 +152,43 @@ impl EmitterWriter {
             dst: Raw(dst),
             cm: code_map,
             short_message: short_message,
+            tab_width: DEFAULT_TAB_WIDTH,
+            replace_tabs: false,
+        }
+    }
+
+    /// Makes tabs in source snippets take up `tab_width` columns when placing
+    /// underlines. If `replace_tabs` is set, tabs are also replaced with that
+    /// many spaces in the rendered snippets, so that the underlines line up
+    /// with the source however the terminal displays tabs.
+    pub fn with_tab_width(mut self, tab_width: usize, replace_tabs: bool) -> EmitterWriter {
+        self.tab_width = tab_width;
+        self.replace_tabs = replace_tabs;
+        self
+    }
+
+    /// Returns the column at which `loc`, the location of `pos`, is displayed,
+    /// with tabs taking up `tab_width` columns rather than `DEFAULT_TAB_WIDTH`.
+    fn display_col(&self, loc: &Loc, pos: BytePos) -> usize {
+        if self.tab_width == DEFAULT_TAB_WIDTH || loc.line == 0 {
+            return loc.col_display;
+        }
+        let line_start = loc.file.lines.borrow()[loc.line - 1];
+        let tabs = loc.file.non_narrow_chars_between(line_start, pos).iter().filter(|c| {
+            match **c {
+                NonNarrowChar::Tab(_) => true,
+                _ => false,
+            }
+        }).count();
+        loc.col_display - tabs * DEFAULT_TAB_WIDTH + tabs * self.tab_width
+    }
+
+    /// Returns `source` as it is rendered in a snippet.
+    fn render_tabs<'a>(&self, source: &'a str) -> Cow<'a, str> {
+        if self.replace_tabs && source.contains('\t') {
+            Cow::Owned(source.replace('\t', &" ".repeat(self.tab_width)))
+        } else {
+            Cow::Borrowed(source)
         }
     }
 
@@ -191,8 +237,10 @@ impl EmitterWriter {
                     continue;
                 }
 
-                let lo = cm.lookup_char_pos(span_label.span.lo());
+                let mut lo = cm.lookup_char_pos(span_label.span.lo());
                 let mut hi = cm.lookup_char_pos(span_label.span.hi());
+                lo.col_display = self.display_col(&lo, span_label.span.lo());
+                hi.col_display = self.display_col(&hi, span_label.span.hi());
 
                 // Watch out for "empty spans". If we get a span like 6..6, we
                 // want to just display a `^` at 6, so convert that to
@@ -292,7 +340,7 @@ impl EmitterWriter {
         let line_offset = buffer.num_lines();
 
         // First create the source line we will highlight.
-        buffer.puts(line_offset, code_offset, &source_string, Style::Quotation);
+        buffer.puts(line_offset, code_offset, &self.render_tabs(&source_string), Style::Quotation);
         buffer.puts(line_offset,
                     0,
                     &(line.line_index.to_string()),
@@ -1175,7 +1223,7 @@ impl EmitterWriter {
                                 Style::LineNumber);
                     // print the suggestion
                     draw_col_separator(&mut buffer, row_num, max_line_num_len + 1);
-                    buffer.append(row_num, line, Style::NoStyle);
+                    buffer.append(row_num, &self.render_tabs(line), Style::NoStyle);
                     line_pos += 1;
                     row_num += 1;
                 }
@@ -1188,8 +1236,9 @@ impl EmitterWriter {
                     let sub_len = parts[0].snippet.trim().chars().fold(0, |acc, ch| {
                         acc + unicode_width::UnicodeWidthChar::width(ch).unwrap_or(0)
                     });
-                    let underline_start = span_start_pos.col_display + start;
-                    let underline_end = span_start_pos.col_display + start + sub_len;
+                    let start_col = self.display_col(&span_start_pos, parts[0].span.lo());
+                    let underline_start = start_col + start;
+                    let underline_end = start_col + start + sub_len;
                     for p in underline_start..underline_end {
                         buffer.putc(row_num,
                                     max_line_num_len + 3 + p,
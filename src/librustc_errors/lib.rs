@@ -33,6 +33,7 @@ pub use emitter::ColorConfig;
 
 use self::Level::*;
 
+use catalog::{Message, MessageLookup};
 use emitter::{Emitter, EmitterWriter};
 
 use rustc_data_structures::fx::FxHashSet;
@@ -45,8 +46,10 @@ use std::rc::Rc;
 use std::{error, fmt};
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering::SeqCst;
+use std::thread::panicking;
 use std::panic;
 
+pub mod catalog;
 mod diagnostic;
 mod diagnostic_builder;
 pub mod emitter;
@@ -83,6 +86,29 @@ pub struct CodeSuggestion {
     pub substitutions: Vec<Substitution>,
     pub msg: String,
     pub show_code_when_inline: bool,
+    /// Whether or not the suggestion is approximate
+    ///
+    /// Sometimes we may show suggestions with placeholders,
+    /// which are useful for users but not useful for
+    /// tools like rustfix
+    pub applicability: Applicability,
+}
+
+/// How safe it is for tools to apply a suggestion without a human looking at
+/// it first.
+#[derive(Copy, Clone, Debug, PartialEq, Hash, RustcEncodable, RustcDecodable)]
+pub enum Applicability {
+    /// The suggestion is definitely what the user intended. It can be applied
+    /// automatically.
+    MachineApplicable,
+    /// The suggestion contains placeholders like `(...)`, which the user has
+    /// to fill in.
+    HasPlaceholders,
+    /// The suggestion may be what the user intended, but that is uncertain.
+    /// It should compile if applied, but may not do what the user wants.
+    MaybeIncorrect,
+    /// The applicability of the suggestion is not known.
+    Unspecified,
 }
 
 #[derive(Clone, Debug, PartialEq, Hash, RustcEncodable, RustcDecodable)]
@@ -245,6 +271,7 @@ impl error::Error for ExplicitBug {
 }
 
 pub use diagnostic::{Diagnostic, SubDiagnostic, DiagnosticStyledString, DiagnosticId};
+pub use diagnostic::ExpectedFound;
 pub use diagnostic_builder::DiagnosticBuilder;
 
 /// A handler deals with errors; certain errors
@@ -256,7 +283,10 @@ pub struct Handler {
     err_count: AtomicUsize,
     emitter: RefCell<Box<Emitter>>,
     continue_after_error: Cell<bool>,
-    delayed_span_bug: RefCell<Option<Diagnostic>>,
+    error_limit: Cell<Option<usize>>,
+    error_limit_reached: Cell<bool>,
+    // Bugs which are only reported if no error is emitted, see `delay_span_bug`.
+    delayed_span_bugs: RefCell<Vec<Diagnostic>>,
     tracked_diagnostics: RefCell<Option<Vec<Diagnostic>>>,
 
     // This set contains the `DiagnosticId` of all emitted diagnostics to avoid
@@ -268,6 +298,8 @@ pub struct Handler {
     // this handler. These hashes is used to avoid emitting the same error
     // twice.
     emitted_diagnostics: RefCell<FxHashSet<u128>>,
+
+    message_lookup: RefCell<Option<MessageLookup>>,
 }
 
 #[derive(Default)]
@@ -275,6 +307,11 @@ pub struct HandlerFlags {
     pub can_emit_warnings: bool,
     pub treat_err_as_bug: bool,
     pub external_macro_backtrace: bool,
+    /// The number of columns tabs take up in source snippets, if not
+    /// `emitter::DEFAULT_TAB_WIDTH`, and whether they are replaced with spaces.
+    /// Only used by the emitter created by `with_tty_emitter_and_flags`.
+    pub tab_width: Option<usize>,
+    pub replace_tabs: bool,
 }
 
 impl Handler {
@@ -297,8 +334,10 @@ impl Handler {
                                       cm: Option<Rc<CodeMapper>>,
                                       flags: HandlerFlags)
                                       -> Handler {
-        let emitter = Box::new(EmitterWriter::stderr(color_config, cm, false));
-        Handler::with_emitter_and_flags(emitter, flags)
+        let tab_width = flags.tab_width.unwrap_or(emitter::DEFAULT_TAB_WIDTH);
+        let emitter = EmitterWriter::stderr(color_config, cm, false)
+            .with_tab_width(tab_width, flags.replace_tabs);
+        Handler::with_emitter_and_flags(Box::new(emitter), flags)
     }
 
     pub fn with_emitter(can_emit_warnings: bool,
@@ -320,10 +359,13 @@ impl Handler {
             err_count: AtomicUsize::new(0),
             emitter: RefCell::new(e),
             continue_after_error: Cell::new(true),
-            delayed_span_bug: RefCell::new(None),
+            error_limit: Cell::new(None),
+            error_limit_reached: Cell::new(false),
+            delayed_span_bugs: RefCell::new(Vec::new()),
             tracked_diagnostics: RefCell::new(None),
             tracked_diagnostic_codes: RefCell::new(FxHashSet()),
             emitted_diagnostics: RefCell::new(FxHashSet()),
+            message_lookup: RefCell::new(None),
         }
     }
 
@@ -331,6 +373,49 @@ impl Handler {
         self.continue_after_error.set(continue_after_error);
     }
 
+    /// Sets the maximum number of errors this handler will report. Once `limit`
+    /// errors have been emitted a summary is reported and a `FatalError` is
+    /// raised, rather than printing a flood of cascading diagnostics (e.g. when
+    /// a non-Rust file is fed to the parser).
+    pub fn set_error_limit(&self, limit: usize) {
+        self.error_limit.set(Some(limit));
+    }
+
+    pub fn clear_error_limit(&self) {
+        self.error_limit.set(None);
+    }
+
+    pub fn error_limit(&self) -> Option<usize> {
+        self.error_limit.get()
+    }
+
+    /// `true` if the error limit was hit. No further diagnostics are emitted
+    /// once this happens.
+    pub fn error_limit_reached(&self) -> bool {
+        self.error_limit_reached.get()
+    }
+
+    /// Sets the lookup for translated diagnostic messages, see the `catalog`
+    /// module.
+    pub fn set_message_lookup(&self, lookup: MessageLookup) {
+        *self.message_lookup.borrow_mut() = Some(lookup);
+    }
+
+    pub fn clear_message_lookup(&self) {
+        *self.message_lookup.borrow_mut() = None;
+    }
+
+    /// Returns the text of `message` with `args` substituted, translated if the
+    /// message lookup has a template for it.
+    pub fn message(&self, message: &Message, args: &[(&str, &str)]) -> String {
+        let translated = match *self.message_lookup.borrow() {
+            Some(ref lookup) => lookup(message.id),
+            None => None,
+        };
+        let template = translated.as_ref().map_or(message.template, |template| &template[..]);
+        catalog::format_message(template, args)
+    }
+
     /// Resets the diagnostic error count as well as the cached emitted diagnostics.
     ///
     /// NOTE: DO NOT call this function from rustc. It is only meant to be called from external
@@ -339,6 +424,9 @@ impl Handler {
     pub fn reset_err_count(&self) {
         self.emitted_diagnostics.replace(FxHashSet());
         self.err_count.store(0, SeqCst);
+        self.error_limit_reached.set(false);
+        // The delayed bugs were waiting for the errors which are forgotten.
+        self.delayed_span_bugs.borrow_mut().clear();
     }
 
     pub fn struct_dummy<'a>(&'a self) -> DiagnosticBuilder<'a> {
@@ -475,13 +563,47 @@ impl Handler {
         self.emit(&sp.into(), msg, Bug);
         panic!(ExplicitBug);
     }
+    /// Records an internal inconsistency which is only reported as a bug if no
+    /// error is emitted, e.g. because error recovery left the input in an
+    /// unexpected state. The bugs are reported by `abort_if_errors` and
+    /// `finish`, or when the handler is dropped.
     pub fn delay_span_bug<S: Into<MultiSpan>>(&self, sp: S, msg: &str) {
         if self.flags.treat_err_as_bug {
             self.span_bug(sp, msg);
         }
         let mut diagnostic = Diagnostic::new(Level::Bug, msg);
         diagnostic.set_span(sp.into());
-        *self.delayed_span_bug.borrow_mut() = Some(diagnostic);
+        self.delayed_span_bugs.borrow_mut().push(diagnostic);
+    }
+
+    pub fn has_delayed_bugs(&self) -> bool {
+        !self.delayed_span_bugs.borrow().is_empty()
+    }
+
+    /// Reports the delayed bugs if no error was emitted, and panics with
+    /// `ExplicitBug` if there were any. Tools which never call
+    /// `abort_if_errors` should call this once they are done with the handler.
+    pub fn finish(&self) {
+        if self.report_delayed_bugs() {
+            panic!(ExplicitBug);
+        }
+    }
+
+    /// Reports the delayed bugs if no error was emitted. Returns whether any
+    /// were reported. The bugs are not counted as errors, so that reporting
+    /// them never raises.
+    fn report_delayed_bugs(&self) -> bool {
+        if self.err_count() > 0 {
+            return false;
+        }
+        let bugs = mem::replace(&mut *self.delayed_span_bugs.borrow_mut(), Vec::new());
+        let reported = !bugs.is_empty();
+        for bug in bugs {
+            let mut db = DiagnosticBuilder::new_diagnostic(self, bug);
+            self.emitter.borrow_mut().emit(&db);
+            db.cancel();
+        }
+        reported
     }
     pub fn span_bug_no_panic<S: Into<MultiSpan>>(&self, sp: S, msg: &str) {
         self.emit(&sp.into(), msg, Bug);
@@ -537,6 +659,22 @@ impl Handler {
         self.err_count.fetch_add(1, SeqCst);
     }
 
+    fn abort_if_error_limit_reached(&self) {
+        let limit = match self.error_limit.get() {
+            Some(limit) => limit,
+            None => return,
+        };
+        if self.err_count() >= limit && !self.error_limit_reached.get() {
+            let mut db = DiagnosticBuilder::new(self, Fatal,
+                                                &format!("aborting after {} errors", limit));
+            db.note("the error limit was reached; later errors were not reported");
+            self.emitter.borrow_mut().emit(&db);
+            db.cancel();
+            self.error_limit_reached.set(true);
+            FatalError.raise();
+        }
+    }
+
     pub fn err_count(&self) -> usize {
         self.err_count.load(SeqCst)
     }
@@ -548,9 +686,7 @@ impl Handler {
         let s;
         match self.err_count() {
             0 => {
-                if let Some(bug) = self.delayed_span_bug.borrow_mut().take() {
-                    DiagnosticBuilder::new_diagnostic(self, bug).emit();
-                }
+                self.report_delayed_bugs();
                 return;
             }
             1 => s = "aborting due to previous error".to_string(),
@@ -604,6 +740,10 @@ impl Handler {
     }
 
     fn emit_db(&self, db: &DiagnosticBuilder) {
+        if self.error_limit_reached.get() {
+            return;
+        }
+
         let diagnostic = &**db;
 
         if let Some(ref mut list) = *self.tracked_diagnostics.borrow_mut() {
@@ -627,11 +767,23 @@ impl Handler {
             self.emitter.borrow_mut().emit(db);
             if db.is_error() {
                 self.bump_err_count();
+                self.abort_if_error_limit_reached();
             }
         }
     }
 }
 
+impl Drop for Handler {
+    fn drop(&mut self) {
+        // Tools which never call `abort_if_errors` or `finish` still learn
+        // about the bugs. Panicking here could abort the process, so they are
+        // only emitted.
+        if !panicking() {
+            self.report_delayed_bugs();
+        }
+    }
+}
+
 
 #[derive(Copy, PartialEq, Clone, Hash, Debug, RustcEncodable, RustcDecodable)]
 pub enum Level {
@@ -728,7 +728,8 @@ impl EarlyLintPass for IllegalFloatLiteralPattern {
             match p.node {
                 // Wildcard patterns and paths are uninteresting for the lint
                 PatKind::Wild |
-                PatKind::Path(..) => (),
+                PatKind::Path(..) |
+                PatKind::Err => (),
 
                 // The walk logic recurses inside these
                 PatKind::Ident(..) |
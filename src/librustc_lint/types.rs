@@ -100,7 +100,7 @@ impl<'a, 'tcx> LateLintPass<'a, 'tcx> for TypeLimits {
 
                     if let Some(bits) = opt_ty_bits {
                         let exceeding = if let hir::ExprLit(ref lit) = r.node {
-                            if let ast::LitKind::Int(shift, _) = lit.node {
+                            if let ast::LitKind::Int(shift, _, _) = lit.node {
                                 shift as u64 >= bits
                             } else {
                                 false
@@ -138,8 +138,8 @@ impl<'a, 'tcx> LateLintPass<'a, 'tcx> for TypeLimits {
                 match cx.tables.node_id_to_type(e.hir_id).sty {
                     ty::TyInt(t) => {
                         match lit.node {
-                            ast::LitKind::Int(v, ast::LitIntType::Signed(_)) |
-                            ast::LitKind::Int(v, ast::LitIntType::Unsuffixed) => {
+                            ast::LitKind::Int(v, ast::LitIntType::Signed(_), _) |
+                            ast::LitKind::Int(v, ast::LitIntType::Unsuffixed, _) => {
                                 let int_type = if let ast::IntTy::Isize = t {
                                     cx.sess().target.isize_ty
                                 } else {
@@ -172,7 +172,7 @@ impl<'a, 'tcx> LateLintPass<'a, 'tcx> for TypeLimits {
                         let lit_val: u128 = match lit.node {
                             // _v is u8, within range by definition
                             ast::LitKind::Byte(_v) => return,
-                            ast::LitKind::Int(v, _) => v,
+                            ast::LitKind::Int(v, _, _) => v,
                             _ => bug!(),
                         };
                         if lit_val < min || lit_val > max {
@@ -183,8 +183,8 @@ impl<'a, 'tcx> LateLintPass<'a, 'tcx> for TypeLimits {
                     }
                     ty::TyFloat(t) => {
                         let is_infinite = match lit.node {
-                            ast::LitKind::Float(v, _) |
-                            ast::LitKind::FloatUnsuffixed(v) => {
+                            ast::LitKind::Float(v, _, _) |
+                            ast::LitKind::FloatUnsuffixed(v, _) => {
                                 match t {
                                     ast::FloatTy::F32 => v.as_str().parse().map(f32::is_infinite),
                                     ast::FloatTy::F64 => v.as_str().parse().map(f64::is_infinite),
@@ -291,8 +291,8 @@ impl<'a, 'tcx> LateLintPass<'a, 'tcx> for TypeLimits {
                     let lit_val: i128 = match lit.node {
                         hir::ExprLit(ref li) => {
                             match li.node {
-                                ast::LitKind::Int(v, ast::LitIntType::Signed(_)) |
-                                ast::LitKind::Int(v, ast::LitIntType::Unsuffixed) => v as i128,
+                                ast::LitKind::Int(v, ast::LitIntType::Signed(_), _) |
+                                ast::LitKind::Int(v, ast::LitIntType::Unsuffixed, _) => v as i128,
                                 _ => return true
                             }
                         },
@@ -305,7 +305,7 @@ impl<'a, 'tcx> LateLintPass<'a, 'tcx> for TypeLimits {
                     let lit_val: u128 = match lit.node {
                         hir::ExprLit(ref li) => {
                             match li.node {
-                                ast::LitKind::Int(v, _) => v,
+                                ast::LitKind::Int(v, _, _) => v,
                                 _ => return true
                             }
                         },
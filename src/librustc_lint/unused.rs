@@ -302,7 +302,7 @@ impl EarlyLintPass for UnusedParens {
             Assign(_, ref value) => (value, "assigned value", false),
             AssignOp(.., ref value) => (value, "assigned value", false),
             InPlace(_, ref value) => (value, "emplacement value", false),
-            Call(_, ref args) => {
+            Call(_, ref args, _) => {
                 for arg in args {
                     self.check_unused_parens_core(cx, arg, "function argument", false)
                 }
@@ -344,7 +344,7 @@ pub struct UnusedImportBraces;
 
 impl UnusedImportBraces {
     fn check_use_tree(&self, cx: &EarlyContext, use_tree: &ast::UseTree, item: &ast::Item) {
-        if let ast::UseTreeKind::Nested(ref items) = use_tree.kind {
+        if let ast::UseTreeKind::Nested(ref items, _) = use_tree.kind {
             // Recursively check nested UseTrees
             for &(ref tree, _) in items {
                 self.check_use_tree(cx, tree, item);
@@ -368,7 +368,7 @@ impl UnusedImportBraces {
                 ast::UseTreeKind::Glob => {
                     node_ident = ast::Ident::from_str("*");
                 }
-                ast::UseTreeKind::Nested(_) => {
+                ast::UseTreeKind::Nested(..) => {
                     return;
                 }
             }
@@ -35,7 +35,7 @@ use std::rc::Rc;
 use syntax::ast;
 use syntax::attr;
 use syntax::ext::base::SyntaxExtension;
-use syntax::parse::filemap_to_stream;
+use syntax::parse::try_filemap_to_stream;
 use syntax::symbol::Symbol;
 use syntax_pos::{Span, NO_EXPANSION, FileName};
 use rustc_data_structures::indexed_set::IdxSetBuf;
@@ -474,7 +474,7 @@ impl CrateStore for cstore::CStore {
 
         let filemap = sess.parse_sess.codemap().new_filemap(source_name, def.body);
         let local_span = Span::new(filemap.start_pos, filemap.end_pos, NO_EXPANSION);
-        let body = filemap_to_stream(&sess.parse_sess, filemap, None);
+        let body = panictry!(try_filemap_to_stream(&sess.parse_sess, filemap, None));
 
         // Mark the attrs as used
         let attrs = data.get_item_attrs(id.index, sess);
@@ -21,7 +21,7 @@ use rustc::session::Session;
 use syntax::ast::*;
 use syntax::attr;
 use syntax::codemap::Spanned;
-use syntax::parse::token;
+use syntax::parse::{self, token};
 use syntax::symbol::keywords;
 use syntax::visit::{self, Visitor};
 use syntax_pos::Span;
@@ -144,6 +144,13 @@ impl<'a> Visitor<'a> for AstValidator<'a> {
             ExprKind::InlineAsm(..) if !self.session.target.target.options.allow_asm => {
                 span_err!(self.session, expr.span, E0472, "asm! is unsupported on this target");
             }
+            ExprKind::Lit(ref lit) => {
+                // Unknown suffixes are only preserved for macros to consume, so
+                // report the error the parser deferred for any that remain.
+                if let LitKind::UnknownSuffix(token_lit, suffix) = lit.node {
+                    parse::lit_token(token_lit, Some(suffix), Some((lit.span, self.err_handler())));
+                }
+            }
             _ => {}
         }
 
@@ -352,7 +359,7 @@ impl<'a> Visitor<'a> for AstValidator<'a> {
                         .span_label(span, "pattern not allowed in foreign function").emit();
                 });
             }
-            ForeignItemKind::Static(..) | ForeignItemKind::Ty => {}
+            ForeignItemKind::Static(..) | ForeignItemKind::Ty | ForeignItemKind::Macro(..) => {}
         }
 
         visit::walk_foreign_item(self, fi)
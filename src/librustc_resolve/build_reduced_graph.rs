@@ -192,7 +192,7 @@ impl<'a> Resolver<'a> {
                     module_path, subclass, use_tree.span, id, vis, expansion,
                 );
             }
-            ast::UseTreeKind::Nested(ref items) => {
+            ast::UseTreeKind::Nested(ref items, _) => {
                 let prefix = ast::Path {
                     segments: module_path.iter()
                         .map(|s| ast::PathSegment {
@@ -286,7 +286,7 @@ impl<'a> Resolver<'a> {
                 self.define(parent, ident, TypeNS, imported_binding);
             }
 
-            ItemKind::GlobalAsm(..) => {}
+            ItemKind::GlobalAsm(..) | ItemKind::Err => {}
 
             ItemKind::Mod(..) if item.ident == keywords::Invalid.ident() => {} // Crate root
 
@@ -417,6 +417,8 @@ impl<'a> Resolver<'a> {
             }
 
             ItemKind::MacroDef(..) | ItemKind::Mac(_) => unreachable!(),
+
+            ItemKind::Extension(..) => panic!("parser extension item in resolve!"),
         }
     }
 
@@ -455,6 +457,7 @@ impl<'a> Resolver<'a> {
             ForeignItemKind::Ty => {
                 (Def::TyForeign(self.definitions.local_def_id(item.id)), TypeNS)
             }
+            ForeignItemKind::Macro(..) => unreachable!(),
         };
         let parent = self.current_module;
         let vis = self.resolve_visibility(&item.vis);
@@ -101,7 +101,7 @@ impl<'a, 'b> Visitor<'a> for UnusedImportCheckVisitor<'a, 'b> {
             self.base_id = id;
         }
 
-        if let ast::UseTreeKind::Nested(ref items) = use_tree.kind {
+        if let ast::UseTreeKind::Nested(ref items, _) = use_tree.kind {
             // If it's the parent group, cover the entire use item
             let span = if nested {
                 use_tree.span
@@ -731,6 +731,7 @@ impl<'a, 'tcx> Visitor<'tcx> for Resolver<'a> {
             }
             ForeignItemKind::Static(..) => NoTypeParameters,
             ForeignItemKind::Ty => NoTypeParameters,
+            ForeignItemKind::Macro(..) => NoTypeParameters,
         };
         self.with_type_parameter_rib(type_parameters, |this| {
             visit::walk_foreign_item(this, foreign_item);
@@ -811,6 +812,7 @@ impl<'a, 'tcx> Visitor<'tcx> for Resolver<'a> {
                     // Allow all following defaults to refer to this type parameter.
                     default_ban_rib.bindings.remove(&Ident::with_empty_ctxt(ty_param.ident.name));
                 }
+                GenericParam::Const(ref const_param) => self.visit_ty(&const_param.ty),
             }
         }
         for p in &generics.where_clause.predicates { self.visit_where_predicate(p); }
@@ -2052,13 +2054,16 @@ impl<'a> Resolver<'a> {
                 // do nothing, these are just around to be encoded
             }
 
+            ItemKind::Err => {}
+
             ItemKind::Mac(_) => panic!("unexpanded macro in resolve!"),
+            ItemKind::Extension(..) => panic!("parser extension item in resolve!"),
         }
     }
 
     fn resolve_use_tree(&mut self, id: NodeId, use_tree: &ast::UseTree, prefix: &Path) {
         match use_tree.kind {
-            ast::UseTreeKind::Nested(ref items) => {
+            ast::UseTreeKind::Nested(ref items, _) => {
                 let path = Path {
                     segments: prefix.segments
                         .iter()
@@ -3539,7 +3544,7 @@ impl<'a> Resolver<'a> {
                     this.visit_expr(count);
                 });
             }
-            ExprKind::Call(ref callee, ref arguments) => {
+            ExprKind::Call(ref callee, ref arguments, _) => {
                 self.resolve_expr(callee, Some(expr));
                 for argument in arguments {
                     self.resolve_expr(argument, None);
@@ -179,6 +179,7 @@ impl<'l, 'tcx: 'l> SaveContext<'l, 'tcx> {
             }
             // FIXME(plietar): needs a new DefKind in rls-data
             ast::ForeignItemKind::Ty => None,
+            ast::ForeignItemKind::Macro(..) => None,
         }
     }
 
@@ -894,6 +895,7 @@ fn make_signature(decl: &ast::FnDecl, generics: &ast::Generics) -> String {
             .map(|param| match *param {
                 ast::GenericParam::Lifetime(ref l) => l.lifetime.ident.name.to_string(),
                 ast::GenericParam::Type(ref t) => t.ident.to_string(),
+                ast::GenericParam::Const(ref c) => c.ident.to_string(),
             })
             .collect::<Vec<_>>()
             .join(", "));
@@ -561,6 +561,8 @@ impl Sig for ast::Item {
             // FIXME should implement this (e.g., pub use).
             ast::ItemKind::Use(_) => Err("import"),
             ast::ItemKind::Mac(..) | ast::ItemKind::MacroDef(_) => Err("Macro"),
+            ast::ItemKind::Extension(_) => Err("extension item"),
+            ast::ItemKind::Err => Err("erroneous item"),
         }
     }
 }
@@ -655,6 +657,19 @@ impl Sig for ast::Generics {
                     text.push_str(&t_text);
                     text.push(',');
                 }
+                ast::GenericParam::Const(ref c) => {
+                    text.push_str("const ");
+                    let c_text = c.ident.to_string();
+                    defs.push(SigElement {
+                        id: id_from_node_id(c.id, scx),
+                        start: offset + text.len(),
+                        end: offset + text.len() + c_text.len(),
+                    });
+                    text.push_str(&c_text);
+                    text.push_str(": ");
+                    text.push_str(&pprust::ty_to_string(&c.ty));
+                    text.push(',');
+                }
             }
         }
 
@@ -822,6 +837,7 @@ impl Sig for ast::ForeignItem {
                     refs: vec![],
                 })
             }
+            ast::ForeignItemKind::Macro(..) => Err("macro"),
         }
     }
 }
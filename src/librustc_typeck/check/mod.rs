@@ -2651,9 +2651,9 @@ impl<'a, 'gcx, 'tcx> FnCtxt<'a, 'gcx, 'tcx> {
             }
             ast::LitKind::Byte(_) => tcx.types.u8,
             ast::LitKind::Char(_) => tcx.types.char,
-            ast::LitKind::Int(_, ast::LitIntType::Signed(t)) => tcx.mk_mach_int(t),
-            ast::LitKind::Int(_, ast::LitIntType::Unsigned(t)) => tcx.mk_mach_uint(t),
-            ast::LitKind::Int(_, ast::LitIntType::Unsuffixed) => {
+            ast::LitKind::Int(_, ast::LitIntType::Signed(t), _) => tcx.mk_mach_int(t),
+            ast::LitKind::Int(_, ast::LitIntType::Unsigned(t), _) => tcx.mk_mach_uint(t),
+            ast::LitKind::Int(_, ast::LitIntType::Unsuffixed, _) => {
                 let opt_ty = expected.to_option(self).and_then(|ty| {
                     match ty.sty {
                         ty::TyInt(_) | ty::TyUint(_) => Some(ty),
@@ -2666,8 +2666,8 @@ impl<'a, 'gcx, 'tcx> FnCtxt<'a, 'gcx, 'tcx> {
                 opt_ty.unwrap_or_else(
                     || tcx.mk_int_var(self.next_int_var_id()))
             }
-            ast::LitKind::Float(_, t) => tcx.mk_mach_float(t),
-            ast::LitKind::FloatUnsuffixed(_) => {
+            ast::LitKind::Float(_, t, _) => tcx.mk_mach_float(t),
+            ast::LitKind::FloatUnsuffixed(..) => {
                 let opt_ty = expected.to_option(self).and_then(|ty| {
                     match ty.sty {
                         ty::TyFloat(_) => Some(ty),
@@ -2677,7 +2677,9 @@ impl<'a, 'gcx, 'tcx> FnCtxt<'a, 'gcx, 'tcx> {
                 opt_ty.unwrap_or_else(
                     || tcx.mk_float_var(self.next_float_var_id()))
             }
-            ast::LitKind::Bool(_) => tcx.types.bool
+            ast::LitKind::Bool(_) => tcx.types.bool,
+            // Already reported by AST validation.
+            ast::LitKind::UnknownSuffix(..) => tcx.types.err,
         }
     }
 
@@ -4046,7 +4048,7 @@ impl<'a, 'gcx, 'tcx> FnCtxt<'a, 'gcx, 'tcx> {
                               // fixed expression:
                               if let hir::ExprLit(ref lit) = idx.node {
                                   if let ast::LitKind::Int(i,
-                                            ast::LitIntType::Unsuffixed) = lit.node {
+                                            ast::LitIntType::Unsuffixed, _) = lit.node {
                                       let snip = tcx.sess.codemap().span_to_snippet(base.span);
                                       if let Ok(snip) = snip {
                                           err.span_suggestion(expr.span,
@@ -351,6 +351,7 @@ impl<'a> Classifier<'a> {
             }
 
             token::Lifetime(..) => Class::Lifetime,
+            token::Placeholder(..) => Class::Ident,
 
             token::Underscore | token::Eof | token::Interpolated(..) |
             token::Tilde | token::At | token::DotEq => Class::None,
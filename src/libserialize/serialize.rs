@@ -519,7 +519,7 @@ impl< T: Decodable> Decodable for Box<[T]> {
     }
 }
 
-impl<T:Encodable> Encodable for Rc<T> {
+impl<T: ?Sized + Encodable> Encodable for Rc<T> {
     #[inline]
     fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
         (**self).encode(s)
@@ -533,6 +533,13 @@ impl<T:Decodable> Decodable for Rc<T> {
     }
 }
 
+impl<T: Decodable> Decodable for Rc<[T]> {
+    fn decode<D: Decoder>(d: &mut D) -> Result<Rc<[T]>, D::Error> {
+        let v: Vec<T> = Decodable::decode(d)?;
+        Ok(Rc::from(v))
+    }
+}
+
 impl<T:Encodable> Encodable for [T] {
     fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
         s.emit_seq(self.len(), |s| {
@@ -21,6 +21,7 @@ use syntax_pos::{Span, DUMMY_SP};
 use codemap::{respan, Spanned};
 use abi::Abi;
 use ext::hygiene::{Mark, SyntaxContext};
+use parse::token;
 use print::pprust;
 use ptr::P;
 use rustc_data_structures::indexed_vec;
@@ -28,8 +29,10 @@ use symbol::{Symbol, keywords};
 use tokenstream::{ThinTokenStream, TokenStream};
 
 use serialize::{self, Encoder, Decoder};
+use std::any::Any;
 use std::collections::HashSet;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::rc::Rc;
 use std::u32;
 
@@ -314,10 +317,22 @@ pub struct TyParam {
     pub span: Span,
 }
 
+/// A const generic parameter, `const N: usize`. These are only parsed when
+/// `ParseSess::const_generics` is set, and are not supported by the compiler.
+#[derive(Clone, PartialEq, Eq, RustcEncodable, RustcDecodable, Hash, Debug)]
+pub struct ConstParam {
+    pub attrs: ThinVec<Attribute>,
+    pub ident: Ident,
+    pub id: NodeId,
+    pub ty: P<Ty>,
+    pub span: Span,
+}
+
 #[derive(Clone, PartialEq, Eq, RustcEncodable, RustcDecodable, Hash, Debug)]
 pub enum GenericParam {
     Lifetime(LifetimeDef),
     Type(TyParam),
+    Const(ConstParam),
 }
 
 impl GenericParam {
@@ -334,6 +349,13 @@ impl GenericParam {
             _ => false,
         }
     }
+
+    pub fn is_const_param(&self) -> bool {
+        match *self {
+            GenericParam::Const(_) => true,
+            _ => false,
+        }
+    }
 }
 
 /// Represents lifetime, type and const parameters attached to a declaration of
@@ -523,6 +545,14 @@ impl fmt::Debug for Pat {
 }
 
 impl Pat {
+    /// Whether this is the placeholder of a pattern which failed to parse.
+    pub fn is_err(&self) -> bool {
+        match self.node {
+            PatKind::Err => true,
+            _ => false,
+        }
+    }
+
     pub(super) fn to_ty(&self) -> Option<P<Ty>> {
         let node = match &self.node {
             PatKind::Wild => TyKind::Infer,
@@ -575,7 +605,8 @@ impl Pat {
             PatKind::Range(..) |
             PatKind::Ident(..) |
             PatKind::Path(..) |
-            PatKind::Mac(_) => {
+            PatKind::Mac(_) |
+            PatKind::Err => {
                 true
             }
         }
@@ -658,6 +689,28 @@ pub enum PatKind {
     Slice(Vec<P<Pat>>, Option<P<Pat>>, Vec<P<Pat>>),
     /// A macro pattern; pre-expansion
     Mac(Mac),
+    /// Placeholder for a pattern which failed to parse.
+    Err,
+}
+
+impl PatKind {
+    pub fn descriptive_variant(&self) -> &'static str {
+        match *self {
+            PatKind::Wild => "wildcard pattern",
+            PatKind::Ident(..) => "binding pattern",
+            PatKind::Struct(..) => "struct pattern",
+            PatKind::TupleStruct(..) => "tuple struct pattern",
+            PatKind::Path(..) => "path pattern",
+            PatKind::Tuple(..) => "tuple pattern",
+            PatKind::Box(..) => "box pattern",
+            PatKind::Ref(..) => "reference pattern",
+            PatKind::Lit(..) => "literal pattern",
+            PatKind::Range(..) => "range pattern",
+            PatKind::Slice(..) => "slice pattern",
+            PatKind::Mac(..) => "macro invocation",
+            PatKind::Err => "erroneous pattern",
+        }
+    }
 }
 
 #[derive(Clone, PartialEq, Eq, RustcEncodable, RustcDecodable, Hash, Debug, Copy)]
@@ -842,6 +895,18 @@ pub enum StmtKind {
     Mac(P<(Mac, MacStmtStyle, ThinVec<Attribute>)>),
 }
 
+impl StmtKind {
+    pub fn descriptive_variant(&self) -> &'static str {
+        match *self {
+            StmtKind::Local(..) => "`let` statement",
+            StmtKind::Item(..) => "item statement",
+            StmtKind::Expr(..) => "expression",
+            StmtKind::Semi(..) => "expression statement",
+            StmtKind::Mac(..) => "macro invocation",
+        }
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, RustcEncodable, RustcDecodable, Hash, Debug)]
 pub enum MacStmtStyle {
     /// The macro statement had a trailing semicolon, e.g. `foo! { ... };`
@@ -884,6 +949,8 @@ pub struct Arm {
     pub guard: Option<P<Expr>>,
     pub body: P<Expr>,
     pub beginning_vert: Option<Span>, // For RFC 1925 feature gate
+    /// The comma after the arm, if any.
+    pub trailing_comma: Option<Span>,
 }
 
 #[derive(Clone, PartialEq, Eq, RustcEncodable, RustcDecodable, Hash, Debug)]
@@ -919,6 +986,14 @@ pub struct Expr {
 }
 
 impl Expr {
+    /// Whether this is the placeholder of an expression which failed to parse.
+    pub fn is_err(&self) -> bool {
+        match self.node {
+            ExprKind::Err => true,
+            _ => false,
+        }
+    }
+
     /// Wether this expression would be valid somewhere that expects a value, for example, an `if`
     /// condition.
     pub fn returns(&self) -> bool {
@@ -963,7 +1038,7 @@ impl Expr {
                 expr.to_ty().map(|ty| TyKind::Array(ty, expr_len.clone()))?,
             ExprKind::Array(exprs) if exprs.len() == 1 =>
                 exprs[0].to_ty().map(TyKind::Slice)?,
-            ExprKind::Tup(exprs) => {
+            ExprKind::Tup(exprs, _) => {
                 let mut tys = Vec::new();
                 for expr in exprs {
                     tys.push(expr.to_ty()?);
@@ -1022,6 +1097,8 @@ impl Expr {
             ExprKind::Paren(..) => ExprPrecedence::Paren,
             ExprKind::Try(..) => ExprPrecedence::Try,
             ExprKind::Yield(..) => ExprPrecedence::Yield,
+            ExprKind::Err => ExprPrecedence::Err,
+            ExprKind::Unparsed(..) => ExprPrecedence::Block,
         }
     }
 }
@@ -1055,7 +1132,9 @@ pub enum ExprKind {
     /// and the second field is the list of arguments.
     /// This also represents calling the constructor of
     /// tuple-like ADTs such as tuple structs and enum variants.
-    Call(P<Expr>, Vec<P<Expr>>),
+    ///
+    /// The optional span is the span of a trailing comma after the arguments.
+    Call(P<Expr>, Vec<P<Expr>>, Option<Span>),
     /// A method call (`x.foo::<'static, Bar, Baz>(a, b, c, d)`)
     ///
     /// The `PathSegment` represents the method name and its generic arguments
@@ -1066,8 +1145,8 @@ pub enum ExprKind {
     /// Thus, `x.foo::<Bar, Baz>(a, b, c, d)` is represented as
     /// `ExprKind::MethodCall(PathSegment { foo, [Bar, Baz] }, [x, a, b, c, d])`.
     MethodCall(PathSegment, Vec<P<Expr>>),
-    /// A tuple (`(a, b, c ,d)`)
-    Tup(Vec<P<Expr>>),
+    /// A tuple (`(a, b, c ,d)`), and the span of its trailing comma, if any
+    Tup(Vec<P<Expr>>, Option<Span>),
     /// A binary operation (For example: `a + b`, `a * b`)
     Binary(BinOp, P<Expr>, P<Expr>),
     /// A unary operation (For example: `!x`, `*x`)
@@ -1110,9 +1189,7 @@ pub enum ExprKind {
     /// A `match` block.
     Match(P<Expr>, Vec<Arm>),
     /// A closure (for example, `move |a, b, c| a + b + c`)
-    ///
-    /// The final span is the span of the argument block `|...|`
-    Closure(CaptureBy, Movability, P<FnDecl>, P<Expr>, Span),
+    Closure(CaptureBy, Movability, P<FnDecl>, P<Expr>, ClosureSpans),
     /// A block (`{ ... }`)
     Block(P<Block>),
     /// A catch block (`catch { ... }`)
@@ -1161,7 +1238,9 @@ pub enum ExprKind {
     ///
     /// For example, `Foo {x: 1, y: 2}`, or
     /// `Foo {x: 1, .. base}`, where `base` is the `Option<Expr>`.
-    Struct(Path, Vec<Field>, Option<P<Expr>>),
+    ///
+    /// The optional span is the span of a trailing comma after the fields.
+    Struct(Path, Vec<Field>, Option<P<Expr>>, Option<Span>),
 
     /// An array literal constructed from one repeated element.
     ///
@@ -1177,6 +1256,61 @@ pub enum ExprKind {
 
     /// A `yield`, with an optional value to be yielded
     Yield(Option<P<Expr>>),
+
+    /// Placeholder for an expression which failed to parse.
+    Err,
+    /// The tokens of a function body which has not been parsed yet, including
+    /// its braces, see `Parser::defer_fn_bodies`. It is the only statement of
+    /// the body's block.
+    Unparsed(ThinTokenStream),
+}
+
+impl ExprKind {
+    pub fn descriptive_variant(&self) -> &'static str {
+        match *self {
+            ExprKind::Box(..) => "box expression",
+            ExprKind::InPlace(..) => "placement expression",
+            ExprKind::Array(..) => "array expression",
+            ExprKind::Call(..) => "function call",
+            ExprKind::MethodCall(..) => "method call",
+            ExprKind::Tup(..) => "tuple expression",
+            ExprKind::Binary(..) => "binary expression",
+            ExprKind::Unary(..) => "unary expression",
+            ExprKind::Lit(..) => "literal",
+            ExprKind::Cast(..) => "cast",
+            ExprKind::Type(..) => "type ascription",
+            ExprKind::If(..) => "`if` expression",
+            ExprKind::IfLet(..) => "`if let` expression",
+            ExprKind::While(..) => "`while` loop",
+            ExprKind::WhileLet(..) => "`while let` loop",
+            ExprKind::ForLoop(..) => "`for` loop",
+            ExprKind::Loop(..) => "`loop`",
+            ExprKind::Match(..) => "`match` expression",
+            ExprKind::Closure(..) => "closure",
+            ExprKind::Block(..) => "block",
+            ExprKind::Catch(..) => "`do catch` block",
+            ExprKind::Assign(..) => "assignment",
+            ExprKind::AssignOp(..) => "compound assignment",
+            ExprKind::Field(..) => "field access",
+            ExprKind::TupField(..) => "tuple field access",
+            ExprKind::Index(..) => "index expression",
+            ExprKind::Range(..) => "range",
+            ExprKind::Path(..) => "path",
+            ExprKind::AddrOf(..) => "borrow",
+            ExprKind::Break(..) => "`break`",
+            ExprKind::Continue(..) => "`continue`",
+            ExprKind::Ret(..) => "`return`",
+            ExprKind::InlineAsm(..) => "inline assembly",
+            ExprKind::Mac(..) => "macro invocation",
+            ExprKind::Struct(..) => "struct literal",
+            ExprKind::Repeat(..) => "repeat expression",
+            ExprKind::Paren(..) => "parenthesized expression",
+            ExprKind::Try(..) => "`?` expression",
+            ExprKind::Yield(..) => "`yield`",
+            ExprKind::Err => "erroneous expression",
+            ExprKind::Unparsed(..) => "unparsed function body",
+        }
+    }
 }
 
 /// The explicit Self type in a "qualified path". The actual
@@ -1213,6 +1347,30 @@ pub enum Movability {
     Movable,
 }
 
+/// The spans of the parts of a closure before its body, e.g. for
+/// `static move |a, b| -> u8 { a + b }`.
+#[derive(Clone, PartialEq, Eq, RustcEncodable, RustcDecodable, Hash, Debug, Copy)]
+pub struct ClosureSpans {
+    /// Everything before the body, `static move |a, b| -> u8`.
+    pub decl: Span,
+    /// The `static` keyword, if any.
+    pub static_kw: Option<Span>,
+    /// The `move` keyword, if any.
+    pub move_kw: Option<Span>,
+    /// The argument list, `|a, b|`.
+    pub args: Span,
+    /// The explicit return type, `-> u8`, if any.
+    pub output: Option<Span>,
+}
+
+impl ClosureSpans {
+    /// The spans of a closure without keywords or an explicit return type,
+    /// whose argument list spans `args`.
+    pub fn from_args(args: Span) -> ClosureSpans {
+        ClosureSpans { decl: args, static_kw: None, move_kw: None, args, output: None }
+    }
+}
+
 pub type Mac = Spanned<Mac_>;
 
 /// Represents a macro invocation. The Path indicates which macro
@@ -1273,19 +1431,27 @@ pub enum LitKind {
     /// A string literal (`"foo"`)
     Str(Symbol, StrStyle),
     /// A byte string (`b"foo"`)
-    ByteStr(Rc<Vec<u8>>),
+    ByteStr(Rc<[u8]>),
     /// A byte char (`b'f'`)
     Byte(u8),
     /// A character literal (`'a'`)
     Char(char),
     /// An integer literal (`1`)
-    Int(u128, LitIntType),
-    /// A float literal (`1f64` or `1E10f64`)
-    Float(Symbol, FloatTy),
+    ///
+    /// Numeric literals parsed from source also keep their text as written,
+    /// without the suffix, like `0x_ff` for `0x_ffu8`. Generated ones do not.
+    Int(u128, LitIntType, Option<Symbol>),
+    /// A float literal (`1f64` or `1E10f64`), without digit separators
+    Float(Symbol, FloatTy, Option<Symbol>),
     /// A float literal without a suffix (`1.0 or 1.0E10`)
-    FloatUnsuffixed(Symbol),
+    FloatUnsuffixed(Symbol, Option<Symbol>),
     /// A boolean literal
     Bool(bool),
+    /// A numeric literal with a suffix the language does not define (`42px`),
+    /// kept as the raw literal token and suffix for macros to consume. These
+    /// are only produced when `ParseSess::preserve_unknown_suffixes` is set,
+    /// and are an error if they remain after expansion.
+    UnknownSuffix(token::Lit, Symbol),
 }
 
 impl LitKind {
@@ -1306,13 +1472,14 @@ impl LitKind {
             LitKind::ByteStr(..) |
             LitKind::Byte(..) |
             LitKind::Char(..) |
-            LitKind::Int(_, LitIntType::Unsuffixed) |
+            LitKind::Int(_, LitIntType::Unsuffixed, _) |
             LitKind::FloatUnsuffixed(..) |
             LitKind::Bool(..) => true,
             // suffixed variants
-            LitKind::Int(_, LitIntType::Signed(..)) |
-            LitKind::Int(_, LitIntType::Unsigned(..)) |
-            LitKind::Float(..) => false,
+            LitKind::Int(_, LitIntType::Signed(..), _) |
+            LitKind::Int(_, LitIntType::Unsigned(..), _) |
+            LitKind::Float(..) |
+            LitKind::UnknownSuffix(..) => false,
         }
     }
 
@@ -1320,6 +1487,36 @@ impl LitKind {
     pub fn is_suffixed(&self) -> bool {
         !self.is_unsuffixed()
     }
+
+    /// Returns the text of a numeric literal as written in the source, without
+    /// its suffix, or `None` if the literal was not parsed from source.
+    pub fn written_text(&self) -> Option<Symbol> {
+        match *self {
+            LitKind::Int(_, _, text) |
+            LitKind::Float(_, _, text) |
+            LitKind::FloatUnsuffixed(_, text) => text,
+            _ => None,
+        }
+    }
+
+    /// Returns the base of an integer literal as written in the source.
+    pub fn written_base(&self) -> Option<u32> {
+        let text = match *self {
+            LitKind::Int(_, _, Some(text)) => text.as_str(),
+            _ => return None,
+        };
+        Some(match text.get(..2) {
+            Some("0x") => 16,
+            Some("0o") => 8,
+            Some("0b") => 2,
+            _ => 10,
+        })
+    }
+
+    /// Returns true if a numeric literal was written with `_` digit separators.
+    pub fn has_digit_separators(&self) -> bool {
+        self.written_text().map_or(false, |text| text.as_str().contains('_'))
+    }
 }
 
 // NB: If you change this, you'll probably want to change the corresponding
@@ -1364,6 +1561,17 @@ pub enum TraitItemKind {
     Macro(Mac),
 }
 
+impl TraitItemKind {
+    pub fn descriptive_variant(&self) -> &'static str {
+        match *self {
+            TraitItemKind::Const(..) => "associated constant",
+            TraitItemKind::Method(..) => "method",
+            TraitItemKind::Type(..) => "associated type",
+            TraitItemKind::Macro(..) => "macro invocation",
+        }
+    }
+}
+
 #[derive(Clone, PartialEq, Eq, RustcEncodable, RustcDecodable, Hash, Debug)]
 pub struct ImplItem {
     pub id: NodeId,
@@ -1386,6 +1594,17 @@ pub enum ImplItemKind {
     Macro(Mac),
 }
 
+impl ImplItemKind {
+    pub fn descriptive_variant(&self) -> &'static str {
+        match *self {
+            ImplItemKind::Const(..) => "associated constant",
+            ImplItemKind::Method(..) => "method",
+            ImplItemKind::Type(..) => "associated type",
+            ImplItemKind::Macro(..) => "macro invocation",
+        }
+    }
+}
+
 #[derive(Clone, PartialEq, Eq, RustcEncodable, RustcDecodable, Hash, Copy,
          PartialOrd, Ord)]
 pub enum IntTy {
@@ -1542,6 +1761,16 @@ pub struct Ty {
     pub span: Span,
 }
 
+impl Ty {
+    /// Whether this is the placeholder of a type which failed to parse.
+    pub fn is_err(&self) -> bool {
+        match self.node {
+            TyKind::Err => true,
+            _ => false,
+        }
+    }
+}
+
 impl fmt::Debug for Ty {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "type({})", pprust::ty_to_string(self))
@@ -1599,6 +1828,29 @@ pub enum TyKind {
     Err,
 }
 
+impl TyKind {
+    pub fn descriptive_variant(&self) -> &'static str {
+        match *self {
+            TyKind::Slice(..) => "slice type",
+            TyKind::Array(..) => "array type",
+            TyKind::Ptr(..) => "raw pointer type",
+            TyKind::Rptr(..) => "reference type",
+            TyKind::BareFn(..) => "function pointer type",
+            TyKind::Never => "never type",
+            TyKind::Tup(..) => "tuple type",
+            TyKind::Path(..) => "path",
+            TyKind::TraitObject(..) => "trait object type",
+            TyKind::ImplTrait(..) => "`impl Trait` type",
+            TyKind::Paren(..) => "parenthesized type",
+            TyKind::Typeof(..) => "`typeof` type",
+            TyKind::Infer => "inferred type",
+            TyKind::ImplicitSelf => "implicit `self` type",
+            TyKind::Mac(..) => "macro invocation",
+            TyKind::Err => "erroneous type",
+        }
+    }
+}
+
 /// Syntax used to declare a trait object.
 #[derive(Clone, Copy, PartialEq, Eq, RustcEncodable, RustcDecodable, Hash, Debug)]
 pub enum TraitObjectSyntax {
@@ -1862,7 +2114,8 @@ pub type Variant = Spanned<Variant_>;
 pub enum UseTreeKind {
     Simple(Ident),
     Glob,
-    Nested(Vec<(UseTree, NodeId)>),
+    /// `{a, b}`, and the span of the trailing comma of the group, if any
+    Nested(Vec<(UseTree, NodeId)>, Option<Span>),
 }
 
 #[derive(Clone, PartialEq, Eq, RustcEncodable, RustcDecodable, Hash, Debug)]
@@ -1881,11 +2134,22 @@ pub enum AttrStyle {
     Inner,
 }
 
+/// The kind of doc comment a sugared doc attribute was written as.
+#[derive(Clone, PartialEq, Eq, RustcEncodable, RustcDecodable, Hash, Debug, Copy)]
+pub enum DocCommentKind {
+    /// `/// ...` or `//! ...`
+    Line,
+    /// `/** ... */` or `/*! ... */`
+    Block,
+}
+
 #[derive(Clone, PartialEq, Eq, RustcEncodable, RustcDecodable, Hash, Debug, Copy)]
 pub struct AttrId(pub usize);
 
 /// Meta-data associated with an item
-/// Doc-comments are promoted to attributes that have is_sugared_doc = true
+/// Doc-comments are promoted to attributes that have is_sugared_doc = true,
+/// and keep the text of the comment as their value, decoration included.
+/// Explicit `#[doc = "..."]` attributes have is_sugared_doc = false.
 #[derive(Clone, PartialEq, Eq, RustcEncodable, RustcDecodable, Hash, Debug)]
 pub struct Attribute {
     pub id: AttrId,
@@ -1946,6 +2210,42 @@ pub enum Visibility {
     Inherited,
 }
 
+impl Visibility {
+    pub fn is_pub(&self) -> bool {
+        *self == Visibility::Public
+    }
+
+    pub fn is_inherited(&self) -> bool {
+        *self == Visibility::Inherited
+    }
+
+    /// Returns whether `self` and `other` are the same visibility, regardless of
+    /// their spans and node IDs, and of whether `crate` or `pub(crate)` was written.
+    pub fn is_equivalent(&self, other: &Visibility) -> bool {
+        match (self, other) {
+            (&Visibility::Public, &Visibility::Public) |
+            (&Visibility::Crate(..), &Visibility::Crate(..)) |
+            (&Visibility::Inherited, &Visibility::Inherited) => true,
+            (&Visibility::Restricted { path: ref a, .. },
+             &Visibility::Restricted { path: ref b, .. }) => {
+                a.segments.len() == b.segments.len() &&
+                a.segments.iter().zip(&b.segments).all(|(a, b)| {
+                    a.identifier.name == b.identifier.name
+                })
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Prints the visibility as written in source, e.g. `pub(crate)`, and nothing
+/// for `Inherited`.
+impl fmt::Display for Visibility {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", pprust::vis_to_string(self).trim_right())
+    }
+}
+
 /// Field of a struct.
 ///
 /// E.g. `bar: usize` as in `struct Foo { bar: usize }`
@@ -2031,6 +2331,16 @@ pub struct Item {
     pub tokens: Option<TokenStream>,
 }
 
+impl Item {
+    /// Whether this is the placeholder of an item which failed to parse.
+    pub fn is_err(&self) -> bool {
+        match self.node {
+            ItemKind::Err => true,
+            _ => false,
+        }
+    }
+}
+
 #[derive(Clone, PartialEq, Eq, RustcEncodable, RustcDecodable, Hash, Debug)]
 pub enum ItemKind {
     /// An `extern crate` item, with optional original crate name.
@@ -2104,10 +2414,17 @@ pub enum ItemKind {
 
     /// A macro definition.
     MacroDef(MacroDef),
+
+    /// An item parsed by an extension registered with
+    /// `ParseSess::register_item_extension`.
+    Extension(ExtensionItem),
+
+    /// Placeholder for an item which failed to parse.
+    Err,
 }
 
 impl ItemKind {
-    pub fn descriptive_variant(&self) -> &str {
+    pub fn descriptive_variant(&self) -> &'static str {
         match *self {
             ItemKind::ExternCrate(..) => "extern crate",
             ItemKind::Use(..) => "use",
@@ -2123,6 +2440,8 @@ impl ItemKind {
             ItemKind::Union(..) => "union",
             ItemKind::Trait(..) => "trait",
             ItemKind::TraitAlias(..) => "trait alias",
+            ItemKind::Extension(..) => "extension item",
+            ItemKind::Err => "erroneous item",
             ItemKind::Mac(..) |
             ItemKind::MacroDef(..) |
             ItemKind::Impl(..) => "item"
@@ -2130,6 +2449,58 @@ impl ItemKind {
     }
 }
 
+/// An item introduced by a contextual keyword registered as a parser
+/// extension, e.g. `state_machine Foo { .. }`.
+#[derive(Clone, PartialEq, Eq, RustcEncodable, RustcDecodable, Hash, Debug)]
+pub struct ExtensionItem {
+    /// The contextual keyword that introduced the item.
+    pub keyword: Name,
+    /// The tokens following the keyword, up to and including the closing `;`
+    /// or brace-delimited body.
+    pub tokens: ThinTokenStream,
+    /// The node produced by the extension.
+    pub data: ExtensionData,
+}
+
+/// The opaque node produced by a parser extension. Downcast `0` to the
+/// extension's own type to inspect it.
+///
+/// Two `ExtensionData` are equal only if they share the same node. The node is
+/// not hashed, and it does not survive serialization: decoding produces an
+/// empty node.
+#[derive(Clone)]
+pub struct ExtensionData(pub Rc<Any>);
+
+impl PartialEq for ExtensionData {
+    fn eq(&self, other: &ExtensionData) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for ExtensionData {}
+
+impl Hash for ExtensionData {
+    fn hash<H: Hasher>(&self, _state: &mut H) {}
+}
+
+impl fmt::Debug for ExtensionData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("ExtensionData(..)")
+    }
+}
+
+impl serialize::Encodable for ExtensionData {
+    fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
+        s.emit_nil()
+    }
+}
+
+impl serialize::Decodable for ExtensionData {
+    fn decode<D: Decoder>(d: &mut D) -> Result<ExtensionData, D::Error> {
+        d.read_nil().map(|()| ExtensionData(Rc::new(())))
+    }
+}
+
 #[derive(Clone, PartialEq, Eq, RustcEncodable, RustcDecodable, Hash, Debug)]
 pub struct ForeignItem {
     pub ident: Ident,
@@ -2150,18 +2521,86 @@ pub enum ForeignItemKind {
     Static(P<Ty>, bool),
     /// A foreign type
     Ty,
+    /// A macro invocation (`foo!()`), which may expand to foreign items
+    Macro(Mac),
 }
 
 impl ForeignItemKind {
-    pub fn descriptive_variant(&self) -> &str {
+    pub fn descriptive_variant(&self) -> &'static str {
         match *self {
             ForeignItemKind::Fn(..) => "foreign function",
             ForeignItemKind::Static(..) => "foreign static item",
             ForeignItemKind::Ty => "foreign type",
+            ForeignItemKind::Macro(..) => "macro in foreign module",
         }
     }
 }
 
+/// AST nodes which cover a span of the source, for tooling which handles
+/// nodes of different kinds uniformly.
+pub trait HasSpan {
+    fn span(&self) -> Span;
+
+    /// A short description of the kind of the node, like "function" or
+    /// "`match` expression", for use in messages.
+    fn kind_name(&self) -> &'static str;
+}
+
+impl<T: HasSpan + 'static> HasSpan for P<T> {
+    fn span(&self) -> Span { (**self).span() }
+    fn kind_name(&self) -> &'static str { (**self).kind_name() }
+}
+
+macro_rules! impl_has_span {
+    ($($ty:ty => |$node:ident| $kind_name:expr),* $(,)*) => { $(
+        impl HasSpan for $ty {
+            fn span(&self) -> Span {
+                self.span
+            }
+
+            fn kind_name(&self) -> &'static str {
+                let $node = self;
+                $kind_name
+            }
+        }
+    )* }
+}
+
+impl_has_span! {
+    Crate => |_crate| "crate",
+    Item => |item| item.node.descriptive_variant(),
+    ForeignItem => |item| item.node.descriptive_variant(),
+    TraitItem => |item| item.node.descriptive_variant(),
+    ImplItem => |item| item.node.descriptive_variant(),
+    Variant => |_variant| "variant",
+    StructField => |_field| "field",
+    Stmt => |stmt| stmt.node.descriptive_variant(),
+    Local => |_local| "`let` statement",
+    Block => |_block| "block",
+    Expr => |expr| expr.node.descriptive_variant(),
+    Field => |_field| "field",
+    Pat => |pat| pat.node.descriptive_variant(),
+    Ty => |ty| ty.node.descriptive_variant(),
+    Path => |_path| "path",
+    Lifetime => |_lifetime| "lifetime",
+    Mac => |_mac| "macro invocation",
+    Attribute => |attr| if attr.is_sugared_doc { "doc comment" } else { "attribute" },
+    Spanned<FieldPat> => |_field| "field pattern",
+}
+
+impl HasSpan for Arm {
+    fn span(&self) -> Span {
+        match self.pats.first() {
+            Some(pat) => pat.span.to(self.body.span),
+            None => self.body.span,
+        }
+    }
+
+    fn kind_name(&self) -> &'static str {
+        "`match` arm"
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use serialize;
@@ -2173,4 +2612,63 @@ mod tests {
         fn assert_encodable<T: serialize::Encodable>() {}
         assert_encodable::<Crate>();
     }
+
+    #[test]
+    fn node_spans_and_kinds() {
+        use util::parser_testing::{string_to_expr, string_to_item};
+
+        let expr = string_to_expr("match x { _ => 1 }".to_string());
+        assert_eq!(HasSpan::span(&expr), expr.span);
+        assert_eq!(expr.kind_name(), "`match` expression");
+
+        let item = string_to_item("fn f() { let y = 2; }".to_string()).unwrap();
+        assert_eq!(item.kind_name(), "function");
+        let body = match item.node {
+            ItemKind::Fn(_, _, _, _, _, ref body) => body,
+            _ => panic!("expected a function"),
+        };
+        let local = match body.stmts[0].node {
+            StmtKind::Local(ref local) => local,
+            _ => panic!("expected a `let` statement"),
+        };
+        let nodes: Vec<&HasSpan> = vec![&body.stmts[0], &**body, &local.pat];
+        assert_eq!(nodes.iter().map(|node| node.kind_name()).collect::<Vec<_>>(),
+                   ["`let` statement", "block", "binding pattern"]);
+    }
+
+    #[test]
+    fn err_placeholders() {
+        use print::pprust;
+        use util::parser_testing::string_to_expr;
+        use visit::{self, Visitor};
+
+        struct ExprCounter {
+            skip_err_nodes: bool,
+            count: usize,
+        }
+
+        impl<'a> Visitor<'a> for ExprCounter {
+            fn visit_expr(&mut self, expr: &'a Expr) {
+                self.count += 1;
+                visit::walk_expr(self, expr);
+            }
+
+            fn skip_err_nodes(&self) -> bool {
+                self.skip_err_nodes
+            }
+        }
+
+        let mut expr = string_to_expr("f(x, y)".to_string());
+        if let ExprKind::Call(_, ref mut args) = expr.node {
+            args[1].node = ExprKind::Err;
+        }
+        assert_eq!(pprust::expr_to_string(&expr), "f(x, <error>)");
+
+        let mut counter = ExprCounter { skip_err_nodes: false, count: 0 };
+        counter.visit_expr(&expr);
+        assert_eq!(counter.count, 4);
+        let mut counter = ExprCounter { skip_err_nodes: true, count: 0 };
+        counter.visit_expr(&expr);
+        assert_eq!(counter.count, 3);
+    }
 }
@@ -15,7 +15,7 @@ pub use self::ReprAttr::*;
 pub use self::IntType::*;
 
 use ast;
-use ast::{AttrId, Attribute, Name, Ident};
+use ast::{AttrId, Attribute, DocCommentKind, Name, Ident};
 use ast::{MetaItem, MetaItemKind, NestedMetaItem, NestedMetaItemKind};
 use ast::{Lit, LitKind, Expr, ExprKind, Item, Local, Stmt, StmtKind};
 use codemap::{Spanned, respan, dummy_spanned};
@@ -30,6 +30,7 @@ use ptr::P;
 use symbol::Symbol;
 use tokenstream::{TokenStream, TokenTree, Delimited};
 use util::ThinVec;
+use visit::{self, Visitor};
 
 use std::cell::RefCell;
 use std::iter;
@@ -248,6 +249,20 @@ impl Attribute {
     pub fn is_value_str(&self) -> bool {
         self.value_str().is_some()
     }
+
+    /// The kind of doc comment this attribute was written as, or `None` if it
+    /// is not a doc comment. Explicit `#[doc = "..."]` attributes are not doc
+    /// comments.
+    pub fn doc_comment_kind(&self) -> Option<DocCommentKind> {
+        if !self.is_sugared_doc {
+            return None;
+        }
+        self.value_str().map(|text| if text.as_str().starts_with("/*") {
+            DocCommentKind::Block
+        } else {
+            DocCommentKind::Line
+        })
+    }
 }
 
 impl MetaItem {
@@ -583,6 +598,28 @@ pub fn cfg_matches(cfg: &ast::MetaItem, sess: &ParseSess, features: Option<&Feat
     })
 }
 
+/// Tests if a cfg-pattern matches `config`, without a session and without
+/// reporting errors, for tools that evaluate cfgs outside of compilation.
+/// Malformed patterns never match.
+pub fn cfg_matches_config(cfg: &ast::MetaItem, config: &ast::CrateConfig) -> bool {
+    match cfg.node {
+        ast::MetaItemKind::List(ref mis) => {
+            let matches = |mi: &NestedMetaItem| {
+                mi.meta_item().map_or(false, |mi| cfg_matches_config(mi, config))
+            };
+            match &*cfg.name.as_str() {
+                "any" => mis.iter().any(matches),
+                "all" => mis.iter().all(|mi| mi.is_meta_item()) && mis.iter().all(matches),
+                "not" if mis.len() == 1 && mis[0].is_meta_item() => !matches(&mis[0]),
+                _ => false,
+            }
+        }
+        ast::MetaItemKind::Word | ast::MetaItemKind::NameValue(..) => {
+            config.contains(&(cfg.name(), cfg.value_str()))
+        }
+    }
+}
+
 /// Evaluate a cfg-like condition (with `any` and `all`), using `eval` to
 /// evaluate individual items.
 pub fn eval_condition<F>(cfg: &ast::MetaItem, sess: &ParseSess, eval: &mut F)
@@ -995,6 +1032,27 @@ pub fn find_deprecation(diagnostic: &Handler, attrs: &[Attribute],
 /// structure layout, `packed` to remove padding, and `transparent` to elegate representation
 /// concerns to the only non-ZST field.
 pub fn find_repr_attrs(diagnostic: &Handler, attr: &Attribute) -> Vec<ReprAttr> {
+    parse_repr_hints(diagnostic, attr).into_iter().filter_map(|hint| {
+        let repr = match hint.node {
+            ReprHint::Int(ity) => ReprInt(ity),
+            ReprHint::C => ReprC,
+            ReprHint::Packed(None) => ReprPacked,
+            ReprHint::Packed(Some(_)) => {
+                span_err!(diagnostic, hint.span, E0552, "unrecognized representation hint");
+                return None;
+            }
+            ReprHint::Simd => ReprSimd,
+            ReprHint::Transparent => ReprTransparent,
+            ReprHint::Align(align) => ReprAlign(align),
+        };
+        Some(repr)
+    }).collect()
+}
+
+/// Parses the hints of a `#[repr(...)]` attribute, along with their spans,
+/// reporting hints which are malformed. Unlike `find_repr_attrs`, this accepts
+/// `packed(N)`. Returns nothing if `attr` is not a `repr` attribute.
+pub fn parse_repr_hints(diagnostic: &Handler, attr: &Attribute) -> Vec<Spanned<ReprHint>> {
     let mut acc = Vec::new();
     if attr.path == "repr" {
         if let Some(items) = attr.meta_item_list() {
@@ -1009,12 +1067,12 @@ pub fn find_repr_attrs(diagnostic: &Handler, attr: &Attribute) -> Vec<ReprAttr>
                 if let Some(mi) = item.word() {
                     let word = &*mi.name().as_str();
                     let hint = match word {
-                        "C" => Some(ReprC),
-                        "packed" => Some(ReprPacked),
-                        "simd" => Some(ReprSimd),
-                        "transparent" => Some(ReprTransparent),
+                        "C" => Some(ReprHint::C),
+                        "packed" => Some(ReprHint::Packed(None)),
+                        "simd" => Some(ReprHint::Simd),
+                        "transparent" => Some(ReprHint::Transparent),
                         _ => match int_type_of_word(word) {
-                            Some(ity) => Some(ReprInt(ity)),
+                            Some(ity) => Some(ReprHint::Int(ity)),
                             None => {
                                 None
                             }
@@ -1023,17 +1081,23 @@ pub fn find_repr_attrs(diagnostic: &Handler, attr: &Attribute) -> Vec<ReprAttr>
 
                     if let Some(h) = hint {
                         recognised = true;
-                        acc.push(h);
+                        acc.push(respan(item.span, h));
                     }
                 } else if let Some((name, value)) = item.name_value_literal() {
-                    if name == "align" {
+                    if name == "align" || name == "packed" {
                         recognised = true;
                         let mut align_error = None;
-                        if let ast::LitKind::Int(align, ast::LitIntType::Unsuffixed) = value.node {
+                        if let ast::LitKind::Int(align, ast::LitIntType::Unsuffixed, _) =
+                               value.node {
                             if align.is_power_of_two() {
                                 // rustc::ty::layout::Align restricts align to <= 2147483647
                                 if align <= 2147483647 {
-                                    acc.push(ReprAlign(align as u32));
+                                    let hint = if name == "align" {
+                                        ReprHint::Align(align as u32)
+                                    } else {
+                                        ReprHint::Packed(Some(align as u32))
+                                    };
+                                    acc.push(respan(item.span, hint));
                                 } else {
                                     align_error = Some("larger than 2147483647");
                                 }
@@ -1044,8 +1108,13 @@ pub fn find_repr_attrs(diagnostic: &Handler, attr: &Attribute) -> Vec<ReprAttr>
                             align_error = Some("not an unsuffixed integer");
                         }
                         if let Some(align_error) = align_error {
-                            span_err!(diagnostic, item.span, E0589,
-                                      "invalid `repr(align)` attribute: {}", align_error);
+                            if name == "align" {
+                                span_err!(diagnostic, item.span, E0589,
+                                          "invalid `repr(align)` attribute: {}", align_error);
+                            } else {
+                                diagnostic.span_err(item.span, &format!(
+                                    "invalid `repr(packed)` attribute: {}", align_error));
+                            }
                         }
                     }
                 }
@@ -1060,6 +1129,190 @@ pub fn find_repr_attrs(diagnostic: &Handler, attr: &Attribute) -> Vec<ReprAttr>
     acc
 }
 
+/// Parses the hints of all `#[repr(...)]` attributes in `attrs` with
+/// `parse_repr_hints`, and reports hints which conflict with each other.
+pub fn find_repr_hints(diagnostic: &Handler, attrs: &[Attribute]) -> Vec<Spanned<ReprHint>> {
+    let hints = attrs.iter()
+                     .flat_map(|attr| parse_repr_hints(diagnostic, attr))
+                     .collect::<Vec<_>>();
+    check_repr_hints(diagnostic, &hints);
+    hints
+}
+
+/// Reports representation hints which conflict with each other: more than one
+/// integer type or packing, `packed` together with `align`, and `transparent`
+/// together with anything else. Returns whether any conflicts were found.
+pub fn check_repr_hints(diagnostic: &Handler, hints: &[Spanned<ReprHint>]) -> bool {
+    let mut conflicts = Vec::new();
+    let mut int: Option<&Spanned<ReprHint>> = None;
+    let mut packed: Option<&Spanned<ReprHint>> = None;
+    let mut align = None;
+    let mut transparent = None;
+    for hint in hints {
+        match hint.node {
+            ReprHint::Int(..) => match int {
+                Some(first) => if first.node != hint.node {
+                    conflicts.push((first, hint, "conflicting integer representation hints"));
+                },
+                None => int = Some(hint),
+            },
+            ReprHint::Packed(..) => match packed {
+                Some(first) => if first.node != hint.node {
+                    conflicts.push((first, hint, "conflicting packed representation hints"));
+                },
+                None => packed = Some(hint),
+            },
+            ReprHint::Align(..) => align = align.or(Some(hint)),
+            ReprHint::Transparent => transparent = transparent.or(Some(hint)),
+            ReprHint::C | ReprHint::Simd => {}
+        }
+    }
+
+    if let (Some(packed), Some(align)) = (packed, align) {
+        let (first, second) = if packed.span.lo() < align.span.lo() {
+            (packed, align)
+        } else {
+            (align, packed)
+        };
+        conflicts.push((first, second, "conflicting packed and align representation hints"));
+    }
+    if let Some(transparent) = transparent {
+        if let Some(other) = hints.iter().find(|hint| hint.node != ReprHint::Transparent) {
+            conflicts.push((transparent, other, "`transparent` cannot be combined with other \
+                                                 representation hints"));
+        }
+    }
+
+    for &(first, second, msg) in &conflicts {
+        diagnostic.struct_span_err(second.span, msg)
+                  .span_label(first.span, "conflicts with this hint")
+                  .emit();
+    }
+    !conflicts.is_empty()
+}
+
+/// Attributes which are only meaningful once on the same node, see
+/// `find_duplicate_attrs`.
+pub const UNIQUE_ATTRS: &[&str] = &[
+    "inline", "path", "export_name", "link_name", "link_section", "no_mangle", "cold",
+    "must_use", "should_panic", "ignore", "start", "main", "crate_name", "recursion_limit",
+    "type_length_limit", "windows_subsystem", "no_std", "no_core", "no_main",
+];
+
+/// An attribute from `UNIQUE_ATTRS` which is repeated on the same node.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DuplicateAttr {
+    pub name: Name,
+    /// The span of the first occurrence of the attribute.
+    pub first: Span,
+    /// The span of the repeated occurrence.
+    pub duplicate: Span,
+}
+
+impl DuplicateAttr {
+    pub fn emit(&self, diagnostic: &Handler) {
+        diagnostic.struct_span_warn(self.duplicate,
+                                    &format!("duplicate `#[{}]` attribute", self.name))
+                  .span_label(self.first, "first specified here")
+                  .emit();
+    }
+}
+
+/// Finds the attributes from `UNIQUE_ATTRS` which occur more than once in
+/// `attrs`, one entry for each repetition.
+pub fn find_duplicate_attrs(attrs: &[Attribute]) -> Vec<DuplicateAttr> {
+    let mut duplicates = Vec::new();
+    for (i, attr) in attrs.iter().enumerate() {
+        let name = match attr.name() {
+            Some(name) if UNIQUE_ATTRS.contains(&&*name.as_str()) => name,
+            _ => continue,
+        };
+        if let Some(first) = attrs[..i].iter().find(|first| first.name() == Some(name)) {
+            duplicates.push(DuplicateAttr { name, first: first.span, duplicate: attr.span });
+        }
+    }
+    duplicates
+}
+
+/// Finds the repeated attributes of all nodes in `krate`, see
+/// `find_duplicate_attrs`. The crate does not need to be expanded, the
+/// arguments of macro invocations are not looked into.
+pub fn find_duplicate_attrs_in_crate(krate: &ast::Crate) -> Vec<DuplicateAttr> {
+    let mut finder = DuplicateAttrFinder { duplicates: find_duplicate_attrs(&krate.attrs) };
+    visit::walk_crate(&mut finder, krate);
+    finder.duplicates
+}
+
+/// Reports the repeated attributes of all nodes in `krate` as warnings, and
+/// returns them.
+pub fn check_duplicate_attrs(diagnostic: &Handler, krate: &ast::Crate) -> Vec<DuplicateAttr> {
+    let duplicates = find_duplicate_attrs_in_crate(krate);
+    for duplicate in &duplicates {
+        duplicate.emit(diagnostic);
+    }
+    duplicates
+}
+
+struct DuplicateAttrFinder {
+    duplicates: Vec<DuplicateAttr>,
+}
+
+impl DuplicateAttrFinder {
+    fn check(&mut self, attrs: &[Attribute]) {
+        self.duplicates.extend(find_duplicate_attrs(attrs));
+    }
+}
+
+impl<'a> Visitor<'a> for DuplicateAttrFinder {
+    fn visit_item(&mut self, item: &'a Item) {
+        self.check(&item.attrs);
+        visit::walk_item(self, item)
+    }
+
+    fn visit_trait_item(&mut self, item: &'a ast::TraitItem) {
+        self.check(&item.attrs);
+        visit::walk_trait_item(self, item)
+    }
+
+    fn visit_impl_item(&mut self, item: &'a ast::ImplItem) {
+        self.check(&item.attrs);
+        visit::walk_impl_item(self, item)
+    }
+
+    fn visit_foreign_item(&mut self, item: &'a ast::ForeignItem) {
+        self.check(&item.attrs);
+        visit::walk_foreign_item(self, item)
+    }
+
+    fn visit_variant(&mut self, variant: &'a ast::Variant, generics: &'a ast::Generics,
+                     item_id: ast::NodeId) {
+        self.check(&variant.node.attrs);
+        visit::walk_variant(self, variant, generics, item_id)
+    }
+
+    fn visit_struct_field(&mut self, field: &'a ast::StructField) {
+        self.check(&field.attrs);
+        visit::walk_struct_field(self, field)
+    }
+
+    fn visit_local(&mut self, local: &'a Local) {
+        self.check(&local.attrs);
+        visit::walk_local(self, local)
+    }
+
+    fn visit_arm(&mut self, arm: &'a ast::Arm) {
+        self.check(&arm.attrs);
+        visit::walk_arm(self, arm)
+    }
+
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        self.check(&expr.attrs);
+        visit::walk_expr(self, expr)
+    }
+
+    fn visit_mac(&mut self, _mac: &'a ast::Mac) {}
+}
+
 fn int_type_of_word(s: &str) -> Option<IntType> {
     match s {
         "i8" => Some(SignedInt(ast::IntTy::I8)),
@@ -1088,6 +1341,18 @@ pub enum ReprAttr {
     ReprAlign(u32),
 }
 
+/// A hint in a `#[repr(...)]` attribute, see `parse_repr_hints`.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum ReprHint {
+    Int(IntType),
+    C,
+    /// `packed`, or `packed(N)` to align fields to at most `N` bytes.
+    Packed(Option<u32>),
+    Simd,
+    Transparent,
+    Align(u32),
+}
+
 #[derive(Eq, Hash, PartialEq, Debug, RustcEncodable, RustcDecodable, Copy, Clone)]
 pub enum IntType {
     SignedInt(ast::IntTy),
@@ -1253,18 +1518,23 @@ impl LitKind {
                 let string: String = ch.escape_default().map(Into::<char>::into).collect();
                 Token::Literal(token::Lit::Char(Symbol::intern(&string)), None)
             }
-            LitKind::Int(n, ty) => {
+            LitKind::Int(n, ty, text) => {
                 let suffix = match ty {
                     ast::LitIntType::Unsigned(ty) => Some(Symbol::intern(ty.ty_to_string())),
                     ast::LitIntType::Signed(ty) => Some(Symbol::intern(ty.ty_to_string())),
                     ast::LitIntType::Unsuffixed => None,
                 };
-                Token::Literal(token::Lit::Integer(Symbol::intern(&n.to_string())), suffix)
+                let text = text.unwrap_or_else(|| Symbol::intern(&n.to_string()));
+                Token::Literal(token::Lit::Integer(text), suffix)
+            }
+            LitKind::Float(symbol, ty, text) => {
+                let suffix = Some(Symbol::intern(ty.ty_to_string()));
+                Token::Literal(token::Lit::Float(text.unwrap_or(symbol)), suffix)
             }
-            LitKind::Float(symbol, ty) => {
-                Token::Literal(token::Lit::Float(symbol), Some(Symbol::intern(ty.ty_to_string())))
+            LitKind::FloatUnsuffixed(symbol, text) => {
+                Token::Literal(token::Lit::Float(text.unwrap_or(symbol)), None)
             }
-            LitKind::FloatUnsuffixed(symbol) => Token::Literal(token::Lit::Float(symbol), None),
+            LitKind::UnknownSuffix(lit, suffix) => Token::Literal(lit, Some(suffix)),
             LitKind::Bool(value) => Token::Ident(Ident::with_empty_ctxt(Symbol::intern(if value {
                 "true"
             } else {
@@ -24,8 +24,10 @@ pub use self::ExpnFormat::*;
 
 use rustc_data_structures::fx::FxHashMap;
 use rustc_data_structures::stable_hasher::StableHasher;
-use std::cell::{RefCell, Ref};
+use std::cell::{Cell, RefCell, Ref};
 use std::cmp;
+use std::error::Error;
+use std::fmt;
 use std::hash::Hash;
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
@@ -34,6 +36,7 @@ use std::env;
 use std::fs;
 use std::io::{self, Read};
 use errors::CodeMapper;
+use parse::mod_path;
 
 /// Return the span itself if it doesn't come from a macro expansion,
 /// otherwise return the call site span up to the `enclosing_sp` by
@@ -54,6 +57,40 @@ pub struct Spanned<T> {
     pub span: Span,
 }
 
+impl<T> Spanned<T> {
+    /// Maps the node, keeping the span.
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> Spanned<U> {
+        respan(self.span, f(self.node))
+    }
+
+    pub fn as_ref(&self) -> Spanned<&T> {
+        respan(self.span, &self.node)
+    }
+
+    pub fn as_mut(&mut self) -> Spanned<&mut T> {
+        respan(self.span, &mut self.node)
+    }
+
+    /// Replaces the span, keeping the node.
+    pub fn respan_keep_node(self, span: Span) -> Spanned<T> {
+        respan(span, self.node)
+    }
+
+    /// Pairs the nodes of `self` and `other`, with a span from the start of
+    /// `self` to the end of `other`.
+    pub fn zip<U>(self, other: Spanned<U>) -> Spanned<(T, U)> {
+        respan(self.span.to(other.span), (self.node, other.node))
+    }
+
+    /// Combines the nodes of `self` and `other` with `f`, with a span from
+    /// the start of `self` to the end of `other`.
+    pub fn combine<U, V, F>(self, other: Spanned<U>, f: F) -> Spanned<V>
+        where F: FnOnce(T, U) -> V
+    {
+        respan(self.span.to(other.span), f(self.node, other.node))
+    }
+}
+
 pub fn respan<T>(sp: Span, t: T) -> Spanned<T> {
     Spanned {node: t, span: sp}
 }
@@ -76,6 +113,13 @@ pub trait FileLoader {
 
     /// Read the contents of an UTF-8 file into memory.
     fn read_file(&self, path: &Path) -> io::Result<String>;
+
+    /// Return the canonical path of a file, with symbolic links resolved, if
+    /// possible. Loaders for file systems which are case-insensitive should
+    /// also normalize the case.
+    fn canonicalize(&self, _path: &Path) -> Option<PathBuf> {
+        None
+    }
 }
 
 /// A FileLoader that uses std::fs to load real files.
@@ -101,6 +145,10 @@ impl FileLoader for RealFileLoader {
         fs::File::open(path)?.read_to_string(&mut src)?;
         Ok(src)
     }
+
+    fn canonicalize(&self, path: &Path) -> Option<PathBuf> {
+        fs::canonicalize(path).ok()
+    }
 }
 
 // This is a FileMap identifier that is used to correlate FileMaps between
@@ -121,6 +169,46 @@ impl StableFilemapId {
     }
 }
 
+/// A small identifier for a FileMap, unique within its CodeMap. FileMaps are
+/// numbered in the order they are added to the CodeMap, starting from zero.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct FileMapId(pub u32);
+
+impl FileMapId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// The files of one crate in a codemap shared by several crates, see
+/// `CodeMap::begin_crate`. Namespaces are numbered in the order they are
+/// begun, starting from zero. A namespace only labels a range of the shared
+/// address space, it does not reserve any of it.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct CrateNamespace(pub u32);
+
+struct CrateNamespaceInfo {
+    name: String,
+    start_pos: BytePos,
+    /// The index of the first file of the namespace.
+    first_file: usize,
+}
+
+/// How CRLF line endings are handled in source files, see
+/// `CodeMap::set_crlf_policy`. The lines which had them are recorded in
+/// `FileMap::crlf_lines` in any case.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, RustcEncodable, RustcDecodable)]
+pub enum CrlfPolicy {
+    /// Keep the CRs in the source. The lexer reads CRLF as LF in doc comments
+    /// and string literals.
+    Preserve,
+    /// Replace CRLF with LF when adding a file, so that spans and snippets
+    /// refer to the normalized source.
+    Normalize,
+    /// Keep the CRs in the source, and report them as errors when lexing.
+    Error,
+}
+
 // _____________________________________________________________________________
 // CodeMap
 //
@@ -135,6 +223,8 @@ pub struct CodeMap {
     /// In case we are in a doctest, replace all file names with the PathBuf,
     /// and add the given offsets to the line info
     doctest_offset: Option<(FileName, isize)>,
+    crate_namespaces: RefCell<Vec<CrateNamespaceInfo>>,
+    crlf_policy: Cell<CrlfPolicy>,
 }
 
 impl CodeMap {
@@ -145,6 +235,8 @@ impl CodeMap {
             path_mapping,
             stable_id_to_filemap: RefCell::new(FxHashMap()),
             doctest_offset: None,
+            crate_namespaces: RefCell::new(Vec::new()),
+            crlf_policy: Cell::new(CrlfPolicy::Preserve),
         }
     }
 
@@ -166,6 +258,8 @@ impl CodeMap {
             path_mapping,
             stable_id_to_filemap: RefCell::new(FxHashMap()),
             doctest_offset: None,
+            crate_namespaces: RefCell::new(Vec::new()),
+            crlf_policy: Cell::new(CrlfPolicy::Preserve),
         }
     }
 
@@ -173,6 +267,15 @@ impl CodeMap {
         &self.path_mapping
     }
 
+    /// Sets how CRLF line endings are handled in files added from now on.
+    pub fn set_crlf_policy(&self, policy: CrlfPolicy) {
+        self.crlf_policy.set(policy);
+    }
+
+    pub fn crlf_policy(&self) -> CrlfPolicy {
+        self.crlf_policy.get()
+    }
+
     pub fn file_exists(&self, path: &Path) -> bool {
         self.file_loader.file_exists(path)
     }
@@ -184,7 +287,19 @@ impl CodeMap {
         } else {
             path.to_owned().into()
         };
-        Ok(self.new_filemap(filename, src))
+        let canonical_path = self.file_loader.canonicalize(path);
+        self.new_filemap_internal(filename, src, canonical_path)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Returns the path identifying the file at `path`: its canonical path if
+    /// the file loader can resolve it, and otherwise its absolute path with the
+    /// `.` and `..` components resolved lexically.
+    pub fn canonicalize(&self, path: &Path) -> PathBuf {
+        self.file_loader.canonicalize(path).unwrap_or_else(|| {
+            let path = self.file_loader.abs_path(path).unwrap_or_else(|| path.to_path_buf());
+            mod_path::normalize_lexically(&path)
+        })
     }
 
     pub fn files(&self) -> Ref<Vec<Rc<FileMap>>> {
@@ -195,6 +310,18 @@ impl CodeMap {
         self.stable_id_to_filemap.borrow().get(&stable_id).map(|fm| fm.clone())
     }
 
+    /// Returns the ID of `filemap`, which must belong to this CodeMap.
+    pub fn file_id(&self, filemap: &FileMap) -> FileMapId {
+        let idx = self.lookup_filemap_idx(filemap.start_pos);
+        assert!(self.files.borrow()[idx].start_pos == filemap.start_pos,
+                "filemap `{}` does not belong to this codemap", filemap.name);
+        FileMapId(idx as u32)
+    }
+
+    pub fn file_by_id(&self, id: FileMapId) -> Option<Rc<FileMap>> {
+        self.files.borrow().get(id.index()).cloned()
+    }
+
     fn next_start_pos(&self) -> usize {
         let files = self.files.borrow();
         match files.last() {
@@ -205,10 +332,42 @@ impl CodeMap {
         }
     }
 
+    /// The number of bytes of source which can still be added to the codemap.
+    /// All files share the space of 32-bit `BytePos`es, 4 GiB.
+    pub fn remaining_address_space(&self) -> usize {
+        (u32::max_value() as usize).saturating_sub(self.next_start_pos())
+    }
+
     /// Creates a new filemap without setting its line information. If you don't
     /// intend to set the line information yourself, you should use new_filemap_and_lines.
+    ///
+    /// Panics if the source does not fit into the remaining address space,
+    /// see `try_new_filemap` for a version which does not.
     pub fn new_filemap(&self, filename: FileName, src: String) -> Rc<FileMap> {
+        self.try_new_filemap(filename, src).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Creates a new filemap like `new_filemap`, or returns an error if the
+    /// source does not fit into the remaining address space.
+    pub fn try_new_filemap(&self, filename: FileName, src: String)
+                           -> Result<Rc<FileMap>, FileTooLarge> {
+        self.new_filemap_internal(filename, src, None)
+    }
+
+    fn new_filemap_internal(&self, filename: FileName, src: String,
+                            canonical_path: Option<PathBuf>)
+                            -> Result<Rc<FileMap>, FileTooLarge> {
+        // The file takes up the positions from `start_pos` to its end,
+        // inclusive, which must all be valid `BytePos`es. This also rejects
+        // empty files once the last position has been used.
         let start_pos = self.next_start_pos();
+        let fits = start_pos.checked_add(src.len())
+                            .map_or(false, |end_pos| end_pos <= u32::max_value() as usize);
+        if !fits {
+            let remaining = self.remaining_address_space();
+            return Err(FileTooLarge { filename, len: src.len(), remaining });
+        }
+
         let mut files = self.files.borrow_mut();
 
         // The path is used to determine the directory for loading submodules and
@@ -225,13 +384,16 @@ impl CodeMap {
             },
             other => (other, false),
         };
-        let filemap = Rc::new(FileMap::new(
+        let mut filemap = FileMap::new(
             filename,
             was_remapped,
             unmapped_path,
             src,
             Pos::from_usize(start_pos),
-        ));
+            self.crlf_policy.get() == CrlfPolicy::Normalize,
+        );
+        filemap.canonical_path = canonical_path;
+        let filemap = Rc::new(filemap);
 
         files.push(filemap.clone());
 
@@ -239,7 +401,7 @@ impl CodeMap {
             .borrow_mut()
             .insert(StableFilemapId::new(&filemap), filemap.clone());
 
-        filemap
+        Ok(filemap)
     }
 
     /// Creates a new filemap and sets its line information.
@@ -304,6 +466,10 @@ impl CodeMap {
             multibyte_chars: RefCell::new(file_local_multibyte_chars),
             non_narrow_chars: RefCell::new(file_local_non_narrow_chars),
             name_hash,
+            syntax_variant: Cell::new(None),
+            frontmatter: RefCell::new(None),
+            crlf_lines: Vec::new(),
+            canonical_path: None,
         });
 
         files.push(filemap.clone());
@@ -345,22 +511,7 @@ impl CodeMap {
                 let linechpos = self.bytepos_to_file_charpos(linebpos);
                 let col = chpos - linechpos;
 
-                let col_display = {
-                    let non_narrow_chars = f.non_narrow_chars.borrow();
-                    let start_width_idx = non_narrow_chars
-                        .binary_search_by_key(&linebpos, |x| x.pos())
-                        .unwrap_or_else(|x| x);
-                    let end_width_idx = non_narrow_chars
-                        .binary_search_by_key(&pos, |x| x.pos())
-                        .unwrap_or_else(|x| x);
-                    let special_chars = end_width_idx - start_width_idx;
-                    let non_narrow: usize =
-                        non_narrow_chars[start_width_idx..end_width_idx]
-                        .into_iter()
-                        .map(|x| x.width())
-                        .sum();
-                    col.0 - special_chars + non_narrow
-                };
+                let col_display = f.display_col(pos);
                 debug!("byte pos {:?} is on the line at byte pos {:?}",
                        pos, linebpos);
                 debug!("char pos {:?} is on the line at char pos {:?}",
@@ -375,18 +526,7 @@ impl CodeMap {
                 }
             }
             Err(f) => {
-                let col_display = {
-                    let non_narrow_chars = f.non_narrow_chars.borrow();
-                    let end_width_idx = non_narrow_chars
-                        .binary_search_by_key(&pos, |x| x.pos())
-                        .unwrap_or_else(|x| x);
-                    let non_narrow: usize =
-                        non_narrow_chars[0..end_width_idx]
-                        .into_iter()
-                        .map(|x| x.width())
-                        .sum();
-                    chpos.0 - end_width_idx + non_narrow
-                };
+                let col_display = f.display_col(pos);
                 Loc {
                     file: f,
                     line: 0,
@@ -578,6 +718,33 @@ impl CodeMap {
         }
     }
 
+    /// Like `span_to_snippet`, but with explicit handling of spans that were
+    /// produced by macro expansion or compiler desugaring.
+    ///
+    /// Spans that do not come from an expansion are always resolved as by
+    /// `span_to_snippet`, regardless of `policy`.
+    pub fn span_to_snippet_with_policy(&self, sp: Span, policy: ExpnSnippetPolicy)
+                                       -> Result<String, SpanSnippetError> {
+        let info = match sp.ctxt().outer().expn_info() {
+            Some(info) => info,
+            None => return self.span_to_snippet(sp),
+        };
+
+        match policy {
+            ExpnSnippetPolicy::CallSite => self.span_to_snippet(sp.source_callsite()),
+            ExpnSnippetPolicy::DefSite => {
+                // Tokens that come from a macro definition keep their positions in
+                // the definition, so the span usually resolves by itself. Spans that
+                // were synthesized by the expansion fall back to the definition.
+                self.span_to_snippet(sp).or_else(|err| match info.callee.span {
+                    Some(def_span) => self.span_to_snippet(def_span),
+                    None => Err(err),
+                })
+            }
+            ExpnSnippetPolicy::Error => Err(SpanSnippetError::FromExpansion(sp)),
+        }
+    }
+
     /// Given a `Span`, try to get a shorter span ending before the first occurrence of `c` `char`
     pub fn span_until_char(&self, sp: Span, c: char) -> Span {
         match self.span_to_snippet(sp) {
@@ -773,6 +940,90 @@ impl CodeMap {
     pub fn count_lines(&self) -> usize {
         self.files().iter().fold(0, |a, f| a + f.count_lines())
     }
+
+    /// Begins a namespace for the crate `name`: the files added to the codemap
+    /// from now on, until the next call, belong to it. This lets several
+    /// crates be parsed into one codemap, with their spans told apart by
+    /// `crate_namespace` and serialized relative to their crate by
+    /// `span_to_crate_relative`. Files added before the first call belong to
+    /// no namespace.
+    ///
+    /// Namespaces are labels only: all of them share the address space of the
+    /// codemap, files are allocated in it as they are added whatever their
+    /// crate, and `FileTooLarge` is returned when the whole space is used up.
+    /// A crate with large files can thus leave no space for the crates after
+    /// it, so a tool parsing many crates into one codemap has to budget for
+    /// all of them together, see `remaining_address_space`.
+    pub fn begin_crate(&self, name: &str) -> CrateNamespace {
+        let start_pos = BytePos::try_from_usize(self.next_start_pos()).unwrap_or(BytePos::MAX);
+        let first_file = self.files.borrow().len();
+        let mut namespaces = self.crate_namespaces.borrow_mut();
+        namespaces.push(CrateNamespaceInfo { name: name.to_string(), start_pos, first_file });
+        CrateNamespace(namespaces.len() as u32 - 1)
+    }
+
+    pub fn crate_namespace_name(&self, namespace: CrateNamespace) -> String {
+        self.crate_namespaces.borrow()[namespace.0 as usize].name.clone()
+    }
+
+    /// The namespace of the file containing `pos`, if it is in one.
+    pub fn crate_namespace(&self, pos: BytePos) -> Option<CrateNamespace> {
+        if self.files.borrow().is_empty() {
+            return None;
+        }
+        let idx = self.lookup_filemap_idx(pos);
+        let namespaces = self.crate_namespaces.borrow();
+        // A namespace without files has the same first file as the next one.
+        namespaces.iter().rposition(|namespace| namespace.first_file <= idx)
+                  .map(|i| CrateNamespace(i as u32))
+    }
+
+    /// The positions of `sp` relative to the start of its crate namespace,
+    /// which do not depend on the files added to the codemap before the crate.
+    /// Returns `None` for spans outside of namespaces or crossing them.
+    pub fn span_to_crate_relative(&self, sp: Span) -> Option<(CrateNamespace, BytePos, BytePos)> {
+        if sp.is_dummy() {
+            return None;
+        }
+        let namespace = self.crate_namespace(sp.lo())?;
+        if self.crate_namespace(sp.hi()) != Some(namespace) {
+            return None;
+        }
+        let start_pos = self.crate_namespaces.borrow()[namespace.0 as usize].start_pos;
+        Some((namespace, sp.lo() - start_pos, sp.hi() - start_pos))
+    }
+
+    /// The inverse of `span_to_crate_relative`.
+    pub fn span_from_crate_relative(&self, namespace: CrateNamespace, lo: BytePos, hi: BytePos)
+                                    -> Span {
+        let start_pos = self.crate_namespaces.borrow()[namespace.0 as usize].start_pos;
+        Span::new(start_pos + lo, start_pos + hi, NO_EXPANSION)
+    }
+}
+
+/// The error returned by `CodeMap::try_new_filemap` for a source file which
+/// does not fit into the remaining address space of the codemap.
+#[derive(Clone, Debug)]
+pub struct FileTooLarge {
+    pub filename: FileName,
+    /// The length of the source, in bytes.
+    pub len: usize,
+    /// The remaining address space, see `CodeMap::remaining_address_space`.
+    pub remaining: usize,
+}
+
+impl fmt::Display for FileTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "source file `{}` is too large: it has {} bytes, but only {} bytes of \
+                   source can be added to the codemap",
+               self.filename, self.len, self.remaining)
+    }
+}
+
+impl Error for FileTooLarge {
+    fn description(&self) -> &str {
+        "source file too large"
+    }
 }
 
 impl CodeMapper for CodeMap {
@@ -813,6 +1064,20 @@ impl CodeMapper for CodeMap {
     }
 }
 
+/// How `CodeMap::span_to_snippet_with_policy` resolves a span produced by
+/// macro expansion.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ExpnSnippetPolicy {
+    /// Use the text of the outermost macro invocation (or desugared
+    /// expression) the span was expanded from.
+    CallSite,
+    /// Use the text the span points to in the macro definition, falling back
+    /// to the whole definition if the span cannot be resolved by itself.
+    DefSite,
+    /// Return `SpanSnippetError::FromExpansion`.
+    Error,
+}
+
 #[derive(Clone)]
 pub struct FilePathMapping {
     mapping: Vec<(PathBuf, PathBuf)>,
@@ -858,6 +1123,20 @@ mod tests {
     use std::borrow::Cow;
     use std::rc::Rc;
 
+    #[test]
+    fn spanned() {
+        let sp = |lo, hi| Span::new(BytePos(lo), BytePos(hi), NO_EXPANSION);
+        let mut lhs = respan(sp(0, 1), 1);
+        let rhs = respan(sp(4, 6), "x");
+        assert_eq!(lhs.map(|n| n + 1), respan(sp(0, 1), 2));
+        assert_eq!(lhs.as_ref(), respan(sp(0, 1), &1));
+        *lhs.as_mut().node = 3;
+        assert_eq!(lhs.respan_keep_node(sp(2, 3)), respan(sp(2, 3), 3));
+        assert_eq!(lhs.zip(rhs), respan(sp(0, 6), (3, "x")));
+        assert_eq!(lhs.combine(rhs, |n, s| format!("{}{}", n, s)),
+                   respan(sp(0, 6), "3x".to_string()));
+    }
+
     #[test]
     fn t1 () {
         let cm = CodeMap::new(FilePathMapping::empty());
@@ -993,6 +1272,107 @@ mod tests {
         assert_eq!(cp4, CharPos(15));
     }
 
+    #[test]
+    fn display_columns() {
+        let cm = CodeMap::new(FilePathMapping::empty());
+        // `中` is a three byte wide character.
+        let fm = cm.new_filemap(PathBuf::from("blork.rs").into(), "\t中a\nb".to_string());
+        fm.next_line(BytePos(0));
+        fm.record_width(BytePos(0), '\t');
+        fm.record_multibyte_char(BytePos(1), 3);
+        fm.record_width(BytePos(1), '中');
+        fm.next_line(BytePos(6));
+
+        assert_eq!(fm.char_count(BytePos(0), BytePos(4)), 2);
+        assert_eq!(fm.display_width(BytePos(1), BytePos(5)), 3);
+        assert_eq!(fm.non_narrow_chars_between(BytePos(1), BytePos(5)),
+                   vec![NonNarrowChar::Wide(BytePos(1))]);
+        assert_eq!(fm.display_col(BytePos(4)), 6);
+        assert_eq!(fm.display_col(BytePos(6)), 0);
+        assert_eq!(cm.lookup_char_pos(BytePos(4)).col_display, 6);
+    }
+
+    #[test]
+    fn oversized_files() {
+        let cm = CodeMap::new(FilePathMapping::empty());
+        let max = u32::max_value() as usize;
+        assert_eq!(cm.remaining_address_space(), max);
+        cm.new_filemap(PathBuf::from("a.rs").into(), "fn a() {}".to_string());
+        assert_eq!(cm.remaining_address_space(), max - 10);
+
+        // An imported filemap takes up space without having its source in memory.
+        cm.new_imported_filemap(PathBuf::from("b.rs").into(), false, 0, 0, 0, max - 20,
+                                Vec::new(), Vec::new(), Vec::new());
+        assert_eq!(cm.remaining_address_space(), 9);
+        let err = cm.try_new_filemap(PathBuf::from("c.rs").into(), "fn c() {}\n".to_string())
+                    .unwrap_err();
+        assert_eq!((err.len, err.remaining), (10, 9));
+        assert_eq!(cm.files().len(), 2);
+        assert!(cm.try_new_filemap(PathBuf::from("d.rs").into(), "fn d(){}".to_string()).is_ok());
+        assert_eq!(cm.remaining_address_space(), 0);
+
+        // An empty file still fits at the last position, but then the space
+        // is used up and the start position of another file would wrap to 0.
+        let e = cm.try_new_filemap(PathBuf::from("e.rs").into(), String::new()).unwrap();
+        assert_eq!(e.start_pos, BytePos(u32::max_value()));
+        let err = cm.try_new_filemap(PathBuf::from("f.rs").into(), String::new()).unwrap_err();
+        assert_eq!((err.len, err.remaining), (0, 0));
+        assert_eq!(cm.files().len(), 4);
+    }
+
+    #[test]
+    fn crlf_policies() {
+        let cm = CodeMap::new(FilePathMapping::empty());
+        let src = "a\r\nb\nc\r\n";
+        let fm = cm.new_filemap(PathBuf::from("preserved.rs").into(), src.to_string());
+        assert_eq!(fm.src.as_ref().unwrap().as_str(), src);
+        assert_eq!(fm.crlf_lines, [0, 2]);
+
+        cm.set_crlf_policy(CrlfPolicy::Normalize);
+        let fm = cm.new_filemap(PathBuf::from("normalized.rs").into(), src.to_string());
+        assert_eq!(fm.src.as_ref().unwrap().as_str(), "a\nb\nc\n");
+        assert_eq!(fm.crlf_lines, [0, 2]);
+        assert_eq!(fm.end_pos - fm.start_pos, BytePos(6));
+    }
+
+    #[test]
+    fn crate_namespaces() {
+        let cm = CodeMap::new(FilePathMapping::empty());
+        cm.new_filemap(PathBuf::from("pre.rs").into(), "fn pre() {}".to_string());
+        let a = cm.begin_crate("a");
+        let fm_a = cm.new_filemap(PathBuf::from("a.rs").into(), "fn a() {}".to_string());
+        let empty = cm.begin_crate("empty");
+        let b = cm.begin_crate("b");
+        cm.new_filemap(PathBuf::from("b1.rs").into(), "fn b1() {}".to_string());
+        let fm_b = cm.new_filemap(PathBuf::from("b2.rs").into(), "fn b2() {}".to_string());
+        assert_eq!((a, empty, b), (CrateNamespace(0), CrateNamespace(1), CrateNamespace(2)));
+        assert_eq!(cm.crate_namespace_name(b), "b");
+
+        assert_eq!(cm.crate_namespace(BytePos(3)), None);
+        assert_eq!(cm.crate_namespace(fm_a.start_pos), Some(a));
+        assert_eq!(cm.crate_namespace(fm_b.end_pos), Some(b));
+
+        let span = Span::new(fm_b.start_pos + BytePos(3), fm_b.end_pos, NO_EXPANSION);
+        let relative = cm.span_to_crate_relative(span).unwrap();
+        assert_eq!(relative, (b, BytePos(14), BytePos(21)));
+        assert_eq!(cm.span_from_crate_relative(b, relative.1, relative.2), span);
+
+        let crossing = Span::new(fm_a.start_pos, fm_b.end_pos, NO_EXPANSION);
+        assert_eq!(cm.span_to_crate_relative(crossing), None);
+        assert_eq!(cm.span_to_crate_relative(DUMMY_SP), None);
+    }
+
+    #[test]
+    fn file_ids() {
+        let cm = init_code_map();
+        for (i, fm) in cm.files().iter().enumerate() {
+            let id = cm.file_id(fm);
+            assert_eq!(id, FileMapId(i as u32));
+            assert!(Rc::ptr_eq(&cm.file_by_id(id).unwrap(), fm));
+        }
+        assert!(cm.file_by_id(FileMapId(3)).is_none());
+    }
+
     #[test]
     fn t7() {
         // Test span_to_lines for a span ending at the end of filemap
@@ -1073,6 +1453,42 @@ mod tests {
         assert!(cm.merge_spans(span1, span2).is_none());
     }
 
+    #[test]
+    fn snippet_of_expanded_span() {
+        let cm = CodeMap::new(FilePathMapping::empty());
+        let src = "macro_rules! m { () => { 1 + 2 } }\nfn f() { m!(); }";
+        let fm = cm.new_filemap(PathBuf::from("blork.rs").into(), src.to_string());
+        let def = cm.span_substr(&fm, src, "{ () => { 1 + 2 } }", 0);
+        let call = cm.span_substr(&fm, src, "m!()", 0);
+        let one = cm.span_substr(&fm, src, "1", 0);
+
+        let mark = hygiene::Mark::fresh(hygiene::Mark::root());
+        mark.set_expn_info(ExpnInfo {
+            call_site: call,
+            callee: NameAndSpan {
+                format: MacroBang(symbol::Symbol::intern("m")),
+                allow_internal_unstable: false,
+                allow_internal_unsafe: false,
+                span: Some(def),
+            },
+        });
+        let expanded = one.with_ctxt(SyntaxContext::empty().apply_mark(mark));
+        // A span synthesized by the expansion that does not map to any text:
+        // it crosses into another file.
+        let other = cm.new_filemap(PathBuf::from("other.rs").into(), "2".to_string());
+        let synthesized = Span::new(one.lo(), other.end_pos, expanded.ctxt());
+        assert!(cm.span_to_snippet(synthesized).is_err());
+
+        let snippet = |sp, policy| cm.span_to_snippet_with_policy(sp, policy);
+        assert_eq!(snippet(one, ExpnSnippetPolicy::Error).unwrap(), "1");
+        assert_eq!(snippet(expanded, ExpnSnippetPolicy::CallSite).unwrap(), "m!()");
+        assert_eq!(snippet(expanded, ExpnSnippetPolicy::DefSite).unwrap(), "1");
+        assert_eq!(snippet(synthesized, ExpnSnippetPolicy::DefSite).unwrap(),
+                   "{ () => { 1 + 2 } }");
+        assert_eq!(snippet(expanded, ExpnSnippetPolicy::Error),
+                   Err(SpanSnippetError::FromExpansion(expanded)));
+    }
+
     /// Returns the span corresponding to the `n`th occurrence of
     /// `substring` in `source_text`.
     trait CodeMapExtension {
@@ -11,11 +11,12 @@
 use attr::HasAttrs;
 use feature_gate::{feature_err, EXPLAIN_STMT_ATTR_SYNTAX, Features, get_features, GateIssue};
 use {fold, attr};
-use ast;
+use ast::{self, HasSpan};
 use codemap::Spanned;
 use parse::{token, ParseSess};
 
 use ptr::P;
+use syntax_pos::Span;
 use util::small_vector::SmallVector;
 
 /// A folder that strips out items that do not belong in the current configuration.
@@ -25,6 +26,27 @@ pub struct StripUnconfigured<'a> {
     pub features: Option<&'a Features>,
 }
 
+/// Code removed by cfg-stripping, recorded in `ParseSess::unconfigured_regions`
+/// when `ParseSess::record_unconfigured_regions` is set, so that tools can
+/// tell which parts of a file are inactive.
+#[derive(Clone, Debug)]
+pub struct UnconfiguredRegion {
+    /// The span of the removed node.
+    pub span: Span,
+    /// The predicate of the `#[cfg]` attribute which did not hold, if the
+    /// node was removed by one.
+    pub predicate: Option<ast::MetaItem>,
+    pub reason: UnconfiguredReason,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum UnconfiguredReason {
+    /// The predicate of a `#[cfg]` attribute does not hold.
+    CfgFalse,
+    /// `#[test]` and `#[bench]` functions are removed unless testing.
+    NotTesting,
+}
+
 // `cfg_attr`-process the crate's attributes and compute the crate's features.
 pub fn features(mut krate: ast::Crate, sess: &ParseSess, should_test: bool)
                 -> (ast::Crate, Features) {
@@ -38,7 +60,7 @@ pub fn features(mut krate: ast::Crate, sess: &ParseSess, should_test: bool)
 
         let unconfigured_attrs = krate.attrs.clone();
         let err_count = sess.span_diagnostic.err_count();
-        if let Some(attrs) = strip_unconfigured.configure(krate.attrs) {
+        if let Some(attrs) = strip_unconfigured.configure_spanned(krate.attrs, krate.span) {
             krate.attrs = attrs;
         } else { // the entire crate is unconfigured
             krate.attrs = Vec::new();
@@ -51,7 +73,7 @@ pub fn features(mut krate: ast::Crate, sess: &ParseSess, should_test: bool)
         // Avoid reconfiguring malformed `cfg_attr`s
         if err_count == sess.span_diagnostic.err_count() {
             strip_unconfigured.features = Some(&features);
-            strip_unconfigured.configure(unconfigured_attrs);
+            strip_unconfigured.configure_spanned(unconfigured_attrs, krate.span);
         }
     }
 
@@ -68,9 +90,22 @@ macro_rules! configure {
 }
 
 impl<'a> StripUnconfigured<'a> {
-    pub fn configure<T: HasAttrs>(&mut self, node: T) -> Option<T> {
+    pub fn configure<T: HasAttrs + HasSpan>(&mut self, node: T) -> Option<T> {
+        let span = node.span();
+        self.configure_spanned(node, span)
+    }
+
+    fn configure_spanned<T: HasAttrs>(&mut self, node: T, span: Span) -> Option<T> {
         let node = self.process_cfg_attrs(node);
-        if self.in_cfg(node.attrs()) { Some(node) } else { None }
+        let (predicate, reason) = match self.unconfigured_reason(node.attrs()) {
+            Some(reason) => reason,
+            None => return Some(node),
+        };
+        if self.sess.record_unconfigured_regions {
+            let region = UnconfiguredRegion { span, predicate, reason };
+            self.sess.unconfigured_regions.borrow_mut().push(region);
+        }
+        None
     }
 
     pub fn process_cfg_attrs<T: HasAttrs>(&mut self, node: T) -> T {
@@ -116,32 +151,43 @@ impl<'a> StripUnconfigured<'a> {
 
     // Determine if a node with the given attributes should be included in this configuation.
     pub fn in_cfg(&mut self, attrs: &[ast::Attribute]) -> bool {
-        attrs.iter().all(|attr| {
+        self.unconfigured_reason(attrs).is_none()
+    }
+
+    // Determine why a node with the given attributes is not included in this configuration, if
+    // it isn't, along with the `#[cfg]` predicate which excludes it.
+    fn unconfigured_reason(&mut self, attrs: &[ast::Attribute])
+                           -> Option<(Option<ast::MetaItem>, UnconfiguredReason)> {
+        for attr in attrs {
             // When not compiling with --test we should not compile the #[test] functions
             if !self.should_test && is_test_or_bench(attr) {
-                return false;
+                return Some((None, UnconfiguredReason::NotTesting));
             }
 
             let mis = if !is_cfg(attr) {
-                return true;
+                continue;
             } else if let Some(mis) = attr.meta_item_list() {
                 mis
             } else {
-                return true;
+                continue;
             };
 
             if mis.len() != 1 {
                 self.sess.span_diagnostic.span_err(attr.span, "expected 1 cfg-pattern");
-                return true;
+                continue;
             }
 
             if !mis[0].is_meta_item() {
                 self.sess.span_diagnostic.span_err(mis[0].span, "unexpected literal");
-                return true;
+                continue;
             }
 
-            attr::cfg_matches(mis[0].meta_item().unwrap(), self.sess, self.features)
-        })
+            let predicate = mis[0].meta_item().unwrap();
+            if !attr::cfg_matches(predicate, self.sess, self.features) {
+                return Some((Some(predicate.clone()), UnconfiguredReason::CfgFalse));
+            }
+        }
+        None
     }
 
     // Visit attributes on expression and statements (but not attributes on items in blocks).
@@ -219,13 +265,13 @@ impl<'a> StripUnconfigured<'a> {
                 let arms = arms.into_iter().filter_map(|a| self.configure(a)).collect();
                 ast::ExprKind::Match(m, arms)
             }
-            ast::ExprKind::Struct(path, fields, base) => {
+            ast::ExprKind::Struct(path, fields, base, comma) => {
                 let fields = fields.into_iter()
                     .filter_map(|field| {
                         self.configure(field)
                     })
                     .collect();
-                ast::ExprKind::Struct(path, fields, base)
+                ast::ExprKind::Struct(path, fields, base, comma)
             }
             _ => expr_kind,
         }
@@ -332,3 +378,46 @@ fn is_cfg(attr: &ast::Attribute) -> bool {
 pub fn is_test_or_bench(attr: &ast::Attribute) -> bool {
     attr.check_name("test") || attr.check_name("bench")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codemap::FilePathMapping;
+    use fold::Folder;
+    use parse::parse_crate_from_source_str;
+    use std::path::PathBuf;
+    use symbol::Symbol;
+
+    #[test]
+    fn unconfigured_regions() {
+        let mut sess = ParseSess::new(FilePathMapping::empty());
+        sess.record_unconfigured_regions = true;
+        let src = "#[cfg(foo)] fn a() {}\n\
+                   #[cfg(not(foo))] fn b() {}\n\
+                   #[test] fn c() {}\n\
+                   fn d(x: u8) { match x { #[cfg(foo)] 0 => {} _ => {} } }".to_string();
+        let krate = parse_crate_from_source_str(PathBuf::from("x").into(), src, &sess).unwrap();
+        let mut strip_unconfigured = StripUnconfigured {
+            should_test: false,
+            sess: &sess,
+            features: None,
+        };
+        let krate = strip_unconfigured.fold_crate(krate);
+        assert_eq!(krate.module.items.len(), 2);
+
+        let regions = sess.unconfigured_regions.borrow();
+        let regions = regions.iter().map(|region| {
+            let snippet = sess.codemap().span_to_snippet(region.span).unwrap();
+            (snippet, region.predicate.as_ref().map(|predicate| predicate.name()), region.reason)
+        }).collect::<Vec<_>>();
+        assert_eq!(regions.len(), 3);
+        assert!(regions[0].0.ends_with("fn a() {}"));
+        assert_eq!(regions[0].1, Some(Symbol::intern("foo")));
+        assert_eq!(regions[0].2, UnconfiguredReason::CfgFalse);
+        assert!(regions[1].0.ends_with("fn c() {}"));
+        assert_eq!(regions[1].1, None);
+        assert_eq!(regions[1].2, UnconfiguredReason::NotTesting);
+        assert_eq!(regions[2].0, "0 => {}");
+        assert_eq!(regions[2].2, UnconfiguredReason::CfgFalse);
+    }
+}
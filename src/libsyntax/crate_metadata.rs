@@ -0,0 +1,96 @@
+// Copyright 2018 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A summary of the crate-level attributes which change how the compiler
+//! treats a crate, for build tools which need the same information right
+//! after parsing.
+//!
+//! The attributes are read the way the compiler reads them, but they are not
+//! marked as used and malformed attributes are only recorded, not reported.
+
+use ast::{self, Attribute};
+use syntax_pos::Span;
+use syntax_pos::symbol::Symbol;
+
+/// The value of a crate-level attribute and the span of the attribute.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CrateAttr<T> {
+    pub value: T,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CrateMetadata {
+    /// `#![no_std]`.
+    pub no_std: Option<Span>,
+    /// The values of the `#![crate_type = "..."]` attributes, in source order.
+    /// The names are not checked.
+    pub crate_types: Vec<CrateAttr<Symbol>>,
+    /// The first valid `#![recursion_limit = "N"]`.
+    pub recursion_limit: Option<CrateAttr<usize>>,
+    /// The spans of `crate_type` and `recursion_limit` attributes without a
+    /// valid value, which the compiler ignores or rejects.
+    pub malformed: Vec<Span>,
+}
+
+impl CrateMetadata {
+    pub fn of(krate: &ast::Crate) -> CrateMetadata {
+        CrateMetadata::from_attrs(&krate.attrs)
+    }
+
+    pub fn from_attrs(attrs: &[Attribute]) -> CrateMetadata {
+        let mut metadata = CrateMetadata::default();
+        for attr in attrs {
+            if attr.path == "no_std" {
+                if metadata.no_std.is_none() {
+                    metadata.no_std = Some(attr.span);
+                }
+            } else if attr.path == "crate_type" {
+                match attr.value_str() {
+                    Some(value) => metadata.crate_types.push(CrateAttr { value, span: attr.span }),
+                    None => metadata.malformed.push(attr.span),
+                }
+            } else if attr.path == "recursion_limit" {
+                match attr.value_str().and_then(|value| value.as_str().parse().ok()) {
+                    Some(value) => if metadata.recursion_limit.is_none() {
+                        metadata.recursion_limit = Some(CrateAttr { value, span: attr.span });
+                    },
+                    None => metadata.malformed.push(attr.span),
+                }
+            }
+        }
+        metadata
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use util::parser_testing::string_to_crate;
+
+    #[test]
+    fn crate_metadata() {
+        let src = "#![no_std]\n\
+                   #![crate_type = \"lib\"]\n\
+                   #![crate_type = \"rlib\"]\n\
+                   #![recursion_limit = \"many\"]\n\
+                   #![recursion_limit = \"128\"]\n\
+                   fn f() {}";
+        let metadata = CrateMetadata::of(&string_to_crate(src.to_string()));
+        assert!(metadata.no_std.is_some());
+        assert_eq!(metadata.crate_types.iter().map(|ty| ty.value).collect::<Vec<_>>(),
+                   vec![Symbol::intern("lib"), Symbol::intern("rlib")]);
+        assert_eq!(metadata.recursion_limit.map(|limit| limit.value), Some(128));
+        assert_eq!(metadata.malformed.len(), 1);
+
+        let metadata = CrateMetadata::of(&string_to_crate("fn f() {}".to_string()));
+        assert_eq!(metadata, CrateMetadata::default());
+    }
+}
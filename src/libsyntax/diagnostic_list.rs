@@ -342,6 +342,24 @@ fn main() {
 ```
 "##,
 
+E0693: r##"
+An integer literal was too large to be represented by any integer type.
+
+Erroneous code example:
+
+```compile_fail,E0693
+let x = 340282366920938463463374607431768211456; // error: 2^128 does not fit
+```
+
+Integer literals are parsed into a 128-bit unsigned value, so no literal can
+exceed `u128::MAX` (340282366920938463463374607431768211455), whatever its
+suffix. If an approximate value is enough, use a float literal instead:
+
+```
+let x = 3.4e38;
+```
+"##,
+
 }
 
 register_diagnostics! {
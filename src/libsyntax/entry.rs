@@ -8,9 +8,13 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use abi::Abi;
 use attr;
-use ast::{Item, ItemKind};
+use ast::{self, Ident, Item, ItemKind};
+use syntax_pos::Span;
+use visit::{self, Visitor};
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum EntryPointType {
     None,
     MainNamed,
@@ -42,3 +46,186 @@ pub fn entry_point_type(item: &Item, depth: usize) -> EntryPointType {
         _ => EntryPointType::None,
     }
 }
+
+/// A function which can be the entry point of a crate.
+#[derive(Clone, Debug)]
+pub struct EntryFn {
+    pub ident: Ident,
+    pub span: Span,
+    pub ty: EntryPointType,
+    /// The problems with the signature of the function, see
+    /// `check_entry_signature`.
+    pub signature_errors: Vec<EntrySignatureError>,
+}
+
+/// The functions of a crate which can be its entry point, as found by
+/// `find_entry_points`.
+#[derive(Clone, Debug, Default)]
+pub struct EntryPoints {
+    /// The span of the `#![no_main]` attribute, if the crate has one.
+    pub no_main: Option<Span>,
+    /// The top-level function named `main`.
+    pub main_fn: Option<EntryFn>,
+    /// The function with the `#[main]` attribute.
+    pub attr_main_fn: Option<EntryFn>,
+    /// The function with the `#[start]` attribute.
+    pub start_fn: Option<EntryFn>,
+    /// The functions of any of the kinds above after the first one.
+    pub duplicates: Vec<EntryFn>,
+    /// The functions named `main` which are not at the top level, and so are
+    /// not entry points.
+    pub non_main_fns: Vec<Span>,
+}
+
+impl EntryPoints {
+    /// The entry point the compiler picks: the `#[start]` function, else the
+    /// `#[main]` function, else `main`. There is none with `#![no_main]`.
+    pub fn entry_fn(&self) -> Option<&EntryFn> {
+        if self.no_main.is_some() {
+            return None;
+        }
+        self.start_fn.as_ref().or(self.attr_main_fn.as_ref()).or(self.main_fn.as_ref())
+    }
+}
+
+/// Finds the functions of `krate` which can be its entry point, and checks
+/// their signatures. This only looks at the syntax, like the test harness
+/// does, so the crate does not need to be expanded or resolved; functions
+/// generated by macros are only found if it was expanded, though.
+pub fn find_entry_points(krate: &ast::Crate) -> EntryPoints {
+    let mut finder = EntryPointFinder { depth: 0, entry_points: EntryPoints::default() };
+    finder.entry_points.no_main = attr::find_by_name(&krate.attrs, "no_main").map(|a| a.span);
+    visit::walk_crate(&mut finder, krate);
+    finder.entry_points
+}
+
+struct EntryPointFinder {
+    // Current depth in the ast
+    depth: usize,
+    entry_points: EntryPoints,
+}
+
+impl<'a> Visitor<'a> for EntryPointFinder {
+    fn visit_item(&mut self, item: &'a Item) {
+        self.depth += 1;
+        let ty = entry_point_type(item, self.depth);
+        {
+            let entry_points = &mut self.entry_points;
+            let slot = match ty {
+                EntryPointType::MainNamed => Some(&mut entry_points.main_fn),
+                EntryPointType::MainAttr => Some(&mut entry_points.attr_main_fn),
+                EntryPointType::Start => Some(&mut entry_points.start_fn),
+                EntryPointType::OtherMain => {
+                    entry_points.non_main_fns.push(item.span);
+                    None
+                }
+                EntryPointType::None => None,
+            };
+            if let Some(slot) = slot {
+                let entry_fn = EntryFn {
+                    ident: item.ident,
+                    span: item.span,
+                    ty,
+                    signature_errors: check_entry_signature(item, ty),
+                };
+                if slot.is_none() {
+                    *slot = Some(entry_fn);
+                } else {
+                    entry_points.duplicates.push(entry_fn);
+                }
+            }
+        }
+        visit::walk_item(self, item);
+        self.depth -= 1;
+    }
+
+    fn visit_mac(&mut self, _mac: &'a ast::Mac) {}
+}
+
+/// A problem with the signature of an entry point function.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EntrySignatureError {
+    /// The function has generic parameters or a where clause.
+    Generics(Span),
+    /// The function takes the wrong number of arguments, none for `main` and
+    /// two for `#[start]` functions.
+    Arguments { expected: usize, found: usize },
+    /// The function has no return type, which `#[start]` functions need.
+    MissingReturnType,
+    Unsafe,
+    /// The function has an ABI other than `Rust`.
+    Abi(Abi),
+}
+
+/// Checks the signature of the entry point `item` of the given type, as far
+/// as is possible without resolving names: the number of arguments is checked,
+/// but not their types.
+pub fn check_entry_signature(item: &Item, ty: EntryPointType) -> Vec<EntrySignatureError> {
+    let mut errors = Vec::new();
+    let (decl, unsafety, abi, generics) = match item.node {
+        ItemKind::Fn(ref decl, unsafety, _, abi, ref generics, _) => {
+            (decl, unsafety, abi, generics)
+        }
+        _ => return errors,
+    };
+    let is_start = match ty {
+        EntryPointType::Start => true,
+        EntryPointType::MainNamed | EntryPointType::MainAttr => false,
+        EntryPointType::OtherMain | EntryPointType::None => return errors,
+    };
+
+    if !generics.params.is_empty() || !generics.where_clause.predicates.is_empty() {
+        errors.push(EntrySignatureError::Generics(generics.span));
+    }
+    let expected = if is_start { 2 } else { 0 };
+    if decl.inputs.len() != expected {
+        errors.push(EntrySignatureError::Arguments { expected, found: decl.inputs.len() });
+    }
+    if is_start {
+        if let ast::FunctionRetTy::Default(..) = decl.output {
+            errors.push(EntrySignatureError::MissingReturnType);
+        }
+    }
+    if unsafety == ast::Unsafety::Unsafe {
+        errors.push(EntrySignatureError::Unsafe);
+    }
+    if abi != Abi::Rust {
+        errors.push(EntrySignatureError::Abi(abi));
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use util::parser_testing::string_to_crate;
+
+    #[test]
+    fn entry_points() {
+        let krate = string_to_crate("fn main() {} mod m { fn main() {} } \
+                                     #[start] unsafe fn start<T>(argc: isize) {} \
+                                     #[start] fn start2() {}".to_string());
+        let entry_points = find_entry_points(&krate);
+        assert_eq!(entry_points.main_fn.as_ref().unwrap().ident.to_string(), "main");
+        assert!(entry_points.main_fn.as_ref().unwrap().signature_errors.is_empty());
+        assert_eq!(entry_points.non_main_fns.len(), 1);
+        assert_eq!(entry_points.duplicates.len(), 1);
+
+        let entry_fn = entry_points.entry_fn().unwrap();
+        assert_eq!(entry_fn.ident.to_string(), "start");
+        assert_eq!(entry_fn.ty, EntryPointType::Start);
+        let errors = &entry_fn.signature_errors;
+        match errors[0] {
+            EntrySignatureError::Generics(..) => {}
+            ref error => panic!("expected generics error, found {:?}", error),
+        }
+        assert_eq!(errors[1..], [EntrySignatureError::Arguments { expected: 2, found: 1 },
+                                 EntrySignatureError::MissingReturnType,
+                                 EntrySignatureError::Unsafe]);
+
+        let krate = string_to_crate("#![no_main] fn main() {}".to_string());
+        let entry_points = find_entry_points(&krate);
+        assert!(entry_points.no_main.is_some());
+        assert!(entry_points.entry_fn().is_none());
+    }
+}
@@ -651,11 +651,11 @@ impl<'a> AstBuilder for ExtCtxt<'a> {
     }
 
     fn expr_call(&self, span: Span, expr: P<ast::Expr>, args: Vec<P<ast::Expr>>) -> P<ast::Expr> {
-        self.expr(span, ast::ExprKind::Call(expr, args))
+        self.expr(span, ast::ExprKind::Call(expr, args, None))
     }
     fn expr_call_ident(&self, span: Span, id: ast::Ident,
                        args: Vec<P<ast::Expr>>) -> P<ast::Expr> {
-        self.expr(span, ast::ExprKind::Call(self.expr_ident(span, id), args))
+        self.expr(span, ast::ExprKind::Call(self.expr_ident(span, id), args, None))
     }
     fn expr_call_global(&self, sp: Span, fn_path: Vec<ast::Ident> ,
                       args: Vec<P<ast::Expr>> ) -> P<ast::Expr> {
@@ -682,7 +682,7 @@ impl<'a> AstBuilder for ExtCtxt<'a> {
         }
     }
     fn expr_struct(&self, span: Span, path: ast::Path, fields: Vec<ast::Field>) -> P<ast::Expr> {
-        self.expr(span, ast::ExprKind::Struct(path, fields, None))
+        self.expr(span, ast::ExprKind::Struct(path, fields, None, None))
     }
     fn expr_struct_ident(&self, span: Span,
                          id: ast::Ident, fields: Vec<ast::Field>) -> P<ast::Expr> {
@@ -694,25 +694,26 @@ impl<'a> AstBuilder for ExtCtxt<'a> {
     }
     fn expr_usize(&self, span: Span, i: usize) -> P<ast::Expr> {
         self.expr_lit(span, ast::LitKind::Int(i as u128,
-                                              ast::LitIntType::Unsigned(ast::UintTy::Usize)))
+                                              ast::LitIntType::Unsigned(ast::UintTy::Usize), None))
     }
     fn expr_isize(&self, sp: Span, i: isize) -> P<ast::Expr> {
         if i < 0 {
             let i = (-i) as u128;
             let lit_ty = ast::LitIntType::Signed(ast::IntTy::Isize);
-            let lit = self.expr_lit(sp, ast::LitKind::Int(i, lit_ty));
+            let lit = self.expr_lit(sp, ast::LitKind::Int(i, lit_ty, None));
             self.expr_unary(sp, ast::UnOp::Neg, lit)
         } else {
             self.expr_lit(sp, ast::LitKind::Int(i as u128,
-                                                ast::LitIntType::Signed(ast::IntTy::Isize)))
+                                                ast::LitIntType::Signed(ast::IntTy::Isize), None))
         }
     }
     fn expr_u32(&self, sp: Span, u: u32) -> P<ast::Expr> {
         self.expr_lit(sp, ast::LitKind::Int(u as u128,
-                                            ast::LitIntType::Unsigned(ast::UintTy::U32)))
+                                            ast::LitIntType::Unsigned(ast::UintTy::U32), None))
     }
     fn expr_u8(&self, sp: Span, u: u8) -> P<ast::Expr> {
-        self.expr_lit(sp, ast::LitKind::Int(u as u128, ast::LitIntType::Unsigned(ast::UintTy::U8)))
+        self.expr_lit(sp, ast::LitKind::Int(u as u128,
+                                            ast::LitIntType::Unsigned(ast::UintTy::U8), None))
     }
     fn expr_bool(&self, sp: Span, value: bool) -> P<ast::Expr> {
         self.expr_lit(sp, ast::LitKind::Bool(value))
@@ -755,7 +756,7 @@ impl<'a> AstBuilder for ExtCtxt<'a> {
 
 
     fn expr_tuple(&self, sp: Span, exprs: Vec<P<ast::Expr>>) -> P<ast::Expr> {
-        self.expr(sp, ast::ExprKind::Tup(exprs))
+        self.expr(sp, ast::ExprKind::Tup(exprs, None))
     }
 
     fn expr_fail(&self, span: Span, msg: Symbol) -> P<ast::Expr> {
@@ -884,6 +885,7 @@ impl<'a> AstBuilder for ExtCtxt<'a> {
             guard: None,
             body: expr,
             beginning_vert: None,
+            trailing_comma: None,
         }
     }
 
@@ -915,7 +917,7 @@ impl<'a> AstBuilder for ExtCtxt<'a> {
                                                ast::Movability::Movable,
                                                fn_decl,
                                                body,
-                                               fn_decl_span))
+                                               ast::ClosureSpans::from_args(fn_decl_span)))
     }
 
     fn lambda(&self,
@@ -935,7 +937,7 @@ impl<'a> AstBuilder for ExtCtxt<'a> {
                                                ast::Movability::Movable,
                                                fn_decl,
                                                body,
-                                               span))
+                                               ast::ClosureSpans::from_args(span)))
     }
 
     fn lambda0(&self, span: Span, body: P<ast::Expr>) -> P<ast::Expr> {
@@ -1190,7 +1192,7 @@ impl<'a> AstBuilder for ExtCtxt<'a> {
         self.item_use(sp, vis, P(ast::UseTree {
             span: sp,
             prefix: self.path(sp, path),
-            kind: ast::UseTreeKind::Nested(imports),
+            kind: ast::UseTreeKind::Nested(imports, None),
         }))
     }
 
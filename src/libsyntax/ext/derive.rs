@@ -13,7 +13,8 @@ use ast;
 use codemap::{ExpnInfo, NameAndSpan, ExpnFormat};
 use ext::base::ExtCtxt;
 use ext::build::AstBuilder;
-use parse::parser::PathStyle;
+use parse::{token, ParseSess, PResult};
+use parse::parser::{Parser, PathStyle};
 use symbol::Symbol;
 use syntax_pos::Span;
 
@@ -26,14 +27,13 @@ pub fn collect_derives(cx: &mut ExtCtxt, attrs: &mut Vec<ast::Attribute>) -> Vec
             return true;
         }
 
-        match attr.parse_list(cx.parse_sess,
-                              |parser| parser.parse_path_allowing_meta(PathStyle::Mod)) {
+        match parse_derive_list(cx.parse_sess, attr) {
             Ok(ref traits) if traits.is_empty() => {
                 cx.span_warn(attr.span, "empty trait list in `derive`");
                 false
             }
             Ok(traits) => {
-                result.extend(traits);
+                result.extend(traits.into_iter().map(|(path, _)| path));
                 true
             }
             Err(mut e) => {
@@ -45,6 +45,100 @@ pub fn collect_derives(cx: &mut ExtCtxt, attrs: &mut Vec<ast::Attribute>) -> Vec
     result
 }
 
+/// Returns the traits listed in all `#[derive(...)]` attributes in `attrs`,
+/// each with the span of its entry in the list. Errors are reported to `sess`.
+pub fn derive_traits(sess: &ParseSess, attrs: &[ast::Attribute]) -> Vec<(ast::Path, Span)> {
+    let mut result = Vec::new();
+    for attr in attrs.iter().filter(|attr| attr.path == "derive") {
+        match parse_derive_list(sess, attr) {
+            Ok(traits) => result.extend(traits),
+            Err(mut e) => e.emit(),
+        }
+    }
+    result
+}
+
+/// Parses the trait list of a `#[derive(...)]` attribute, returning each
+/// trait with the span of its entry in the list. `#[derive]` and `#[derive()]`
+/// give an empty list.
+///
+/// Some malformed entries are reported and recovered from, so that the rest
+/// of the list is still available: string literals (`"Debug"`), generic
+/// arguments (`Foo<T>`) and arguments (`Foo(x)` or `Foo = "x"`).
+pub fn parse_derive_list<'a>(sess: &'a ParseSess, attr: &ast::Attribute)
+                             -> PResult<'a, Vec<(ast::Path, Span)>> {
+    attr.parse_list(sess, |parser| {
+        let lo = parser.span;
+        let path = parse_derive_trait(parser)?;
+        Ok((path, lo.to(parser.prev_span)))
+    })
+}
+
+fn parse_derive_trait<'a>(parser: &mut Parser<'a>) -> PResult<'a, ast::Path> {
+    let path = match parser.token {
+        token::Literal(token::Str_(s), None) | token::Literal(token::StrRaw(s, _), None) => {
+            let span = parser.span;
+            let path = path_from_str(&s.as_str(), span);
+            let mut err = parser.diagnostic()
+                .struct_span_err(span, "expected a trait path, found a string literal");
+            match path {
+                Some(path) => {
+                    err.span_suggestion(span, "remove the quotes", path.to_string());
+                    err.emit();
+                    parser.bump();
+                    path
+                }
+                None => return Err(err),
+            }
+        }
+        _ => parser.parse_path_allowing_meta(PathStyle::Mod)?,
+    };
+
+    if parser.token == token::Lt {
+        let lo = parser.span;
+        let mut depth = 0;
+        while parser.token != token::Eof {
+            match parser.token {
+                token::Lt => depth += 1,
+                token::Gt => depth -= 1,
+                token::BinOp(token::Shl) => depth += 2,
+                token::BinOp(token::Shr) => depth -= 2,
+                _ => {}
+            }
+            parser.bump();
+            if depth <= 0 {
+                break;
+            }
+        }
+        parser.diagnostic()
+            .span_err(lo.to(parser.prev_span), "generic arguments are not allowed in `derive`");
+    }
+
+    if parser.token == token::OpenDelim(token::Paren) || parser.token == token::Eq {
+        let lo = parser.span;
+        parser.eat(&token::Eq);
+        parser.parse_token_tree();
+        parser.diagnostic()
+            .span_err(lo.to(parser.prev_span), "traits in `derive` cannot have arguments");
+    }
+
+    Ok(path)
+}
+
+/// Parses the contents of a string literal like `"fmt::Debug"` as a path.
+fn path_from_str(s: &str, span: Span) -> Option<ast::Path> {
+    let mut segments = Vec::new();
+    for segment in s.split("::") {
+        let valid = segment.chars().next().map_or(false, |c| c == '_' || c.is_alphabetic()) &&
+                    segment.chars().all(|c| c == '_' || c.is_alphanumeric());
+        if !valid {
+            return None;
+        }
+        segments.push(ast::PathSegment::from_ident(ast::Ident::from_str(segment), span));
+    }
+    Some(ast::Path { span, segments })
+}
+
 pub fn add_derived_markers<T>(cx: &mut ExtCtxt, span: Span, traits: &[ast::Path], item: T) -> T
     where T: HasAttrs,
 {
@@ -81,3 +175,35 @@ pub fn add_derived_markers<T>(cx: &mut ExtCtxt, span: Span, traits: &[ast::Path]
         attrs
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codemap::FilePathMapping;
+    use parse;
+    use std::path::PathBuf;
+
+    fn derives(src: &str) -> (Vec<String>, usize) {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let item = parse::parse_item_from_source_str(PathBuf::from("derive.rs").into(),
+                                                     src.to_string(), &sess).unwrap().unwrap();
+        let traits = derive_traits(&sess, &item.attrs);
+        let names = traits.iter().map(|&(ref path, _)| path.to_string()).collect();
+        (names, sess.span_diagnostic.err_count())
+    }
+
+    #[test]
+    fn derive_lists() {
+        assert_eq!(derives("#[derive(Clone, fmt::Debug,)] #[derive(Eq)] struct S;"),
+                   (vec!["Clone".to_string(), "fmt::Debug".to_string(), "Eq".to_string()], 0));
+        assert_eq!(derives("#[derive] #[derive()] struct S;"), (vec![], 0));
+    }
+
+    #[test]
+    fn recovers_from_malformed_entries() {
+        let (names, errors) =
+            derives("#[derive(Foo<T, Vec<u8>>, \"Bar\", Baz(x), Qux = \"y\", Copy)] struct S;");
+        assert_eq!(names, ["Foo", "Bar", "Baz", "Qux", "Copy"]);
+        assert_eq!(errors, 4);
+    }
+}
@@ -9,7 +9,7 @@
 // except according to those terms.
 
 use ast::{self, Block, Ident, NodeId, PatKind, Path};
-use ast::{MacStmtStyle, StmtKind, ItemKind};
+use ast::{HasSpan, MacStmtStyle, StmtKind, ItemKind};
 use attr::{self, HasAttrs};
 use codemap::{ExpnInfo, NameAndSpan, MacroBang, MacroAttribute, dummy_spanned};
 use config::{is_test_or_bench, StripUnconfigured};
@@ -848,7 +848,7 @@ impl<'a, 'b> InvocationCollector<'a, 'b> {
         (attr, traits, item)
     }
 
-    fn configure<T: HasAttrs>(&mut self, node: T) -> Option<T> {
+    fn configure<T: HasAttrs + HasSpan>(&mut self, node: T) -> Option<T> {
         self.cfg.configure(node)
     }
 
@@ -1083,7 +1083,18 @@ impl<'a, 'b> Folder for InvocationCollector<'a, 'b> {
     }
 
     fn fold_foreign_mod(&mut self, foreign_mod: ast::ForeignMod) -> ast::ForeignMod {
-        noop_fold_foreign_mod(self.cfg.configure_foreign_mod(foreign_mod), self)
+        let mut foreign_mod = self.cfg.configure_foreign_mod(foreign_mod);
+        // FIXME: expand macro invocations in foreign modules. Until then they
+        // are only kept in the AST for tools working on unexpanded crates.
+        foreign_mod.items.retain(|item| match item.node {
+            ast::ForeignItemKind::Macro(_) => {
+                self.cx.span_err(item.span,
+                                 "macro invocations in `extern` blocks are not supported yet");
+                false
+            }
+            _ => true,
+        });
+        noop_fold_foreign_mod(foreign_mod, self)
     }
 
     fn fold_item_kind(&mut self, item: ast::ItemKind) -> ast::ItemKind {
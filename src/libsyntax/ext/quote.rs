@@ -297,7 +297,7 @@ pub mod rt {
                     } else {
                         *self
                     };
-                    let lit = ast::LitKind::Int(val as u128, ast::LitIntType::Signed($tag));
+                    let lit = ast::LitKind::Int(val as u128, ast::LitIntType::Signed($tag), None);
                     let lit = P(ast::Expr {
                         id: ast::DUMMY_NODE_ID,
                         node: ast::ExprKind::Lit(P(dummy_spanned(lit))),
@@ -319,7 +319,9 @@ pub mod rt {
         (unsigned, $t:ty, $tag:expr) => (
             impl ToTokens for $t {
                 fn to_tokens(&self, cx: &ExtCtxt) -> Vec<TokenTree> {
-                    let lit = ast::LitKind::Int(*self as u128, ast::LitIntType::Unsigned($tag));
+                    let lit = ast::LitKind::Int(*self as u128,
+                                                ast::LitIntType::Unsigned($tag),
+                                                None);
                     dummy_spanned(lit).to_tokens(cx)
                 }
             }
@@ -711,7 +713,7 @@ fn expr_mk_token(cx: &ExtCtxt, sp: Span, tok: &token::Token) -> P<ast::Expr> {
         token::Underscore   => "Underscore",
         token::Eof          => "Eof",
 
-        token::Whitespace | token::Comment | token::Shebang(_) => {
+        token::Whitespace | token::Comment | token::Shebang(_) | token::Placeholder(_) => {
             panic!("unhandled token in quote!");
         }
     };
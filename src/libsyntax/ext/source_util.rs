@@ -22,8 +22,9 @@ use tokenstream;
 use util::small_vector::SmallVector;
 
 use std::fs::File;
+use std::io;
 use std::io::prelude::*;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
 // These macros all relate to the file system; they either return
@@ -101,7 +102,17 @@ pub fn expand_include<'cx>(cx: &'cx mut ExtCtxt, sp: Span, tts: &[tokenstream::T
     // The file will be added to the code map by the parser
     let path = res_rel_file(cx, sp, file);
     let directory_ownership = DirectoryOwnership::Owned { relative: None };
-    let p = parse::new_sub_parser_from_file(cx.parse_sess(), &path, directory_ownership, None, sp);
+    let p = match cx.parse_sess.included_source(&path) {
+        Some(src) => {
+            let filemap = cx.codemap().new_filemap(path.into(), src);
+            let mut p = panictry!(parse::try_filemap_to_parser(cx.parse_sess, filemap));
+            p.directory.ownership = directory_ownership;
+            p
+        }
+        None => {
+            parse::new_sub_parser_from_file(cx.parse_sess(), &path, directory_ownership, None, sp)
+        }
+    };
 
     struct ExpandResult<'a> {
         p: parse::parser::Parser<'a>,
@@ -137,9 +148,8 @@ pub fn expand_include_str(cx: &mut ExtCtxt, sp: Span, tts: &[tokenstream::TokenT
         None => return DummyResult::expr(sp)
     };
     let file = res_rel_file(cx, sp, file);
-    let mut bytes = Vec::new();
-    match File::open(&file).and_then(|mut f| f.read_to_end(&mut bytes)) {
-        Ok(..) => {}
+    let bytes = match read_included_file(cx, &file) {
+        Ok(bytes) => bytes,
         Err(e) => {
             cx.span_err(sp,
                         &format!("couldn't read {}: {}",
@@ -172,23 +182,33 @@ pub fn expand_include_bytes(cx: &mut ExtCtxt, sp: Span, tts: &[tokenstream::Toke
         None => return DummyResult::expr(sp)
     };
     let file = res_rel_file(cx, sp, file);
-    let mut bytes = Vec::new();
-    match File::open(&file).and_then(|mut f| f.read_to_end(&mut bytes)) {
+    match read_included_file(cx, &file) {
         Err(e) => {
             cx.span_err(sp,
                         &format!("couldn't read {}: {}", file.display(), e));
             DummyResult::expr(sp)
         }
-        Ok(..) => {
+        Ok(bytes) => {
             // Add this input file to the code map to make it available as
             // dependency information, but don't enter it's contents
             cx.codemap().new_filemap_and_lines(&file, "");
 
-            base::MacEager::expr(cx.expr_lit(sp, ast::LitKind::ByteStr(Rc::new(bytes))))
+            base::MacEager::expr(cx.expr_lit(sp, ast::LitKind::ByteStr(Rc::from(bytes))))
         }
     }
 }
 
+// read an included file, or take the contents the session's include hook
+// supplied for it
+fn read_included_file(cx: &ExtCtxt, file: &Path) -> io::Result<Vec<u8>> {
+    if let Some(src) = cx.parse_sess.included_source(file) {
+        return Ok(src.into_bytes());
+    }
+    let mut bytes = Vec::new();
+    File::open(file)?.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
 // resolve a file-system path to an absolute file-system path (if it
 // isn't already)
 fn res_rel_file(cx: &mut ExtCtxt, sp: syntax_pos::Span, arg: String) -> PathBuf {
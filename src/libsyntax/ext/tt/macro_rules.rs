@@ -19,10 +19,11 @@ use ext::tt::macro_parser::{parse, parse_failure_msg};
 use ext::tt::quoted;
 use ext::tt::transcribe::transcribe;
 use feature_gate::{self, emit_feature_err, Features, GateIssue};
-use parse::{Directory, ParseSess};
+use parse::{Directory, ParseSess, PResult};
 use parse::parser::Parser;
 use parse::token::{self, NtTT};
 use parse::token::Token::*;
+use print::pprust;
 use symbol::Symbol;
 use tokenstream::{TokenStream, TokenTree};
 
@@ -292,6 +293,108 @@ pub fn compile(sess: &ParseSess, features: &RefCell<Features>, def: &ast::Item)
     }
 }
 
+/// One `matcher => transcriber` rule of a macro definition.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MacroRule {
+    /// The matcher, including its outer delimiters.
+    pub lhs: quoted::TokenTree,
+    /// The transcriber, including its outer delimiters.
+    pub rhs: quoted::TokenTree,
+}
+
+impl MacroRule {
+    /// Returns the metavariables declared by the matcher together with their
+    /// fragment specifiers, in order of appearance. Metavariables inside
+    /// repetitions are included.
+    pub fn fragments(&self) -> Vec<(ast::Ident, ast::Ident)> {
+        fn collect(tt: &quoted::TokenTree, fragments: &mut Vec<(ast::Ident, ast::Ident)>) {
+            match *tt {
+                quoted::TokenTree::MetaVarDecl(_, name, kind) => fragments.push((name, kind)),
+                quoted::TokenTree::Delimited(_, ref delimited) => {
+                    for tt in &delimited.tts {
+                        collect(tt, fragments);
+                    }
+                }
+                quoted::TokenTree::Sequence(_, ref seq) => {
+                    for tt in &seq.tts {
+                        collect(tt, fragments);
+                    }
+                }
+                quoted::TokenTree::Token(..) | quoted::TokenTree::MetaVar(..) => {}
+            }
+        }
+
+        let mut fragments = Vec::new();
+        collect(&self.lhs, &mut fragments);
+        fragments
+    }
+}
+
+/// Parses the body of the macro definition `def` into its rules.
+///
+/// Unlike `compile`, this does not check the matchers against the follow-set
+/// rules or feature gates, and reports a malformed body as an error rather
+/// than aborting, so it can be used by tools that only want to describe a
+/// macro's syntax. Problems within individual matchers and transcribers are
+/// reported to `sess` as they would be during compilation.
+pub fn parse_rules<'a>(sess: &'a ParseSess, def: &ast::Item) -> PResult<'a, Vec<MacroRule>> {
+    let body = match def.node {
+        ast::ItemKind::MacroDef(ref body) => body,
+        _ => {
+            return Err(sess.span_diagnostic.struct_span_err(def.span,
+                                                            "expected a macro definition"));
+        }
+    };
+    let separator = if body.legacy { token::Semi } else { token::Comma };
+
+    let mut rules = Vec::new();
+    let mut trees = body.stream().trees();
+    loop {
+        let lhs = match trees.next() {
+            Some(tt @ TokenTree::Delimited(..)) => tt,
+            Some(tt) => {
+                return Err(sess.span_diagnostic.struct_span_err(tt.span(),
+                    "invalid macro matcher; matchers must be contained in balanced delimiters"));
+            }
+            None if rules.is_empty() => {
+                return Err(sess.span_diagnostic.struct_span_err(def.span,
+                                                                "macro definition has no rules"));
+            }
+            None => break,
+        };
+        match trees.next() {
+            Some(TokenTree::Token(_, FatArrow)) => {}
+            tt => {
+                let span = tt.map_or(lhs.span(), |tt| tt.span());
+                return Err(sess.span_diagnostic.struct_span_err(span, "expected `=>`"));
+            }
+        }
+        let rhs = match trees.next() {
+            Some(tt @ TokenTree::Delimited(..)) => tt,
+            tt => {
+                let span = tt.map_or(lhs.span(), |tt| tt.span());
+                return Err(sess.span_diagnostic.struct_span_err(span,
+                    "macro rhs must be delimited"));
+            }
+        };
+
+        rules.push(MacroRule {
+            lhs: quoted::parse(lhs.into(), true, sess).pop().unwrap(),
+            rhs: quoted::parse(rhs.into(), false, sess).pop().unwrap(),
+        });
+
+        match trees.next() {
+            Some(TokenTree::Token(_, ref tok)) if *tok == separator || *tok == Semi => {}
+            Some(tt) => {
+                let msg = format!("expected `{}`", pprust::token_to_string(&separator));
+                return Err(sess.span_diagnostic.struct_span_err(tt.span(), &msg));
+            }
+            None => break,
+        }
+    }
+    Ok(rules)
+}
+
 fn check_lhs_nt_follows(sess: &ParseSess,
                         features: &RefCell<Features>,
                         attrs: &[ast::Attribute],
@@ -925,3 +1028,40 @@ fn quoted_tt_to_string(tt: &quoted::TokenTree) -> String {
                      in follow set checker"),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use util::parser_testing::string_to_item;
+    use codemap::FilePathMapping;
+
+    #[test]
+    fn rules_and_fragments() {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let item = string_to_item("macro_rules! m { \
+                                     () => {}; \
+                                     ($a:expr, $($b:ident),* ; $c:tt) => { $a } \
+                                   }".to_string()).unwrap();
+        let rules = panictry!(parse_rules(&sess, &item));
+        assert_eq!(rules.len(), 2);
+        assert!(rules[0].fragments().is_empty());
+
+        let fragments = rules[1].fragments().into_iter().map(|(name, kind)| {
+            (name.to_string(), kind.to_string())
+        }).collect::<Vec<_>>();
+        assert_eq!(fragments, vec![("a".to_string(), "expr".to_string()),
+                                   ("b".to_string(), "ident".to_string()),
+                                   ("c".to_string(), "tt".to_string())]);
+
+        match rules[1].lhs {
+            quoted::TokenTree::Delimited(_, ref delimited) => match delimited.tts[2] {
+                quoted::TokenTree::Sequence(_, ref seq) => {
+                    assert_eq!(seq.separator, Some(token::Comma));
+                    assert_eq!(seq.op, quoted::KleeneOp::ZeroOrMore);
+                }
+                ref tt => panic!("expected a repetition, found {:?}", tt),
+            },
+            ref tt => panic!("expected a delimited matcher, found {:?}", tt),
+        }
+    }
+}
@@ -452,6 +452,10 @@ declare_features! (
 
     // Allows `#[repr(transparent)]` attribute on newtype structs
     (active, repr_transparent, "1.25.0", Some(43036)),
+
+    // Allows `#![syntax_variant("...")]`, selecting the item extensions registered
+    // for a syntax variant by tools embedding the parser.
+    (active, syntax_variant, "1.25.0", None),
 );
 
 declare_features! (
@@ -993,6 +997,14 @@ pub const BUILTIN_ATTRIBUTES: &'static [(&'static str, AttributeType, AttributeG
     ("no_builtins", CrateLevel, Ungated),
     ("recursion_limit", CrateLevel, Ungated),
     ("type_length_limit", CrateLevel, Ungated),
+
+    // Read by the parser, which enables the item extensions registered for the
+    // variant with `ParseSess::register_variant_item_extension`. The variant is
+    // chosen per file, so the attribute is also used in module files.
+    ("syntax_variant", Whitelisted, Gated(Stability::Unstable,
+                                          "syntax_variant",
+                                          "the `#[syntax_variant]` attribute is experimental",
+                                          cfg_fn!(syntax_variant))),
 ];
 
 // cfg(...)'s that are feature gated
@@ -1125,6 +1137,24 @@ pub fn find_lang_feature_accepted_version(feature: &str) -> Option<&'static str>
     ACCEPTED_FEATURES.iter().find(|t| t.0 == feature).map(|t| t.1)
 }
 
+/// The names of the unstable language features, with the version they were
+/// added in and their tracking issue.
+pub fn active_lang_features() -> Vec<(&'static str, &'static str, Option<u32>)> {
+    ACTIVE_FEATURES.iter().map(|&(name, since, issue, _)| (name, since, issue)).collect()
+}
+
+/// The names of the language features which have been stabilized, with the
+/// version they were stabilized in and their tracking issue.
+pub fn accepted_lang_features() -> Vec<(&'static str, &'static str, Option<u32>)> {
+    ACCEPTED_FEATURES.to_vec()
+}
+
+/// Whether `name` is a language feature, unstable or stabilized.
+pub fn is_lang_feature(name: &str) -> bool {
+    ACTIVE_FEATURES.iter().any(|&(feature, ..)| feature == name) ||
+    ACCEPTED_FEATURES.iter().any(|&(feature, ..)| feature == name)
+}
+
 fn find_lang_feature_issue(feature: &str) -> Option<u32> {
     if let Some(info) = ACTIVE_FEATURES.iter().find(|t| t.0 == feature) {
         let issue = info.2;
@@ -1606,6 +1636,7 @@ impl<'a> Visitor<'a> for PostExpansionVisitor<'a> {
                     gate_feature_post!(&self, extern_types, i.span,
                                        "extern types are experimental");
             }
+            ast::ForeignItemKind::Macro(..) => {}
         }
 
         visit::walk_foreign_item(self, i)
@@ -1660,7 +1691,7 @@ impl<'a> Visitor<'a> for PostExpansionVisitor<'a> {
                                   "yield syntax is experimental");
             }
             ast::ExprKind::Lit(ref lit) => {
-                if let ast::LitKind::Int(_, ref ty) = lit.node {
+                if let ast::LitKind::Int(_, ref ty, _) = lit.node {
                     match *ty {
                         ast::LitIntType::Signed(ast::IntTy::I128) |
                         ast::LitIntType::Unsigned(ast::UintTy::U128) => {
@@ -1821,7 +1852,7 @@ impl<'a> Visitor<'a> for PostExpansionVisitor<'a> {
                     gate_feature_post!(&self, use_nested_groups, use_tree.span,
                                        "glob imports in `use` groups are experimental");
                 }
-                ast::UseTreeKind::Nested(_) => {
+                ast::UseTreeKind::Nested(..) => {
                     gate_feature_post!(&self, use_nested_groups, use_tree.span,
                                        "nested groups in `use` are experimental");
                 }
@@ -1845,6 +1876,8 @@ impl<'a> Visitor<'a> for PostExpansionVisitor<'a> {
                 (&ld.attrs, "attributes on lifetime bindings are experimental"),
             ast::GenericParam::Type(ref t) =>
                 (&t.attrs, "attributes on type parameter bindings are experimental"),
+            ast::GenericParam::Const(ref c) =>
+                (&c.attrs, "attributes on const parameter bindings are experimental"),
         };
 
         if !attrs.is_empty() {
@@ -325,9 +325,9 @@ pub fn noop_fold_use_tree<T: Folder>(use_tree: UseTree, fld: &mut T) -> UseTree
         kind: match use_tree.kind {
             UseTreeKind::Simple(ident) => UseTreeKind::Simple(fld.fold_ident(ident)),
             UseTreeKind::Glob => UseTreeKind::Glob,
-            UseTreeKind::Nested(items) => UseTreeKind::Nested(items.move_map(|(tree, id)| {
+            UseTreeKind::Nested(items, comma) => UseTreeKind::Nested(items.move_map(|(tree, id)| {
                 (fld.fold_use_tree(tree), fld.new_id(id))
-            })),
+            }), comma.map(|sp| fld.new_span(sp))),
         },
     }
 }
@@ -340,7 +340,7 @@ pub fn fold_thin_attrs<T: Folder>(attrs: ThinVec<Attribute>, fld: &mut T) -> Thi
     fold_attrs(attrs.into(), fld).into()
 }
 
-pub fn noop_fold_arm<T: Folder>(Arm {attrs, pats, guard, body, beginning_vert}: Arm,
+pub fn noop_fold_arm<T: Folder>(Arm {attrs, pats, guard, body, beginning_vert, trailing_comma}: Arm,
     fld: &mut T) -> Arm {
     Arm {
         attrs: fold_attrs(attrs, fld),
@@ -348,6 +348,7 @@ pub fn noop_fold_arm<T: Folder>(Arm {attrs, pats, guard, body, beginning_vert}:
         guard: guard.map(|x| fld.fold_expr(x)),
         body: fld.fold_expr(body),
         beginning_vert,
+        trailing_comma: trailing_comma.map(|sp| fld.new_span(sp)),
     }
 }
 
@@ -686,10 +687,26 @@ pub fn noop_fold_ty_param<T: Folder>(tp: TyParam, fld: &mut T) -> TyParam {
     }
 }
 
+pub fn noop_fold_const_param<T: Folder>(param: ConstParam, fld: &mut T) -> ConstParam {
+    let ConstParam {attrs, id, ident, ty, span} = param;
+    let attrs: Vec<_> = attrs.into();
+    ConstParam {
+        attrs: attrs.into_iter()
+            .flat_map(|x| fld.fold_attribute(x).into_iter())
+            .collect::<Vec<_>>()
+            .into(),
+        id: fld.new_id(id),
+        ident: fld.fold_ident(ident),
+        ty: fld.fold_ty(ty),
+        span: fld.new_span(span),
+    }
+}
+
 pub fn noop_fold_generic_param<T: Folder>(param: GenericParam, fld: &mut T) -> GenericParam {
     match param {
         GenericParam::Lifetime(l) => GenericParam::Lifetime(fld.fold_lifetime_def(l)),
         GenericParam::Type(t) => GenericParam::Type(fld.fold_ty_param(t)),
+        GenericParam::Const(c) => GenericParam::Const(noop_fold_const_param(c, fld)),
     }
 }
 
@@ -949,6 +966,8 @@ pub fn noop_fold_item_kind<T: Folder>(i: ItemKind, folder: &mut T) -> ItemKind {
             folder.fold_bounds(bounds)),
         ItemKind::Mac(m) => ItemKind::Mac(folder.fold_mac(m)),
         ItemKind::MacroDef(def) => ItemKind::MacroDef(folder.fold_macro_def(def)),
+        ItemKind::Extension(ext) => ItemKind::Extension(ext),
+        ItemKind::Err => ItemKind::Err,
     }
 }
 
@@ -1085,6 +1104,7 @@ pub fn noop_fold_foreign_item<T: Folder>(ni: ForeignItem, folder: &mut T) -> For
                 ForeignItemKind::Static(folder.fold_ty(t), m)
             }
             ForeignItemKind::Ty => ForeignItemKind::Ty,
+            ForeignItemKind::Macro(mac) => ForeignItemKind::Macro(folder.fold_mac(mac)),
         },
         span: folder.new_span(ni.span)
     }
@@ -1149,7 +1169,8 @@ pub fn noop_fold_pat<T: Folder>(p: P<Pat>, folder: &mut T) -> P<Pat> {
                        slice.map(|x| folder.fold_pat(x)),
                        after.move_map(|x| folder.fold_pat(x)))
             }
-            PatKind::Mac(mac) => PatKind::Mac(folder.fold_mac(mac))
+            PatKind::Mac(mac) => PatKind::Mac(folder.fold_mac(mac)),
+            PatKind::Err => PatKind::Err,
         },
         span: folder.new_span(span)
     })
@@ -1174,10 +1195,13 @@ pub fn noop_fold_expr<T: Folder>(Expr {id, node, span, attrs}: Expr, folder: &mu
             ExprKind::Repeat(expr, count) => {
                 ExprKind::Repeat(folder.fold_expr(expr), folder.fold_expr(count))
             }
-            ExprKind::Tup(exprs) => ExprKind::Tup(folder.fold_exprs(exprs)),
-            ExprKind::Call(f, args) => {
+            ExprKind::Tup(exprs, comma) => {
+                ExprKind::Tup(folder.fold_exprs(exprs), comma.map(|sp| folder.new_span(sp)))
+            }
+            ExprKind::Call(f, args, comma) => {
                 ExprKind::Call(folder.fold_expr(f),
-                         folder.fold_exprs(args))
+                         folder.fold_exprs(args),
+                         comma.map(|sp| folder.new_span(sp)))
             }
             ExprKind::MethodCall(seg, args) => {
                 ExprKind::MethodCall(
@@ -1242,12 +1266,19 @@ pub fn noop_fold_expr<T: Folder>(Expr {id, node, span, attrs}: Expr, folder: &mu
                 ExprKind::Match(folder.fold_expr(expr),
                           arms.move_map(|x| folder.fold_arm(x)))
             }
-            ExprKind::Closure(capture_clause, movability, decl, body, span) => {
+            ExprKind::Closure(capture_clause, movability, decl, body, spans) => {
+                let spans = ClosureSpans {
+                    decl: folder.new_span(spans.decl),
+                    static_kw: spans.static_kw.map(|span| folder.new_span(span)),
+                    move_kw: spans.move_kw.map(|span| folder.new_span(span)),
+                    args: folder.new_span(spans.args),
+                    output: spans.output.map(|span| folder.new_span(span)),
+                };
                 ExprKind::Closure(capture_clause,
                                   movability,
                                   folder.fold_fn_decl(decl),
                                   folder.fold_expr(body),
-                                  folder.new_span(span))
+                                  spans)
             }
             ExprKind::Block(blk) => ExprKind::Block(folder.fold_block(blk)),
             ExprKind::Assign(el, er) => {
@@ -1310,10 +1341,11 @@ pub fn noop_fold_expr<T: Folder>(Expr {id, node, span, attrs}: Expr, folder: &mu
                 }
             })),
             ExprKind::Mac(mac) => ExprKind::Mac(folder.fold_mac(mac)),
-            ExprKind::Struct(path, fields, maybe_expr) => {
+            ExprKind::Struct(path, fields, maybe_expr, comma) => {
                 ExprKind::Struct(folder.fold_path(path),
                         fields.move_map(|x| folder.fold_field(x)),
-                        maybe_expr.map(|x| folder.fold_expr(x)))
+                        maybe_expr.map(|x| folder.fold_expr(x)),
+                        comma.map(|sp| folder.new_span(sp)))
             },
             ExprKind::Paren(ex) => {
                 let sub_expr = folder.fold_expr(ex);
@@ -1328,6 +1360,8 @@ pub fn noop_fold_expr<T: Folder>(Expr {id, node, span, attrs}: Expr, folder: &mu
             ExprKind::Yield(ex) => ExprKind::Yield(ex.map(|x| folder.fold_expr(x))),
             ExprKind::Try(ex) => ExprKind::Try(folder.fold_expr(ex)),
             ExprKind::Catch(body) => ExprKind::Catch(folder.fold_block(body)),
+            ExprKind::Err => ExprKind::Err,
+            ExprKind::Unparsed(tts) => ExprKind::Unparsed(folder.fold_tts(tts.into()).into()),
         },
         id: folder.new_id(id),
         span: folder.new_span(span),
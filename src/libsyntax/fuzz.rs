@@ -0,0 +1,99 @@
+// Copyright 2018 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! An entry point for fuzzing the lexer and the parser, e.g. with cargo-fuzz:
+//!
+//! ```text
+//! fuzz_target!(|data: &[u8]| { syntax::fuzz::parse_arbitrary(data); });
+//! ```
+//!
+//! Fatal errors unwind, so the fuzz target must be built with
+//! `-C panic=unwind`. Any other panic, and any input which does not
+//! terminate, is a bug.
+
+use ast;
+use codemap::{CodeMap, FilePathMapping};
+use errors::{DiagnosticBuilder, FatalErrorMarker, Handler};
+use errors::emitter::Emitter;
+use parse::{self, ParseSess};
+use syntax_pos::FileName;
+
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+
+/// Discards all diagnostics.
+struct SilentEmitter;
+
+impl Emitter for SilentEmitter {
+    fn emit(&mut self, _db: &DiagnosticBuilder) {}
+}
+
+/// Lexes and parses `data` as a crate, with all the error recovery of the
+/// parser enabled, and returns the crate unless there was a fatal error.
+/// Invalid UTF-8 is replaced by `U+FFFD`, which is lexed as a placeholder.
+/// Diagnostics are discarded and out-of-line modules are not loaded, so the
+/// result only depends on `data`.
+pub fn parse_arbitrary(data: &[u8]) -> Option<ast::Crate> {
+    let src = String::from_utf8_lossy(data).into_owned();
+    let cm = Rc::new(CodeMap::new(FilePathMapping::empty()));
+    let handler = Handler::with_emitter(true, false, Box::new(SilentEmitter));
+    let mut sess = ParseSess::with_span_handler(handler, cm);
+    sess.preserve_unknown_suffixes = true;
+    sess.placeholder_chars = vec!['\u{FFFD}'];
+    sess.label_enclosing_constructs = true;
+    sess.const_generics = true;
+    sess.skip_control_chars = true;
+
+    let name = FileName::Custom("fuzz".to_string());
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        match parse::parse_crate_from_source_str(name, src, &sess) {
+            Ok(krate) => Some(krate),
+            Err(mut err) => {
+                err.emit();
+                None
+            }
+        }
+    }));
+    match result {
+        Ok(krate) => krate,
+        Err(payload) => {
+            if !payload.is::<FatalErrorMarker>() {
+                panic::resume_unwind(payload);
+            }
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arbitrary_input() {
+        assert!(parse_arbitrary(b"fn main() { let x = 1; }").is_some());
+
+        let inputs: &[&[u8]] = &[
+            b"",
+            b"fn f() { let x = ",
+            b"fn f(a b c d) { g(1 2 3) }",
+            b"struct S { a: u8 b: u8 } enum E { A B }",
+            b"fn f() { ((((((((( }",
+            b"\xff\xfe fn \xc0() {}",
+            b"\0 fn f() { 1\x07 + 2 }",
+            b"fn f() { \"\r\" '\\q' 1.e 0x }",
+            b"#![x] #[",
+            b"impl<T: A B> X for { fn }",
+        ];
+        for input in inputs {
+            parse_arbitrary(input);
+        }
+    }
+}
@@ -37,6 +37,7 @@ extern crate std_unicode;
 pub extern crate rustc_errors as errors;
 extern crate syntax_pos;
 extern crate rustc_data_structures;
+extern crate fmt_macros;
 
 extern crate serialize as rustc_serialize; // used by deriving
 
@@ -83,13 +84,18 @@ pub mod diagnostics {
 pub mod diagnostic_list;
 
 pub mod util {
+    pub mod item_paths;
     pub mod lev_distance;
     pub mod node_count;
     pub mod parser;
     #[cfg(test)]
     pub mod parser_testing;
     pub mod small_vector;
+    pub mod span_rewrite;
+    pub mod splice;
+    pub mod structural;
     pub mod move_map;
+    pub mod str_lits;
 
     mod thin_vec;
     pub use self::thin_vec::ThinVec;
@@ -112,17 +118,22 @@ pub mod attr;
 pub mod codemap;
 #[macro_use]
 pub mod config;
+pub mod crate_metadata;
 pub mod entry;
 pub mod feature_gate;
 pub mod fold;
+pub mod fuzz;
 pub mod parse;
 pub mod ptr;
 pub mod show_span;
 pub mod std_inject;
 pub mod str;
 pub use syntax_pos::symbol;
+pub use version_info::VERSION_INFO;
 pub mod test;
+pub mod token_dump;
 pub mod tokenstream;
+pub mod version_info;
 pub mod visit;
 
 pub mod print {
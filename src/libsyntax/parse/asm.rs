@@ -0,0 +1,413 @@
+// Copyright 2018 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Structural parsing of the arguments of `asm!` and `global_asm!`.
+//!
+//! The arguments of `asm!(template : outputs : inputs : clobbers : options)`
+//! are parsed into an `AsmArgs` that keeps the span of every part, so that
+//! they can be validated, and assembly templates checked, without expanding
+//! the invocation or involving the compiler backend.
+
+use self::State::*;
+
+use ast::{self, StrStyle};
+use codemap::{respan, Spanned};
+use errors::Handler;
+use parse::{self, token, ParseSess, PResult};
+use parse::parser::Parser;
+use ptr::P;
+use symbol::Symbol;
+use syntax_pos::Span;
+use tokenstream::TokenTree;
+
+use std::ops::Range;
+
+/// The options understood by `asm!`.
+pub const ASM_OPTIONS: &'static [&'static str] = &["volatile", "alignstack", "intel"];
+
+/// The parsed arguments of an `asm!` invocation.
+#[derive(Clone, Debug)]
+pub struct AsmArgs {
+    /// The assembly template. This is usually a string literal, but may be a
+    /// macro invocation that expands to one.
+    pub template: P<ast::Expr>,
+    pub outputs: Vec<AsmOperand>,
+    pub inputs: Vec<AsmOperand>,
+    pub clobbers: Vec<Spanned<Symbol>>,
+    pub options: Vec<Spanned<Symbol>>,
+}
+
+/// An input or output operand, like `"=r"(x)`.
+#[derive(Clone, Debug)]
+pub struct AsmOperand {
+    pub constraint: Spanned<Symbol>,
+    pub expr: P<ast::Expr>,
+}
+
+impl AsmOperand {
+    /// Whether this is a read+write output operand, like `"+r"(x)`.
+    pub fn is_rw(&self) -> bool {
+        self.constraint.node.as_str().starts_with('+')
+    }
+}
+
+/// The parsed arguments of a `global_asm!` invocation.
+#[derive(Clone, Debug)]
+pub struct GlobalAsmArgs {
+    /// The assembly template, see `AsmArgs::template`.
+    pub template: P<ast::Expr>,
+}
+
+/// A reference to an operand in an assembly template, like `$0` or `${1:w}`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AsmOperandRef {
+    /// The byte range of the reference in the template.
+    pub range: Range<usize>,
+    pub index: usize,
+    /// The operand modifier, like `w` in `${1:w}`.
+    pub modifier: Option<String>,
+}
+
+#[derive(Copy, Clone)]
+enum State {
+    Asm,
+    Outputs,
+    Inputs,
+    Clobbers,
+    Options,
+    StateNone,
+}
+
+impl State {
+    fn next(&self) -> State {
+        match *self {
+            Asm => Outputs,
+            Outputs => Inputs,
+            Inputs => Clobbers,
+            Clobbers => Options,
+            Options => StateNone,
+            StateNone => StateNone,
+        }
+    }
+}
+
+/// Parses the arguments of an `asm!` invocation at `sp`. Returns `None` after
+/// reporting an error if they are malformed, so that the caller can continue.
+pub fn parse_asm_args<'a>(sess: &'a ParseSess, sp: Span, tts: &[TokenTree])
+                          -> PResult<'a, Option<AsmArgs>> {
+    // Split the tts before the first colon, to avoid `asm!("x": y)`  being
+    // parsed as `asm!(z)` with `z = "x": y` which is type ascription.
+    let first_colon = tts.iter()
+        .position(|tt| {
+            match *tt {
+                TokenTree::Token(_, token::Colon) |
+                TokenTree::Token(_, token::ModSep) => true,
+                _ => false,
+            }
+        })
+        .unwrap_or(tts.len());
+    let mut p = parse::stream_to_parser(sess, tts[first_colon..].iter().cloned().collect());
+    let mut template = None;
+    let mut outputs = Vec::new();
+    let mut inputs = Vec::new();
+    let mut clobbers = Vec::new();
+    let mut options = Vec::new();
+
+    let mut state = Asm;
+
+    'statement: loop {
+        match state {
+            Asm => {
+                if template.is_some() {
+                    // If we already have a string with instructions,
+                    // ending up in Asm state again is an error.
+                    sess.span_diagnostic.span_err(sp, "malformed inline assembly");
+                    return Ok(None);
+                }
+                // Nested parser, stop before the first colon (see above).
+                let mut p2 = parse::stream_to_parser(sess,
+                                                     tts[..first_colon].iter().cloned().collect());
+                template = Some(p2.parse_expr()?);
+
+                // This is most likely malformed.
+                if p2.token != token::Eof {
+                    let mut extra_tts = p2.parse_all_token_trees()?;
+                    extra_tts.extend(tts[first_colon..].iter().cloned());
+                    p = parse::stream_to_parser(sess, extra_tts.into_iter().collect());
+                }
+            }
+            Outputs => {
+                while p.token != token::Eof && p.token != token::Colon && p.token != token::ModSep {
+                    if !outputs.is_empty() {
+                        p.eat(&token::Comma);
+                    }
+                    outputs.push(parse_operand(&mut p)?);
+                }
+            }
+            Inputs => {
+                while p.token != token::Eof && p.token != token::Colon && p.token != token::ModSep {
+                    if !inputs.is_empty() {
+                        p.eat(&token::Comma);
+                    }
+                    inputs.push(parse_operand(&mut p)?);
+                }
+            }
+            Clobbers => {
+                while p.token != token::Eof && p.token != token::Colon && p.token != token::ModSep {
+                    if !clobbers.is_empty() {
+                        p.eat(&token::Comma);
+                    }
+                    let (s, _str_style) = p.parse_str()?;
+                    clobbers.push(respan(p.prev_span, s));
+                }
+            }
+            Options => {
+                let (option, _str_style) = p.parse_str()?;
+                options.push(respan(p.prev_span, option));
+
+                if p.token == token::Comma {
+                    p.eat(&token::Comma);
+                }
+            }
+            StateNone => (),
+        }
+
+        loop {
+            // MOD_SEP is a double colon '::' without space in between.
+            // When encountered, the state must be advanced twice.
+            match (&p.token, state.next(), state.next().next()) {
+                (&token::Colon, StateNone, _) |
+                (&token::ModSep, _, StateNone) => {
+                    p.bump();
+                    break 'statement;
+                }
+                (&token::Colon, st, _) |
+                (&token::ModSep, _, st) => {
+                    p.bump();
+                    state = st;
+                }
+                (&token::Eof, ..) => break 'statement,
+                _ => break,
+            }
+        }
+    }
+
+    Ok(Some(AsmArgs {
+        template: template.unwrap(),
+        outputs,
+        inputs,
+        clobbers,
+        options,
+    }))
+}
+
+fn parse_operand<'a>(p: &mut Parser<'a>) -> PResult<'a, AsmOperand> {
+    let (constraint, _str_style) = p.parse_str()?;
+    let constraint = respan(p.prev_span, constraint);
+    p.expect(&token::OpenDelim(token::Paren))?;
+    let expr = p.parse_expr()?;
+    p.expect(&token::CloseDelim(token::Paren))?;
+    Ok(AsmOperand { constraint, expr })
+}
+
+/// Parses the arguments of a `global_asm!` invocation.
+pub fn parse_global_asm_args<'a>(sess: &'a ParseSess, tts: &[TokenTree])
+                                 -> PResult<'a, GlobalAsmArgs> {
+    let mut p = parse::stream_to_parser(sess, tts.iter().cloned().collect());
+    let template = p.parse_expr()?;
+    p.expect(&token::Eof)?;
+    Ok(GlobalAsmArgs { template })
+}
+
+impl AsmArgs {
+    /// Returns the template if it is a string literal.
+    pub fn template_str(&self) -> Option<(Symbol, StrStyle)> {
+        template_str(&self.template)
+    }
+
+    /// The number of operands the template can refer to. Each read+write
+    /// output is also passed as an input tied to the output, which comes
+    /// after all other inputs.
+    pub fn operand_count(&self) -> usize {
+        let tied = self.outputs.iter().filter(|out| out.is_rw()).count();
+        self.outputs.len() + self.inputs.len() + tied
+    }
+
+    /// Reports malformed constraints, clobbers and options, and references in
+    /// a string literal template to operands that do not exist.
+    pub fn validate(&self, handler: &Handler) {
+        for output in &self.outputs {
+            let constraint = output.constraint.node.as_str();
+            if !constraint.starts_with('=') && !constraint.starts_with('+') {
+                handler.span_err(output.constraint.span,
+                                 "output operand constraint lacks '=' or '+'");
+            }
+        }
+
+        for input in &self.inputs {
+            let constraint = input.constraint.node.as_str();
+            if constraint.starts_with('=') {
+                handler.span_err(input.constraint.span, "input operand constraint contains '='");
+            } else if constraint.starts_with('+') {
+                handler.span_err(input.constraint.span, "input operand constraint contains '+'");
+            }
+        }
+
+        for clobber in &self.clobbers {
+            let s = clobber.node.as_str();
+            if ASM_OPTIONS.iter().any(|&opt| s == opt) {
+                handler.span_warn(clobber.span, "expected a clobber, found an option");
+            } else if s.starts_with('{') || s.ends_with('}') {
+                handler.span_err(clobber.span, "clobber should not be surrounded by braces");
+            }
+        }
+
+        for option in &self.options {
+            if !ASM_OPTIONS.iter().any(|&opt| option.node == opt) {
+                handler.span_warn(option.span, "unrecognized option");
+            }
+        }
+
+        if let Some((template, _)) = self.template_str() {
+            let count = self.operand_count();
+            for operand in template_operand_refs(&template.as_str()) {
+                if operand.index >= count {
+                    let msg = format!("invalid reference to operand `{}` in inline assembly \
+                                       template", operand.index);
+                    handler.struct_span_err(self.template.span, &msg)
+                        .note(&format!("the assembly has {} operand{}",
+                                       count, if count == 1 { "" } else { "s" }))
+                        .emit();
+                }
+            }
+        }
+    }
+}
+
+impl GlobalAsmArgs {
+    /// Returns the template if it is a string literal.
+    pub fn template_str(&self) -> Option<(Symbol, StrStyle)> {
+        template_str(&self.template)
+    }
+}
+
+fn template_str(expr: &ast::Expr) -> Option<(Symbol, StrStyle)> {
+    match expr.node {
+        ast::ExprKind::Lit(ref lit) => match lit.node {
+            ast::LitKind::Str(s, style) => Some((s, style)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Finds the operand references in an assembly template.
+///
+/// References are written `$N` or `${N:modifier}`. `$$` is an escaped `$`,
+/// and other uses of `$`, like LLVM's `${:uid}`, are not operand references.
+pub fn template_operand_refs(template: &str) -> Vec<AsmOperandRef> {
+    let bytes = template.as_bytes();
+    let mut refs = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'$' {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        i += 1;
+        match bytes.get(i) {
+            Some(&b'$') => i += 1,
+            Some(&b'{') => {
+                let end = match template[i..].find('}') {
+                    Some(end) => i + end,
+                    None => break,
+                };
+                let body = &template[i + 1..end];
+                let (index, modifier) = match body.find(':') {
+                    Some(colon) => (&body[..colon], Some(body[colon + 1..].to_string())),
+                    None => (body, None),
+                };
+                i = end + 1;
+                if let Ok(index) = index.parse() {
+                    refs.push(AsmOperandRef { range: start..i, index, modifier });
+                }
+            }
+            Some(&c) if is_digit(c) => {
+                let digits = bytes[i..].iter().take_while(|&&c| is_digit(c)).count();
+                let index = template[i..i + digits].parse().unwrap_or(usize::max_value());
+                i += digits;
+                refs.push(AsmOperandRef { range: start..i, index, modifier: None });
+            }
+            _ => {}
+        }
+    }
+    refs
+}
+
+fn is_digit(c: u8) -> bool {
+    c >= b'0' && c <= b'9'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codemap::FilePathMapping;
+    use syntax_pos::DUMMY_SP;
+    use util::parser_testing::string_to_stream;
+
+    fn parse(src: &str) -> AsmArgs {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let tts = string_to_stream(src.to_string()).trees().collect::<Vec<_>>();
+        let args = parse_asm_args(&sess, DUMMY_SP, &tts);
+        args.unwrap().unwrap()
+    }
+
+    #[test]
+    fn parses_all_sections() {
+        let args = parse(r#""add $2, $0" : "=r"(x), "+r"(y) : "r"(z) : "cc", "memory"
+                            : "volatile", "intel""#);
+        assert_eq!(args.template_str().unwrap().0, "add $2, $0");
+        let constraints = args.outputs.iter().chain(&args.inputs)
+            .map(|op| op.constraint.node.to_string()).collect::<Vec<_>>();
+        assert_eq!(constraints, ["=r", "+r", "r"]);
+        let clobbers = args.clobbers.iter().map(|c| c.node.to_string()).collect::<Vec<_>>();
+        assert_eq!(clobbers, ["cc", "memory"]);
+        let options = args.options.iter().map(|o| o.node.to_string()).collect::<Vec<_>>();
+        assert_eq!(options, ["volatile", "intel"]);
+        assert_eq!(args.operand_count(), 4);
+    }
+
+    #[test]
+    fn parses_mod_sep_between_sections() {
+        let args = parse(r#""nop" :: "r"(a) :: "volatile""#);
+        assert!(args.outputs.is_empty());
+        assert_eq!(args.inputs.len(), 1);
+        assert!(args.clobbers.is_empty());
+        assert_eq!(args.options.len(), 1);
+    }
+
+    #[test]
+    fn reports_malformed_asm() {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let tts = string_to_stream(r#""nop" "nop""#.to_string()).trees().collect::<Vec<_>>();
+        assert!(parse_asm_args(&sess, DUMMY_SP, &tts).unwrap().is_none());
+        assert_eq!(sess.span_diagnostic.err_count(), 1);
+    }
+
+    #[test]
+    fn finds_operand_refs() {
+        let refs = template_operand_refs("mov $$1, ${12:w} $0 ${:uid} $");
+        assert_eq!(refs, vec![
+            AsmOperandRef { range: 9..16, index: 12, modifier: Some("w".to_string()) },
+            AsmOperandRef { range: 17..19, index: 0, modifier: None },
+        ]);
+    }
+}
@@ -12,14 +12,22 @@
 
 use parse::token;
 
-/// `SeqSep` : a sequence separator (token)
-/// and whether a trailing separator is allowed.
+/// How the items of a sequence parsed with `Parser::parse_seq_to_end` and
+/// its relatives are separated: by a separator token, `,` in most of the
+/// grammar, or not at all. A trailing separator before the closing token may
+/// be allowed, as in `f(a, b,)`.
+///
+/// When a separator is missing between two items, the sequence parsers
+/// report an error, skip a similar token used in its place (like `;` for
+/// `,`), and keep going if another item follows, as the main grammar does.
+#[derive(Clone, Debug)]
 pub struct SeqSep {
     pub sep: Option<token::Token>,
     pub trailing_sep_allowed: bool,
 }
 
 impl SeqSep {
+    /// Items separated by `t`, optionally followed by a trailing `t`.
     pub fn trailing_allowed(t: token::Token) -> SeqSep {
         SeqSep {
             sep: Some(t),
@@ -27,6 +35,16 @@ impl SeqSep {
         }
     }
 
+    /// Items separated by `t`, without a trailing `t`: one is parsed as the
+    /// start of another item, which usually fails.
+    pub fn trailing_disallowed(t: token::Token) -> SeqSep {
+        SeqSep {
+            sep: Some(t),
+            trailing_sep_allowed: false,
+        }
+    }
+
+    /// Items which are not separated, like the token trees of a macro body.
     pub fn none() -> SeqSep {
         SeqSep {
             sep: None,
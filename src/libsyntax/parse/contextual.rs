@@ -0,0 +1,81 @@
+// Copyright 2018 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Contextual keywords, identifiers that only act as keywords in specific
+//! positions, like `union` in `union U { .. }`.
+//!
+//! Each contextual keyword has a rule which decides, with the parser
+//! positioned at the identifier, whether it is used as a keyword there. The
+//! rules are kept in a registry on the `ParseSess`, so grammar extensions can
+//! add keywords of their own with `ParseSess::register_contextual_keyword`
+//! and recognize them with `Parser::eat_contextual_keyword`.
+
+use ast;
+use parse::parser::{Parser, can_continue_type_after_ident};
+use symbol::keywords;
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Decides whether the contextual keyword at the parser's current token is
+/// used as a keyword.
+pub type ContextualKeywordRule = Rc<Fn(&Parser) -> bool>;
+
+pub struct ContextualKeywords {
+    rules: HashMap<ast::Name, ContextualKeywordRule>,
+}
+
+impl ContextualKeywords {
+    /// A registry with the contextual keywords of the language itself.
+    pub fn builtin() -> ContextualKeywords {
+        let mut registry = ContextualKeywords { rules: HashMap::new() };
+        registry.register(keywords::Auto.name(), Rc::new(is_auto));
+        registry.register(keywords::Default.name(), Rc::new(is_default));
+        registry.register(keywords::Dyn.name(), Rc::new(is_dyn));
+        registry.register(keywords::Union.name(), Rc::new(is_union));
+        registry
+    }
+
+    /// Registers `rule` for the keyword `name`, replacing any previous rule.
+    pub fn register(&mut self, name: ast::Name, rule: ContextualKeywordRule) {
+        self.rules.insert(name, rule);
+    }
+
+    pub fn rule(&self, name: ast::Name) -> Option<ContextualKeywordRule> {
+        self.rules.get(&name).cloned()
+    }
+}
+
+// `auto trait Foo {}`
+fn is_auto(p: &Parser) -> bool {
+    p.look_ahead(1, |t| t.is_keyword(keywords::Trait))
+}
+
+// `default impl`, `default fn` and the like in impls. `pub` is included for
+// better error messages.
+fn is_default(p: &Parser) -> bool {
+    p.look_ahead(1, |t| t.is_keyword(keywords::Impl) ||
+                        t.is_keyword(keywords::Const) ||
+                        t.is_keyword(keywords::Fn) ||
+                        t.is_keyword(keywords::Unsafe) ||
+                        t.is_keyword(keywords::Extern) ||
+                        t.is_keyword(keywords::Type) ||
+                        t.is_keyword(keywords::Pub))
+}
+
+// `dyn Trait`, but not the paths `dyn::a`, `dyn<T>` or `dyn(u8)`.
+fn is_dyn(p: &Parser) -> bool {
+    p.look_ahead(1, |t| t.can_begin_bound() && !can_continue_type_after_ident(t))
+}
+
+// `union U`, but not the path `union::a`.
+fn is_union(p: &Parser) -> bool {
+    p.look_ahead(1, |t| t.is_ident() && !t.is_reserved_ident())
+}
@@ -0,0 +1,254 @@
+// Copyright 2018 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Parsing of the Rust code blocks in documentation, for doctest and
+//! intra-doc tooling which needs spans pointing into the doc comments.
+//!
+//! The code of a block is parsed from a filemap of its own, and the spans of
+//! the result are mapped back to the documentation the code was taken from.
+//! The code of `#[doc = "..."]` attributes whose value is not their source
+//! text, e.g. because of escapes, gets the span of the whole attribute.
+//! Diagnostics of the parser still point into the filemap of the block.
+
+use ast::{self, Attribute};
+use codemap::CodeMap;
+use fold::{self, Folder};
+use parse::{try_filemap_to_parser, token, ParseSess, PResult};
+use syntax_pos::{BytePos, FileName, Span, DUMMY_SP};
+
+use std::cmp;
+
+/// A fenced code block of documentation.
+#[derive(Clone, Debug)]
+pub struct DocCodeBlock {
+    /// The info string after the opening fence, e.g. `rust,no_run`.
+    pub info: String,
+    /// The code, without the decoration of the doc comments and with the
+    /// `# ` prefix of lines hidden by rustdoc removed.
+    pub code: String,
+    // The span in the documentation of each byte of `code`.
+    spans: Vec<Span>,
+}
+
+impl DocCodeBlock {
+    /// The span in the documentation of the bytes `lo..hi` of the code.
+    pub fn original_span(&self, lo: usize, hi: usize) -> Span {
+        let last = match self.spans.len() {
+            0 => return DUMMY_SP,
+            len => len - 1,
+        };
+        let lo_span = self.spans[cmp::min(lo, last)];
+        if hi > lo {
+            lo_span.to(self.spans[cmp::min(hi - 1, last)])
+        } else {
+            lo_span.with_hi(lo_span.lo())
+        }
+    }
+
+    /// Parses the code as the statements of a block, e.g. the body of a
+    /// doctest. Items are parsed as `StmtKind::Item`.
+    pub fn parse_stmts<'a>(&self, sess: &'a ParseSess) -> PResult<'a, Vec<ast::Stmt>> {
+        let name = FileName::Custom("doc code block".to_string());
+        let filemap = sess.codemap().new_filemap(name, self.code.clone());
+        let start_pos = filemap.start_pos;
+        let mut parser = try_filemap_to_parser(sess, filemap)?;
+        let mut stmts = Vec::new();
+        while parser.token != token::Eof {
+            match parser.parse_full_stmt(false)? {
+                Some(stmt) => stmts.push(stmt),
+                None => return parser.unexpected(),
+            }
+        }
+
+        let mut mapper = SpanMapper { block: self, start_pos };
+        Ok(stmts.into_iter().flat_map(|stmt| mapper.fold_stmt(stmt)).collect())
+    }
+}
+
+/// The values of the doc attributes in `attrs`, with their spans.
+pub fn doc_fragments(attrs: &[Attribute]) -> Vec<(String, Span)> {
+    attrs.iter().filter(|attr| attr.path == "doc").filter_map(|attr| {
+        attr.value_str().map(|value| (value.to_string(), attr.span))
+    }).collect()
+}
+
+/// The Rust code blocks of the documentation made up of `fragments`, which
+/// are the values of doc attributes with their spans, in source order.
+/// Blocks without an info string are Rust code, like in rustdoc.
+pub fn doc_code_blocks(codemap: &CodeMap, fragments: &[(String, Span)]) -> Vec<DocCodeBlock> {
+    let mut text = String::new();
+    let mut spans = Vec::new();
+    for &(ref doc, span) in fragments {
+        let exact = codemap.span_to_snippet(span).ok().map_or(false, |snippet| snippet == *doc);
+        for (start, end) in undecorated_ranges(doc) {
+            for (i, c) in doc[start..end].char_indices() {
+                let byte_span = if exact {
+                    let lo = span.lo() + BytePos((start + i) as u32);
+                    span.with_lo(lo).with_hi(lo + BytePos(c.len_utf8() as u32))
+                } else {
+                    span
+                };
+                text.push(c);
+                spans.extend((0..c.len_utf8()).map(|_| byte_span));
+            }
+        }
+        text.push('\n');
+        spans.push(if exact { span.with_lo(span.hi()) } else { span });
+    }
+
+    let mut blocks = Vec::new();
+    let mut open: Option<DocCodeBlock> = None;
+    let mut line_start = 0;
+    for line in text.split('\n') {
+        let line_end = cmp::min(line_start + line.len() + 1, text.len());
+        let trimmed = line.trim_left();
+        if trimmed.starts_with("```") {
+            match open.take() {
+                Some(block) => blocks.push(block),
+                None => {
+                    open = Some(DocCodeBlock {
+                        info: trimmed[3..].trim().to_string(),
+                        code: String::new(),
+                        spans: Vec::new(),
+                    });
+                }
+            }
+        } else if let Some(ref mut block) = open {
+            let indent = line.len() - trimmed.len();
+            let start = if trimmed == "#" {
+                line.len()
+            } else if trimmed.starts_with("# ") {
+                indent + 2
+            } else {
+                0
+            };
+            block.code.push_str(&text[line_start + start..line_end]);
+            block.spans.extend_from_slice(&spans[line_start + start..line_end]);
+        }
+        line_start = line_end;
+    }
+    blocks.extend(open);
+    blocks.retain(|block| is_rust(&block.info));
+    blocks
+}
+
+// The byte ranges of a doc comment without its decoration: the `///` or `//!`
+// of line comments, and the delimiters and the leading `*` of the lines of
+// block comments. Other doc strings have no decoration.
+fn undecorated_ranges(doc: &str) -> Vec<(usize, usize)> {
+    if doc.starts_with("///") || doc.starts_with("//!") {
+        return vec![(3, doc.len())];
+    }
+    if !(doc.starts_with("/**") || doc.starts_with("/*!")) || !doc.ends_with("*/") ||
+       doc.len() < 5 {
+        return vec![(0, doc.len())];
+    }
+    let mut ranges = Vec::new();
+    let mut line_start = 3;
+    let end = doc.len() - 2;
+    for (i, line) in doc[3..end].split('\n').enumerate() {
+        let line_end = cmp::min(line_start + line.len() + 1, end);
+        let trimmed = line.trim_left();
+        let start = if i > 0 && trimmed.starts_with('*') {
+            line_start + line.len() - trimmed.len() + 1
+        } else {
+            line_start
+        };
+        ranges.push((start, line_end));
+        line_start = line_end;
+    }
+    ranges
+}
+
+fn is_rust(info: &str) -> bool {
+    info.split(|c: char| c == ',' || c.is_whitespace()).all(|attr| {
+        match attr {
+            "" | "rust" | "ignore" | "should_panic" | "no_run" | "compile_fail" |
+            "allow_fail" | "test_harness" => true,
+            _ => false,
+        }
+    })
+}
+
+/// Maps the spans of the filemap of a code block to the documentation.
+struct SpanMapper<'a> {
+    block: &'a DocCodeBlock,
+    start_pos: BytePos,
+}
+
+impl<'a> Folder for SpanMapper<'a> {
+    fn new_span(&mut self, sp: Span) -> Span {
+        let end_pos = self.start_pos + BytePos(self.block.code.len() as u32);
+        if sp.lo() < self.start_pos || sp.hi() > end_pos {
+            return sp;
+        }
+        let lo = (sp.lo() - self.start_pos).0 as usize;
+        let hi = (sp.hi() - self.start_pos).0 as usize;
+        self.block.original_span(lo, hi)
+    }
+
+    fn fold_mac(&mut self, mac: ast::Mac) -> ast::Mac {
+        fold::noop_fold_mac(mac, self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codemap::FilePathMapping;
+    use parse;
+    use std::path::PathBuf;
+
+    #[test]
+    fn doc_code_blocks_in_comments() {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let src = "/// Adds one.\n\
+                   ///\n\
+                   /// ```\n\
+                   /// # fn add_one(x: i32) -> i32 { x + 1 }\n\
+                   /// let y = add_one(1);\n\
+                   /// ```\n\
+                   ///\n\
+                   /// ```text\n\
+                   /// not rust\n\
+                   /// ```\n\
+                   #[doc = \"```\\nlet z = 2;\\n```\"]\n\
+                   fn add_one() {}";
+        let item = parse::parse_item_from_source_str(PathBuf::from("doc.rs").into(),
+                                                     src.to_string(), &sess).unwrap().unwrap();
+        let blocks = doc_code_blocks(sess.codemap(), &doc_fragments(&item.attrs));
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].code, "fn add_one(x: i32) -> i32 { x + 1 }\n let y = add_one(1);\n");
+
+        let stmts = blocks[0].parse_stmts(&sess).unwrap();
+        assert_eq!(stmts.len(), 2);
+        let snippet = |span| sess.codemap().span_to_snippet(span).unwrap();
+        assert_eq!(snippet(stmts[0].span), "fn add_one(x: i32) -> i32 { x + 1 }");
+        assert!(snippet(stmts[1].span).starts_with("let y = add_one(1)"));
+
+        // The code of the attribute is not its source text.
+        let stmts = blocks[1].parse_stmts(&sess).unwrap();
+        assert_eq!(stmts.len(), 1);
+        assert_eq!(snippet(stmts[0].span), "#[doc = \"```\\nlet z = 2;\\n```\"]");
+    }
+
+    #[test]
+    fn doc_code_blocks_in_block_comments() {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let src = "/**\n * ```\n * let x = 1;\n * ```\n */\nfn f() {}";
+        let item = parse::parse_item_from_source_str(PathBuf::from("doc.rs").into(),
+                                                     src.to_string(), &sess).unwrap().unwrap();
+        let blocks = doc_code_blocks(sess.codemap(), &doc_fragments(&item.attrs));
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].code, " let x = 1;\n");
+        let stmts = blocks[0].parse_stmts(&sess).unwrap();
+        assert_eq!(sess.codemap().span_to_snippet(stmts[0].span).unwrap(), "let x = 1;");
+    }
+}
@@ -0,0 +1,188 @@
+// Copyright 2018 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A cheap pass over a parsed crate which finds names declared twice in the
+//! same parameter list, struct or enum.
+//!
+//! The compiler reports these during name resolution, so the pass is only run
+//! by crate parsing when `ParseSess::check_duplicate_names` is set, for tools
+//! which never get that far. Macro invocations are not expanded.
+
+use ast::*;
+use errors::Handler;
+use symbol::Symbol;
+use syntax_pos::Span;
+use visit::{self, FnKind, Visitor};
+
+use std::collections::HashMap;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum DuplicateKind {
+    /// A binding in the parameters of a function, method or closure.
+    Param,
+    /// A named field of a struct, union or struct variant.
+    Field,
+    Variant,
+}
+
+/// A name declared a second time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DuplicateName {
+    pub kind: DuplicateKind,
+    pub name: Symbol,
+    /// The span of the first declaration.
+    pub first: Span,
+    /// The span of the duplicate declaration.
+    pub second: Span,
+}
+
+impl DuplicateName {
+    pub fn message(&self) -> String {
+        match self.kind {
+            DuplicateKind::Param => {
+                format!("identifier `{}` is bound more than once in this parameter list",
+                        self.name)
+            }
+            DuplicateKind::Field => format!("field `{}` is already declared", self.name),
+            DuplicateKind::Variant => format!("variant `{}` is already declared", self.name),
+        }
+    }
+
+    /// Reports the duplicate as an error, with both declarations labelled.
+    pub fn report(&self, handler: &Handler) {
+        handler.struct_span_err(self.second, &self.message())
+               .span_label(self.first, format!("first declaration of `{}`", self.name))
+               .span_label(self.second, "declared again here")
+               .emit();
+    }
+}
+
+/// Finds the duplicate names in `krate`, in source order.
+pub fn find_duplicates(krate: &Crate) -> Vec<DuplicateName> {
+    let mut finder = DuplicateFinder { duplicates: Vec::new() };
+    visit::walk_crate(&mut finder, krate);
+    finder.duplicates
+}
+
+/// Reports the duplicate names in `krate` as errors and returns them.
+pub fn report_duplicates(krate: &Crate, handler: &Handler) -> Vec<DuplicateName> {
+    let duplicates = find_duplicates(krate);
+    for duplicate in &duplicates {
+        duplicate.report(handler);
+    }
+    duplicates
+}
+
+struct DuplicateFinder {
+    duplicates: Vec<DuplicateName>,
+}
+
+impl DuplicateFinder {
+    fn check<I: IntoIterator<Item = (Ident, Span)>>(&mut self, kind: DuplicateKind, names: I) {
+        let mut seen = HashMap::new();
+        for (ident, span) in names {
+            if let Some(&first) = seen.get(&ident.name) {
+                self.duplicates.push(DuplicateName { kind, name: ident.name, first, second: span });
+            } else {
+                seen.insert(ident.name, span);
+            }
+        }
+    }
+
+    fn check_params(&mut self, decl: &FnDecl) {
+        let mut bindings = Vec::new();
+        for arg in &decl.inputs {
+            arg.pat.walk(&mut |pat| {
+                if let PatKind::Ident(_, ident, _) = pat.node {
+                    bindings.push((ident.node, ident.span));
+                }
+                true
+            });
+        }
+        self.check(DuplicateKind::Param, bindings);
+    }
+}
+
+impl<'a> Visitor<'a> for DuplicateFinder {
+    fn visit_fn(&mut self, fk: FnKind<'a>, decl: &'a FnDecl, span: Span, _: NodeId) {
+        self.check_params(decl);
+        visit::walk_fn(self, fk, decl, span);
+    }
+
+    fn visit_trait_item(&mut self, item: &'a TraitItem) {
+        if let TraitItemKind::Method(ref sig, None) = item.node {
+            self.check_params(&sig.decl);
+        }
+        visit::walk_trait_item(self, item);
+    }
+
+    fn visit_foreign_item(&mut self, item: &'a ForeignItem) {
+        if let ForeignItemKind::Fn(ref decl, _) = item.node {
+            self.check_params(decl);
+        }
+        visit::walk_foreign_item(self, item);
+    }
+
+    fn visit_variant_data(&mut self, data: &'a VariantData, _: Ident, _: &'a Generics,
+                          _: NodeId, _: Span) {
+        self.check(DuplicateKind::Field, data.fields().iter().filter_map(|field| {
+            field.ident.map(|ident| (ident, field.span))
+        }));
+        visit::walk_struct_def(self, data);
+    }
+
+    fn visit_enum_def(&mut self, def: &'a EnumDef, generics: &'a Generics, id: NodeId,
+                      span: Span) {
+        self.check(DuplicateKind::Variant, def.variants.iter().map(|variant| {
+            (variant.node.name, variant.span)
+        }));
+        visit::walk_enum_def(self, def, generics, id, span);
+    }
+
+    fn visit_mac(&mut self, _mac: &'a Mac) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codemap::FilePathMapping;
+    use parse::{self, ParseSess};
+    use std::path::PathBuf;
+
+    #[test]
+    fn duplicate_names() {
+        let mut sess = ParseSess::new(FilePathMapping::empty());
+        let src = "fn f(a: u8, (b, a): (u8, u8)) {}\n\
+                   struct S { x: u8, y: u8, x: u16 }\n\
+                   enum E { A, B { z: u8, z: u8 }, A }\n\
+                   trait T { fn m(&self, c: u8, c: u8); }\n\
+                   fn g() { let _ = |d: u8, d: u8| d; }\n\
+                   fn ok(a: u8, b: u8) {}";
+        let krate = parse::parse_crate_from_source_str(PathBuf::from("dups.rs").into(),
+                                                       src.to_string(), &sess).unwrap();
+        let duplicates = find_duplicates(&krate).into_iter().map(|duplicate| {
+            (duplicate.kind, duplicate.name.to_string())
+        }).collect::<Vec<_>>();
+        assert_eq!(duplicates, vec![
+            (DuplicateKind::Param, "a".to_string()),
+            (DuplicateKind::Field, "x".to_string()),
+            (DuplicateKind::Variant, "A".to_string()),
+            (DuplicateKind::Field, "z".to_string()),
+            (DuplicateKind::Param, "c".to_string()),
+            (DuplicateKind::Param, "d".to_string()),
+        ]);
+        assert!(!sess.span_diagnostic.has_errors());
+
+        sess.check_duplicate_names = true;
+        parse::parse_crate_from_source_str(PathBuf::from("dups.rs").into(),
+                                           src.to_string(), &sess).unwrap();
+        assert_eq!(sess.span_diagnostic.err_count(), 6);
+    }
+}
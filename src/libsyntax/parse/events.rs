@@ -0,0 +1,123 @@
+// Copyright 2018 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A flat stream of parse events, for building concrete syntax trees outside
+//! of the parser and for debugging the grammar.
+//!
+//! Recording is off by default. When enabled with `Parser::record_events`,
+//! the parser reports every token it consumes, and the start and end of the
+//! productions it parses, in order. The events are retrieved with
+//! `Parser::take_events`.
+//!
+//! Events from speculative parsing which the parser rewinds are discarded,
+//! so the stream always describes the parse which was finally taken. Outer
+//! attributes are reported before the start of the node they belong to, and
+//! nodes which consume no tokens are not reported at all.
+
+use parse::token::Token;
+use syntax_pos::Span;
+
+use std::rc::Rc;
+
+/// The kind of syntax node started or finished by an event.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum NodeKind {
+    Item,
+    TraitItem,
+    ImplItem,
+    ForeignItem,
+    Stmt,
+    Block,
+    Expr,
+    Pat,
+    Ty,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseEvent {
+    /// A node starts, at the token with the given span.
+    StartNode(NodeKind, Span),
+    /// A token was consumed. A delimited group consumed as a whole is reported
+    /// token by token, delimiters included.
+    Token(Token, Span),
+    /// The innermost node which has not finished yet ends. The span covers
+    /// the whole node. Nodes are finished even if parsing them failed.
+    FinishNode(NodeKind, Span),
+}
+
+/// The events recorded by a parser, as a list shared with the snapshots taken
+/// of the parser by cloning it. Restoring a snapshot thus also restores the
+/// events recorded up to that point.
+#[derive(Clone)]
+pub(super) struct EventBuffer {
+    last: Option<Rc<RecordedEvent>>,
+}
+
+struct RecordedEvent {
+    event: ParseEvent,
+    /// The number of events recorded before this one.
+    index: usize,
+    prev: Option<Rc<RecordedEvent>>,
+}
+
+impl EventBuffer {
+    pub(super) fn new() -> EventBuffer {
+        EventBuffer { last: None }
+    }
+
+    /// The number of events recorded so far.
+    pub(super) fn len(&self) -> usize {
+        self.last.as_ref().map_or(0, |last| last.index + 1)
+    }
+
+    pub(super) fn push(&mut self, event: ParseEvent) {
+        let index = self.len();
+        self.last = Some(Rc::new(RecordedEvent { event, index, prev: self.last.take() }));
+    }
+
+    /// Drops all events after the first `len`.
+    pub(super) fn truncate(&mut self, len: usize) {
+        while self.len() > len {
+            self.last = self.last.take().and_then(|last| last.prev.clone());
+        }
+    }
+
+    pub(super) fn take(&mut self) -> Vec<ParseEvent> {
+        let mut events = Vec::with_capacity(self.len());
+        {
+            let mut next = self.last.as_ref();
+            while let Some(recorded) = next {
+                events.push(recorded.event.clone());
+                next = recorded.prev.as_ref();
+            }
+        }
+        events.reverse();
+        self.clear();
+        events
+    }
+
+    /// Drops the events one at a time, as dropping a long list recursively
+    /// could overflow the stack.
+    fn clear(&mut self) {
+        let mut next = self.last.take();
+        while let Some(recorded) = next {
+            next = match Rc::try_unwrap(recorded) {
+                Ok(mut recorded) => recorded.prev.take(),
+                Err(_) => None,
+            };
+        }
+    }
+}
+
+impl Drop for EventBuffer {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
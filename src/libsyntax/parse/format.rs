@@ -0,0 +1,201 @@
+// Copyright 2018 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Parsing of format strings, like the ones taken by `format!`.
+//!
+//! The format strings are parsed by `fmt_macros`, as in the `format!`
+//! expansion. `parse_format` additionally records the byte range of every
+//! piece. A `FormatLit` does the same for a string literal token and maps
+//! those ranges back into the source, so that tools checking format
+//! arguments can point at the exact argument in the literal.
+
+use fmt_macros;
+use parse::{self, token};
+use syntax_pos::{BytePos, Span};
+
+use std::ops::Range;
+
+pub use fmt_macros::{Alignment, Argument, Count, Flag, FormatSpec, Piece, Position};
+
+/// A parsed format string.
+#[derive(Clone, PartialEq)]
+pub struct Format<'a> {
+    input: &'a str,
+    pub pieces: Vec<SpannedPiece<'a>>,
+    pub errors: Vec<FormatError>,
+}
+
+/// A piece of a format string, with its byte range in the string.
+#[derive(Clone, PartialEq)]
+pub struct SpannedPiece<'a> {
+    pub node: Piece<'a>,
+    pub range: Range<usize>,
+}
+
+/// An error in a format string, with the byte range of the piece it was
+/// found in. Parsing stops at the first unmatched `}`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FormatError {
+    pub message: String,
+    pub note: Option<String>,
+    pub range: Range<usize>,
+}
+
+/// Parses the format string `s`.
+pub fn parse_format(s: &str) -> Format {
+    let mut parser = fmt_macros::Parser::new(s);
+    let mut pieces = Vec::new();
+    let mut errors = Vec::new();
+    loop {
+        let start = parser.offset();
+        let piece = parser.next();
+        let range = start..parser.offset();
+        errors.extend(parser.errors.drain(..).map(|(message, note)| {
+            FormatError { message, note, range: range.clone() }
+        }));
+        match piece {
+            Some(node) => pieces.push(SpannedPiece { node, range }),
+            None => break,
+        }
+    }
+    Format { input: s, pieces, errors }
+}
+
+impl<'a> Format<'a> {
+    /// Returns the byte range of `part`, a slice of the format string such as
+    /// the name of an `ArgumentNamed`, or `None` if it is not one.
+    pub fn range_of(&self, part: &str) -> Option<Range<usize>> {
+        let start = (part.as_ptr() as usize).checked_sub(self.input.as_ptr() as usize)?;
+        if start + part.len() <= self.input.len() {
+            Some(start..start + part.len())
+        } else {
+            None
+        }
+    }
+}
+
+/// A format string parsed from a string literal token, with the ranges of
+/// its pieces mapped back into the source of the literal.
+#[derive(Clone, Debug)]
+pub struct FormatLit {
+    /// The unescaped value of the literal.
+    pub value: String,
+    /// The span of the literal token.
+    span: Span,
+    /// The length of the literal's opening quote and raw string prefix.
+    prefix_len: usize,
+    /// The byte offset in the literal's contents of each byte of `value`.
+    offsets: Vec<usize>,
+    contents_len: usize,
+}
+
+impl FormatLit {
+    /// Parses the string literal `lit`, whose token spans `span`. Returns
+    /// `None` if `lit` is not a (non-byte) string literal.
+    ///
+    /// The mapped spans are only meaningful if `span` covers the literal as
+    /// written, which is not the case for literals produced by macros like
+    /// `stringify!`.
+    pub fn from_token(lit: token::Lit, span: Span) -> Option<FormatLit> {
+        let (contents, prefix_len, (value, offsets)) = match lit {
+            token::Str_(s) => {
+                let s = s.as_str();
+                (s, 1, parse::str_lit_with_offsets(&s))
+            }
+            token::StrRaw(s, n) => {
+                let s = s.as_str();
+                (s, n + 2, parse::raw_str_lit_with_offsets(&s))
+            }
+            _ => return None,
+        };
+        Some(FormatLit {
+            value,
+            span,
+            prefix_len,
+            offsets,
+            contents_len: contents.len(),
+        })
+    }
+
+    /// Parses the value of the literal as a format string.
+    pub fn format(&self) -> Format {
+        parse_format(&self.value)
+    }
+
+    /// Returns the span in the source of `range`, a byte range in `value`.
+    pub fn span(&self, range: Range<usize>) -> Span {
+        let pos = |i: usize| {
+            let offset = self.offsets.get(i).cloned().unwrap_or(self.contents_len);
+            self.span.lo() + BytePos((self.prefix_len + offset) as u32)
+        };
+        self.span.with_lo(pos(range.start)).with_hi(pos(range.end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use symbol::Symbol;
+    use syntax_pos::DUMMY_SP;
+
+    fn arg<'a>(piece: &SpannedPiece<'a>) -> Argument<'a> {
+        match piece.node {
+            Piece::NextArgument(arg) => arg,
+            Piece::String(s) => panic!("expected an argument, found {:?}", s),
+        }
+    }
+
+    #[test]
+    fn records_ranges() {
+        let format = parse_format("a {{b}} {} {name:>8.3} {0:#x}");
+        assert!(format.errors.is_empty());
+        let ranges = format.pieces.iter().map(|piece| piece.range.clone()).collect::<Vec<_>>();
+        assert_eq!(ranges, [0..2, 2..5, 5..8, 8..10, 10..11, 11..22, 22..23, 23..29]);
+
+        let named = arg(&format.pieces[5]);
+        match named.position {
+            Position::ArgumentNamed(name) => {
+                assert_eq!(name, "name");
+                assert_eq!(format.range_of(name), Some(12..16));
+            }
+            _ => panic!("expected a named argument"),
+        }
+        assert!(named.format.align == Alignment::AlignRight);
+        assert!(named.format.width == Count::CountIs(8));
+        assert_eq!(format.range_of(&"name".to_string()), None);
+    }
+
+    #[test]
+    fn reports_errors() {
+        let format = parse_format("a {0 b} }");
+        let messages = format.errors.iter().map(|e| &e.message[..]).collect::<Vec<_>>();
+        assert_eq!(messages, ["expected `'}'`, found `'b'`", "unmatched `}` found"]);
+        assert_eq!(format.errors[0].range, 2..5);
+        assert_eq!(format.errors[1].range, 6..7);
+    }
+
+    #[test]
+    fn maps_spans_into_literal() {
+        // The source `"a\n{b}"` at offset 10.
+        let span = DUMMY_SP.with_lo(BytePos(10)).with_hi(BytePos(18));
+        let lit = FormatLit::from_token(token::Str_(Symbol::intern("a\\n{b}")), span).unwrap();
+        assert_eq!(lit.value, "a\n{b}");
+        let range = lit.format().pieces[1].range.clone();
+        assert_eq!(range, 2..5);
+        let span = lit.span(range);
+        assert_eq!((span.lo(), span.hi()), (BytePos(14), BytePos(17)));
+
+        // The source `r#"{x}"#` at offset 0.
+        let span = DUMMY_SP.with_hi(BytePos(8));
+        let lit = FormatLit::from_token(token::StrRaw(Symbol::intern("{x}"), 1), span).unwrap();
+        let span = lit.span(lit.format().pieces[0].range.clone());
+        assert_eq!((span.lo(), span.hi()), (BytePos(3), BytePos(6)));
+    }
+}
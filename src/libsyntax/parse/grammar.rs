@@ -0,0 +1,408 @@
+// Copyright 2018 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! An approximate grammar of the language, extracted from the annotations on
+//! the productions of the parser, for documentation tools and for diffing the
+//! grammar between versions. An annotation is a comment of the form
+//!
+//! ```text
+//! /// Parses the following grammar:
+//! ///     ItemTy = "type" Ident [Generics] [WhereClause] "=" Ty ";"
+//! ```
+//!
+//! on a parsing method, or a `// This parses the grammar:` comment inside of
+//! one. Rules are written with quoted terminals, `|` for alternatives, `[...]`
+//! for optional parts, `{...}` for repetitions, `(...)` for grouping, the
+//! postfix operators `?`, `*` and `+`, and `...` for elided parts. As the
+//! annotations are next to the code they describe, the grammar follows the
+//! parser rather than a separate specification.
+//!
+//! The source of the parser is not part of this crate, so tools pass the
+//! contents of `src/libsyntax/parse/parser.rs` to `extract_productions`.
+
+use serialize::json::{Json, ToJson};
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// A rule of the grammar.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Rule {
+    Terminal(String),
+    NonTerminal(String),
+    Sequence(Vec<Rule>),
+    Choice(Vec<Rule>),
+    Optional(Box<Rule>),
+    Repeat(Box<Rule>),
+    /// A part of the rule which the annotation leaves out, written `...`.
+    Elided,
+}
+
+impl Rule {
+    fn nonterminals<'a>(&'a self, names: &mut BTreeSet<&'a str>) {
+        match *self {
+            Rule::NonTerminal(ref name) => {
+                names.insert(name);
+            }
+            Rule::Sequence(ref rules) | Rule::Choice(ref rules) => {
+                for rule in rules {
+                    rule.nonterminals(names);
+                }
+            }
+            Rule::Optional(ref rule) | Rule::Repeat(ref rule) => rule.nonterminals(names),
+            Rule::Terminal(_) | Rule::Elided => {}
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Rule::Terminal(ref text) => write!(f, "{:?}", text),
+            Rule::NonTerminal(ref name) => f.write_str(name),
+            Rule::Sequence(ref rules) => {
+                for (i, rule) in rules.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" ")?;
+                    }
+                    match *rule {
+                        Rule::Choice(_) => write!(f, "({})", rule)?,
+                        _ => write!(f, "{}", rule)?,
+                    }
+                }
+                Ok(())
+            }
+            Rule::Choice(ref rules) => {
+                for (i, rule) in rules.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" | ")?;
+                    }
+                    write!(f, "{}", rule)?;
+                }
+                Ok(())
+            }
+            Rule::Optional(ref rule) => write!(f, "[{}]", rule),
+            Rule::Repeat(ref rule) => write!(f, "{{{}}}", rule),
+            Rule::Elided => f.write_str("..."),
+        }
+    }
+}
+
+/// The railroad diagram of the rule, e.g.
+/// `{"type": "Sequence", "items": [{"type": "Terminal", "text": "type"}, ...]}`.
+impl ToJson for Rule {
+    fn to_json(&self) -> Json {
+        let mut object = BTreeMap::new();
+        let kind = match *self {
+            Rule::Terminal(ref text) => {
+                object.insert("text".to_string(), text.to_json());
+                "Terminal"
+            }
+            Rule::NonTerminal(ref name) => {
+                object.insert("text".to_string(), name.to_json());
+                "NonTerminal"
+            }
+            Rule::Sequence(ref rules) => {
+                object.insert("items".to_string(), rules.to_json());
+                "Sequence"
+            }
+            Rule::Choice(ref rules) => {
+                object.insert("items".to_string(), rules.to_json());
+                "Choice"
+            }
+            Rule::Optional(ref rule) => {
+                object.insert("item".to_string(), rule.to_json());
+                "Optional"
+            }
+            Rule::Repeat(ref rule) => {
+                object.insert("item".to_string(), rule.to_json());
+                "ZeroOrMore"
+            }
+            Rule::Elided => "Elided",
+        };
+        object.insert("type".to_string(), kind.to_json());
+        Json::Object(object)
+    }
+}
+
+/// An annotated production of the parser.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Production {
+    pub name: String,
+    pub rule: Rule,
+    /// The parsing method which is annotated.
+    pub parser: String,
+    /// The line of the annotation in `parser.rs`, starting at 1.
+    pub line: usize,
+}
+
+impl ToJson for Production {
+    fn to_json(&self) -> Json {
+        let mut object = BTreeMap::new();
+        object.insert("name".to_string(), self.name.to_json());
+        object.insert("parser".to_string(), self.parser.to_json());
+        object.insert("diagram".to_string(), self.rule.to_json());
+        Json::Object(object)
+    }
+}
+
+/// A grammar annotation which could not be read.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GrammarError {
+    pub line: usize,
+    pub msg: String,
+}
+
+/// Returns the productions annotated in `src`, the source of a parser.
+pub fn extract_productions(src: &str) -> Result<Vec<Production>, GrammarError> {
+    let lines = src.lines().collect::<Vec<_>>();
+    let mut productions = Vec::new();
+    let mut current_fn = None;
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i].trim();
+        if let Some(name) = fn_name(line) {
+            current_fn = Some(name);
+        }
+        let doc = match line {
+            "/// Parses the following grammar:" => true,
+            "// This parses the grammar:" => false,
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+        let prefix = if doc { "///" } else { "//" };
+        // Doc comments annotate the method after them, other comments the
+        // method they are in.
+        let parser = if doc {
+            lines[i..].iter().filter_map(|line| fn_name(line.trim())).next()
+        } else {
+            current_fn
+        };
+        i += 1;
+        while i < lines.len() {
+            let definition = match lines[i].trim().splitn(2, prefix).nth(1) {
+                Some(definition) if lines[i].trim().starts_with(prefix) &&
+                                    definition.contains(" = ") => definition.trim(),
+                _ => break,
+            };
+            let mut parts = definition.splitn(2, " = ");
+            let name = parts.next().unwrap().trim();
+            let rule = parse_rule(parts.next().unwrap())
+                .map_err(|msg| GrammarError { line: i + 1, msg })?;
+            productions.push(Production {
+                name: name.to_string(),
+                rule,
+                parser: parser.unwrap_or("").to_string(),
+                line: i + 1,
+            });
+            i += 1;
+        }
+    }
+    Ok(productions)
+}
+
+fn fn_name(line: &str) -> Option<&str> {
+    let rest = line.trim_left_matches("pub ").trim_left_matches("pub(crate) ");
+    if !rest.starts_with("fn ") {
+        return None;
+    }
+    rest[3..].split(|c: char| !(c.is_alphanumeric() || c == '_')).next()
+}
+
+/// Returns the grammar as EBNF, one production per line.
+pub fn to_ebnf(productions: &[Production]) -> String {
+    productions.iter().map(|production| {
+        format!("{} = {} ;\n", production.name, production.rule)
+    }).collect()
+}
+
+/// Returns the railroad diagrams of the grammar as a JSON array.
+pub fn to_railroad_json(productions: &[Production]) -> String {
+    productions.to_json().to_string()
+}
+
+/// Returns the nonterminals which are used but not defined by `productions`,
+/// which are parsed by methods without annotations.
+pub fn undefined(productions: &[Production]) -> Vec<String> {
+    let mut used = BTreeSet::new();
+    for production in productions {
+        production.rule.nonterminals(&mut used);
+    }
+    for production in productions {
+        used.remove(&production.name[..]);
+    }
+    used.into_iter().map(|name| name.to_string()).collect()
+}
+
+/// Parses the right-hand side of a production.
+pub fn parse_rule(src: &str) -> Result<Rule, String> {
+    let tokens = tokenize(src)?;
+    let mut pos = 0;
+    let rule = parse_choice(&tokens, &mut pos)?;
+    match tokens.get(pos) {
+        None => Ok(rule),
+        Some(token) => Err(format!("unexpected `{}` in grammar rule", token)),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Terminal(String),
+    Name(String),
+    Punct(char),
+    Elided,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Token::Terminal(ref text) => write!(f, "{:?}", text),
+            Token::Name(ref name) => f.write_str(name),
+            Token::Punct(c) => write!(f, "{}", c),
+            Token::Elided => f.write_str("..."),
+        }
+    }
+}
+
+fn tokenize(src: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = src.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '"' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, c)) => text.push(c),
+                        None => return Err("unterminated terminal in grammar rule".to_string()),
+                    }
+                }
+                tokens.push(Token::Terminal(text));
+            }
+            '.' if src[i..].starts_with("...") => {
+                chars.next();
+                chars.next();
+                tokens.push(Token::Elided);
+            }
+            '|' | '[' | ']' | '{' | '}' | '(' | ')' | '?' | '*' | '+' => {
+                tokens.push(Token::Punct(c))
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut name = c.to_string();
+                while let Some(&(_, c)) = chars.peek() {
+                    if !(c.is_alphanumeric() || c == '_') {
+                        break;
+                    }
+                    name.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Name(name));
+            }
+            c => return Err(format!("unexpected `{}` in grammar rule", c)),
+        }
+    }
+    Ok(tokens)
+}
+
+fn parse_choice(tokens: &[Token], pos: &mut usize) -> Result<Rule, String> {
+    let mut alternatives = vec![parse_sequence(tokens, pos)?];
+    while tokens.get(*pos) == Some(&Token::Punct('|')) {
+        *pos += 1;
+        alternatives.push(parse_sequence(tokens, pos)?);
+    }
+    Ok(if alternatives.len() == 1 { alternatives.pop().unwrap() } else {
+        Rule::Choice(alternatives)
+    })
+}
+
+fn parse_sequence(tokens: &[Token], pos: &mut usize) -> Result<Rule, String> {
+    let mut rules = Vec::new();
+    loop {
+        let mut rule = match tokens.get(*pos) {
+            Some(&Token::Terminal(ref text)) => Rule::Terminal(text.clone()),
+            Some(&Token::Name(ref name)) => Rule::NonTerminal(name.clone()),
+            Some(&Token::Elided) => Rule::Elided,
+            Some(&Token::Punct(open)) if open == '[' || open == '{' || open == '(' => {
+                *pos += 1;
+                let inner = parse_choice(tokens, pos)?;
+                let close = match open { '[' => ']', '{' => '}', _ => ')' };
+                if tokens.get(*pos) != Some(&Token::Punct(close)) {
+                    return Err(format!("expected `{}` in grammar rule", close));
+                }
+                match open {
+                    '[' => Rule::Optional(Box::new(inner)),
+                    '{' => Rule::Repeat(Box::new(inner)),
+                    _ => inner,
+                }
+            }
+            _ => break,
+        };
+        *pos += 1;
+        loop {
+            rule = match tokens.get(*pos) {
+                Some(&Token::Punct('?')) => Rule::Optional(Box::new(rule)),
+                Some(&Token::Punct('*')) => Rule::Repeat(Box::new(rule)),
+                Some(&Token::Punct('+')) => {
+                    Rule::Sequence(vec![rule.clone(), Rule::Repeat(Box::new(rule))])
+                }
+                _ => break,
+            };
+            *pos += 1;
+        }
+        rules.push(rule);
+    }
+    match rules.len() {
+        0 => Err("empty grammar rule".to_string()),
+        1 => Ok(rules.pop().unwrap()),
+        _ => Ok(Rule::Sequence(rules)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PARSER_SOURCE: &str = include_str!("parser.rs");
+
+    #[test]
+    fn parser_annotations() {
+        let productions = extract_productions(PARSER_SOURCE).unwrap();
+        let assoc_ty = productions.iter().find(|p| p.name == "TraitItemAssocTy").unwrap();
+        assert_eq!(assoc_ty.parser, "parse_trait_item_assoc_ty");
+        let impl_const = productions.iter().find(|p| p.name == "ImplItemConst").unwrap();
+        assert_eq!(impl_const.parser, "parse_impl_item_");
+        assert_eq!(impl_const.rule.to_string(), r#""const" Ident ":" Ty "=" Expr ";""#);
+        // Every annotation names a method of the parser.
+        assert!(productions.iter().all(|p| PARSER_SOURCE.contains(&format!("fn {}", p.parser))));
+    }
+
+    #[test]
+    fn ebnf_and_railroad() {
+        let src = "    /// Parses the following grammar:\n\
+                   \x20   ///     A = \"a\" (B | C)+ [\",\"]\n\
+                   \x20   ///     B = {\"b\"} ...\n\
+                   \x20   fn parse_a(&mut self) {}";
+        let productions = extract_productions(src).unwrap();
+        assert_eq!(to_ebnf(&productions),
+                   "A = \"a\" (B | C) {B | C} [\",\"] ;\nB = {\"b\"} ... ;\n");
+        assert_eq!(undefined(&productions), vec!["C".to_string()]);
+        let json = to_railroad_json(&productions[1..]);
+        assert!(json.starts_with(r#"[{"diagram":{"items":[{"item":{"text":"b","#));
+        assert!(json.contains(r#""type":"ZeroOrMore"},{"type":"Elided"}],"type":"Sequence"}"#));
+        assert!(json.ends_with(r#""name":"B","parser":"parse_a"}]"#));
+
+        assert!(parse_rule("\"a\" | ").is_err());
+        assert!(parse_rule("[A").is_err());
+    }
+}
@@ -9,8 +9,8 @@
 // except according to those terms.
 
 use ast::{self, Ident};
-use syntax_pos::{self, BytePos, CharPos, Pos, Span, NO_EXPANSION};
-use codemap::{CodeMap, FilePathMapping};
+use syntax_pos::{self, BytePos, CharPos, Frontmatter, Pos, Span, NO_EXPANSION};
+use codemap::{CodeMap, CrlfPolicy, FilePathMapping};
 use errors::{FatalError, DiagnosticBuilder};
 use parse::{token, ParseSess};
 use str::char_at;
@@ -19,10 +19,13 @@ use std_unicode::property::Pattern_White_Space;
 
 use std::borrow::Cow;
 use std::char;
+use std::cmp;
 use std::mem::replace;
 use std::rc::Rc;
+use std::time::{Duration, Instant};
 
 pub mod comments;
+pub mod state;
 mod tokentrees;
 mod unicode_chars;
 
@@ -67,6 +70,15 @@ pub struct StringReader<'a> {
     span: Span,
     open_braces: Vec<(token::DelimToken, Span)>,
     pub override_span: Option<Span>,
+    /// Time spent in `real_token`, if parse timings are enabled.
+    pub lex_time: Option<Duration>,
+    /// The number of tokens returned by `real_token`.
+    pub token_count: usize,
+    /// If set, the start and end offsets in the file of the tokens returned
+    /// by `real_token`, except for `Eof`.
+    pub token_offsets: Option<Vec<(u32, u32)>>,
+    /// The start and end of the frontmatter block of the file, if any.
+    frontmatter: Option<(BytePos, BytePos)>,
 }
 
 impl<'a> StringReader<'a> {
@@ -109,8 +121,21 @@ impl<'a> StringReader<'a> {
         Ok(t)
     }
     pub fn real_token(&mut self) -> TokenAndSpan {
+        let start = self.lex_time.map(|_| Instant::now());
         let res = self.try_real_token();
-        self.unwrap_or_abort(res)
+        if let Some(start) = start {
+            self.lex_time = self.lex_time.map(|time| time + start.elapsed());
+        }
+        self.token_count += 1;
+        let t = self.unwrap_or_abort(res);
+        if let Some(ref mut offsets) = self.token_offsets {
+            if t.tok != token::Eof {
+                let start = t.sp.lo() - self.filemap.start_pos;
+                let end = t.sp.hi() - self.filemap.start_pos;
+                offsets.push((start.0, end.0));
+            }
+        }
+        t
     }
     fn is_eof(&self) -> bool {
         if self.ch.is_none() {
@@ -154,6 +179,9 @@ impl<'a> StringReader<'a> {
     /// For comments.rs, which hackily pokes into next_pos and ch
     pub fn new_raw(sess: &'a ParseSess, filemap: Rc<syntax_pos::FileMap>) -> Self {
         let mut sr = StringReader::new_raw_internal(sess, filemap);
+        if sess.allow_frontmatter && sr.frontmatter.is_none() {
+            sr.find_frontmatter();
+        }
         sr.bump();
         sr
     }
@@ -165,6 +193,9 @@ impl<'a> StringReader<'a> {
         }
 
         let source_text = (*filemap.src.as_ref().unwrap()).clone();
+        let frontmatter = filemap.frontmatter.borrow().as_ref().map(|block| {
+            (block.span.lo(), block.span.hi())
+        });
 
         StringReader {
             sess,
@@ -184,11 +215,18 @@ impl<'a> StringReader<'a> {
             span: syntax_pos::DUMMY_SP,
             open_braces: Vec::new(),
             override_span: None,
+            lex_time: if sess.parse_timings_enabled() { Some(Duration::new(0, 0)) } else { None },
+            token_count: 0,
+            token_offsets: None,
+            frontmatter,
         }
     }
 
     pub fn new(sess: &'a ParseSess, filemap: Rc<syntax_pos::FileMap>) -> Self {
         let mut sr = StringReader::new_raw(sess, filemap);
+        if sess.codemap().crlf_policy() == CrlfPolicy::Error {
+            sr.report_crlf_lines();
+        }
         if sr.advance_token().is_err() {
             sr.emit_fatal_errors();
             FatalError.raise();
@@ -221,6 +259,89 @@ impl<'a> StringReader<'a> {
         sr
     }
 
+    /// Creates a reader for the tokens of `filemap` within `span`, positioned at
+    /// the first token which ends after the start of `span`, for lexing part of
+    /// a file without lexing all of it.
+    ///
+    /// Lexing starts at the beginning of the line containing the start of
+    /// `span`, so that a span starting in the middle of a token is handled.
+    /// The line must not start within a comment or a string literal.
+    pub fn new_in_range(sess: &'a ParseSess, filemap: Rc<syntax_pos::FileMap>, span: Span)
+                        -> Self {
+        let lo = cmp::min(cmp::max(span.lo(), filemap.start_pos), filemap.end_pos);
+        let hi = cmp::min(cmp::max(span.hi(), lo), filemap.end_pos);
+
+        let mut sr = StringReader::new_raw_internal(sess, filemap);
+        let offset = (lo - sr.filemap.start_pos).to_usize();
+        let line_start = sr.source_text[..offset].rfind('\n').map_or(0, |i| i + 1);
+
+        // Seek the lexer to the start of the line, like `retokenize`.
+        sr.save_new_lines_and_multibyte = false;
+        sr.next_pos = sr.filemap.start_pos + Pos::from_usize(line_start);
+        sr.terminator = Some(hi);
+
+        sr.bump();
+
+        if sr.advance_token().is_err() {
+            sr.emit_fatal_errors();
+            FatalError.raise();
+        }
+        sr.real_token();
+        while sr.token != token::Eof && sr.span.hi() <= lo {
+            sr.real_token();
+        }
+        sr
+    }
+
+    /// Finds the frontmatter block at the top of the file, after the shebang
+    /// line if there is one, and records it in the filemap.
+    fn find_frontmatter(&mut self) {
+        let src = self.source_text.clone();
+        let start = if src.starts_with("#!") && !src.starts_with("#![") {
+            src.find('\n').map_or(src.len(), |i| i + 1)
+        } else {
+            0
+        };
+        let rest = &src[start..];
+        let dashes = rest.bytes().take_while(|&b| b == b'-').count();
+        if dashes < 3 {
+            return;
+        }
+        let open_end = rest.find('\n').unwrap_or(rest.len());
+        let info = rest[dashes..open_end].trim();
+        let text_start = cmp::min(open_end + 1, rest.len());
+
+        // The block ends with a line of as many dashes as it started with.
+        let mut line_start = text_start;
+        let mut close = None;
+        while line_start < rest.len() {
+            let line_end = rest[line_start..].find('\n').map_or(rest.len(), |i| line_start + i);
+            let line = rest[line_start..line_end].trim_right();
+            if line.len() == dashes && line.bytes().all(|b| b == b'-') {
+                close = Some((line_start, line_end));
+                break;
+            }
+            line_start = line_end + 1;
+        }
+
+        let lo = self.filemap.start_pos + Pos::from_usize(start);
+        let (text_end, end) = match close {
+            Some(close) => close,
+            None => {
+                // Treat the rest of the file as the block rather than lexing it.
+                self.err_span_(lo, lo + Pos::from_usize(open_end), "unclosed frontmatter block");
+                (rest.len(), rest.len())
+            }
+        };
+        let hi = lo + Pos::from_usize(end);
+        *self.filemap.frontmatter.borrow_mut() = Some(Frontmatter {
+            info: if info.is_empty() { None } else { Some(info.to_string()) },
+            text: rest[text_start..text_end].to_string(),
+            span: Span::new(lo, hi, NO_EXPANSION),
+        });
+        self.frontmatter = Some((lo, hi));
+    }
+
     pub fn ch_is(&self, c: char) -> bool {
         self.ch == Some(c)
     }
@@ -270,6 +391,39 @@ impl<'a> StringReader<'a> {
         self.sess.span_diagnostic.struct_span_fatal(self.mk_sp(from_pos, to_pos), &m[..])
     }
 
+    /// Reports the CRLF line endings of the file, for `CrlfPolicy::Error`.
+    /// Reports the CRLF line endings of the file, unless they were reported
+    /// when it was lexed before.
+    fn report_crlf_lines(&self) {
+        if !self.sess.crlf_reported_files.borrow_mut().insert(self.filemap.start_pos) {
+            return;
+        }
+        let first = match self.source_text.find("\r\n") {
+            Some(first) => self.filemap.start_pos + BytePos(first as u32),
+            None => return,
+        };
+        let mut err = self.sess.span_diagnostic.struct_span_err(
+            self.mk_sp(first, first + BytePos(1)), "CRLF line ending");
+        let count = self.filemap.crlf_lines.len();
+        if count > 1 {
+            err.note(&format!("{} lines in this file end in CRLF", count));
+        }
+        err.emit();
+    }
+
+    /// Builds the error for the control character `c` at the current position.
+    fn struct_control_char_err(&self, c: char, fatal: bool) -> DiagnosticBuilder<'a> {
+        let (lo, hi) = (self.pos, self.next_pos);
+        let msg = "control character in source";
+        let mut err = if fatal {
+            self.struct_fatal_span_char(lo, hi, msg, c)
+        } else {
+            self.struct_err_span_char(lo, hi, msg, c)
+        };
+        err.span_label(self.mk_sp(lo, hi), "control character");
+        err
+    }
+
     /// Report a lexical error spanning [`from_pos`, `to_pos`), appending an
     /// escaped character to the error message
     fn err_span_char(&self, from_pos: BytePos, to_pos: BytePos, m: &str, c: char) {
@@ -608,6 +762,17 @@ impl<'a> StringReader<'a> {
     /// return None.
     fn scan_whitespace_or_comment(&mut self) -> Option<TokenAndSpan> {
         match self.ch.unwrap_or('\0') {
+            // The frontmatter block is skipped like a comment.
+            '-' if self.frontmatter.map_or(false, |(lo, _)| lo == self.pos) => {
+                let (lo, hi) = self.frontmatter.unwrap();
+                while self.pos < hi && !self.is_eof() {
+                    self.bump();
+                }
+                Some(TokenAndSpan {
+                    tok: token::Comment,
+                    sp: self.mk_sp(lo, self.pos),
+                })
+            }
             // # to handle shebang at start of file -- this is the entry point
             // for skipping over all "junk"
             '/' | '#' => {
@@ -627,6 +792,18 @@ impl<'a> StringReader<'a> {
                 debug!("scanning whitespace: {:?}", c);
                 c
             }
+            _ if self.sess.skip_control_chars && self.ch.map_or(false, is_control_char) => {
+                let start_bpos = self.pos;
+                while self.ch.map_or(false, is_control_char) {
+                    let c = self.ch.unwrap();
+                    self.struct_control_char_err(c, false).emit();
+                    self.bump();
+                }
+                Some(TokenAndSpan {
+                    tok: token::Whitespace,
+                    sp: self.mk_sp(start_bpos, self.pos),
+                })
+            }
             _ => None,
         }
     }
@@ -1087,6 +1264,13 @@ impl<'a> StringReader<'a> {
     /// token, and updates the interner
     fn next_token_inner(&mut self) -> Result<token::Token, ()> {
         let c = self.ch;
+        if c.map_or(false, |c| self.sess.placeholder_chars.contains(&c)) {
+            let start = self.pos;
+            self.bump();
+            return Ok(self.with_str_from(start, |string| {
+                token::Placeholder(Symbol::intern(string))
+            }));
+        }
         if ident_start(c) &&
            match (c.unwrap(), self.nextch(), self.nextnextch()) {
             // Note: r as in r" or r#" is part of a raw string literal,
@@ -1503,6 +1687,12 @@ impl<'a> StringReader<'a> {
             '%' => {
                 Ok(self.binop(token::Percent))
             }
+            c if is_control_char(c) => {
+                let mut err = self.struct_control_char_err(c, true);
+                err.help("control characters can only appear in comments and literals");
+                self.fatal_errs.push(err);
+                Err(())
+            }
             c => {
                 let last_bpos = self.pos;
                 let bpos = self.next_pos;
@@ -1680,6 +1870,19 @@ pub fn is_pattern_whitespace(c: Option<char>) -> bool {
     c.map_or(false, Pattern_White_Space)
 }
 
+/// Whether `c` is a control character other than whitespace. These are only
+/// allowed in comments and literals.
+pub fn is_control_char(c: char) -> bool {
+    c.is_control() && !Pattern_White_Space(c)
+}
+
+/// Returns the byte offsets of the control characters in `src`, see
+/// `is_control_char`, including those in comments and literals. This lets
+/// tools check a buffer before lexing it.
+pub fn find_control_chars(src: &str) -> Vec<(usize, char)> {
+    src.char_indices().filter(|&(_, c)| is_control_char(c)).collect()
+}
+
 fn in_range(c: Option<char>, lo: char, hi: char) -> bool {
     match c {
         Some(c) => lo <= c && c <= hi,
@@ -1729,15 +1932,13 @@ fn ident_continue(c: Option<char>) -> bool {
 mod tests {
     use super::*;
 
-    use ast::{Ident, CrateConfig};
-    use symbol::Symbol;
+    use ast::Ident;
+    use symbol::{keywords, Symbol};
     use syntax_pos::{BytePos, Span, NO_EXPANSION};
     use codemap::CodeMap;
     use errors;
-    use feature_gate::UnstableFeatures;
     use parse::token;
-    use std::cell::RefCell;
-    use std::collections::HashSet;
+    use tokenstream::TokenTree;
     use std::io;
     use std::path::PathBuf;
     use std::rc::Rc;
@@ -1746,15 +1947,8 @@ mod tests {
         let emitter = errors::emitter::EmitterWriter::new(Box::new(io::sink()),
                                                           Some(cm.clone()),
                                                           false);
-        ParseSess {
-            span_diagnostic: errors::Handler::with_emitter(true, false, Box::new(emitter)),
-            unstable_features: UnstableFeatures::from_environment(),
-            config: CrateConfig::new(),
-            included_mod_stack: RefCell::new(Vec::new()),
-            code_map: cm,
-            missing_fragment_specifiers: RefCell::new(HashSet::new()),
-            non_modrs_mods: RefCell::new(vec![]),
-        }
+        let handler = errors::Handler::with_emitter(true, false, Box::new(emitter));
+        ParseSess::with_span_handler(handler, cm)
     }
 
     // open a string reader for the given string
@@ -1953,4 +2147,139 @@ mod tests {
         assert_eq!(lexer.next_token().tok,
                    token::DocComment(Symbol::intern("/// test")));
     }
+
+    #[test]
+    fn token_display_matches_source() {
+        let cm = Rc::new(CodeMap::new(FilePathMapping::empty()));
+        let sh = mk_sess(cm.clone());
+        let src = "1u8 'a /// doc\nb\"x\\n\" r#\"y\"# 2.5e3f64 '\\'' foo >>=";
+        let mut lexer = setup(&cm, &sh, src.to_string());
+        loop {
+            let TokenAndSpan { tok, sp } = lexer.real_token();
+            if tok == token::Eof {
+                assert_eq!(tok.approx_len(), 0);
+                break;
+            }
+            let snippet = cm.span_to_snippet(sp).unwrap();
+            assert_eq!(tok.display().to_string(), snippet.trim_right());
+            assert_eq!(tok.approx_len(), snippet.trim_right().chars().count());
+        }
+    }
+
+    #[test]
+    fn token_lists_match_lexer() {
+        let cm = Rc::new(CodeMap::new(FilePathMapping::empty()));
+        let sh = mk_sess(cm.clone());
+        for (tok, text) in token::punctuation_tokens() {
+            if tok == token::DotEq {
+                // Only produced by procedural macros.
+                continue;
+            }
+            assert_eq!(setup(&cm, &sh, text.to_string()).next_token().tok, tok);
+            assert_eq!(tok.display().to_string(), text);
+        }
+        for &(name, example) in token::LITERAL_KINDS {
+            match setup(&cm, &sh, example.to_string()).next_token().tok {
+                token::Literal(..) => {}
+                tok => panic!("{} literal `{}` lexed as {:?}", name, example, tok),
+            }
+        }
+
+        let keywords = token::all_keywords();
+        assert!(keywords.contains(&(keywords::Fn, token::KeywordKind::Strict)));
+        assert!(keywords.contains(&(keywords::Become, token::KeywordKind::Reserved)));
+        assert!(keywords.contains(&(keywords::Union, token::KeywordKind::Weak)));
+        assert!(keywords.contains(&(keywords::DollarCrate, token::KeywordKind::Special)));
+    }
+
+    #[test]
+    fn control_chars() {
+        let cm = Rc::new(CodeMap::new(FilePathMapping::empty()));
+        let mut sh = mk_sess(cm.clone());
+        let src = "a\u{0}\u{7} b \"\u{0}\"";
+        assert_eq!(find_control_chars(src), vec![(1, '\u{0}'), (2, '\u{7}'), (7, '\u{0}')]);
+
+        {
+            let mut lexer = setup(&cm, &sh, "a\u{0}b".to_string());
+            assert!(lexer.try_next_token().is_err());
+            assert_eq!(lexer.fatal_errs[0].message(), "control character in source: \\u{0}");
+            assert_eq!(lexer.fatal_errs[0].span.primary_span(),
+                       Some(Span::new(BytePos(1), BytePos(2), NO_EXPANSION)));
+            for err in &mut lexer.fatal_errs {
+                err.cancel();
+            }
+        }
+
+        sh.skip_control_chars = true;
+        let mut lexer = setup(&cm, &sh, src.to_string());
+        assert_eq!(lexer.next_token().tok, token::Ident(Ident::from_str("a")));
+        assert_eq!(lexer.next_token().tok, token::Whitespace);
+        assert_eq!(lexer.next_token().tok, token::Whitespace);
+        assert_eq!(lexer.next_token().tok, token::Ident(Ident::from_str("b")));
+        assert_eq!(sh.span_diagnostic.err_count(), 2);
+    }
+
+    #[test]
+    fn crlf_policy() {
+        let cm = Rc::new(CodeMap::new(FilePathMapping::empty()));
+        let sh = mk_sess(cm.clone());
+        setup(&cm, &sh, "a\r\nb\r\n".to_string());
+        assert_eq!(sh.span_diagnostic.err_count(), 0);
+
+        cm.set_crlf_policy(CrlfPolicy::Error);
+        let mut lexer = setup(&cm, &sh, "a\nb\r\nc\r\n".to_string());
+        assert_eq!(sh.span_diagnostic.err_count(), 1);
+        assert_eq!(lexer.next_token().tok, token::Ident(Ident::from_str("a")));
+
+        // Lexing the file again does not report it again.
+        StringReader::new(&sh, lexer.filemap.clone());
+        assert_eq!(sh.span_diagnostic.err_count(), 1);
+    }
+
+    #[test]
+    fn token_tree_limits() {
+        let cm = Rc::new(CodeMap::new(FilePathMapping::empty()));
+        let mut sh = mk_sess(cm.clone());
+        let nested = format!("a {}b{} c", "([{".repeat(200), "}])".repeat(200));
+        let stream = {
+            let mut lexer = setup(&cm, &sh, nested.clone());
+            lexer.real_token();
+            lexer.parse_all_token_trees().unwrap()
+        };
+        assert_eq!(stream.trees().count(), 3);
+        let mut tree = stream.trees().nth(1).unwrap();
+        for _ in 0..600 {
+            tree = match tree {
+                TokenTree::Delimited(_, delimited) => delimited.stream().trees().next().unwrap(),
+                TokenTree::Token(..) => panic!("expected a delimited tree"),
+            };
+        }
+        assert_eq!(tree, TokenTree::Token(tree.span(), token::Ident(Ident::from_str("b"))));
+
+        // Unclosed delimiters are closed at the end of the file.
+        let stream = {
+            let mut lexer = setup(&cm, &sh, "a (b [c".to_string());
+            lexer.real_token();
+            lexer.parse_all_token_trees().unwrap()
+        };
+        assert_eq!(stream.trees().count(), 2);
+        assert_eq!(sh.span_diagnostic.err_count(), 1);
+
+        sh.max_delim_depth = Some(100);
+        {
+            let mut lexer = setup(&cm, &sh, nested.clone());
+            lexer.real_token();
+            let mut err = lexer.parse_all_token_trees().err().unwrap();
+            assert_eq!(err.message(), "delimiters are nested too deeply");
+            err.cancel();
+        }
+
+        sh.max_delim_depth = None;
+        sh.max_tokens = Some(1000);
+        let mut lexer = setup(&cm, &sh, nested);
+        lexer.real_token();
+        let mut err = lexer.parse_all_token_trees().err().unwrap();
+        assert_eq!(err.message(), "this file contains too many tokens");
+        err.cancel();
+    }
 }
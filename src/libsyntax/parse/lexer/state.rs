@@ -0,0 +1,354 @@
+// Copyright 2018 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The lexical state at the end of a partial source file.
+//!
+//! Editors highlighting a file incrementally, or deciding how a newline
+//! continues the token being typed, need to know whether a prefix of the file
+//! ends inside a comment or a literal. The lexer itself reports unterminated
+//! comments and literals as fatal errors, so `lexical_state` scans the prefix
+//! on its own. It only tracks what is needed for that, and does not report
+//! malformed tokens.
+//!
+//! `LineTokenIndex` keeps the state at the start of each line, so that an
+//! editor can relex a file line by line after an edit. Lines are lexed on
+//! their own, starting in the state the previous line ended in. The only
+//! tokens spanning lines are the comments and literals such a carried over
+//! state is inside of, so relexing can stop at the first line after the edit
+//! which starts in the same state as before.
+
+use super::{ident_continue, ident_start};
+use syntax_pos::FileMap;
+
+use std::ops::Range;
+
+/// What the end of a prefix of a source file is inside of, see
+/// `lexical_state`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LexicalState {
+    /// Between tokens, or in a token which is not a comment or literal.
+    Normal,
+    /// In a `//` comment, which ends at the next newline. `doc` is set for
+    /// `///` and `//!` comments.
+    LineComment { doc: bool },
+    /// In a block comment, nested `depth` levels deep. `doc` is set for
+    /// `/**` and `/*!` comments.
+    BlockComment { depth: usize, doc: bool },
+    /// In a string literal, a byte string for `b"`. `escape` is set if the
+    /// last character starts an escape, in which case a newline continues
+    /// the string without being part of it.
+    Str { byte: bool, escape: bool },
+    /// In a raw string literal, which ends at a `"` followed by `hashes` `#`s.
+    RawStr { byte: bool, hashes: usize },
+    /// In a character literal, a byte literal for `b'`.
+    Char { byte: bool },
+}
+
+/// Returns the lexical state at the end of `src`, a prefix of a source file.
+/// A `'` at the very end is taken to start a character literal, rather than
+/// a lifetime.
+pub fn lexical_state(src: &str) -> LexicalState {
+    lexical_state_from(LexicalState::Normal, src)
+}
+
+/// Returns the lexical state at the end of `src`, which continues input that
+/// ended in `state`.
+pub fn lexical_state_from(state: LexicalState, src: &str) -> LexicalState {
+    let chars = src.chars().collect::<Vec<_>>();
+    let mut i = match resume(state, &chars, 0) {
+        Ok(end) => end,
+        Err(state) => return state,
+    };
+    let at = |i: usize| chars.get(i).cloned();
+    while i < chars.len() {
+        let state = match chars[i] {
+            '/' if at(i + 1) == Some('/') => {
+                let doc = (at(i + 2) == Some('/') && at(i + 3) != Some('/')) ||
+                          at(i + 2) == Some('!');
+                LexicalState::LineComment { doc }
+            }
+            '/' if at(i + 1) == Some('*') => {
+                let doc = (at(i + 2) == Some('*') && at(i + 3) != Some('*') &&
+                           at(i + 3) != Some('/')) ||
+                          at(i + 2) == Some('!');
+                i += 2;
+                LexicalState::BlockComment { depth: 1, doc }
+            }
+            '"' => {
+                i += 1;
+                LexicalState::Str { byte: false, escape: false }
+            }
+            '\'' => {
+                i += 1;
+                LexicalState::Char { byte: false }
+            }
+            c if ident_start(Some(c)) => {
+                let start = i;
+                while ident_continue(at(i)) {
+                    i += 1;
+                }
+                let ident = chars[start..i].iter().cloned().collect::<String>();
+                let byte = ident == "b" || ident == "br";
+                match (&*ident, at(i)) {
+                    ("b", Some('"')) => {
+                        i += 1;
+                        LexicalState::Str { byte, escape: false }
+                    }
+                    ("b", Some('\'')) => {
+                        i += 1;
+                        LexicalState::Char { byte }
+                    }
+                    ("r", Some('"')) | ("r", Some('#')) |
+                    ("br", Some('"')) | ("br", Some('#')) => {
+                        let hashes = chars[i..].iter().take_while(|&&c| c == '#').count();
+                        i += hashes;
+                        if at(i) != Some('"') {
+                            // Not a raw string, `r#` is not valid by itself.
+                            continue;
+                        }
+                        i += 1;
+                        LexicalState::RawStr { byte, hashes }
+                    }
+                    _ => continue,
+                }
+            }
+            // Numbers, so that suffixes are not taken for identifiers.
+            '0' ... '9' => {
+                while ident_continue(at(i)) {
+                    i += 1;
+                }
+                continue;
+            }
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+        match resume(state, &chars, i) {
+            Ok(end) => i = end,
+            Err(state) => return state,
+        }
+    }
+    LexicalState::Normal
+}
+
+/// Skips the rest of a comment or literal in `state` starting at `i`,
+/// returning the index after it, or the state at the end of the input if it
+/// ends first.
+fn resume(state: LexicalState, chars: &[char], mut i: usize) -> Result<usize, LexicalState> {
+    let at = |i: usize| chars.get(i).cloned();
+    match state {
+        LexicalState::Normal => Ok(i),
+        LexicalState::LineComment { .. } => {
+            match chars[i..].iter().position(|&c| c == '\n') {
+                Some(len) => Ok(i + len + 1),
+                None => Err(state),
+            }
+        }
+        LexicalState::BlockComment { mut depth, doc } => {
+            while depth > 0 {
+                match (at(i), at(i + 1)) {
+                    (None, _) => return Err(LexicalState::BlockComment { depth, doc }),
+                    (Some('/'), Some('*')) => { depth += 1; i += 2; }
+                    (Some('*'), Some('/')) => { depth -= 1; i += 2; }
+                    _ => i += 1,
+                }
+            }
+            Ok(i)
+        }
+        LexicalState::Str { byte, escape } => {
+            if escape {
+                i += 1;
+            }
+            loop {
+                match at(i) {
+                    None if i > chars.len() => return Err(state),
+                    None => return Err(LexicalState::Str { byte, escape: false }),
+                    Some('"') => return Ok(i + 1),
+                    Some('\\') if i + 1 == chars.len() => {
+                        return Err(LexicalState::Str { byte, escape: true });
+                    }
+                    Some('\\') => i += 2,
+                    Some(_) => i += 1,
+                }
+            }
+        }
+        LexicalState::RawStr { hashes, .. } => {
+            loop {
+                match at(i) {
+                    None => return Err(state),
+                    Some('"') if chars[i + 1..].iter().take(hashes)
+                                               .filter(|&&c| c == '#')
+                                               .count() == hashes => {
+                        return Ok(i + hashes + 1);
+                    }
+                    _ => i += 1,
+                }
+            }
+        }
+        // The character is at `i`. If there is no closing `'` after it, the
+        // `'` started a lifetime or label instead, which ends before `i`.
+        LexicalState::Char { .. } => {
+            match (at(i), at(i + 1)) {
+                (None, _) => Err(state),
+                (Some('\\'), _) => {
+                    // The escape is at least two characters, like `\n` or `\u{..}`.
+                    match chars[i + 1..].iter().skip(1).position(|&c| c == '\'' || c == '\n') {
+                        Some(len) if chars[i + 2 + len] == '\'' => Ok(i + 3 + len),
+                        Some(len) => Ok(i + 2 + len),
+                        None => Err(state),
+                    }
+                }
+                (Some(_), Some('\'')) => Ok(i + 2),
+                (Some(_), None) => Err(state),
+                (Some(_), Some(_)) => Ok(i),
+            }
+        }
+    }
+}
+
+/// The lexical state at the start of each line of a file, see the module
+/// documentation. Lines are separated by `\n`, and include it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LineTokenIndex {
+    /// The state at the start of each line, followed by the state at the end
+    /// of the file.
+    states: Vec<LexicalState>,
+}
+
+impl LineTokenIndex {
+    pub fn new(src: &str) -> LineTokenIndex {
+        let mut states = vec![LexicalState::Normal];
+        for line in split_lines(src) {
+            let state = lexical_state_from(states[states.len() - 1], line);
+            states.push(state);
+        }
+        LineTokenIndex { states }
+    }
+
+    /// Returns the index of `filemap`, or `None` if its source is not
+    /// available.
+    pub fn from_filemap(filemap: &FileMap) -> Option<LineTokenIndex> {
+        filemap.src.as_ref().map(|src| LineTokenIndex::new(src))
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.states.len() - 1
+    }
+
+    /// Returns the state at the start of line `line`, counting from zero.
+    /// This is what a relexed line has to be lexed from.
+    pub fn state_at_line(&self, line: usize) -> Option<LexicalState> {
+        if line < self.line_count() { Some(self.states[line]) } else { None }
+    }
+
+    /// Returns the state at the end of the file.
+    pub fn end_state(&self) -> LexicalState {
+        self.states[self.states.len() - 1]
+    }
+
+    /// Updates the index after `removed`, a range of lines in the previously
+    /// indexed source, was replaced with `added` lines, giving `src`.
+    ///
+    /// Returns the range of lines in `src` which need to be relexed. It starts
+    /// at the first edited line, and ends at the first line after the edit
+    /// which starts in the same state as before, or at the end of the file.
+    /// Lines after that lex to the same tokens as before.
+    pub fn relex_lines(&mut self, src: &str, removed: Range<usize>, added: usize)
+                       -> Range<usize> {
+        let lines = split_lines(src);
+        assert!(removed.start <= removed.end && removed.end <= self.line_count());
+        assert_eq!(lines.len(), self.line_count() - (removed.end - removed.start) + added);
+
+        let old_states = self.states.split_off(removed.end);
+        self.states.truncate(removed.start + 1);
+        let edited_end = removed.start + added;
+        let mut line = removed.start;
+        loop {
+            if line >= edited_end && self.states[line] == old_states[line - edited_end] {
+                self.states.extend_from_slice(&old_states[line - edited_end + 1..]);
+                return removed.start..line;
+            }
+            if line == lines.len() {
+                return removed.start..line;
+            }
+            let state = lexical_state_from(self.states[line], lines[line]);
+            self.states.push(state);
+            line += 1;
+        }
+    }
+}
+
+fn split_lines(src: &str) -> Vec<&str> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for (i, _) in src.match_indices('\n') {
+        lines.push(&src[start..i + 1]);
+        start = i + 1;
+    }
+    lines.push(&src[start..]);
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::LexicalState::*;
+
+    #[test]
+    fn states() {
+        assert_eq!(lexical_state("fn main() { let x = 'a'; }"), Normal);
+        assert_eq!(lexical_state("fn f<'a>(x: &'a str) {} // note"), LineComment { doc: false });
+        assert_eq!(lexical_state("/// Docs"), LineComment { doc: true });
+        assert_eq!(lexical_state("//// Not docs\nfn f() {}"), Normal);
+        assert_eq!(lexical_state("/* a /* b */ /* c"), BlockComment { depth: 2, doc: false });
+        assert_eq!(lexical_state("/*! a \"b */"), Normal);
+        assert_eq!(lexical_state("/** a"), BlockComment { depth: 1, doc: true });
+        assert_eq!(lexical_state("let s = \"a // b"), Str { byte: false, escape: false });
+        assert_eq!(lexical_state("let s = b\"a\\\"b\\"), Str { byte: true, escape: true });
+        assert_eq!(lexical_state("r##\"a\"# \"##"), Normal);
+        assert_eq!(lexical_state("br##\"a\"# \""), RawStr { byte: true, hashes: 2 });
+        assert_eq!(lexical_state("for\"a"), Str { byte: false, escape: false });
+        assert_eq!(lexical_state("let c = '\\u{1F"), Char { byte: false });
+        assert_eq!(lexical_state("let c = b'"), Char { byte: true });
+        assert_eq!(lexical_state("let c = '\\''; 'outer: loop {}"), Normal);
+        assert_eq!(lexical_state("1u8 /*"), BlockComment { depth: 1, doc: false });
+    }
+
+    #[test]
+    fn relex_lines() {
+        let src = "fn f() {\n    let s = \"a\n    b\";\n}\n";
+        let mut index = LineTokenIndex::new(src);
+        assert_eq!(index.line_count(), 5);
+        assert_eq!(index.state_at_line(2), Some(Str { byte: false, escape: false }));
+        assert_eq!(index.state_at_line(3), Some(Normal));
+        assert_eq!(index.state_at_line(5), None);
+
+        // Edits which do not change the state at the end of the edited lines
+        // do not need the following lines to be relexed.
+        let src = "fn f() {\n    let s = \"a\n    b\";\n} // \"\n";
+        assert_eq!(index.relex_lines(src, 3..4, 1), 3..4);
+        assert_eq!(index, LineTokenIndex::new(src));
+
+        let src = "/* fn f() {\n    let s = \"a\n    b\";\n} // \"\n";
+        assert_eq!(index.relex_lines(src, 0..1, 1), 0..5);
+        assert_eq!(index.end_state(), BlockComment { depth: 1, doc: false });
+        assert_eq!(index, LineTokenIndex::new(src));
+
+        let src = "/* fn f() {\n*/\n    b\";\n} // \"\n";
+        assert_eq!(index.relex_lines(src, 1..2, 1), 1..5);
+        assert_eq!(index.state_at_line(3), Some(Str { byte: false, escape: false }));
+        assert_eq!(index, LineTokenIndex::new(src));
+
+        let src = "/* fn f() {\n*/\n} // \"\n";
+        assert_eq!(index.relex_lines(src, 2..3, 0), 2..2);
+        assert_eq!(index, LineTokenIndex::new(src));
+    }
+}
@@ -10,84 +10,96 @@
 
 use print::pprust::token_to_string;
 use parse::lexer::StringReader;
-use parse::{token, PResult};
+use parse::{messages, token, PResult};
+use syntax_pos::Span;
 use tokenstream::{Delimited, TokenStream, TokenTree};
 
+/// A delimited group whose closing delimiter has not been reached yet.
+struct OpenGroup {
+    delim: token::DelimToken,
+    // The span of the opening delimiter.
+    span: Span,
+    tts: Vec<TokenStream>,
+}
+
 impl<'a> StringReader<'a> {
     // Parse a stream of tokens into a list of `TokenTree`s, up to an `Eof`.
+    //
+    // Delimited groups are tracked on an explicit stack rather than by
+    // recursion, so deeply nested delimiters cannot overflow the stack. The
+    // nesting depth and the number of tokens are limited by
+    // `ParseSess::max_delim_depth` and `ParseSess::max_tokens`.
     pub fn parse_all_token_trees(&mut self) -> PResult<'a, TokenStream> {
         let mut tts = Vec::new();
-        while self.token != token::Eof {
-            let tree = self.parse_token_tree()?;
-            let is_joint = tree.span().hi() == self.span.lo() && token::is_op(&self.token);
-            tts.push(if is_joint { tree.joint() } else { tree.into() });
-        }
-        Ok(TokenStream::concat(tts))
-    }
-
-    // Parse a stream of tokens into a list of `TokenTree`s, up to a `CloseDelim`.
-    fn parse_token_trees_until_close_delim(&mut self) -> TokenStream {
-        let mut tts = vec![];
+        let mut groups: Vec<OpenGroup> = Vec::new();
         loop {
-            if let token::CloseDelim(..) = self.token {
-                return TokenStream::concat(tts);
+            if let Some(max_tokens) = self.sess.max_tokens {
+                if self.token_count > max_tokens {
+                    let msg = self.sess.span_diagnostic.message(&messages::TOO_MANY_TOKENS, &[]);
+                    let mut err = self.sess.span_diagnostic.struct_span_fatal(self.span, &msg);
+                    err.note(&format!("the maximum number of tokens is {}", max_tokens));
+                    return Err(err);
+                }
             }
-            let tree = match self.parse_token_tree() {
-                Ok(tree) => tree,
-                Err(mut e) => {
-                    e.emit();
-                    return TokenStream::concat(tts);
+            let tree = match self.token {
+                token::Eof => {
+                    if groups.is_empty() {
+                        break;
+                    }
+                    // Report the unclosed delimiters, then close all groups
+                    // at the end of the file.
+                    let msg = self.sess.span_diagnostic.message(&messages::UNCLOSED_DELIM, &[]);
+                    let mut err = self.sess.span_diagnostic.struct_span_err(self.span, &msg);
+                    for &(_, sp) in &self.open_braces {
+                        err.span_label(sp, "unclosed delimiter");
+                    }
+                    err.emit();
+                    while let Some(group) = groups.pop() {
+                        let tree = self.close_group(group);
+                        push_tree(&mut tts, &mut groups, tree, false);
+                    }
+                    continue;
                 }
-            };
-            let is_joint = tree.span().hi() == self.span.lo() && token::is_op(&self.token);
-            tts.push(if is_joint { tree.joint() } else { tree.into() });
-        }
-    }
-
-    fn parse_token_tree(&mut self) -> PResult<'a, TokenTree> {
-        match self.token {
-            token::Eof => {
-                let msg = "this file contains an un-closed delimiter";
-                let mut err = self.sess.span_diagnostic.struct_span_err(self.span, msg);
-                for &(_, sp) in &self.open_braces {
-                    err.span_help(sp, "did you mean to close this delimiter?");
+                token::OpenDelim(delim) => {
+                    if let Some(max_depth) = self.sess.max_delim_depth {
+                        if groups.len() >= max_depth {
+                            let msg = self.sess.span_diagnostic.message(
+                                &messages::TOO_DEEPLY_NESTED, &[]);
+                            let mut err =
+                                self.sess.span_diagnostic.struct_span_fatal(self.span, &msg);
+                            err.note(&format!("the maximum nesting depth is {}", max_depth));
+                            return Err(err);
+                        }
+                    }
+                    // Parse the open delimiter.
+                    self.open_braces.push((delim, self.span));
+                    groups.push(OpenGroup { delim, span: self.span, tts: Vec::new() });
+                    self.real_token();
+                    continue;
                 }
-                Err(err)
-            },
-            token::OpenDelim(delim) => {
-                // The span for beginning of the delimited section
-                let pre_span = self.span;
-
-                // Parse the open delimiter.
-                self.open_braces.push((delim, self.span));
-                self.real_token();
-
-                // Parse the token trees within the delimiters.
-                // We stop at any delimiter so we can try to recover if the user
-                // uses an incorrect delimiter.
-                let tts = self.parse_token_trees_until_close_delim();
-
-                // Expand to cover the entire delimited token tree
-                let span = pre_span.with_hi(self.span.hi());
-
-                match self.token {
-                    // Correct delimiter.
-                    token::CloseDelim(d) if d == delim => {
+                token::CloseDelim(delim) if !groups.is_empty() => {
+                    let group = groups.pop().unwrap();
+                    let correct = group.delim == delim;
+                    // Expand to cover the entire delimited token tree
+                    let tree = self.close_group(group);
+                    if correct {
+                        // Correct delimiter.
                         self.open_braces.pop().unwrap();
 
                         // Parse the close delimiter.
                         self.real_token();
-                    }
-                    // Incorrect delimiter.
-                    token::CloseDelim(other) => {
+                    } else {
+                        // Incorrect delimiter.
                         let token_str = token_to_string(&self.token);
-                        let msg = format!("incorrect close delimiter: `{}`", token_str);
+                        let msg = self.sess.span_diagnostic.message(
+                            &messages::INCORRECT_CLOSE_DELIM, &[("found", &token_str)]);
                         let mut err = self.sess.span_diagnostic.struct_span_err(self.span, &msg);
+                        err.span_label(self.span, "incorrect close delimiter");
                         // This is a conservative error: only report the last unclosed delimiter.
                         // The previous unclosed delimiters could actually be closed! The parser
                         // just hasn't gotten to them yet.
                         if let Some(&(_, sp)) = self.open_braces.last() {
-                            err.span_note(sp, "unclosed delimiter");
+                            err.span_label(sp, "unclosed delimiter");
                         };
                         err.emit();
 
@@ -100,36 +112,48 @@ impl<'a> StringReader<'a> {
                         // fn foo() {
                         //     bar(baz(
                         // }  // Incorrect delimiter but matches the earlier `{`
-                        if !self.open_braces.iter().any(|&(b, _)| b == other) {
+                        if !self.open_braces.iter().any(|&(b, _)| b == delim) {
                             self.real_token();
                         }
                     }
-                    token::Eof => {
-                        // Silently recover, the EOF token will be seen again
-                        // and an error emitted then. Thus we don't pop from
-                        // self.open_braces here.
-                    },
-                    _ => {}
+                    tree
                 }
-
-                Ok(TokenTree::Delimited(span, Delimited {
-                    delim,
-                    tts: tts.into(),
-                }))
-            },
-            token::CloseDelim(_) => {
-                // An unexpected closing delimiter (i.e., there is no
-                // matching opening delimiter).
-                let token_str = token_to_string(&self.token);
-                let msg = format!("unexpected close delimiter: `{}`", token_str);
-                let err = self.sess.span_diagnostic.struct_span_err(self.span, &msg);
-                Err(err)
-            },
-            _ => {
-                let tt = TokenTree::Token(self.span, self.token.clone());
-                self.real_token();
-                Ok(tt)
-            }
+                token::CloseDelim(_) => {
+                    // An unexpected closing delimiter (i.e., there is no
+                    // matching opening delimiter).
+                    let token_str = token_to_string(&self.token);
+                    let msg = self.sess.span_diagnostic.message(&messages::UNEXPECTED_CLOSE_DELIM,
+                                                                &[("found", &token_str)]);
+                    let err = self.sess.span_diagnostic.struct_span_err(self.span, &msg);
+                    return Err(err);
+                }
+                _ => {
+                    let tt = TokenTree::Token(self.span, self.token.clone());
+                    self.real_token();
+                    tt
+                }
+            };
+            let is_joint = tree.span().hi() == self.span.lo() && token::is_op(&self.token);
+            push_tree(&mut tts, &mut groups, tree, is_joint);
         }
+        Ok(TokenStream::concat(tts))
     }
+
+    // Builds the token tree of `group`, which ends at the current token.
+    fn close_group(&self, group: OpenGroup) -> TokenTree {
+        TokenTree::Delimited(group.span.with_hi(self.span.hi()), Delimited {
+            delim: group.delim,
+            tts: TokenStream::concat(group.tts).into(),
+        })
+    }
+}
+
+// Adds `tree` to the innermost open group, or to the top level.
+fn push_tree(tts: &mut Vec<TokenStream>, groups: &mut Vec<OpenGroup>, tree: TokenTree,
+             is_joint: bool) {
+    let tts = match groups.last_mut() {
+        Some(group) => &mut group.tts,
+        None => tts,
+    };
+    tts.push(if is_joint { tree.joint() } else { tree.into() });
 }
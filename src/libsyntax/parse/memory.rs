@@ -0,0 +1,112 @@
+// Copyright 2018 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Estimates of the memory used by parsing, see `ParseSess::memory_report`.
+//!
+//! The numbers are estimates computed from the sizes and capacities of the
+//! data structures involved, not measurements of the allocator, and are
+//! meant for enforcing coarse memory budgets.
+
+use ast;
+use syntax_pos::Span;
+use tokenstream::TokenTree;
+use visit::{self, Visitor};
+
+use std::mem;
+
+/// An estimate of the memory used by a `ParseSess`, in bytes.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryReport {
+    /// Source text and position tables of all files in the codemap.
+    pub filemaps: usize,
+    /// Token trees produced by lexing source files.
+    pub token_trees: usize,
+    /// The symbol interner. The interner is shared by all sessions on the
+    /// current thread, so this is not specific to one session.
+    pub interner: usize,
+    /// Spans which are too large to be stored inline, or have a syntax
+    /// context. Like the symbol interner, these are shared by all sessions on
+    /// the current thread.
+    pub spans: usize,
+    /// The ASTs of the crates parsed in this session.
+    pub ast: usize,
+}
+
+impl MemoryReport {
+    pub fn total(&self) -> usize {
+        self.filemaps + self.token_trees + self.interner + self.spans + self.ast
+    }
+}
+
+/// Returns an estimate of the memory used by `count` token trees.
+pub fn token_trees_memory_usage(count: usize) -> usize {
+    count * mem::size_of::<TokenTree>()
+}
+
+/// Returns an estimate of the memory used by the AST of `krate`.
+pub fn crate_memory_usage(krate: &ast::Crate) -> usize {
+    let mut counter = AstSizeCounter { size: mem::size_of::<ast::Crate>() };
+    visit::walk_crate(&mut counter, krate);
+    counter.size
+}
+
+struct AstSizeCounter {
+    size: usize,
+}
+
+impl<'ast> Visitor<'ast> for AstSizeCounter {
+    fn visit_item(&mut self, i: &'ast ast::Item) {
+        self.size += mem::size_of::<ast::Item>();
+        visit::walk_item(self, i)
+    }
+    fn visit_foreign_item(&mut self, i: &'ast ast::ForeignItem) {
+        self.size += mem::size_of::<ast::ForeignItem>();
+        visit::walk_foreign_item(self, i)
+    }
+    fn visit_trait_item(&mut self, ti: &'ast ast::TraitItem) {
+        self.size += mem::size_of::<ast::TraitItem>();
+        visit::walk_trait_item(self, ti)
+    }
+    fn visit_impl_item(&mut self, ii: &'ast ast::ImplItem) {
+        self.size += mem::size_of::<ast::ImplItem>();
+        visit::walk_impl_item(self, ii)
+    }
+    fn visit_block(&mut self, b: &'ast ast::Block) {
+        self.size += mem::size_of::<ast::Block>();
+        visit::walk_block(self, b)
+    }
+    fn visit_stmt(&mut self, s: &'ast ast::Stmt) {
+        self.size += mem::size_of::<ast::Stmt>();
+        visit::walk_stmt(self, s)
+    }
+    fn visit_expr(&mut self, e: &'ast ast::Expr) {
+        self.size += mem::size_of::<ast::Expr>();
+        visit::walk_expr(self, e)
+    }
+    fn visit_pat(&mut self, p: &'ast ast::Pat) {
+        self.size += mem::size_of::<ast::Pat>();
+        visit::walk_pat(self, p)
+    }
+    fn visit_ty(&mut self, t: &'ast ast::Ty) {
+        self.size += mem::size_of::<ast::Ty>();
+        visit::walk_ty(self, t)
+    }
+    fn visit_path_segment(&mut self, path_span: Span, s: &'ast ast::PathSegment) {
+        self.size += mem::size_of::<ast::PathSegment>();
+        visit::walk_path_segment(self, path_span, s)
+    }
+    fn visit_attribute(&mut self, _attr: &'ast ast::Attribute) {
+        self.size += mem::size_of::<ast::Attribute>();
+    }
+    fn visit_mac(&mut self, mac: &'ast ast::Mac) {
+        self.size += mem::size_of::<ast::Mac>();
+        visit::walk_mac(self, mac)
+    }
+}
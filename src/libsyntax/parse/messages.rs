@@ -0,0 +1,51 @@
+// Copyright 2018 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The translatable messages of the parser, see `errors::catalog`.
+//!
+//! Token arguments are the source text of the token, while `{found}` in the
+//! `expected ..., found {found}` messages without backticks is a description
+//! like ``keyword `fn` ``.
+
+use errors::catalog::Message;
+
+macro_rules! messages {
+    ($($name:ident: $id:expr => $template:expr,)*) => {
+        $(pub const $name: Message = Message { id: $id, template: $template };)*
+
+        /// All messages of the parser, e.g. to check catalogs for missing
+        /// translations.
+        pub const ALL: &[Message] = &[$($name),*];
+    }
+}
+
+messages! {
+    EXPECTED_TOKEN: "parse-expected-token" => "expected `{expected}`, found `{found}`",
+    EXPECTED_ONE_OF: "parse-expected-one-of" => "expected one of {expected}, found `{found}`",
+    EXPECTED_ONE_OF_LABEL: "parse-expected-one-of-label" => "expected one of {expected} here",
+    POSSIBLE_TOKENS: "parse-possible-tokens" => "{count} possible tokens",
+    EXPECTED: "parse-expected" => "expected {expected}, found `{found}`",
+    EXPECTED_LABEL: "parse-expected-label" => "expected {expected} here",
+    UNEXPECTED_TOKEN: "parse-unexpected-token" => "unexpected token: `{found}`",
+    UNEXPECTED_TOKEN_LABEL: "parse-unexpected-token-label" => "unexpected token",
+    UNEXPECTED_AFTER_LABEL: "parse-unexpected-after-label" => "unexpected token after this",
+    EXPECTED_IDENT: "parse-expected-ident" => "expected identifier, found {found}",
+    EXPECTED_IDENT_LABEL: "parse-expected-ident-label" => "expected identifier",
+    EXPECTED_TYPE: "parse-expected-type" => "expected type, found {found}",
+    EXPECTED_EXPR: "parse-expected-expr" => "expected expression, found {found}",
+    EXPECTED_PAT: "parse-expected-pat" => "expected pattern, found {found}",
+    EXPECTED_ITEM: "parse-expected-item" => "expected item, found `{found}`",
+    UNCLOSED_DELIM: "parse-unclosed-delim" => "this file contains an un-closed delimiter",
+    INCORRECT_CLOSE_DELIM: "parse-incorrect-close-delim" => "incorrect close delimiter: `{found}`",
+    UNEXPECTED_CLOSE_DELIM: "parse-unexpected-close-delim" =>
+        "unexpected close delimiter: `{found}`",
+    TOO_DEEPLY_NESTED: "parse-too-deeply-nested" => "delimiters are nested too deeply",
+    TOO_MANY_TOKENS: "parse-too-many-tokens" => "this file contains too many tokens",
+}
@@ -12,21 +12,28 @@
 
 use ast::{self, CrateConfig};
 use codemap::{CodeMap, FilePathMapping};
-use syntax_pos::{self, Span, FileMap, NO_EXPANSION, FileName};
+use config;
+use syntax_pos::{self, BytePos, Span, FileMap, Pos, NO_EXPANSION, FileName};
 use errors::{Handler, ColorConfig, DiagnosticBuilder};
 use feature_gate::UnstableFeatures;
-use parse::parser::Parser;
+use parse::contextual::{ContextualKeywords, ContextualKeywordRule};
+use parse::parser::{Parser, PathStyle};
 use ptr::P;
 use str::char_at;
-use symbol::Symbol;
+use symbol::{self, Symbol};
 use tokenstream::{TokenStream, TokenTree};
 
-use std::cell::RefCell;
-use std::collections::HashSet;
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::cmp;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::{i8, i16, i32, i64, u8, u16, u32, u64};
 use std::iter;
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::str;
+use std::time::{Duration, Instant};
 
 pub type PResult<'a, T> = Result<T, DiagnosticBuilder<'a>>;
 
@@ -39,7 +46,27 @@ pub mod attr;
 
 pub mod common;
 pub mod classify;
+pub mod contextual;
+pub mod doc_code;
+pub mod duplicates;
+pub mod events;
+pub mod grammar;
+pub mod messages;
+pub mod mod_path;
+pub mod naming;
 pub mod obsolete;
+pub mod provenance;
+pub mod sniff;
+pub mod timing;
+pub mod token_offsets;
+pub mod memory;
+pub mod asm;
+pub mod format;
+
+use self::memory::MemoryReport;
+use self::provenance::{EntryPoint, ParseProvenance};
+use self::timing::{ParsePhase, ParseTimings};
+use self::token_offsets::TokenOffsets;
 
 /// Info about a parsing session.
 pub struct ParseSess {
@@ -50,8 +77,86 @@ pub struct ParseSess {
     // Spans where a `mod foo;` statement was included in a non-mod.rs file.
     // These are used to issue errors if the non_modrs_mods feature is not enabled.
     pub non_modrs_mods: RefCell<Vec<(ast::Ident, Span)>>,
-    /// Used to determine and report recursive mod inclusions
+    /// If set, crate parsing reports a single error for input that is
+    /// obviously not Rust source (see `sniff::sniff_non_rust`).
+    pub reject_non_rust_input: bool,
+    /// If set, numeric literals with unknown suffixes (`42px`) are parsed as
+    /// `LitKind::UnknownSuffix` instead of being reported immediately, so
+    /// that macros can accept them. Any that survive expansion are reported
+    /// by AST validation.
+    pub preserve_unknown_suffixes: bool,
+    /// Characters the lexer reads as `token::Placeholder` instead of reporting
+    /// them, typically `'\u{FFFD}'` or a codepoint chosen by an editor, so that
+    /// an editor can insert a placeholder at the cursor and still get a parse
+    /// tree around it.
+    pub placeholder_chars: Vec<char>,
+    /// If set, parse errors are labelled with all the constructs they were
+    /// raised in, like "while parsing this `match` expression", to help find
+    /// them in large items. Otherwise only errors at the end of the input are.
+    pub label_enclosing_constructs: bool,
+    /// If set, `const` generic parameters (`<const N: usize>`) are parsed into
+    /// `GenericParam::Const`, for tools analyzing code which uses them. The
+    /// compiler itself does not support them.
+    pub const_generics: bool,
+    /// If set, the parser accepts extra `+`s between bounds and warns about
+    /// them, as well as about trailing `+`s in types and duplicate bounds, with
+    /// suggestions to remove them. Otherwise an extra `+` is an error and the
+    /// others are accepted silently.
+    pub recover_redundant_bounds: bool,
+    /// If set, control characters outside of comments and literals, like NUL,
+    /// are reported as errors and skipped like whitespace. Otherwise the first
+    /// one is a fatal error.
+    pub skip_control_chars: bool,
+    /// If set, a metadata block delimited by lines of dashes at the top of a
+    /// file is recorded in `FileMap::frontmatter` and skipped by the parser.
+    pub allow_frontmatter: bool,
+    /// The maximum nesting depth of delimiters in a file, if any. Deeper
+    /// nesting is a fatal error when the file is lexed, rather than overflowing
+    /// the stack of the parser later on.
+    pub max_delim_depth: Option<usize>,
+    /// The maximum number of tokens in a file, if any. Larger files are a fatal
+    /// error when they are lexed.
+    pub max_tokens: Option<usize>,
+    /// If set, the parser warns about items which do not follow the naming
+    /// conventions, see `naming`.
+    pub check_naming: bool,
+    /// The naming warnings of the parser, in the order they were reported.
+    pub naming_warnings: RefCell<Vec<naming::NamingWarning>>,
+    /// If set, crate parsing reports names declared twice in the same
+    /// parameter list, struct or enum, see `duplicates`.
+    pub check_duplicate_names: bool,
+    /// If set, cfg-stripping records the code it removes, see
+    /// `config::UnconfiguredRegion`.
+    pub record_unconfigured_regions: bool,
+    /// The code removed by cfg-stripping, in the order it was removed.
+    pub unconfigured_regions: RefCell<Vec<config::UnconfiguredRegion>>,
+    /// Used to determine and report recursive mod inclusions, by the canonical
+    /// paths of the files, see `CodeMap::canonicalize`.
     included_mod_stack: RefCell<Vec<PathBuf>>,
+    /// Extensions invoked for contextual keywords at item position.
+    item_extensions: RefCell<HashMap<ast::Name, Rc<ItemExtension>>>,
+    /// Rules for recognizing contextual keywords.
+    contextual_keywords: RefCell<ContextualKeywords>,
+    /// Callback for `include!`-family macro invocations, see `IncludeHook`.
+    include_hook: RefCell<Option<IncludeHook>>,
+    /// Contents supplied by the include hook, keyed by the included file.
+    included_sources: RefCell<HashMap<PathBuf, String>>,
+    /// Decides which module files may be opened, see `mod_path`.
+    mod_path_policy: RefCell<Option<mod_path::ModPathPolicy>>,
+    /// Receives the state of the parser when it panics unexpectedly.
+    ice_hook: RefCell<Option<IceHook>>,
+    /// Item extensions that are only enabled in files opting into a syntax
+    /// variant with `#![syntax_variant("...")]`, keyed by variant name.
+    syntax_variants: RefCell<HashMap<ast::Name, HashMap<ast::Name, Rc<ItemExtension>>>>,
+    /// Timings of the parse phases, if enabled.
+    parse_timings: RefCell<Option<ParseTimings>>,
+    /// The start positions of the files whose CRLF line endings have been
+    /// reported, so that files lexed again are not reported again.
+    crlf_reported_files: RefCell<HashSet<BytePos>>,
+    /// Estimated memory used by the token trees lexed in this session.
+    token_tree_bytes: Cell<usize>,
+    /// Estimated memory used by the crates parsed in this session.
+    ast_bytes: Cell<usize>,
     code_map: Rc<CodeMap>,
 }
 
@@ -72,16 +177,342 @@ impl ParseSess {
             config: HashSet::new(),
             missing_fragment_specifiers: RefCell::new(HashSet::new()),
             included_mod_stack: RefCell::new(vec![]),
+            item_extensions: RefCell::new(HashMap::new()),
+            syntax_variants: RefCell::new(HashMap::new()),
+            contextual_keywords: RefCell::new(ContextualKeywords::builtin()),
+            include_hook: RefCell::new(None),
+            included_sources: RefCell::new(HashMap::new()),
+            mod_path_policy: RefCell::new(None),
+            ice_hook: RefCell::new(None),
+            parse_timings: RefCell::new(None),
+            crlf_reported_files: RefCell::new(HashSet::new()),
+            token_tree_bytes: Cell::new(0),
+            ast_bytes: Cell::new(0),
             code_map,
             non_modrs_mods: RefCell::new(vec![]),
+            reject_non_rust_input: false,
+            preserve_unknown_suffixes: false,
+            placeholder_chars: Vec::new(),
+            label_enclosing_constructs: false,
+            const_generics: false,
+            recover_redundant_bounds: false,
+            skip_control_chars: false,
+            allow_frontmatter: false,
+            max_delim_depth: None,
+            max_tokens: None,
+            check_naming: false,
+            naming_warnings: RefCell::new(Vec::new()),
+            check_duplicate_names: false,
+            record_unconfigured_regions: false,
+            unconfigured_regions: RefCell::new(Vec::new()),
         }
     }
 
     pub fn codemap(&self) -> &CodeMap {
         &self.code_map
     }
+
+    /// Registers `extension` to parse items introduced by the contextual
+    /// keyword `keyword`, replacing any extension previously registered for
+    /// it. Reserved identifiers cannot be used as extension keywords.
+    pub fn register_item_extension(&self, keyword: &str, extension: Rc<ItemExtension>) {
+        let keyword = Symbol::intern(keyword);
+        assert!(!token::Ident(ast::Ident::with_empty_ctxt(keyword)).is_reserved_ident(),
+                "cannot register an item extension for reserved identifier `{}`", keyword);
+        self.item_extensions.borrow_mut().insert(keyword, extension);
+    }
+
+    pub fn item_extension(&self, keyword: ast::Name) -> Option<Rc<ItemExtension>> {
+        self.item_extensions.borrow().get(&keyword).cloned()
+    }
+
+    /// Registers `extension` like `register_item_extension`, but only for
+    /// files that opt into the syntax variant `variant`. Within such files,
+    /// it takes precedence over an extension registered for all files.
+    pub fn register_variant_item_extension(&self,
+                                           variant: &str,
+                                           keyword: &str,
+                                           extension: Rc<ItemExtension>) {
+        let keyword = Symbol::intern(keyword);
+        assert!(!token::Ident(ast::Ident::with_empty_ctxt(keyword)).is_reserved_ident(),
+                "cannot register an item extension for reserved identifier `{}`", keyword);
+        self.syntax_variants.borrow_mut()
+            .entry(Symbol::intern(variant))
+            .or_insert_with(HashMap::new)
+            .insert(keyword, extension);
+    }
+
+    /// Returns whether any item extensions were registered for `variant`.
+    pub fn is_known_syntax_variant(&self, variant: ast::Name) -> bool {
+        self.syntax_variants.borrow().contains_key(&variant)
+    }
+
+    /// Looks up the item extension for `keyword` in a file using the syntax
+    /// variant `variant`, falling back to the extensions for all files.
+    pub fn variant_item_extension(&self, variant: Option<ast::Name>, keyword: ast::Name)
+                                  -> Option<Rc<ItemExtension>> {
+        variant.and_then(|variant| {
+            self.syntax_variants.borrow().get(&variant)
+                .and_then(|extensions| extensions.get(&keyword).cloned())
+        }).or_else(|| self.item_extension(keyword))
+    }
+
+    /// Registers `rule` to decide where `keyword` acts as a contextual keyword,
+    /// replacing any rule previously registered for it, including the
+    /// language's own. Reserved identifiers are keywords everywhere and
+    /// cannot be registered.
+    pub fn register_contextual_keyword(&self, keyword: &str, rule: ContextualKeywordRule) {
+        let keyword = Symbol::intern(keyword);
+        assert!(!token::Ident(ast::Ident::with_empty_ctxt(keyword)).is_reserved_ident(),
+                "cannot register reserved identifier `{}` as a contextual keyword", keyword);
+        self.contextual_keywords.borrow_mut().register(keyword, rule);
+    }
+
+    pub fn contextual_keyword_rule(&self, keyword: ast::Name) -> Option<ContextualKeywordRule> {
+        self.contextual_keywords.borrow().rule(keyword)
+    }
+
+    /// Sets the callback invoked by the parser for `include!`-family macro
+    /// invocations, replacing any callback set before.
+    pub fn set_include_hook(&self, hook: IncludeHook) {
+        *self.include_hook.borrow_mut() = Some(hook);
+    }
+
+    pub fn include_hook(&self) -> Option<IncludeHook> {
+        self.include_hook.borrow().clone()
+    }
+
+    /// The contents the include hook supplied for `file`, which the
+    /// `include!`-family macros expand to instead of reading the file.
+    pub fn included_source(&self, file: &Path) -> Option<String> {
+        self.included_sources.borrow().get(file).cloned()
+    }
+
+    /// Sets the policy deciding which module files the parser may open for
+    /// `mod foo;` items, replacing any policy set before. By default all are
+    /// allowed.
+    pub fn set_mod_path_policy(&self, policy: mod_path::ModPathPolicy) {
+        *self.mod_path_policy.borrow_mut() = Some(policy);
+    }
+
+    /// Sets the callback receiving the state of the parser when it panics
+    /// other than with a fatal error, replacing any callback set before. The
+    /// panic itself is left as it is.
+    pub fn set_ice_hook(&self, hook: IceHook) {
+        *self.ice_hook.borrow_mut() = Some(hook);
+    }
+
+    pub fn ice_hook(&self) -> Option<IceHook> {
+        self.ice_hook.borrow().clone()
+    }
+
+    pub fn mod_path_policy(&self) -> Option<mod_path::ModPathPolicy> {
+        self.mod_path_policy.borrow().clone()
+    }
+
+    /// Returns an estimate of the memory used by the files, token trees and
+    /// crates parsed in this session, and by the symbol interner.
+    pub fn memory_report(&self) -> MemoryReport {
+        MemoryReport {
+            filemaps: self.codemap().files().iter().map(|fm| fm.memory_usage()).sum(),
+            token_trees: self.token_tree_bytes.get(),
+            interner: symbol::interner_memory_usage(),
+            spans: syntax_pos::span_interner_memory_usage(),
+            ast: self.ast_bytes.get(),
+        }
+    }
+
+    fn record_crate_memory(&self, krate: &ast::Crate) {
+        self.ast_bytes.set(self.ast_bytes.get() + memory::crate_memory_usage(krate));
+    }
+
+    /// Starts collecting timings of the parse phases, discarding any
+    /// timings collected so far.
+    pub fn enable_parse_timings(&self) {
+        *self.parse_timings.borrow_mut() = Some(ParseTimings::default());
+    }
+
+    pub fn parse_timings_enabled(&self) -> bool {
+        self.parse_timings.borrow().is_some()
+    }
+
+    /// Stops collecting timings and returns the report, if timing was enabled.
+    pub fn take_parse_timings(&self) -> Option<ParseTimings> {
+        self.parse_timings.borrow_mut().take()
+    }
+
+    /// Runs `f`, attributing the time it takes to `phase` of `file`, minus
+    /// the time spent in any phases timed while it runs.
+    pub fn time_parse_phase<T, F>(&self, file: &FileName, phase: ParsePhase, f: F) -> T
+        where F: FnOnce() -> T
+    {
+        match *self.parse_timings.borrow_mut() {
+            Some(ref mut timings) => timings.start_phase(),
+            None => return f(),
+        }
+        let start = Instant::now();
+        let result = f();
+        let elapsed = start.elapsed();
+        if let Some(ref mut timings) = *self.parse_timings.borrow_mut() {
+            timings.end_phase(file, phase, elapsed);
+        }
+        result
+    }
+
+    /// Records `time` spent in `phase` of `file`, if timing is enabled.
+    pub fn record_parse_time(&self, file: &FileName, phase: ParsePhase, time: Duration) {
+        if let Some(ref mut timings) = *self.parse_timings.borrow_mut() {
+            timings.record(file, phase, time);
+        }
+    }
+}
+
+/// A parser extension for items introduced by a contextual keyword.
+///
+/// When the parser finds a registered keyword at item position followed by an
+/// identifier, it collects the following token trees up to and including the
+/// first `;` or brace-delimited group and hands them to the extension through
+/// a sub-parser. The extension must consume all of them and return the item's
+/// name along with its own node, which is stored in `ast::ItemKind::Extension`.
+pub trait ItemExtension {
+    fn parse_item<'a>(&self, parser: &mut Parser<'a>)
+                      -> PResult<'a, (ast::Ident, Rc<Any>)>;
+}
+
+impl<F> ItemExtension for F
+    where F: for<'a> Fn(&mut Parser<'a>) -> PResult<'a, (ast::Ident, Rc<Any>)>
+{
+    fn parse_item<'a>(&self, parser: &mut Parser<'a>)
+                      -> PResult<'a, (ast::Ident, Rc<Any>)> {
+        (*self)(parser)
+    }
+}
+
+/// The macros which include the contents of another file.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum IncludeKind {
+    /// `include!`, which parses the file as an expression or items.
+    Include,
+    /// `include_str!`
+    IncludeStr,
+    /// `include_bytes!`
+    IncludeBytes,
+}
+
+impl IncludeKind {
+    /// The macro invoked by `path`, by its last segment so that paths like
+    /// `::std::include` are recognized. Macros are not resolved while parsing,
+    /// so other macros with the same name are taken for the built-in ones.
+    pub fn from_path(path: &ast::Path) -> Option<IncludeKind> {
+        let name = match path.segments.last() {
+            Some(segment) => segment.identifier.name,
+            None => return None,
+        };
+        if name == "include" {
+            Some(IncludeKind::Include)
+        } else if name == "include_str" {
+            Some(IncludeKind::IncludeStr)
+        } else if name == "include_bytes" {
+            Some(IncludeKind::IncludeBytes)
+        } else {
+            None
+        }
+    }
+}
+
+/// An invocation of an `include!`-family macro found by the parser.
+pub struct IncludeMacro<'a> {
+    pub kind: IncludeKind,
+    pub mac: &'a ast::Mac,
+    /// The path passed to the macro, if it is a single string literal. A
+    /// relative path is relative to the file containing the invocation.
+    pub path: Option<String>,
+    /// `path` resolved like the macro resolves it, if the invocation is in a
+    /// file on disk or `path` is absolute.
+    pub file: Option<PathBuf>,
+}
+
+impl<'a> IncludeMacro<'a> {
+    pub fn new(kind: IncludeKind, mac: &'a ast::Mac, codemap: &CodeMap) -> IncludeMacro<'a> {
+        let mut trees = mac.node.stream().trees();
+        let path = match (trees.next(), trees.next()) {
+            (Some(TokenTree::Token(_, token::Literal(token::Str_(s), None))), None) => {
+                Some(str_lit(&s.as_str(), None))
+            }
+            (Some(TokenTree::Token(_, token::Literal(token::StrRaw(s, _), None))), None) => {
+                Some(raw_str_lit(&s.as_str()))
+            }
+            _ => None,
+        };
+        let file = path.as_ref().and_then(|path| {
+            let path = PathBuf::from(path);
+            if path.is_absolute() {
+                return Some(path);
+            }
+            if mac.span.is_dummy() {
+                return None;
+            }
+            match codemap.lookup_char_pos(mac.span.lo()).file.unmapped_path {
+                Some(FileName::Real(ref file)) => {
+                    let mut file = file.clone();
+                    file.pop();
+                    file.push(path);
+                    Some(file)
+                }
+                _ => None,
+            }
+        });
+        IncludeMacro { kind, mac, path, file }
+    }
 }
 
+/// A callback invoked by the parser for each invocation of an `include!`-family
+/// macro, long before the macro is expanded.
+///
+/// Tools which do not expand macros can use it to see the contents of included
+/// files, by loading them into the session's codemap and lexing them, or just
+/// to record the dependency. The callback may also be invoked for invocations
+/// in code which the parser parses speculatively and then discards.
+///
+/// The callback can return the contents of the included file, which the macro
+/// then expands to instead of reading `IncludeMacro::file`, e.g. for files
+/// which are generated or edited but not saved. The contents are dropped if
+/// the invocation has no `file`.
+pub type IncludeHook = Rc<Fn(&ParseSess, &IncludeMacro) -> Option<String>>;
+
+/// The state of the parser when it panicked unexpectedly, for bug reports from
+/// tools using the parser.
+#[derive(Clone, Debug)]
+pub struct IceReport {
+    /// The panic message, if it is a string.
+    pub message: Option<String>,
+    /// The current token.
+    pub token: String,
+    pub span: Span,
+    /// The file and position of `span`, like `src/lib.rs:3:5: 3:8`.
+    pub location: String,
+    /// The constructs the parser was in, outermost first, like "function".
+    pub contexts: Vec<String>,
+}
+
+impl fmt::Display for IceReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.message {
+            Some(ref message) => write!(f, "the parser panicked: {}", message)?,
+            None => write!(f, "the parser panicked")?,
+        }
+        write!(f, "\n  at `{}` ({})", self.token, self.location)?;
+        for context in &self.contexts {
+            write!(f, "\n  while parsing {}", context)?;
+        }
+        Ok(())
+    }
+}
+
+/// Receives the state of the parser when it panics, e.g. to print it after the
+/// panic message. See `ParseSess::set_ice_hook`.
+pub type IceHook = Rc<Fn(&IceReport)>;
+
 #[derive(Clone)]
 pub struct Directory {
     pub path: PathBuf,
@@ -104,29 +535,118 @@ pub enum DirectoryOwnership {
 // source_str.
 
 pub fn parse_crate_from_file<'a>(input: &Path, sess: &'a ParseSess) -> PResult<'a, ast::Crate> {
-    let mut parser = new_parser_from_file(sess, input);
-    parser.parse_crate_mod()
+    let filemap = file_to_filemap(sess, input, None)?;
+    check_rust_source(sess, &filemap)?;
+    let name = filemap.name.clone();
+    let mut parser = try_filemap_to_parser(sess, filemap)?;
+    let krate = sess.time_parse_phase(&name, ParsePhase::Parsing, || parser.parse_crate_mod())?;
+    sess.record_crate_memory(&krate);
+    Ok(krate)
+}
+
+/// Like `parse_crate_from_file`, but also returns the provenance of the crate,
+/// see `provenance`.
+pub fn parse_crate_from_file_with_provenance<'a>(input: &Path, sess: &'a ParseSess)
+                                                 -> PResult<'a, (ast::Crate, ParseProvenance)> {
+    let name = FileName::from(input.to_path_buf());
+    let provenance = ParseProvenance::new(EntryPoint::CrateFromFile, name, sess);
+    let krate = parse_crate_from_file(input, sess)?;
+    Ok((krate, provenance))
 }
 
 pub fn parse_crate_attrs_from_file<'a>(input: &Path, sess: &'a ParseSess)
                                        -> PResult<'a, Vec<ast::Attribute>> {
-    let mut parser = new_parser_from_file(sess, input);
+    let mut parser = try_new_parser_from_file(sess, input)?;
     parser.parse_inner_attributes()
 }
 
 pub fn parse_crate_from_source_str(name: FileName, source: String, sess: &ParseSess)
                                        -> PResult<ast::Crate> {
-    new_parser_from_source_str(sess, name, source).parse_crate_mod()
+    parse_crate_from_filemap(sess.codemap().new_filemap(name, source), sess)
+}
+
+/// Like `parse_crate_from_source_str`, but also returns the provenance of the
+/// crate, see `provenance`.
+pub fn parse_crate_from_source_str_with_provenance(name: FileName, source: String,
+                                                   sess: &ParseSess)
+                                                   -> PResult<(ast::Crate, ParseProvenance)> {
+    let provenance = ParseProvenance::new(EntryPoint::CrateFromSource, name.clone(), sess);
+    let krate = parse_crate_from_source_str(name, source, sess)?;
+    Ok((krate, provenance))
+}
+
+/// Like `parse_crate_from_source_str`, but borrows the source. The codemap
+/// owns the sources of its files, so the source is still copied into it.
+pub fn parse_crate_from_str<'a>(name: FileName, source: &str, sess: &'a ParseSess)
+                                -> PResult<'a, ast::Crate> {
+    parse_crate_from_filemap(sess.codemap().new_filemap(name, source.to_owned()), sess)
+}
+
+fn parse_crate_from_filemap(filemap: Rc<FileMap>, sess: &ParseSess) -> PResult<ast::Crate> {
+    check_rust_source(sess, &filemap)?;
+    let name = filemap.name.clone();
+    let mut parser = try_filemap_to_parser(sess, filemap)?;
+    parser.recurse_into_file_modules = false;
+    let krate = sess.time_parse_phase(&name, ParsePhase::Parsing, || parser.parse_crate_mod())?;
+    sess.record_crate_memory(&krate);
+    Ok(krate)
 }
 
 pub fn parse_crate_attrs_from_source_str(name: FileName, source: String, sess: &ParseSess)
                                              -> PResult<Vec<ast::Attribute>> {
-    new_parser_from_source_str(sess, name, source).parse_inner_attributes()
+    try_new_parser_from_source_str(sess, name, source)?
+        .with_ice_report(|p| p.parse_inner_attributes())
 }
 
 pub fn parse_expr_from_source_str(name: FileName, source: String, sess: &ParseSess)
                                       -> PResult<P<ast::Expr>> {
-    new_parser_from_source_str(sess, name, source).parse_expr()
+    try_new_parser_from_source_str(sess, name, source)?.with_ice_report(|p| p.parse_expr())
+}
+
+/// Like `parse_expr_from_source_str`, but borrows the source.
+pub fn parse_expr_from_str<'a>(name: FileName, source: &str, sess: &'a ParseSess)
+                               -> PResult<'a, P<ast::Expr>> {
+    try_new_parser_from_str(sess, name, source)?.with_ice_report(|p| p.parse_expr())
+}
+
+/// Parses an expression as if it were in a method of an impl for `self_ty`,
+/// e.g. a method body being expanded in isolation, so that errors read like
+/// those for the body in place.
+pub fn parse_expr_in_impl_from_source_str(name: FileName, source: String, self_ty: P<ast::Ty>,
+                                          sess: &ParseSess) -> PResult<P<ast::Expr>> {
+    parser_in_impl(sess, name, source, self_ty)?.with_ice_report(|p| p.parse_expr())
+}
+
+/// Like `parse_expr_in_impl_from_source_str`, but parses an item, e.g. one
+/// nested in a method body.
+pub fn parse_item_in_impl_from_source_str(name: FileName, source: String, self_ty: P<ast::Ty>,
+                                          sess: &ParseSess) -> PResult<Option<P<ast::Item>>> {
+    parser_in_impl(sess, name, source, self_ty)?.with_ice_report(|p| p.parse_item())
+}
+
+/// Like `parse_expr_in_impl_from_source_str`, but parses a statement.
+pub fn parse_stmt_in_impl_from_source_str(name: FileName, source: String, self_ty: P<ast::Ty>,
+                                          sess: &ParseSess) -> PResult<Option<ast::Stmt>> {
+    parser_in_impl(sess, name, source, self_ty)?.with_ice_report(|p| p.parse_stmt())
+}
+
+/// Like `parse_expr_in_impl_from_source_str`, but parses a block, e.g. a whole
+/// method body. It is an error if the source continues after the block.
+pub fn parse_block_in_impl_from_source_str(name: FileName, source: String, self_ty: P<ast::Ty>,
+                                           sess: &ParseSess) -> PResult<P<ast::Block>> {
+    let mut parser = parser_in_impl(sess, name, source, self_ty)?;
+    let block = parser.with_ice_report(|p| p.parse_block())?;
+    if parser.token != token::Eof {
+        parser.unexpected()?;
+    }
+    Ok(block)
+}
+
+fn parser_in_impl(sess: &ParseSess, name: FileName, source: String, self_ty: P<ast::Ty>)
+                  -> PResult<Parser> {
+    let mut parser = try_new_parser_from_source_str(sess, name, source)?;
+    parser.impl_self_ty = Some(self_ty);
+    Ok(parser)
 }
 
 /// Parses an item.
@@ -135,37 +655,142 @@ pub fn parse_expr_from_source_str(name: FileName, source: String, sess: &ParseSe
 /// when a syntax error occurred.
 pub fn parse_item_from_source_str(name: FileName, source: String, sess: &ParseSess)
                                       -> PResult<Option<P<ast::Item>>> {
-    new_parser_from_source_str(sess, name, source).parse_item()
+    try_new_parser_from_source_str(sess, name, source)?.with_ice_report(|p| p.parse_item())
+}
+
+/// Like `parse_item_from_source_str`, but borrows the source.
+pub fn parse_item_from_str<'a>(name: FileName, source: &str, sess: &'a ParseSess)
+                               -> PResult<'a, Option<P<ast::Item>>> {
+    try_new_parser_from_str(sess, name, source)?.with_ice_report(|p| p.parse_item())
+}
+
+/// Parses a visibility, e.g. `pub(crate)`. An empty source is the inherited
+/// visibility.
+pub fn parse_visibility_from_source_str(name: FileName, source: String, sess: &ParseSess)
+                                        -> PResult<ast::Visibility> {
+    try_new_parser_from_source_str(sess, name, source)?
+        .with_ice_report(|p| p.parse_visibility(true))
 }
 
 pub fn parse_meta_from_source_str(name: FileName, source: String, sess: &ParseSess)
                                       -> PResult<ast::MetaItem> {
-    new_parser_from_source_str(sess, name, source).parse_meta_item()
+    try_new_parser_from_source_str(sess, name, source)?.with_ice_report(|p| p.parse_meta_item())
 }
 
 pub fn parse_stmt_from_source_str(name: FileName, source: String, sess: &ParseSess)
                                       -> PResult<Option<ast::Stmt>> {
-    new_parser_from_source_str(sess, name, source).parse_stmt()
+    try_new_parser_from_source_str(sess, name, source)?.with_ice_report(|p| p.parse_stmt())
+}
+
+/// Like `parse_stmt_from_source_str`, but borrows the source.
+pub fn parse_stmt_from_str<'a>(name: FileName, source: &str, sess: &'a ParseSess)
+                               -> PResult<'a, Option<ast::Stmt>> {
+    try_new_parser_from_str(sess, name, source)?.with_ice_report(|p| p.parse_stmt())
+}
+
+/// Parses a single pattern, e.g. the pattern of a `match` arm. Alternatives
+/// separated by `|` are not a pattern.
+pub fn parse_pat_from_source_str(name: FileName, source: String, sess: &ParseSess)
+                                     -> PResult<P<ast::Pat>> {
+    try_new_parser_from_source_str(sess, name, source)?.with_ice_report(|p| p.parse_pat())
+}
+
+/// Parses a path in the given style, e.g. `foo::bar::<T>` as `PathStyle::Expr`
+/// or `foo::bar<T>` as `PathStyle::Type`. It is an error if the source
+/// continues after the path.
+pub fn parse_path_from_source_str(name: FileName, source: String, style: PathStyle,
+                                  sess: &ParseSess)
+                                  -> PResult<ast::Path> {
+    let mut parser = try_new_parser_from_source_str(sess, name, source)?;
+    let path = parser.with_ice_report(|p| p.parse_path(style))?;
+    if parser.token != token::Eof {
+        parser.unexpected()?;
+    }
+    Ok(path)
+}
+
+/// Parses a block, e.g. the body of a doctest wrapped in braces. It is an
+/// error if the source continues after the closing brace.
+pub fn parse_block_from_source_str(name: FileName, source: String, sess: &ParseSess)
+                                   -> PResult<P<ast::Block>> {
+    let mut parser = try_new_parser_from_source_str(sess, name, source)?;
+    let block = parser.with_ice_report(|p| p.parse_block())?;
+    if parser.token != token::Eof {
+        parser.unexpected()?;
+    }
+    Ok(block)
+}
+
+/// Parses generic parameters followed by a where clause, e.g.
+/// `<T: Clone, U> where U: Default`. Either may be omitted. The where clause
+/// is returned separately, the one in the `Generics` is empty. It is an error
+/// if the source continues after the where clause.
+pub fn parse_generics_from_source_str(name: FileName, source: String, sess: &ParseSess)
+                                      -> PResult<(ast::Generics, ast::WhereClause)> {
+    let mut parser = try_new_parser_from_source_str(sess, name, source)?;
+    let (generics, where_clause) = parser.with_ice_report(|p| -> PResult<_> {
+        Ok((p.parse_generics()?, p.parse_where_clause()?))
+    })?;
+    if parser.token != token::Eof {
+        parser.unexpected()?;
+    }
+    Ok((generics, where_clause))
 }
 
 pub fn parse_stream_from_source_str(name: FileName, source: String, sess: &ParseSess,
                                     override_span: Option<Span>)
                                     -> TokenStream {
-    filemap_to_stream(sess, sess.codemap().new_filemap(name, source), override_span)
+    let filemap = sess.codemap().new_filemap(name, source);
+    panictry!(try_filemap_to_stream(sess, filemap, override_span))
+}
+
+/// Like `parse_stream_from_source_str`, but borrows the source.
+pub fn parse_stream_from_str(name: FileName, source: &str, sess: &ParseSess,
+                             override_span: Option<Span>)
+                             -> TokenStream {
+    let filemap = sess.codemap().new_filemap(name, source.to_owned());
+    panictry!(try_filemap_to_stream(sess, filemap, override_span))
 }
 
 // Create a new parser from a source string
 pub fn new_parser_from_source_str(sess: &ParseSess, name: FileName, source: String)
                                       -> Parser {
-    let mut parser = filemap_to_parser(sess, sess.codemap().new_filemap(name, source));
+    panictry!(try_new_parser_from_source_str(sess, name, source))
+}
+
+/// Like `new_parser_from_source_str`, but returns an error if the source
+/// can't be lexed instead of aborting.
+pub fn try_new_parser_from_source_str(sess: &ParseSess, name: FileName, source: String)
+                                      -> PResult<Parser> {
+    let mut parser = try_filemap_to_parser(sess, sess.codemap().new_filemap(name, source))?;
     parser.recurse_into_file_modules = false;
-    parser
+    Ok(parser)
+}
+
+/// Like `new_parser_from_source_str`, but borrows the source.
+pub fn new_parser_from_str<'a>(sess: &'a ParseSess, name: FileName, source: &str)
+                               -> Parser<'a> {
+    panictry!(try_new_parser_from_str(sess, name, source))
+}
+
+/// Like `try_new_parser_from_source_str`, but borrows the source.
+pub fn try_new_parser_from_str<'a>(sess: &'a ParseSess, name: FileName, source: &str)
+                                   -> PResult<'a, Parser<'a>> {
+    try_new_parser_from_source_str(sess, name, source.to_owned())
 }
 
 /// Create a new parser, handling errors as appropriate
 /// if the file doesn't exist
+#[deprecated(since = "1.25.0", note = "use `try_new_parser_from_file` instead")]
 pub fn new_parser_from_file<'a>(sess: &'a ParseSess, path: &Path) -> Parser<'a> {
-    filemap_to_parser(sess, file_to_filemap(sess, path, None))
+    panictry!(try_new_parser_from_file(sess, path))
+}
+
+/// Create a new parser, returning an error if the file can't be read or
+/// lexed.
+pub fn try_new_parser_from_file<'a>(sess: &'a ParseSess, path: &Path)
+                                    -> PResult<'a, Parser<'a>> {
+    try_filemap_to_parser(sess, file_to_filemap(sess, path, None)?)
 }
 
 /// Given a session, a crate config, a path, and a span, add
@@ -176,22 +801,30 @@ pub fn new_sub_parser_from_file<'a>(sess: &'a ParseSess,
                                     directory_ownership: DirectoryOwnership,
                                     module_name: Option<String>,
                                     sp: Span) -> Parser<'a> {
-    let mut p = filemap_to_parser(sess, file_to_filemap(sess, path, Some(sp)));
+    let filemap = panictry!(file_to_filemap(sess, path, Some(sp)));
+    let mut p = panictry!(try_filemap_to_parser(sess, filemap));
     p.directory.ownership = directory_ownership;
     p.root_module_name = module_name;
     p
 }
 
 /// Given a filemap and config, return a parser
+#[deprecated(since = "1.25.0", note = "use `try_filemap_to_parser` instead")]
 pub fn filemap_to_parser(sess: & ParseSess, filemap: Rc<FileMap>, ) -> Parser {
+    panictry!(try_filemap_to_parser(sess, filemap))
+}
+
+/// Given a filemap, return a parser, or the error which stopped the filemap
+/// from being lexed.
+pub fn try_filemap_to_parser(sess: &ParseSess, filemap: Rc<FileMap>) -> PResult<Parser> {
     let end_pos = filemap.end_pos;
-    let mut parser = stream_to_parser(sess, filemap_to_stream(sess, filemap, None));
+    let mut parser = stream_to_parser(sess, try_filemap_to_stream(sess, filemap, None)?);
 
     if parser.token == token::Eof && parser.span == syntax_pos::DUMMY_SP {
         parser.span = Span::new(end_pos, end_pos, NO_EXPANSION);
     }
 
-    parser
+    Ok(parser)
 }
 
 // must preserve old name for now, because quote! from the *existing*
@@ -205,27 +838,95 @@ pub fn new_parser_from_tts(sess: &ParseSess, tts: Vec<TokenTree>) -> Parser {
 
 /// Given a session and a path and an optional span (for error reporting),
 /// add the path to the session's codemap and return the new filemap.
-fn file_to_filemap(sess: &ParseSess, path: &Path, spanopt: Option<Span>)
-                   -> Rc<FileMap> {
+fn file_to_filemap<'a>(sess: &'a ParseSess, path: &Path, spanopt: Option<Span>)
+                       -> PResult<'a, Rc<FileMap>> {
+    let start = Instant::now();
     match sess.codemap().load_file(path) {
-        Ok(filemap) => filemap,
+        Ok(filemap) => {
+            sess.record_parse_time(&filemap.name, ParsePhase::FileLoading, start.elapsed());
+            Ok(filemap)
+        }
         Err(e) => {
             let msg = format!("couldn't read {:?}: {}", path.display(), e);
             match spanopt {
-                Some(sp) => sess.span_diagnostic.span_fatal(sp, &msg).raise(),
-                None => sess.span_diagnostic.fatal(&msg).raise()
+                Some(sp) => Err(sess.span_diagnostic.struct_span_fatal(sp, &msg)),
+                None => Err(sess.span_diagnostic.struct_fatal(&msg)),
             }
         }
     }
 }
 
+/// If `sess.reject_non_rust_input` is set and the filemap is obviously not
+/// Rust source, return a single error pointing at the start of the file.
+fn check_rust_source<'a>(sess: &'a ParseSess, filemap: &FileMap) -> PResult<'a, ()> {
+    if !sess.reject_non_rust_input {
+        return Ok(());
+    }
+    let kind = match filemap.src.as_ref().and_then(|src| sniff::sniff_non_rust(src)) {
+        Some(kind) => kind,
+        None => return Ok(()),
+    };
+    let span = Span::new(filemap.start_pos, filemap.start_pos, NO_EXPANSION);
+    let mut err = sess.span_diagnostic.struct_span_fatal(
+        span, &format!("`{}` does not appear to be Rust source", filemap.name));
+    err.note(&kind.to_string());
+    Err(err)
+}
+
 /// Given a filemap, produce a sequence of token-trees
+#[deprecated(since = "1.25.0", note = "use `try_filemap_to_stream` instead")]
 pub fn filemap_to_stream(sess: &ParseSess, filemap: Rc<FileMap>, override_span: Option<Span>)
                          -> TokenStream {
-    let mut srdr = lexer::StringReader::new(sess, filemap);
-    srdr.override_span = override_span;
-    srdr.real_token();
-    panictry!(srdr.parse_all_token_trees())
+    panictry!(try_filemap_to_stream(sess, filemap, override_span))
+}
+
+/// Given a filemap, produce a sequence of token-trees, or the error which
+/// stopped the filemap from being lexed.
+pub fn try_filemap_to_stream(sess: &ParseSess, filemap: Rc<FileMap>, override_span: Option<Span>)
+                             -> PResult<TokenStream> {
+    lex_filemap(sess, filemap, override_span, false).map(|(stream, _)| stream)
+}
+
+/// Like `filemap_to_stream`, but also returns the offsets of all tokens of
+/// the file, see `token_offsets`.
+pub fn filemap_to_stream_with_offsets(sess: &ParseSess, filemap: Rc<FileMap>)
+                                      -> (TokenStream, TokenOffsets) {
+    let (stream, offsets) = panictry!(lex_filemap(sess, filemap, None, true));
+    (stream, offsets.unwrap())
+}
+
+/// Lexes a filemap into token trees, recording the time and memory spent in
+/// `sess`. Also returns the offsets of all tokens if `offsets` is set.
+fn lex_filemap(sess: &ParseSess, filemap: Rc<FileMap>, override_span: Option<Span>, offsets: bool)
+               -> PResult<(TokenStream, Option<TokenOffsets>)> {
+    let name = filemap.name.clone();
+    let len = (filemap.end_pos - filemap.start_pos).to_usize();
+    sess.time_parse_phase(&name, ParsePhase::TokenTrees, || {
+        let mut srdr = lexer::StringReader::new(sess, filemap);
+        srdr.override_span = override_span;
+        if offsets {
+            srdr.token_offsets = Some(Vec::new());
+        }
+        srdr.real_token();
+        let stream = srdr.parse_all_token_trees()?;
+        if let Some(lex_time) = srdr.lex_time {
+            sess.record_parse_time(&name, ParsePhase::Lexing, lex_time);
+        }
+        let token_tree_bytes = memory::token_trees_memory_usage(srdr.token_count);
+        sess.token_tree_bytes.set(sess.token_tree_bytes.get() + token_tree_bytes);
+        let offsets = srdr.token_offsets.take().map(|offsets| TokenOffsets::new(offsets, len));
+        Ok((stream, offsets))
+    })
+}
+
+/// Given a filemap and a span within it, lexes only the source covered by the
+/// span into token trees, for tools which need the tokens of a single item
+/// without lexing the whole file again. Delimiters in the span must be balanced.
+/// See `StringReader::new_in_range` for where lexing starts.
+pub fn filemap_to_tts_in_range(sess: &ParseSess, filemap: Rc<FileMap>, span: Span)
+                               -> Vec<TokenTree> {
+    let mut srdr = lexer::StringReader::new_in_range(sess, filemap, span);
+    panictry!(srdr.parse_all_token_trees()).into_trees().collect()
 }
 
 /// Given stream and the `ParseSess`, produce a parser
@@ -233,6 +934,34 @@ pub fn stream_to_parser(sess: &ParseSess, stream: TokenStream) -> Parser {
     Parser::new(sess, stream, None, true, false)
 }
 
+/// Parses the input of an attribute macro: `attr` is the attribute without
+/// the surrounding `#[` and `]`, e.g. `route(GET, "/")`, and `item` is the
+/// annotated item. Both streams are parsed in `sess`, so their spans and
+/// diagnostics are consistent, and neither may contain trailing tokens.
+pub fn parse_attr_macro_input<'a>(sess: &'a ParseSess, attr: TokenStream, item: TokenStream)
+                                  -> PResult<'a, (ast::MetaItem, P<ast::Item>)> {
+    let mut parser = stream_to_parser(sess, attr);
+    let meta = parser.with_ice_report(|p| p.parse_meta_item())?;
+    if parser.token != token::Eof {
+        parser.unexpected()?;
+    }
+
+    let mut parser = stream_to_parser(sess, item);
+    let item = match parser.with_ice_report(|p| p.parse_item())? {
+        Some(item) => item,
+        None => {
+            let token_str = parser.this_token_to_string();
+            let msg = sess.span_diagnostic.message(&messages::EXPECTED_ITEM,
+                                                   &[("found", &token_str)]);
+            return Err(parser.fatal(&msg));
+        }
+    };
+    if parser.token != token::Eof {
+        parser.unexpected()?;
+    }
+    Ok((meta, item))
+}
+
 /// Parse a string representing a character literal into its final form.
 /// Rather than just accepting/rejecting a given literal, unescapes it as
 /// well. Can take any slice prefixed by a character escape. Returns the
@@ -293,7 +1022,30 @@ pub fn escape_default(s: &str) -> String {
 pub fn str_lit(lit: &str, diag: Option<(Span, &Handler)>) -> String {
     debug!("parse_str_lit: given {}", escape_default(lit));
     let mut res = String::with_capacity(lit.len());
+    unescape_str_lit(lit, diag, |c, _| res.push(c));
+
+    res.shrink_to_fit(); // probably not going to do anything, unless there was an escape.
+    debug!("parse_str_lit: returning {}", res);
+    res
+}
+
+/// Like `str_lit`, but also returns the byte offset in `lit` of the character
+/// or escape sequence that produced each byte of the result.
+pub fn str_lit_with_offsets(lit: &str) -> (String, Vec<usize>) {
+    let mut res = String::with_capacity(lit.len());
+    let mut offsets = Vec::with_capacity(lit.len());
+    unescape_str_lit(lit, None, |c, i| {
+        res.push(c);
+        offsets.extend(iter::repeat(i).take(c.len_utf8()));
+    });
+    (res, offsets)
+}
 
+/// Unescapes `lit`, calling `push` with each resulting character and the byte
+/// offset in `lit` where it starts.
+fn unescape_str_lit<F>(lit: &str, diag: Option<(Span, &Handler)>, mut push: F)
+    where F: FnMut(char, usize)
+{
     // FIXME #8372: This could be a for-loop if it didn't borrow the iterator
     let error = |i| format!("lexer should have rejected {} at {}", lit, i);
 
@@ -335,7 +1087,7 @@ pub fn str_lit(lit: &str, diag: Option<(Span, &Handler)>) -> String {
                     for _ in 0..n - 1 { // we don't need to move past the first \
                         chars.next();
                     }
-                    res.push(c);
+                    push(c, i);
                 }
             },
             '\r' => {
@@ -347,15 +1099,11 @@ pub fn str_lit(lit: &str, diag: Option<(Span, &Handler)>) -> String {
                     panic!("lexer accepted bare CR");
                 }
                 chars.next();
-                res.push('\n');
+                push('\n', i);
             }
-            c => res.push(c),
+            c => push(c, i),
         }
     }
-
-    res.shrink_to_fit(); // probably not going to do anything, unless there was an escape.
-    debug!("parse_str_lit: returning {}", res);
-    res
 }
 
 /// Parse a string representing a raw string literal into its final form. The
@@ -381,6 +1129,29 @@ pub fn raw_str_lit(lit: &str) -> String {
     res
 }
 
+/// Like `raw_str_lit`, but also returns the byte offset in `lit` of the
+/// character that produced each byte of the result.
+pub fn raw_str_lit_with_offsets(lit: &str) -> (String, Vec<usize>) {
+    let mut res = String::with_capacity(lit.len());
+    let mut offsets = Vec::with_capacity(lit.len());
+
+    let mut chars = lit.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let c = if c == '\r' {
+            if chars.peek().unwrap().1 != '\n' {
+                panic!("lexer accepted bare CR");
+            }
+            chars.next();
+            '\n'
+        } else {
+            c
+        };
+        res.push(c);
+        offsets.extend(iter::repeat(i).take(c.len_utf8()));
+    }
+    (res, offsets)
+}
+
 // check if `s` looks like i32 or u1234 etc.
 fn looks_like_width_suffix(first_chars: &[char], s: &str) -> bool {
     s.len() > 1 &&
@@ -422,22 +1193,38 @@ pub fn lit_token(lit: token::Lit, suf: Option<Symbol>, diag: Option<(Span, &Hand
             (true, Some(LitKind::ByteStr(byte_str_lit(&i.as_str()))))
         }
         token::ByteStrRaw(i, _) => {
-            (true, Some(LitKind::ByteStr(Rc::new(i.to_string().into_bytes()))))
+            (true, Some(LitKind::ByteStr(Rc::from(i.as_str().as_bytes()))))
         }
     }
 }
 
-fn filtered_float_lit(data: Symbol, suffix: Option<Symbol>, diag: Option<(Span, &Handler)>)
-                      -> Option<ast::LitKind> {
+/// Returns true if `suffix` is one the language defines for the numeric
+/// literal `lit`. Returns false for non-numeric literals.
+pub fn is_known_numeric_suffix(lit: token::Lit, suffix: &str) -> bool {
+    match lit {
+        token::Integer(_) => match suffix {
+            "isize" | "i8" | "i16" | "i32" | "i64" | "i128" |
+            "usize" | "u8" | "u16" | "u32" | "u64" | "u128" |
+            "f32" | "f64" => true,
+            _ => false,
+        },
+        token::Float(_) => suffix == "f32" || suffix == "f64",
+        _ => false,
+    }
+}
+
+/// `data` is the literal without digit separators, `text` the literal as written.
+fn filtered_float_lit(data: Symbol, text: Symbol, suffix: Option<Symbol>,
+                      diag: Option<(Span, &Handler)>) -> Option<ast::LitKind> {
     debug!("filtered_float_lit: {}, {:?}", data, suffix);
     let suffix = match suffix {
         Some(suffix) => suffix,
-        None => return Some(ast::LitKind::FloatUnsuffixed(data)),
+        None => return Some(ast::LitKind::FloatUnsuffixed(data, Some(text))),
     };
 
     Some(match &*suffix.as_str() {
-        "f32" => ast::LitKind::Float(data, ast::FloatTy::F32),
-        "f64" => ast::LitKind::Float(data, ast::FloatTy::F64),
+        "f32" => ast::LitKind::Float(data, ast::FloatTy::F32, Some(text)),
+        "f64" => ast::LitKind::Float(data, ast::FloatTy::F64, Some(text)),
         suf => {
             err!(diag, |span, diag| {
                 if suf.len() >= 2 && looks_like_width_suffix(&['f'], suf) {
@@ -452,7 +1239,7 @@ fn filtered_float_lit(data: Symbol, suffix: Option<Symbol>, diag: Option<(Span,
                 }
             });
 
-            ast::LitKind::FloatUnsuffixed(data)
+            ast::LitKind::FloatUnsuffixed(data, Some(text))
         }
     })
 }
@@ -460,8 +1247,9 @@ pub fn float_lit(s: &str, suffix: Option<Symbol>, diag: Option<(Span, &Handler)>
                  -> Option<ast::LitKind> {
     debug!("float_lit: {:?}, {:?}", s, suffix);
     // FIXME #2252: bounds checking float literals is deferred until trans
+    let text = Symbol::intern(s);
     let s = s.chars().filter(|&c| c != '_').collect::<String>();
-    filtered_float_lit(Symbol::intern(&s), suffix, diag)
+    filtered_float_lit(Symbol::intern(&s), text, suffix, diag)
 }
 
 /// Parse a string representing a byte literal into its final form. Similar to `char_lit`
@@ -496,7 +1284,13 @@ pub fn byte_lit(lit: &str) -> (u8, usize) {
     }
 }
 
-pub fn byte_str_lit(lit: &str) -> Rc<Vec<u8>> {
+pub fn byte_str_lit(lit: &str) -> Rc<[u8]> {
+    // Without escapes or line endings to normalize the literal is its own
+    // value, so copy it in one go.
+    if !lit.bytes().any(|b| b == b'\\' || b == b'\r') {
+        return Rc::from(lit.as_bytes());
+    }
+
     let mut res = Vec::with_capacity(lit.len());
 
     // FIXME #8372: This could be a for-loop if it didn't borrow the iterator
@@ -553,13 +1347,14 @@ pub fn byte_str_lit(lit: &str) -> Rc<Vec<u8>> {
         }
     }
 
-    Rc::new(res)
+    Rc::from(res)
 }
 
 pub fn integer_lit(s: &str, suffix: Option<Symbol>, diag: Option<(Span, &Handler)>)
                    -> Option<ast::LitKind> {
     // s can only be ascii, byte indexing is fine
 
+    let text = Symbol::intern(s);
     let s2 = s.chars().filter(|&c| c != '_').collect::<String>();
     let mut s = &s2[..];
 
@@ -590,7 +1385,7 @@ pub fn integer_lit(s: &str, suffix: Option<Symbol>, diag: Option<(Span, &Handler
             if let Some(err) = err {
                 err!(diag, |span, diag| diag.span_err(span, err));
             }
-            return filtered_float_lit(Symbol::intern(s), Some(suf), diag)
+            return filtered_float_lit(Symbol::intern(s), text, Some(suf), diag)
         }
     }
 
@@ -642,7 +1437,7 @@ pub fn integer_lit(s: &str, suffix: Option<Symbol>, diag: Option<(Span, &Handler
            string was {:?}, the original suffix was {:?}", ty, base, s, orig, suffix);
 
     Some(match u128::from_str_radix(s, base) {
-        Ok(r) => ast::LitKind::Int(r, ty),
+        Ok(r) => ast::LitKind::Int(r, ty, Some(text)),
         Err(_) => {
             // small bases are lexed as if they were base 10, e.g, the string
             // might be `0b10201`. This will cause the conversion above to fail,
@@ -653,29 +1448,100 @@ pub fn integer_lit(s: &str, suffix: Option<Symbol>, diag: Option<(Span, &Handler
                 s.chars().any(|c| c.to_digit(10).map_or(false, |d| d >= base));
 
             if !already_errored {
-                err!(diag, |span, diag| diag.span_err(span, "int literal is too large"));
+                err!(diag, |span, diag| report_int_lit_overflow(span, diag, orig, base, ty));
             }
-            ast::LitKind::Int(0, ty)
+            ast::LitKind::Int(0, ty, Some(text))
         }
     })
 }
 
+/// Reports an integer literal whose value does not fit in `u128`, and so
+/// cannot be represented by any integer type, whatever its suffix.
+fn report_int_lit_overflow(span: Span, diag: &Handler, lit: &str, base: u32,
+                           ty: ast::LitIntType) {
+    let (ty_name, max) = match ty {
+        ast::LitIntType::Signed(t) => (t.ty_to_string(), max_signed_value(t)),
+        ast::LitIntType::Unsigned(t) => (t.ty_to_string(), max_unsigned_value(t)),
+        ast::LitIntType::Unsuffixed => ("u128", !0),
+    };
+    let max = match base {
+        16 => format!("{:#x}", max),
+        8 => format!("{:#o}", max),
+        2 => format!("{:#b}", max),
+        _ => max.to_string(),
+    };
+    let pointer_sized = match ty {
+        ast::LitIntType::Signed(ast::IntTy::Isize) |
+        ast::LitIntType::Unsigned(ast::UintTy::Usize) => " on 64-bit targets",
+        _ => "",
+    };
+
+    let mut err = struct_span_err!(diag, span, E0693, "int literal is too large");
+    match ty {
+        ast::LitIntType::Unsuffixed => {
+            err.span_label(span, format!("`{}` does not fit in any integer type", lit));
+        }
+        _ => {
+            err.span_label(span, format!("`{}` does not fit in `{}`", lit, ty_name));
+        }
+    }
+    err.note(&format!("the maximum value of `{}` is `{}`{}", ty_name, max, pointer_sized));
+    if ty != ast::LitIntType::Unsuffixed {
+        err.note("no integer type can hold this value, the widest is `u128`");
+    }
+    // Float literals only exist in base 10, and `f64` reaches about 1.8e308.
+    let digits = lit.trim_left_matches('0');
+    if base == 10 && digits.len() < 309 {
+        let mantissa = digits[1..].trim_right_matches('0');
+        let mantissa = &mantissa[..cmp::min(mantissa.len(), 3)];
+        let float = format!("{}{}{}e{}", &digits[..1], if mantissa.is_empty() { "" } else { "." },
+                            mantissa, digits.len() - 1);
+        err.help(&format!("if an approximate value is enough, use a float literal like `{}`",
+                          float));
+    } else if base != 10 {
+        err.help("consider splitting the value into several integers, or writing it in \
+                  decimal as a float literal if an approximate value is enough");
+    }
+    err.emit();
+}
+
+fn max_signed_value(ty: ast::IntTy) -> u128 {
+    match ty {
+        ast::IntTy::I8 => i8::MAX as u128,
+        ast::IntTy::I16 => i16::MAX as u128,
+        ast::IntTy::I32 => i32::MAX as u128,
+        ast::IntTy::Isize | ast::IntTy::I64 => i64::MAX as u128,
+        ast::IntTy::I128 => !0 >> 1,
+    }
+}
+
+fn max_unsigned_value(ty: ast::UintTy) -> u128 {
+    match ty {
+        ast::UintTy::U8 => u8::MAX as u128,
+        ast::UintTy::U16 => u16::MAX as u128,
+        ast::UintTy::U32 => u32::MAX as u128,
+        ast::UintTy::Usize | ast::UintTy::U64 => u64::MAX as u128,
+        ast::UintTy::U128 => !0,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use syntax_pos::{self, Span, BytePos, Pos, NO_EXPANSION};
-    use codemap::Spanned;
+    use codemap::{CrlfPolicy, Spanned};
     use ast::{self, Ident, PatKind};
     use abi::Abi;
     use attr::first_attr_value_str_by_name;
     use parse;
     use parse::parser::Parser;
-    use print::pprust::item_to_string;
+    use print::pprust::{self, item_to_string};
     use ptr::P;
     use tokenstream::{self, TokenTree};
     use util::parser_testing::{string_to_stream, string_to_parser};
     use util::parser_testing::{string_to_expr, string_to_item, string_to_stmt};
     use util::ThinVec;
+    use symbol::keywords;
 
     // produce a syntax_pos::span
     fn sp(a: u32, b: u32) -> Span {
@@ -843,6 +1709,566 @@ mod tests {
         assert_eq!(p.token.clone(), token::Eof);
     }
 
+    #[test] fn peek_tokens_does_not_consume() {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let parser = string_to_parser(&sess, "union U { x: u8 }".to_string());
+        assert!(parser.look_ahead_keyword(0, keywords::Union));
+        let toks = parser.peek_tokens(10);
+        assert_eq!(toks.len(), 4);
+        assert_eq!(toks[2], (token::OpenDelim(token::Brace), sp(8, 17)));
+        assert_eq!(toks[3].0, token::CloseDelim(token::NoDelim));
+        assert_eq!(parser.look_ahead_token(1).1, sp(6, 7));
+        assert!(parser.token.is_keyword(keywords::Union));
+    }
+
+    #[test] fn expr_restrictions() {
+        use parse::parser::Restrictions;
+
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let mut parser = string_to_parser(&sess, "x {}".to_string());
+        let expr = panictry!(parser.parse_expr_with_restrictions(Restrictions::NO_STRUCT_LITERAL));
+        assert_eq!(pprust::expr_to_string(&expr), "x");
+        assert_eq!(parser.token, token::OpenDelim(token::Brace));
+
+        let mut parser = string_to_parser(&sess, "(x {}) {}".to_string());
+        let expr = panictry!(parser.parse_expr_with_restrictions(Restrictions::NO_STRUCT_LITERAL));
+        assert_eq!(pprust::expr_to_string(&expr), "(x{})");
+
+        let mut parser = string_to_parser(&sess, "match x {} - 1".to_string());
+        let expr = panictry!(parser.parse_expr_with_restrictions(Restrictions::STMT_EXPR));
+        match expr.node {
+            ast::ExprKind::Match(..) => {}
+            _ => panic!("expected a match expression, found {:?}", expr),
+        }
+        assert_eq!(parser.token, token::BinOp(token::Minus));
+    }
+
+    #[test] fn separated_sequences() {
+        use parse::common::SeqSep;
+
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let mut parser = string_to_parser(&sess, "[a, b,]".to_string());
+        let (idents, trailing_comma) = panictry!(parser.parse_unspanned_comma_seq(
+            &token::OpenDelim(token::Bracket),
+            &token::CloseDelim(token::Bracket),
+            |p| p.parse_ident()));
+        assert_eq!(idents.len(), 2);
+        assert_eq!(trailing_comma, Some(sp(5, 6)));
+        parser_done(parser);
+
+        // The missing `;` is reported, and parsing goes on.
+        let mut parser = string_to_parser(&sess, "a; b c".to_string());
+        let idents = panictry!(parser.parse_seq_to_end(&token::Eof,
+                                                       SeqSep::trailing_disallowed(token::Semi),
+                                                       |p| p.parse_ident()));
+        assert_eq!(idents.len(), 3);
+        assert_eq!(sess.span_diagnostic.err_count(), 1);
+
+        let mut parser = string_to_parser(&sess, "a; b;".to_string());
+        let result = parser.parse_seq_to_end(&token::Eof,
+                                             SeqSep::trailing_disallowed(token::Semi),
+                                             |p| p.parse_ident());
+        result.err().unwrap().cancel();
+
+        let mut parser = string_to_parser(&sess, "a; b;".to_string());
+        let idents = panictry!(parser.parse_seq_to_end(&token::Eof,
+                                                       SeqSep::trailing_allowed(token::Semi),
+                                                       |p| p.parse_ident()));
+        assert_eq!(idents.len(), 2);
+    }
+
+    fn is_lazy(p: &Parser) -> bool {
+        p.look_ahead(1, |t| t.is_keyword(keywords::Static))
+    }
+
+    #[test] fn contextual_keywords() {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let parser = string_to_parser(&sess, "union U {}".to_string());
+        assert!(parser.is_contextual_keyword(keywords::Union.name()));
+        let parser = string_to_parser(&sess, "union::a".to_string());
+        assert!(!parser.is_contextual_keyword(keywords::Union.name()));
+
+        let lazy = Symbol::intern("lazy");
+        sess.register_contextual_keyword("lazy", Rc::new(is_lazy));
+        let mut parser = string_to_parser(&sess, "lazy static".to_string());
+        assert!(parser.eat_contextual_keyword(lazy));
+        assert!(parser.token.is_keyword(keywords::Static));
+        let mut parser = string_to_parser(&sess, "lazy + 1".to_string());
+        assert!(!parser.eat_contextual_keyword(lazy));
+        assert!(!parser.eat_contextual_keyword(keywords::Union.name()));
+    }
+
+    fn has_label(err: &DiagnosticBuilder, span: Span, label: &str) -> bool {
+        err.span.span_labels().iter().any(|l| {
+            l.span == span && l.label.as_ref().map(|s| &s[..]) == Some(label)
+        })
+    }
+
+    #[test] fn eof_context() {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let mut err = parse_item_from_source_str(
+            PathBuf::from("x").into(), "enum E".to_owned(), &sess).unwrap_err();
+        assert!(has_label(&err, sp(0, 4), "while parsing this enum"));
+        err.cancel();
+
+        let mut err = parse_expr_from_source_str(
+            PathBuf::from("x").into(), "match x".to_owned(), &sess).unwrap_err();
+        assert!(has_label(&err, sp(0, 5), "while parsing this `match` expression"));
+        err.cancel();
+    }
+
+    #[test] fn misspelled_keywords() {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let krate = parse_crate_from_source_str(
+            PathBuf::from("x").into(),
+            "pub strcut S; pubfn f() { retrun 1 }".to_owned(),
+            &sess,
+        ).unwrap();
+
+        let items = &krate.module.items;
+        assert_eq!(items.len(), 2);
+        match items[0].node {
+            ast::ItemKind::Struct(..) => {}
+            _ => panic!("expected a struct"),
+        }
+        assert_eq!(items[0].vis, ast::Visibility::Public);
+        assert_eq!(items[1].vis, ast::Visibility::Public);
+        match items[1].node {
+            ast::ItemKind::Fn(.., ref body) => match body.stmts[0].node {
+                ast::StmtKind::Expr(ref e) => match e.node {
+                    ast::ExprKind::Ret(Some(_)) => {}
+                    _ => panic!("expected a `return` expression"),
+                },
+                _ => panic!("expected an expression statement"),
+            },
+            _ => panic!("expected a function"),
+        }
+    }
+
+    #[test] fn stmt_recovery() {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let src = "{ let x = ; let y = 1; let z = 1 + fn f() {} z }";
+        let mut parser = string_to_parser(&sess, src.to_string());
+        let block = parser.parse_block().unwrap();
+        assert!(block.recovered);
+        let kinds: Vec<_> = block.stmts.iter().map(|stmt| match stmt.node {
+            ast::StmtKind::Local(ref local) => match local.ty {
+                Some(ref ty) if ty.node == ast::TyKind::Err => "recovered local",
+                _ => "local",
+            },
+            ast::StmtKind::Item(..) => "item",
+            ast::StmtKind::Semi(ref e) => match e.node {
+                ast::ExprKind::Err => "placeholder",
+                _ => "semi",
+            },
+            ast::StmtKind::Expr(..) => "expr",
+            ast::StmtKind::Mac(..) => "mac",
+        }).collect();
+        assert_eq!(kinds, ["recovered local", "local", "recovered local", "item", "expr"]);
+        assert_eq!(block.stmts[0].span, sp(2, 11));
+        // The bindings are kept, so the use of `z` in the tail resolves.
+        match block.stmts[2].node {
+            ast::StmtKind::Local(ref local) => {
+                assert_eq!(pprust::pat_to_string(&local.pat), "z");
+                assert!(local.init.as_ref().map_or(false, |e| e.node == ast::ExprKind::Err));
+            }
+            _ => unreachable!(),
+        }
+
+        let src = "{ 1 + ; g(); }";
+        let mut parser = string_to_parser(&sess, src.to_string());
+        let block = parser.parse_block().unwrap();
+        match block.stmts[0].node {
+            ast::StmtKind::Semi(ref e) => assert!(e.node == ast::ExprKind::Err),
+            _ => unreachable!(),
+        }
+        parser_done(parser);
+    }
+
+    #[test] fn skip_delimited_groups() {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let mut parser = string_to_parser(&sess, "f (a, [b]) { c d } e".to_string());
+        assert_eq!(parser.skip_to_matching_delimiter(), None);
+        assert!(parser.consume_block_tokens().is_empty());
+        parser.bump();
+        assert_eq!(parser.skip_to_matching_delimiter(), Some(sp(2, 10)));
+
+        let tts = parser.consume_block_tokens();
+        assert_eq!(tts.len(), 2);
+        assert_eq!(tts[0].span(), sp(13, 14));
+        assert_eq!(tts[1].span(), sp(15, 16));
+        assert_eq!(parser.span, sp(19, 20));
+        parser.bump();
+        parser_done(parser);
+    }
+
+    #[test] fn tts_in_range() {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let source = "fn f() { /* } */ }\nimpl S { fn g(x: u8) -> u8 { x } }\n".to_string();
+        let filemap = sess.codemap().new_filemap(PathBuf::from("bogofile").into(), source);
+        let start = filemap.start_pos.0;
+
+        // `fn g(x: u8) -> u8 { x }`, and the same range starting inside `fn`.
+        for &lo in &[28, 29] {
+            let span = sp(start + lo, start + 51);
+            let tts = filemap_to_tts_in_range(&sess, filemap.clone(), span);
+            assert_eq!(tts.len(), 6);
+            assert_eq!(tts[0].span(), sp(start + 28, start + 30));
+            assert_eq!(tts[5].span(), sp(start + 46, start + 51));
+        }
+    }
+
+    #[test] fn placeholders() {
+        let mut sess = ParseSess::new(FilePathMapping::empty());
+        sess.placeholder_chars = vec!['\u{FFFD}', '\u{2038}'];
+
+        let stream = parse_stream_from_source_str(PathBuf::from("placeholder").into(),
+                                                  "a.\u{FFFD}".to_string(), &sess, None);
+        let placeholder = token::Placeholder(Symbol::intern("\u{FFFD}"));
+        assert!(stream.trees().nth(2).unwrap().eq_token(placeholder));
+
+        let expr = panictry!(parse_expr_from_source_str(PathBuf::from("placeholder").into(),
+                                                        "f(a.\u{2038}, 1)".to_string(), &sess));
+        match expr.node {
+            ast::ExprKind::Call(_, ref args, _) => match args[0].node {
+                ast::ExprKind::Field(_, ref ident) => {
+                    assert_eq!(ident.node.name, Symbol::intern("\u{2038}"));
+                }
+                _ => panic!("expected a field access"),
+            },
+            _ => panic!("expected a call"),
+        }
+    }
+
+    #[test] fn visibilities() {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let parse = |src: &str| {
+            panictry!(parse_visibility_from_source_str(PathBuf::from("vis").into(),
+                                                       src.to_string(), &sess))
+        };
+
+        assert!(parse("pub").is_pub());
+        assert!(parse("").is_inherited());
+        assert!(parse("crate").is_equivalent(&parse("pub(crate)")));
+        assert!(parse("pub(in a::b)").is_equivalent(&parse("pub(in  a :: b)")));
+        assert!(!parse("pub(in a::b)").is_equivalent(&parse("pub(in a)")));
+        assert!(!parse("pub(self)").is_equivalent(&parse("pub(super)")));
+        assert_eq!(parse("pub(in a::b)").to_string(), "pub(in a::b)");
+        assert_eq!(parse("pub(super)").to_string(), "pub(super)");
+        assert_eq!(parse("").to_string(), "");
+    }
+
+    #[test] fn recovered_bounds() {
+        let mut sess = ParseSess::new(FilePathMapping::empty());
+        sess.recover_redundant_bounds = true;
+        let bound_count = |src: &str| {
+            let ty = panictry!(string_to_parser(&sess, src.to_string()).parse_ty());
+            match ty.node {
+                ast::TyKind::ImplTrait(ref bounds) => bounds.len(),
+                ast::TyKind::Path(_, ref path) => match path.segments[0].parameters {
+                    Some(ref parameters) => match **parameters {
+                        ast::PathParameters::AngleBracketed(ref data) => {
+                            match data.types[0].node {
+                                ast::TyKind::TraitObject(ref bounds, _) => bounds.len(),
+                                _ => panic!("expected a trait object"),
+                            }
+                        }
+                        _ => panic!("expected angle bracketed parameters"),
+                    },
+                    None => panic!("expected parameters"),
+                },
+                _ => panic!("unexpected type"),
+            }
+        };
+
+        assert_eq!(bound_count("Box<Trait + >"), 1);
+        assert_eq!(bound_count("Box<Trait + + Send + 'a>"), 3);
+        assert_eq!(bound_count("impl Clone + + Copy + Clone"), 2);
+        assert_eq!(bound_count("impl Iterator<Item = u8> + Iterator<Item = u16>"), 2);
+        assert_eq!(sess.span_diagnostic.err_count(), 0);
+    }
+
+    #[test] fn redundant_bounds_without_recovery() {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let src = "impl Clone + Copy + Clone +".to_string();
+        match panictry!(string_to_parser(&sess, src).parse_ty()).node {
+            ast::TyKind::ImplTrait(ref bounds) => assert_eq!(bounds.len(), 3),
+            _ => panic!("expected an impl Trait type"),
+        }
+    }
+
+    #[test] fn foreign_macros_and_types() {
+        let item = string_to_item("extern { type T; m!(a); pub static S: T; n! { b } }"
+                                  .to_string()).unwrap();
+        let items = match item.node {
+            ast::ItemKind::ForeignMod(ref foreign_mod) => &foreign_mod.items,
+            _ => panic!("expected a foreign module"),
+        };
+        let kinds = items.iter().map(|item| item.node.descriptive_variant()).collect::<Vec<_>>();
+        assert_eq!(kinds, ["foreign type", "macro in foreign module",
+                           "foreign static item", "macro in foreign module"]);
+        let printed = item_to_string(&item);
+        assert!(printed.contains("type T;"));
+        assert!(printed.contains("m!(a);"));
+        assert!(printed.contains("n!(b);"));
+    }
+
+    #[test] fn const_generic_params() {
+        let mut sess = ParseSess::new(FilePathMapping::empty());
+        let src = "struct S<'a, T, #[attr] const N: [u8; 2]>;";
+        let mut parser = string_to_parser(&sess, src.to_string());
+        parser.parse_item().err().unwrap().cancel();
+
+        sess.const_generics = true;
+        let item = panictry!(string_to_parser(&sess, src.to_string()).parse_item()).unwrap();
+        match item.node {
+            ast::ItemKind::Struct(_, ref generics) => match generics.params[2] {
+                ast::GenericParam::Const(ref param) => {
+                    assert_eq!(param.ident.to_string(), "N");
+                    assert_eq!(param.attrs.len(), 1);
+                    assert_eq!(param.span, sp(24, 40));
+                }
+                _ => panic!("expected a const parameter"),
+            },
+            _ => panic!("expected a struct"),
+        }
+        assert_eq!(item_to_string(&item), src);
+    }
+
+    #[test] fn closure_spans() {
+        let spans = |src: &str| match string_to_expr(src.to_string()).node {
+            ast::ExprKind::Closure(_, _, _, _, spans) => spans,
+            _ => panic!("expected a closure"),
+        };
+
+        assert_eq!(spans("static move |a, b| -> u8 { a + b }"), ast::ClosureSpans {
+            decl: sp(0, 24),
+            static_kw: Some(sp(0, 6)),
+            move_kw: Some(sp(7, 11)),
+            args: sp(12, 18),
+            output: Some(sp(19, 24)),
+        });
+        assert_eq!(spans("|| 1"), ast::ClosureSpans::from_args(sp(0, 2)));
+    }
+
+    #[test] fn trailing_commas() {
+        let comma = |src: &str| match string_to_expr(src.to_string()).node {
+            ast::ExprKind::Call(_, _, comma) |
+            ast::ExprKind::Tup(_, comma) |
+            ast::ExprKind::Struct(_, _, _, comma) => comma,
+            _ => panic!("expected a call, tuple or struct"),
+        };
+        assert_eq!(comma("f(a, b,)"), Some(sp(6, 7)));
+        assert_eq!(comma("f(a, b)"), None);
+        assert_eq!(comma("f()"), None);
+        assert_eq!(comma("(a,)"), Some(sp(2, 3)));
+        assert_eq!(comma("(a, b)"), None);
+        assert_eq!(comma("S { a: 1, }"), Some(sp(8, 9)));
+        assert_eq!(comma("S { a: 1, ..s }"), None);
+
+        let item = string_to_item("use a::{b, c::{d,},};".to_string()).unwrap();
+        match item.node {
+            ast::ItemKind::Use(ref tree) => match tree.kind {
+                ast::UseTreeKind::Nested(ref items, comma) => {
+                    assert_eq!(comma, Some(sp(18, 19)));
+                    match items[1].0.kind {
+                        ast::UseTreeKind::Nested(_, comma) => assert_eq!(comma, Some(sp(16, 17))),
+                        _ => panic!("expected a nested use tree"),
+                    }
+                }
+                _ => panic!("expected a nested use tree"),
+            },
+            _ => panic!("expected a use item"),
+        }
+
+        match string_to_expr("match x { a => {} b => 1, c => 2 }".to_string()).node {
+            ast::ExprKind::Match(_, ref arms) => {
+                let commas = arms.iter().map(|arm| arm.trailing_comma).collect::<Vec<_>>();
+                assert_eq!(commas, vec![None, Some(sp(24, 25)), None]);
+            }
+            _ => panic!("expected a match"),
+        }
+    }
+
+    #[test] fn written_literals() {
+        let lit = |src: &str| match string_to_expr(src.to_string()).node {
+            ast::ExprKind::Lit(ref lit) => lit.node.clone(),
+            _ => panic!("expected a literal"),
+        };
+
+        let hex = lit("0x_FF_u8");
+        assert_eq!(hex, ast::LitKind::Int(255, ast::LitIntType::Unsigned(ast::UintTy::U8),
+                                          Some(Symbol::intern("0x_FF_"))));
+        assert_eq!(hex.written_base(), Some(16));
+        assert!(hex.has_digit_separators());
+        assert_eq!(lit("1_000").written_base(), Some(10));
+        assert!(!lit("1000").has_digit_separators());
+
+        let float = lit("1_000.5e3");
+        assert_eq!(float, ast::LitKind::FloatUnsuffixed(Symbol::intern("1000.5e3"),
+                                                        Some(Symbol::intern("1_000.5e3"))));
+        assert_eq!(float.written_base(), None);
+        assert_eq!(lit("2f32").written_text(), Some(Symbol::intern("2")));
+
+        let expr = string_to_expr("f(0b1010_1010, 1_0.0f64)".to_string());
+        assert_eq!(pprust::expr_to_string(&expr), "f(0b1010_1010, 1_0.0f64)");
+        assert_eq!(pprust::to_canonical_string(|s| s.print_expr(&expr)), "f(170, 10.0f64)");
+    }
+
+    #[test] fn enclosing_construct_labels() {
+        let mut sess = ParseSess::new(FilePathMapping::empty());
+        sess.label_enclosing_constructs = true;
+        let mut parser = string_to_parser(&sess, "if match ) {} {}".to_string());
+        let mut err = parser.parse_expr().err().unwrap();
+        let labels = err.span.span_labels().into_iter()
+                        .filter(|label| !label.is_primary)
+                        .map(|label| (label.span, label.label.unwrap()))
+                        .collect::<Vec<_>>();
+        assert_eq!(labels, vec![
+            (sp(3, 8), "while parsing this `match` expression".to_string()),
+            (sp(0, 2), "while parsing this `if` expression".to_string()),
+        ]);
+        err.cancel();
+    }
+
+    #[test] fn include_hook() {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let includes = Rc::new(RefCell::new(Vec::new()));
+        let recorded = includes.clone();
+        sess.set_include_hook(Rc::new(move |_: &ParseSess, include: &IncludeMacro| {
+            recorded.borrow_mut().push((include.kind, include.path.clone(), include.file.clone()));
+            match include.path {
+                Some(ref path) if path == "gen.rs" => Some("fn generated() {}".to_string()),
+                _ => None,
+            }
+        }));
+
+        let src = "include!(\"a.rs\"); fn f() { m!(); g(include_bytes!(r\"b\\c\")); } \
+                   static S: &str = include_str!(concat!(\"d\", \".txt\")); \
+                   ::std::include!(\"gen.rs\");";
+        panictry!(parse_crate_from_source_str(PathBuf::from("src/lib.rs").into(),
+                                              src.to_string(), &sess));
+        assert_eq!(*includes.borrow(), vec![
+            (IncludeKind::Include, Some("a.rs".to_string()), Some(PathBuf::from("src/a.rs"))),
+            (IncludeKind::IncludeBytes, Some("b\\c".to_string()),
+             Some(PathBuf::from("src/b\\c"))),
+            (IncludeKind::IncludeStr, None, None),
+            (IncludeKind::Include, Some("gen.rs".to_string()), Some(PathBuf::from("src/gen.rs"))),
+        ]);
+        assert_eq!(sess.included_source(Path::new("src/gen.rs")),
+                   Some("fn generated() {}".to_string()));
+        assert_eq!(sess.included_source(Path::new("src/a.rs")), None);
+    }
+
+    #[test] fn canonical_mod_paths() {
+        use codemap::FileLoader;
+        use std::io;
+
+        /// Files by path, with their contents and canonical paths.
+        struct Files(HashMap<PathBuf, (&'static str, &'static str)>);
+
+        impl FileLoader for Files {
+            fn file_exists(&self, path: &Path) -> bool {
+                self.0.contains_key(path)
+            }
+            fn abs_path(&self, path: &Path) -> Option<PathBuf> {
+                Some(path.to_path_buf())
+            }
+            fn read_file(&self, path: &Path) -> io::Result<String> {
+                self.0.get(path).map(|file| file.0.to_string())
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such file"))
+            }
+            fn canonicalize(&self, path: &Path) -> Option<PathBuf> {
+                self.0.get(path).map(|file| PathBuf::from(file.1))
+            }
+        }
+
+        let mut files = HashMap::new();
+        files.insert(PathBuf::from("dir/main.rs"), ("mod a;", "/dir/main.rs"));
+        files.insert(PathBuf::from("dir/a.rs"), ("#[path = \"link.rs\"] mod b;", "/dir/a.rs"));
+        // A symbolic link to `a.rs`.
+        files.insert(PathBuf::from("dir/link.rs"), ("", "/dir/a.rs"));
+        let cm = Rc::new(CodeMap::with_file_loader(Box::new(Files(files)),
+                                                   FilePathMapping::empty()));
+        let handler = Handler::with_tty_emitter(ColorConfig::Auto, true, false, Some(cm.clone()));
+        let sess = ParseSess::with_span_handler(handler, cm.clone());
+
+        let mut err = parse_crate_from_file(Path::new("dir/main.rs"), &sess).err().unwrap();
+        assert!(err.message().starts_with("circular modules: /dir/a.rs -> "));
+        err.cancel();
+        let canonical_paths = cm.files().iter().map(|fm| {
+            fm.canonical_path.clone().unwrap()
+        }).collect::<Vec<_>>();
+        assert_eq!(canonical_paths, [PathBuf::from("/dir/main.rs"), PathBuf::from("/dir/a.rs")]);
+        assert_eq!(cm.canonicalize(Path::new("dir/link.rs")), PathBuf::from("/dir/a.rs"));
+        assert_eq!(cm.canonicalize(Path::new("dir/./x/../b.rs")), PathBuf::from("dir/b.rs"));
+    }
+
+    #[test] fn parse_events() {
+        use parse::events::{NodeKind, ParseEvent};
+
+        fn describe(events: Vec<ParseEvent>) -> String {
+            events.iter().map(|event| match *event {
+                ParseEvent::StartNode(kind, _) => format!("{:?}(", kind),
+                ParseEvent::Token(ref tok, _) => pprust::token_to_string(tok),
+                ParseEvent::FinishNode(..) => ")".to_string(),
+            }).collect::<Vec<_>>().join(" ")
+        }
+
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let mut parser = string_to_parser(&sess, "let x: Vec<Vec<u8>> = m!(1);".to_string());
+        parser.record_events();
+        panictry!(parser.parse_full_stmt(false));
+        let events = parser.take_events();
+        assert_eq!(events[0], ParseEvent::StartNode(NodeKind::Stmt, sp(0, 3)));
+        assert_eq!(events[events.len() - 1], ParseEvent::FinishNode(NodeKind::Stmt, sp(0, 28)));
+        assert!(events.contains(&ParseEvent::Token(token::Gt, sp(17, 18))));
+        assert!(events.contains(&ParseEvent::FinishNode(NodeKind::Ty, sp(11, 18))));
+        assert_eq!(describe(events),
+                   "Stmt( let Pat( x ) : Ty( Vec < Ty( Vec < Ty( u8 ) > ) > ) = \
+                    Expr( m ! ( 1 ) ) ; )");
+        parser_done(parser);
+
+        // The events of the attempt to parse `usize < b` as a type are dropped.
+        let mut parser = string_to_parser(&sess, "a as usize < b".to_string());
+        parser.record_events();
+        panictry!(parser.parse_expr());
+        assert_eq!(describe(parser.take_events()), "Expr( a as usize < b )");
+    }
+
+    #[test] fn deferred_fn_bodies() {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let mut parser = string_to_parser(&sess, "fn f() { #![a] g(1) }".to_string());
+        parser.defer_fn_bodies = true;
+        let item = panictry!(parser.parse_item()).unwrap();
+        assert_eq!(parser.captured_groups.len(), 1);
+        assert_eq!(parser.captured_groups[0].span, sp(7, 21));
+        // The inner attributes of the body are kept on the item.
+        assert_eq!(item.attrs.len(), 1);
+        match item.node {
+            ast::ItemKind::Fn(.., ref body) => {
+                assert_eq!(body.span, sp(7, 21));
+                assert_eq!(body.stmts.len(), 1);
+                match body.stmts[0].node {
+                    ast::StmtKind::Expr(ref expr) => match expr.node {
+                        ast::ExprKind::Unparsed(ref tts) => {
+                            assert!(TokenStream::from(tts.clone())
+                                .eq_unspanned(&parser.captured_groups[0].tokens));
+                        }
+                        _ => panic!("expected an unparsed body"),
+                    },
+                    _ => panic!("expected an expression statement"),
+                }
+            }
+            _ => panic!("expected a function"),
+        }
+
+        let (attrs, body) = panictry!(parser.parse_captured(&parser.captured_groups[0]));
+        assert_eq!(attrs.len(), 1);
+        assert_eq!(body.stmts.len(), 1);
+        assert_eq!(body.span, sp(7, 21));
+    }
+
     #[test] fn parse_ident_pat () {
         let sess = ParseSess::new(FilePathMapping::empty());
         let mut parser = string_to_parser(&sess, "b".to_string());
@@ -1007,6 +2433,22 @@ mod tests {
         string_to_expr("a::z.froob(b,&(987+3))".to_string());
     }
 
+    #[test] fn prefix_exprs() {
+        let expr = string_to_expr("-!*&mut box x".to_string());
+        assert_eq!(pprust::expr_to_string(&expr), "-!*&mut box x");
+        assert_eq!(expr.span, sp(0, 13));
+
+        // Long chains of prefix operators don't recurse.
+        let expr = string_to_expr(format!("{}x", "!".repeat(5000)));
+        let mut depth = 0;
+        let mut e = &expr;
+        while let ast::ExprKind::Unary(ast::UnOp::Not, ref operand) = e.node {
+            depth += 1;
+            e = operand;
+        }
+        assert_eq!(depth, 5000);
+    }
+
     #[test] fn attrs_fix_bug () {
         string_to_item("pub fn mk_file_writer(path: &Path, flags: &[FileFlag])
                    -> Result<Box<Writer>, String> {
@@ -1046,6 +2488,64 @@ mod tests {
         assert_eq!(doc, "/** doc comment\n *  with CRLF */");
     }
 
+    #[test]
+    fn doc_comment_kinds() {
+        use ast::DocCommentKind;
+
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let source = "/// line\n/** block */\n#[doc = \"raw\"]\nstruct S;".to_string();
+        let item = parse_item_from_source_str(PathBuf::from("docs").into(), source, &sess)
+            .unwrap().unwrap();
+        let kinds = item.attrs.iter().map(|a| a.doc_comment_kind()).collect::<Vec<_>>();
+        assert_eq!(kinds, [Some(DocCommentKind::Line), Some(DocCommentKind::Block), None]);
+        assert_eq!(pprust::item_to_string(&item),
+                   "/// line\n/** block */\n#[doc = \"raw\"]\nstruct S;");
+    }
+
+    #[test]
+    fn repr_hints() {
+        use attr::{self, ReprHint, SignedInt};
+
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let name: FileName = PathBuf::from("repr").into();
+        let source = "#[repr(C, packed(4))] #[repr(i8)] struct S;".to_string();
+        let item = parse_item_from_source_str(name.clone(), source, &sess).unwrap().unwrap();
+        let hints = attr::find_repr_hints(&sess.span_diagnostic, &item.attrs);
+        assert_eq!(hints.iter().map(|hint| hint.node).collect::<Vec<_>>(),
+                   vec![ReprHint::C,
+                        ReprHint::Packed(Some(4)),
+                        ReprHint::Int(SignedInt(ast::IntTy::I8))]);
+        assert_eq!(hints[1].span, sp(10, 19));
+        assert_eq!(sess.span_diagnostic.err_count(), 0);
+
+        let source = "#[repr(packed, align(8), u8, u8, i32)] struct S;".to_string();
+        let item = parse_item_from_source_str(name, source, &sess).unwrap().unwrap();
+        let hints = attr::parse_repr_hints(&sess.span_diagnostic, &item.attrs[0]);
+        assert_eq!(hints.len(), 5);
+        assert!(attr::check_repr_hints(&sess.span_diagnostic, &hints));
+        // `i32` conflicts with `u8`, and `packed` with `align(8)`.
+        assert_eq!(sess.span_diagnostic.err_count(), 2);
+    }
+
+    #[test]
+    fn duplicate_attrs() {
+        use attr::{self, DuplicateAttr};
+
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let source = "#[inline] #[inline(always)] fn f() { #[cold] #[cold] fn g() {} } \
+                      #[path = \"a.rs\"] #[doc = \"a\"] #[doc = \"b\"] mod m {}".to_string();
+        let krate = parse_crate_from_source_str(PathBuf::from("dup").into(), source, &sess)
+            .unwrap();
+        let duplicates = attr::check_duplicate_attrs(&sess.span_diagnostic, &krate);
+        let inline = Symbol::intern("inline");
+        let cold = Symbol::intern("cold");
+        assert_eq!(duplicates, vec![
+            DuplicateAttr { name: inline, first: sp(0, 9), duplicate: sp(10, 27) },
+            DuplicateAttr { name: cold, first: sp(37, 44), duplicate: sp(45, 52) },
+        ]);
+        assert_eq!(sess.span_diagnostic.err_count(), 0);
+    }
+
     #[test]
     fn ttdelim_span() {
         let sess = ParseSess::new(FilePathMapping::empty());
@@ -1065,6 +2565,100 @@ mod tests {
         }
     }
 
+    fn parse_widget<'a>(p: &mut Parser<'a>) -> PResult<'a, (Ident, Rc<Any>)> {
+        let ident = p.parse_ident()?;
+        p.expect(&token::Semi)?;
+        Ok((ident, Rc::new(42u32)))
+    }
+
+    fn parse_boom<'a>(_: &mut Parser<'a>) -> PResult<'a, (Ident, Rc<Any>)> {
+        panic!("unexpected state")
+    }
+
+    #[test]
+    fn ice_reports() {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        sess.register_item_extension("boom", Rc::new(parse_boom));
+        let reports = Rc::new(RefCell::new(Vec::new()));
+        let hook_reports = reports.clone();
+        sess.set_ice_hook(Rc::new(move |report: &IceReport| {
+            hook_reports.borrow_mut().push(report.to_string())
+        }));
+        let parse = |file: &str, source: &str, parse_item: bool| {
+            ::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(|| {
+                let name = PathBuf::from(file).into();
+                if parse_item {
+                    parse_item_from_source_str(name, source.to_owned(), &sess).ok();
+                } else {
+                    parse_crate_from_source_str(name, source.to_owned(), &sess).ok();
+                }
+            })).err().unwrap()
+        };
+
+        // The original payload is kept, the report goes to the hook.
+        let payload = parse("ice.rs", "fn f() {}\n\nfn g() {\n    boom x;\n}", false);
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"unexpected state"));
+        let payload = parse("item.rs", "boom y;", true);
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"unexpected state"));
+        assert_eq!(*reports.borrow(), vec![
+            "the parser panicked: unexpected state\n  at `x` (ice.rs:4:10: 4:11)\n  \
+             while parsing function".to_string(),
+            "the parser panicked: unexpected state\n  at `y` (item.rs:1:6: 1:7)".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn item_extension() {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        sess.register_item_extension("widget", Rc::new(parse_widget));
+        let krate = parse_crate_from_source_str(
+            PathBuf::from("foo").into(),
+            "pub widget Foo; fn widget() {}".to_owned(),
+            &sess,
+        ).unwrap();
+
+        assert_eq!(krate.module.items.len(), 2);
+        let item = &krate.module.items[0];
+        assert_eq!(item.ident.name, "Foo");
+        assert_eq!(item.span, sp(0, 15));
+        match item.node {
+            ast::ItemKind::Extension(ref ext) => {
+                assert_eq!(ext.keyword, "widget");
+                assert_eq!(ext.data.0.downcast_ref::<u32>(), Some(&42));
+            }
+            _ => panic!("expected an extension item"),
+        }
+    }
+
+    #[test]
+    fn syntax_variant() {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        sess.register_variant_item_extension("gadgets", "widget", Rc::new(parse_widget));
+
+        let krate = parse_crate_from_source_str(
+            PathBuf::from("variant.rs").into(),
+            "#![syntax_variant(\"gadgets\")] widget Foo;".to_owned(),
+            &sess,
+        ).unwrap();
+        assert_eq!(krate.module.items.len(), 1);
+        match krate.module.items[0].node {
+            ast::ItemKind::Extension(ref ext) => assert_eq!(ext.keyword, "widget"),
+            _ => panic!("expected an extension item"),
+        }
+        let file = sess.codemap().get_filemap(&PathBuf::from("variant.rs").into()).unwrap();
+        assert_eq!(file.syntax_variant.get(), Some(Symbol::intern("gadgets")));
+
+        // Without the attribute, `widget` is an ordinary identifier.
+        let krate = parse_crate_from_source_str(
+            PathBuf::from("plain.rs").into(),
+            "fn widget() {}".to_owned(),
+            &sess,
+        ).unwrap();
+        assert_eq!(krate.module.items[0].ident.name, "widget");
+        let file = sess.codemap().get_filemap(&PathBuf::from("plain.rs").into()).unwrap();
+        assert_eq!(file.syntax_variant.get(), None);
+    }
+
     // This tests that when parsing a string (rather than a file) we don't try
     // and read in a file for a module declaration and just parse a stub.
     // See `recurse_into_file_modules` in the parser.
@@ -1083,4 +2677,442 @@ mod tests {
             panic!();
         }
     }
+
+    #[test]
+    fn parse_timings() {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        assert!(sess.take_parse_timings().is_none());
+
+        sess.enable_parse_timings();
+        let name: FileName = PathBuf::from("timed.rs").into();
+        parse_crate_from_source_str(name.clone(), "fn f() { g(1, 2); }".to_owned(), &sess)
+            .unwrap();
+        let timings = sess.take_parse_timings().unwrap();
+        assert!(!sess.parse_timings_enabled());
+
+        assert_eq!(timings.files.len(), 1);
+        assert_eq!(*timings.file(&name).unwrap(), timings.total);
+        assert_eq!(timings.total.file_loading, Duration::new(0, 0));
+    }
+
+    #[test]
+    fn memory_report() {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let before = sess.memory_report();
+        assert_eq!((before.filemaps, before.token_trees, before.ast), (0, 0, 0));
+
+        parse_crate_from_source_str(PathBuf::from("mem.rs").into(),
+                                    "fn f() { g(1, 2); }".to_owned(), &sess).unwrap();
+        let after = sess.memory_report();
+        assert!(after.filemaps >= "fn f() { g(1, 2); }".len());
+        assert!(after.token_trees > 0);
+        assert!(after.ast > 0);
+        assert!(after.interner > 0);
+    }
+
+    #[test]
+    fn expected_found() {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let mut err = parse_item_from_source_str(PathBuf::from("missing_semi.rs").into(),
+                                                 "fn f() { let x = 1 }".to_owned(), &sess)
+            .err().unwrap();
+        {
+            let expected_found = err.expected_found.as_ref().unwrap();
+            assert!(expected_found.expected.contains(&"Semi".to_string()));
+            assert_eq!(expected_found.found, "CloseDelim(Brace)");
+            assert_eq!(expected_found.production, Some("function".to_string()));
+        }
+        err.cancel();
+    }
+
+    #[test]
+    fn translated_messages() {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        sess.span_diagnostic.set_message_lookup(Rc::new(|id: &str| {
+            if id == messages::EXPECTED_ONE_OF.id {
+                Some("{{{found}}} statt {expected}".to_string())
+            } else {
+                None
+            }
+        }));
+        let mut err = parse_item_from_source_str(PathBuf::from("translated.rs").into(),
+                                                 "fn f() { let x = 1 }".to_owned(), &sess)
+            .err().unwrap();
+        assert!(err.message().starts_with("{}} statt "));
+        err.cancel();
+
+        sess.span_diagnostic.clear_message_lookup();
+        let mut err = parse_item_from_source_str(PathBuf::from("english.rs").into(),
+                                                 "fn f() { let x = 1 }".to_owned(), &sess)
+            .err().unwrap();
+        assert!(err.message().starts_with("expected one of "));
+        err.cancel();
+    }
+
+    #[test]
+    fn delayed_bugs() {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        sess.span_diagnostic.delay_span_bug(syntax_pos::DUMMY_SP, "recovery went wrong");
+        assert!(sess.span_diagnostic.has_delayed_bugs());
+        // The bug is masked by the error.
+        parse_item_from_source_str(PathBuf::from("error.rs").into(),
+                                   "fn f() { let x = 1 }".to_owned(), &sess)
+            .err().unwrap().emit();
+    }
+
+    #[test]
+    #[should_panic]
+    fn delayed_bugs_without_errors() {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        sess.span_diagnostic.delay_span_bug(syntax_pos::DUMMY_SP, "recovery went wrong");
+        parse_item_from_source_str(PathBuf::from("ok.rs").into(),
+                                   "fn f() { let x = 1; }".to_owned(), &sess).unwrap();
+        sess.span_diagnostic.finish();
+    }
+
+    #[test]
+    fn delayed_bugs_after_reset() {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        sess.span_diagnostic.delay_span_bug(syntax_pos::DUMMY_SP, "recovery went wrong");
+        sess.span_diagnostic.err("masks the bug");
+        sess.span_diagnostic.reset_err_count();
+        assert!(!sess.span_diagnostic.has_delayed_bugs());
+        sess.span_diagnostic.finish();
+
+        // Dropping the handler only emits the bug.
+        sess.span_diagnostic.delay_span_bug(syntax_pos::DUMMY_SP, "recovery went wrong");
+    }
+
+    #[test]
+    fn error_limit() {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let handler = &sess.span_diagnostic;
+        handler.set_error_limit(2);
+        handler.err("first error");
+        assert!(!handler.error_limit_reached());
+        let payload = ::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(|| {
+            handler.err("second error");
+            handler.err("third error");
+        })).err().unwrap();
+        assert!(payload.is::<::errors::FatalErrorMarker>());
+        assert!(handler.error_limit_reached());
+        assert_eq!(handler.err_count(), 2);
+    }
+
+    #[test]
+    fn preserved_literal_suffixes() {
+        let mut sess = ParseSess::new(FilePathMapping::empty());
+        sess.preserve_unknown_suffixes = true;
+        let expr = parse_expr_from_source_str(PathBuf::from("lits.rs").into(),
+                                              "f(42px, 1.5em, 3u8)".to_owned(), &sess).unwrap();
+        let args = match expr.node {
+            ast::ExprKind::Call(_, ref args, _) => args,
+            _ => panic!("expected a call"),
+        };
+        let kinds = args.iter().map(|arg| match arg.node {
+            ast::ExprKind::Lit(ref lit) => lit.node.clone(),
+            _ => panic!("expected a literal"),
+        }).collect::<Vec<_>>();
+        assert_eq!(kinds, vec![
+            ast::LitKind::UnknownSuffix(token::Integer(Symbol::intern("42")),
+                                        Symbol::intern("px")),
+            ast::LitKind::UnknownSuffix(token::Float(Symbol::intern("1.5")),
+                                        Symbol::intern("em")),
+            ast::LitKind::Int(3, ast::LitIntType::Unsigned(ast::UintTy::U8),
+                              Some(Symbol::intern("3"))),
+        ]);
+        assert_eq!(pprust::expr_to_string(&expr), "f(42px, 1.5em, 3u8)");
+        assert!(!sess.span_diagnostic.has_errors());
+    }
+
+    #[test]
+    fn attr_macro_input() {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let stream = |src: &str| {
+            parse_stream_from_source_str(PathBuf::from("input.rs").into(), src.to_string(),
+                                         &sess, None)
+        };
+
+        let (meta, item) = parse_attr_macro_input(&sess, stream("route(GET, \"/\")"),
+                                                  stream("#[inline] fn index() {}")).unwrap();
+        assert_eq!(meta.name, Symbol::intern("route"));
+        assert_eq!(meta.meta_item_list().map(|list| list.len()), Some(2));
+        assert_eq!(item.ident.name, Symbol::intern("index"));
+        assert_eq!(item.attrs.len(), 1);
+        assert_eq!(sess.codemap().span_to_snippet(meta.span).unwrap(), "route(GET, \"/\")");
+        assert_eq!(sess.codemap().span_to_snippet(item.span).unwrap(), "fn index() {}");
+
+        parse_attr_macro_input(&sess, stream("route GET"), stream("fn index() {}"))
+            .err().unwrap().cancel();
+        parse_attr_macro_input(&sess, stream("route"), stream("1 + 1"))
+            .err().unwrap().cancel();
+        parse_attr_macro_input(&sess, stream("route"), stream("fn f() {} fn g() {}"))
+            .err().unwrap().cancel();
+    }
+
+    #[test]
+    fn parse_borrowed_source() {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let buffer = b"fn f() {}\nstruct S;\n".to_vec();
+        let src = str::from_utf8(&buffer).unwrap();
+        let krate = parse_crate_from_str(PathBuf::from("borrowed.rs").into(), src, &sess).unwrap();
+        assert_eq!(krate.module.items.len(), 2);
+        assert_eq!(sess.codemap().span_to_snippet(krate.module.items[1].span).unwrap(),
+                   "struct S;");
+
+        let expr = new_parser_from_str(&sess, PathBuf::from("expr.rs").into(), &src[7..9])
+            .parse_expr().unwrap();
+        assert_eq!(pprust::expr_to_string(&expr), "{}");
+        let stream = parse_stream_from_str(PathBuf::from("stream.rs").into(), src, &sess, None);
+        assert_eq!(stream.trees().count(), 7);
+
+        let expr = parse_expr_from_str(PathBuf::from("expr.rs").into(), &src[7..9], &sess);
+        assert_eq!(pprust::expr_to_string(&expr.unwrap()), "{}");
+        let item = parse_item_from_str(PathBuf::from("item.rs").into(), &src[10..], &sess);
+        assert_eq!(pprust::item_to_string(&item.unwrap().unwrap()), "struct S;");
+        let stmt = parse_stmt_from_str(PathBuf::from("stmt.rs").into(), &src[..9], &sess);
+        assert!(stmt.unwrap().unwrap().is_item());
+    }
+
+    #[test]
+    fn token_offsets() {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let src = "fn f(x: u8) { /* c */ x }";
+        let filemap = sess.codemap().new_filemap(PathBuf::from("offsets.rs").into(),
+                                                 src.to_string());
+        let (stream, offsets) = filemap_to_stream_with_offsets(&sess, filemap);
+        assert_eq!(stream.trees().count(), 4);
+        assert_eq!(offsets.len(), 10);
+        let texts = (0..offsets.len()).map(|index| {
+            let (start, end) = offsets.token(index).unwrap();
+            &src[start..end]
+        }).collect::<Vec<_>>();
+        assert_eq!(texts, ["fn", "f", "(", "x", ":", "u8", ")", "{", "x", "}"]);
+        assert_eq!(offsets.token_at(0), Some(0));
+        assert_eq!(offsets.token_at(1), Some(0));
+        assert_eq!(offsets.token_at(2), None);
+        assert_eq!(offsets.token_at(src.find("u8").unwrap() + 1), Some(5));
+        assert_eq!(offsets.token_at(src.find("c").unwrap()), None);
+        assert_eq!(offsets.token_at(src.len() - 1), Some(9));
+        assert_eq!(offsets.token_at(src.len()), None);
+        assert_eq!(offsets.token(10), None);
+    }
+
+    #[test]
+    fn byte_str_lits() {
+        assert_eq!(&*byte_str_lit("plain bytes"), b"plain bytes");
+        assert_eq!(&*byte_str_lit("a\\x00\\n\\\n   b"), b"a\x00\nb");
+        assert_eq!(&*byte_str_lit("a\r\nb"), b"a\nb");
+    }
+
+    #[test]
+    fn fallible_entry_points() {
+        let mut sess = ParseSess::new(FilePathMapping::empty());
+        sess.max_delim_depth = Some(2);
+        let name = FileName::from(PathBuf::from("nested"));
+        let mut err = try_new_parser_from_source_str(&sess, name.clone(), "(((a)))".to_string())
+            .err().unwrap();
+        assert_eq!(err.message(), "delimiters are nested too deeply");
+        err.cancel();
+        let mut err = parse_expr_from_source_str(name, "(((a)))".to_string(), &sess)
+            .err().unwrap();
+        err.cancel();
+
+        let mut err = try_new_parser_from_file(&sess, Path::new("no/such/file.rs"))
+            .err().unwrap();
+        assert!(err.message().starts_with("couldn't read"));
+        err.cancel();
+    }
+
+    #[test]
+    fn standalone_pat() {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let name = FileName::from(PathBuf::from("pat"));
+        let src = "Foo { x, ref y, .. }".to_string();
+        let pat = parse_pat_from_source_str(name.clone(), src, &sess).unwrap();
+        match pat.node {
+            PatKind::Struct(_, ref fields, true) => assert_eq!(fields.len(), 2),
+            _ => panic!("expected a struct pattern, found {:?}", pat),
+        }
+
+        let mut err = parse_pat_from_source_str(name, "+".to_string(), &sess).err().unwrap();
+        err.cancel();
+    }
+
+    #[test]
+    fn standalone_path() {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let parse = |src: &str, style| {
+            let name = FileName::from(PathBuf::from("path"));
+            parse_path_from_source_str(name, src.to_string(), style, &sess)
+        };
+
+        let path = parse("foo::bar::<T>", PathStyle::Expr).unwrap();
+        assert_eq!(path.segments.len(), 2);
+        assert!(path.segments[1].parameters.is_some());
+        let path = parse("::std::vec::Vec<u8>", PathStyle::Type).unwrap();
+        assert!(path.is_global());
+        assert!(path.segments.last().unwrap().parameters.is_some());
+        assert_eq!(parse("a::b", PathStyle::Mod).unwrap().segments.len(), 2);
+
+        parse("foo<T>", PathStyle::Expr).err().unwrap().cancel();
+        parse("a::b<T>", PathStyle::Mod).err().unwrap().cancel();
+        parse("a b", PathStyle::Type).err().unwrap().cancel();
+    }
+
+    #[test]
+    fn frontmatter() {
+        let mut sess = ParseSess::new(FilePathMapping::empty());
+        sess.allow_frontmatter = true;
+        let src = "#!/usr/bin/env cargo\n\
+                   ---cargo\n\
+                   [dependencies]\n\
+                   libc = \"0.2\"\n\
+                   ---\n\
+                   #![allow(dead_code)]\n\
+                   fn main() {}\n";
+        let krate = parse_crate_from_source_str(PathBuf::from("script.rs").into(),
+                                                src.to_string(), &sess).unwrap();
+        assert_eq!(krate.attrs.len(), 1);
+        assert_eq!(krate.module.items.len(), 1);
+        let file = sess.codemap().get_filemap(&PathBuf::from("script.rs").into()).unwrap();
+        let block = file.frontmatter.borrow().clone().unwrap();
+        assert_eq!(block.info, Some("cargo".to_string()));
+        assert_eq!(block.text, "[dependencies]\nlibc = \"0.2\"\n");
+        assert_eq!(sess.codemap().span_to_snippet(block.span).unwrap(),
+                   "---cargo\n[dependencies]\nlibc = \"0.2\"\n---");
+
+        // An unclosed block is reported and covers the rest of the file.
+        let krate = parse_crate_from_source_str(PathBuf::from("unclosed.rs").into(),
+                                                "---\nfn main() {}\n".to_string(), &sess)
+            .unwrap();
+        assert!(krate.module.items.is_empty());
+        assert_eq!(sess.span_diagnostic.err_count(), 1);
+
+        sess.allow_frontmatter = false;
+        let file = sess.codemap().new_filemap(PathBuf::from("plain.rs").into(), src.to_string());
+        let mut parser = try_filemap_to_parser(&sess, file.clone()).unwrap();
+        parser.parse_crate_mod().err().unwrap().cancel();
+        assert!(file.frontmatter.borrow().is_none());
+    }
+
+    #[test]
+    fn crate_provenance() {
+        let mut sess = ParseSess::new(FilePathMapping::empty());
+        sess.config.insert((Symbol::intern("feature"), Some(Symbol::intern("b"))));
+        sess.config.insert((Symbol::intern("feature"), Some(Symbol::intern("a"))));
+        sess.config.insert((Symbol::intern("unix"), None));
+        let name = FileName::from(PathBuf::from("cached.rs"));
+        let (krate, provenance) = parse_crate_from_source_str_with_provenance(
+            name.clone(), "fn f() {}".to_string(), &sess).unwrap();
+        assert_eq!(krate.module.items.len(), 1);
+        assert_eq!(provenance.entry_point, EntryPoint::CrateFromSource);
+        assert_eq!(provenance.source_name, name);
+        assert_eq!(provenance.cfg, vec![
+            ("feature".to_string(), Some("a".to_string())),
+            ("feature".to_string(), Some("b".to_string())),
+            ("unix".to_string(), None),
+        ]);
+        let expected = ParseProvenance::new(EntryPoint::CrateFromSource, name.clone(), &sess);
+        assert_eq!(provenance, expected);
+
+        sess.codemap().set_crlf_policy(CrlfPolicy::Normalize);
+        assert!(provenance != ParseProvenance::new(EntryPoint::CrateFromSource, name.clone(),
+                                                   &sess));
+        sess.codemap().set_crlf_policy(CrlfPolicy::Preserve);
+        sess.const_generics = true;
+        assert!(provenance != ParseProvenance::new(EntryPoint::CrateFromSource, name, &sess));
+    }
+
+    #[test]
+    fn standalone_block() {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let name = FileName::from(PathBuf::from("block"));
+        let src = "{ let x = 1; fn f() {} x + 1 }".to_string();
+        let block = parse_block_from_source_str(name.clone(), src, &sess).unwrap();
+        assert_eq!(block.stmts.len(), 3);
+        match block.stmts[2].node {
+            ast::StmtKind::Expr(_) => {}
+            ref stmt => panic!("expected a trailing expression, found {:?}", stmt),
+        }
+
+        for src in &["let x = 1;", "{ x } y"] {
+            parse_block_from_source_str(name.clone(), src.to_string(), &sess)
+                .err().unwrap().cancel();
+        }
+    }
+
+    #[test]
+    fn standalone_generics() {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let parse = |src: &str| {
+            let name = FileName::from(PathBuf::from("generics"));
+            parse_generics_from_source_str(name, src.to_string(), &sess)
+        };
+
+        let (generics, where_clause) = parse("<'a, T: Clone + 'a, U> where U: Default").unwrap();
+        assert_eq!(generics.params.len(), 3);
+        assert!(generics.where_clause.predicates.is_empty());
+        assert_eq!(where_clause.predicates.len(), 1);
+
+        let (generics, where_clause) = parse("where Vec<T>: Clone,").unwrap();
+        assert!(generics.params.is_empty());
+        assert_eq!(where_clause.predicates.len(), 1);
+
+        let (generics, where_clause) = parse("").unwrap();
+        assert!(generics.params.is_empty() && where_clause.predicates.is_empty());
+
+        parse("<T").err().unwrap().cancel();
+        parse("<T> U").err().unwrap().cancel();
+    }
+
+    #[test]
+    fn nested_self_param() {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let name = || FileName::from(PathBuf::from("body"));
+        let self_ty = || {
+            let src = "Foo<T>".to_string();
+            try_new_parser_from_source_str(&sess, name(), src).unwrap().parse_ty().unwrap()
+        };
+
+        let mut parser = try_new_parser_from_source_str(&sess, name(),
+                                                        "fn helper(&self) {}".to_string())
+            .unwrap();
+        parser.impl_self_ty = Some(self_ty());
+        let mut err = parser.parse_item().err().unwrap();
+        assert_eq!(err.message(), "unexpected `self` parameter in function");
+        assert!(err.children.iter().any(|note| note.message().contains("`impl Foo<T>`")));
+        err.cancel();
+
+        // `mut self` is also rejected as a binding, before the error about it.
+        let mut parser = try_new_parser_from_source_str(&sess, name(),
+                                                        "fn helper(mut self) {}".to_string())
+            .unwrap();
+        let mut err = parser.parse_item().err().unwrap();
+        assert_eq!(err.message(), "unexpected `self` parameter in function");
+        err.cancel();
+        sess.span_diagnostic.reset_err_count();
+
+        let src = "{ let x = self.0; fn helper(&mut self) {} x }".to_string();
+        parse_expr_in_impl_from_source_str(name(), src, self_ty(), &sess).unwrap();
+        assert_eq!(sess.span_diagnostic.err_count(), 1);
+
+        let src = "fn helper(self) {}".to_string();
+        let mut err = parse_item_in_impl_from_source_str(name(), src, self_ty(), &sess)
+            .err().unwrap();
+        assert!(err.children.iter().any(|note| note.message().contains("`impl Foo<T>`")));
+        err.cancel();
+        let src = "fn helper(self) {}".to_string();
+        let mut err = parse_stmt_in_impl_from_source_str(name(), src, self_ty(), &sess)
+            .err().unwrap();
+        assert!(err.children.iter().any(|note| note.message().contains("`impl Foo<T>`")));
+        err.cancel();
+        let src = "{ fn helper(self) {} }".to_string();
+        parse_block_in_impl_from_source_str(name(), src, self_ty(), &sess).unwrap();
+        assert_eq!(sess.span_diagnostic.err_count(), 2);
+
+        let src = "impl Foo { fn f(&self) { fn g(a: u8, self: Self) {} } }".to_string();
+        parse_item_from_source_str(name(), src, &sess).unwrap().unwrap();
+        assert_eq!(sess.span_diagnostic.err_count(), 1);
+    }
 }
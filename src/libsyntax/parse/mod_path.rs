@@ -0,0 +1,119 @@
+// Copyright 2018 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! `#[path]` attribute values, and the paths of module files.
+//!
+//! `#[path = "..."]` values are read the same on all platforms: both `/` and
+//! `\` separate components, and `.` and `..` components are resolved
+//! lexically. Build sandboxes can restrict which module files the parser may
+//! open with `ParseSess::set_mod_path_policy`, e.g. to the crate directory
+//! with `confine_to_directory`.
+
+use std::fmt;
+use std::path::{self, Component, Path, PathBuf};
+use std::rc::Rc;
+
+/// Decides whether the parser may open the module file at the given path,
+/// returning the reason if it may not.
+pub type ModPathPolicy = Rc<Fn(&Path) -> Result<(), String>>;
+
+/// Why a `#[path]` value is invalid.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PathAttrError {
+    Empty,
+    Nul,
+}
+
+impl fmt::Display for PathAttrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            PathAttrError::Empty => "the path is empty",
+            PathAttrError::Nul => "the path contains a NUL character",
+        })
+    }
+}
+
+/// Converts a `#[path]` value to a path, see the module documentation. The
+/// path is relative to the directory of the module unless it is absolute.
+pub fn normalize_path_attr(value: &str) -> Result<PathBuf, PathAttrError> {
+    if value.is_empty() {
+        return Err(PathAttrError::Empty);
+    }
+    if value.contains('\0') {
+        return Err(PathAttrError::Nul);
+    }
+    let native = value.chars().map(|c| {
+        if c == '/' || c == '\\' { path::MAIN_SEPARATOR } else { c }
+    }).collect::<String>();
+    Ok(normalize_lexically(Path::new(&native)))
+}
+
+/// Resolves the `.` and `..` components of `path` without looking at the file
+/// system. Leading `..` components of a relative path are kept.
+pub fn normalize_lexically(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                // `/..` is `/`.
+                Some(Component::RootDir) | Some(Component::Prefix(_)) => {}
+                _ => normalized.push(".."),
+            },
+            component => normalized.push(component.as_os_str()),
+        }
+    }
+    normalized
+}
+
+/// Returns a policy which only allows module files inside of `root`, usually
+/// the directory of the crate root file. Paths are compared lexically, so
+/// symbolic links are not followed.
+pub fn confine_to_directory(root: PathBuf) -> ModPathPolicy {
+    let root = normalize_lexically(&root);
+    Rc::new(move |path: &Path| {
+        if normalize_lexically(path).starts_with(&root) {
+            Ok(())
+        } else {
+            Err(format!("`{}` is outside of the directory `{}`",
+                        path.display(), root.display()))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalized(value: &str) -> String {
+        normalize_path_attr(value).unwrap().to_string_lossy().replace('\\', "/")
+    }
+
+    #[test]
+    fn path_attrs() {
+        assert_eq!(normalized("a/b.rs"), "a/b.rs");
+        assert_eq!(normalized("a\\b.rs"), "a/b.rs");
+        assert_eq!(normalized("./a/../../b/./c.rs"), "../b/c.rs");
+        assert_eq!(normalize_path_attr(""), Err(PathAttrError::Empty));
+        assert_eq!(normalize_path_attr("a\0.rs"), Err(PathAttrError::Nul));
+    }
+
+    #[test]
+    fn confined_paths() {
+        let policy = confine_to_directory(PathBuf::from("crate/src"));
+        assert!(policy(Path::new("crate/src/a/b.rs")).is_ok());
+        assert!(policy(Path::new("crate/src/a/../../src/b.rs")).is_ok());
+        assert!(policy(Path::new("crate/src/../b.rs")).is_err());
+        assert!(policy(Path::new("crate/src2/b.rs")).is_err());
+    }
+}
@@ -0,0 +1,213 @@
+// Copyright 2018 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Checks of the naming conventions of items at parse time, for style
+//! checkers which do not run the lints of the compiler.
+//!
+//! When `ParseSess::check_naming` is set, the parser warns about names which
+//! do not follow the convention of their kind of item, and records each
+//! warning in `ParseSess::naming_warnings`. Lint attributes like
+//! `#[allow(non_snake_case)]` are not taken into account.
+
+use syntax_pos::Span;
+use syntax_pos::symbol::Symbol;
+
+use std::fmt;
+
+/// The kinds of named items which are checked.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum NameKind {
+    /// Functions, methods and foreign functions.
+    Fn,
+    Mod,
+    /// Structs, enums, unions, traits, type aliases, associated types and
+    /// foreign types.
+    Type,
+    Variant,
+    /// Constants, statics, foreign statics and associated constants.
+    Const,
+}
+
+impl NameKind {
+    pub fn style(self) -> NamingStyle {
+        match self {
+            NameKind::Fn | NameKind::Mod => NamingStyle::SnakeCase,
+            NameKind::Type | NameKind::Variant => NamingStyle::CamelCase,
+            NameKind::Const => NamingStyle::UpperCase,
+        }
+    }
+
+    pub fn descr(self) -> &'static str {
+        match self {
+            NameKind::Fn => "function",
+            NameKind::Mod => "module",
+            NameKind::Type => "type",
+            NameKind::Variant => "variant",
+            NameKind::Const => "constant",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum NamingStyle {
+    /// `snake_case`.
+    SnakeCase,
+    /// `CamelCase`.
+    CamelCase,
+    /// `SCREAMING_SNAKE_CASE`.
+    UpperCase,
+}
+
+impl NamingStyle {
+    /// Checks whether `name` follows the style. Leading and trailing
+    /// underscores are ignored.
+    pub fn matches(self, name: &str) -> bool {
+        let name = name.trim_matches('_');
+        match self {
+            NamingStyle::SnakeCase => !name.chars().any(char::is_uppercase),
+            NamingStyle::CamelCase => {
+                !name.contains('_') && !name.chars().next().map_or(false, char::is_lowercase)
+            }
+            NamingStyle::UpperCase => !name.chars().any(char::is_lowercase),
+        }
+    }
+
+    /// Converts `name` to the style, e.g. for suggestions.
+    pub fn convert(self, name: &str) -> String {
+        match self {
+            NamingStyle::SnakeCase => to_snake_case(name),
+            NamingStyle::CamelCase => {
+                let mut camel = String::new();
+                for part in name.split('_').filter(|part| !part.is_empty()) {
+                    let mut chars = part.chars();
+                    camel.extend(chars.next().into_iter().flat_map(char::to_uppercase));
+                    camel.extend(chars);
+                }
+                camel
+            }
+            NamingStyle::UpperCase => to_snake_case(name).to_uppercase(),
+        }
+    }
+}
+
+impl fmt::Display for NamingStyle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            NamingStyle::SnakeCase => "snake case",
+            NamingStyle::CamelCase => "camel case",
+            NamingStyle::UpperCase => "upper case",
+        })
+    }
+}
+
+/// Splits words at changes of case, and before the last letter of an acronym
+/// followed by a lowercase letter, so `HTTPServer` becomes `http_server`.
+fn to_snake_case(name: &str) -> String {
+    let chars = name.chars().collect::<Vec<_>>();
+    let mut snake = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i > 0 && !snake.ends_with('_') {
+            let prev = chars[i - 1];
+            let ends_acronym = prev.is_uppercase() &&
+                               chars.get(i + 1).map_or(false, |next| next.is_lowercase());
+            if prev.is_lowercase() || prev.is_numeric() || ends_acronym {
+                snake.push('_');
+            }
+        }
+        snake.extend(c.to_lowercase());
+    }
+    snake
+}
+
+/// A name which does not follow the convention of its kind.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NamingWarning {
+    pub kind: NameKind,
+    pub name: Symbol,
+    /// The span of the item.
+    pub span: Span,
+}
+
+impl NamingWarning {
+    pub fn message(&self) -> String {
+        let style = self.kind.style();
+        format!("{} `{}` should have a {} name such as `{}`",
+                self.kind.descr(), self.name, style, style.convert(&self.name.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codemap::FilePathMapping;
+    use parse::{self, ParseSess};
+    use std::path::PathBuf;
+
+    #[test]
+    fn naming_styles() {
+        assert!(NamingStyle::SnakeCase.matches("parse_item_2"));
+        assert!(NamingStyle::SnakeCase.matches("_private"));
+        assert!(!NamingStyle::SnakeCase.matches("parseItem"));
+        assert!(NamingStyle::CamelCase.matches("TokenTree"));
+        assert!(!NamingStyle::CamelCase.matches("token_tree"));
+        assert!(!NamingStyle::CamelCase.matches("tokenTree"));
+        assert!(NamingStyle::UpperCase.matches("MAX_DEPTH"));
+        assert!(!NamingStyle::UpperCase.matches("MaxDepth"));
+
+        assert_eq!(NamingStyle::SnakeCase.convert("parseItem2Fast"), "parse_item2_fast");
+        assert_eq!(NamingStyle::SnakeCase.convert("HTTPServer"), "http_server");
+        assert_eq!(NamingStyle::SnakeCase.convert("parseHTTP"), "parse_http");
+        assert_eq!(NamingStyle::SnakeCase.convert("_IOError"), "_io_error");
+        assert_eq!(NamingStyle::CamelCase.convert("token_tree"), "TokenTree");
+        assert_eq!(NamingStyle::UpperCase.convert("maxDepth"), "MAX_DEPTH");
+        assert_eq!(NamingStyle::UpperCase.convert("HTTPServer"), "HTTP_SERVER");
+    }
+
+    #[test]
+    fn naming_warnings() {
+        let mut sess = ParseSess::new(FilePathMapping::empty());
+        let src = "fn parseItem() {}\n\
+                   struct token_tree;\n\
+                   enum Delim { Paren, open_brace }\n\
+                   const maxDepth: usize = 1;\n\
+                   mod Lexer { static count: u32 = 0; }\n\
+                   impl Foo { fn Bump(&self) {} type item = u8; }\n\
+                   fn ok_name() {}\n\
+                   struct OkName;\n\
+                   const OK_NAME: u8 = 0;\n\
+                   extern { fn getPid() -> i32; static errNo: i32; type file_t; fn ok_fn(); }\n";
+        parse::parse_crate_from_source_str(PathBuf::from("naming.rs").into(),
+                                           src.to_string(), &sess).unwrap();
+        assert!(sess.naming_warnings.borrow().is_empty());
+
+        sess.check_naming = true;
+        parse::parse_crate_from_source_str(PathBuf::from("naming.rs").into(),
+                                           src.to_string(), &sess).unwrap();
+        let warnings = sess.naming_warnings.borrow().iter().map(|warning| {
+            (warning.kind, warning.name.to_string())
+        }).collect::<Vec<_>>();
+        assert_eq!(warnings, vec![
+            (NameKind::Fn, "parseItem".to_string()),
+            (NameKind::Type, "token_tree".to_string()),
+            (NameKind::Variant, "open_brace".to_string()),
+            (NameKind::Const, "maxDepth".to_string()),
+            (NameKind::Const, "count".to_string()),
+            (NameKind::Mod, "Lexer".to_string()),
+            (NameKind::Fn, "Bump".to_string()),
+            (NameKind::Type, "item".to_string()),
+            (NameKind::Fn, "getPid".to_string()),
+            (NameKind::Const, "errNo".to_string()),
+            (NameKind::Type, "file_t".to_string()),
+        ]);
+        assert_eq!(sess.naming_warnings.borrow()[0].message(),
+                   "function `parseItem` should have a snake case name such as `parse_item`");
+        assert!(!sess.span_diagnostic.has_errors());
+    }
+}
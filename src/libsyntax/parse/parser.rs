@@ -14,7 +14,7 @@ use ast::{RegionTyParamBound, TraitTyParamBound, TraitBoundModifier};
 use ast::Unsafety;
 use ast::{Mod, Arg, Arm, Attribute, BindingMode, TraitItemKind};
 use ast::Block;
-use ast::{BlockCheckMode, CaptureBy, Movability};
+use ast::{BlockCheckMode, CaptureBy, ClosureSpans, Movability};
 use ast::{Constness, Crate};
 use ast::Defaultness;
 use ast::EnumDef;
@@ -35,7 +35,7 @@ use ast::{VariantData, StructField};
 use ast::StrStyle;
 use ast::SelfKind;
 use ast::{TraitItem, TraitRef, TraitObjectSyntax};
-use ast::{Ty, TyKind, TypeBinding, TyParam, TyParamBounds};
+use ast::{Ty, TyKind, TypeBinding, TyParam, TyParamBound, TyParamBounds};
 use ast::{Visibility, WhereClause, CrateSugar};
 use ast::{UseTree, UseTreeKind};
 use ast::{BinOpKind, UnOp};
@@ -43,13 +43,21 @@ use ast::{RangeEnd, RangeSyntax};
 use {ast, attr};
 use codemap::{self, CodeMap, Spanned, respan};
 use syntax_pos::{self, Span, MultiSpan, BytePos, FileName, DUMMY_SP};
-use errors::{self, DiagnosticBuilder};
+use errors::{self, Applicability, DiagnosticBuilder, ExpectedFound};
+use errors::catalog::Message;
 use parse::{self, classify, token};
 use parse::common::SeqSep;
+use parse::duplicates;
+use parse::events::{EventBuffer, NodeKind, ParseEvent};
 use parse::lexer::TokenAndSpan;
 use parse::lexer::comments::{doc_comment_style, strip_doc_comment_decoration};
+use parse::messages;
+use parse::naming::{NameKind, NamingWarning};
 use parse::obsolete::ObsoleteSyntax;
-use parse::{new_sub_parser_from_file, ParseSess, Directory, DirectoryOwnership};
+use parse::timing::ParsePhase;
+use parse::{new_sub_parser_from_file, ParseSess, Directory, DirectoryOwnership, IceReport};
+use parse::mod_path;
+use util::lev_distance::find_best_match_for_name;
 use util::parser::{AssocOp, Fixity};
 use print::pprust;
 use ptr::P;
@@ -61,18 +69,41 @@ use util::ThinVec;
 use std::cmp;
 use std::collections::HashSet;
 use std::mem;
+use std::panic::{self, AssertUnwindSafe};
 use std::path::{self, Path, PathBuf};
+use std::rc::Rc;
 use std::slice;
 
 bitflags! {
+    /// Context-sensitive restrictions on the expressions the parser accepts,
+    /// see `Parser::parse_expr_with_restrictions`.
     pub struct Restrictions: u8 {
+        /// The expression is in statement position, so a block-like
+        /// expression such as `match x {}` or `if c {}` ends it: `{} - 1`
+        /// is two statements rather than a subtraction.
         const STMT_EXPR         = 1 << 0;
+        /// Struct literals are not allowed outside of parentheses, as in the
+        /// condition of `if` and `while`, the scrutinee of `match` and the
+        /// iterator of `for`, where `x {}` is a path followed by a block.
         const NO_STRUCT_LITERAL = 1 << 1;
     }
 }
 
 type ItemInfo = (Ident, ItemKind, Option<Vec<Attribute>>);
 
+/// The maximum number of tokens `Parser::peek_tokens` will return.
+pub const MAX_LOOKAHEAD: usize = 16;
+
+/// A delimited group that was skipped without being parsed, see
+/// `Parser::defer_fn_bodies`.
+#[derive(Clone, Debug)]
+pub struct CapturedGroup {
+    /// The span of the group, including its delimiters.
+    pub span: Span,
+    /// The tokens of the group, including its delimiters.
+    pub tokens: TokenStream,
+}
+
 /// How to parse a path.
 #[derive(Copy, Clone, PartialEq)]
 pub enum PathStyle {
@@ -108,6 +139,28 @@ pub enum BlockMode {
     Ignore,
 }
 
+/// Where parsing resumes after a statement fails to parse. Tokens are only
+/// considered outside of delimiters; the `}` closing the block always ends
+/// recovery.
+#[derive(Clone, Debug)]
+pub struct StmtSyncPoints {
+    /// Parsing resumes after these tokens.
+    pub tokens: Vec<token::Token>,
+    /// Parsing resumes before these keywords.
+    pub keywords: Vec<keywords::Keyword>,
+}
+
+impl Default for StmtSyncPoints {
+    fn default() -> StmtSyncPoints {
+        StmtSyncPoints {
+            tokens: vec![token::Semi],
+            keywords: vec![keywords::Let, keywords::Fn, keywords::Struct, keywords::Enum,
+                           keywords::Trait, keywords::Impl, keywords::Mod, keywords::Use,
+                           keywords::Static, keywords::Const, keywords::Type],
+        }
+    }
+}
+
 /// Possibly accept an `token::Interpolated` expression (a pre-parsed expression
 /// dropped into the token stream, which happens while parsing the result of
 /// macro expansion). Placement of these is not as complex as I feared it would
@@ -245,6 +298,42 @@ pub struct Parser<'a> {
     pub desugar_doc_comments: bool,
     /// Whether we should configure out of line modules as we parse.
     pub cfg_mods: bool,
+    /// If set, function and method bodies are not parsed. Each body is
+    /// replaced with a block spanning the original one, whose only statement
+    /// is an `ExprKind::Unparsed` expression holding its tokens. The tokens
+    /// are also recorded in `captured_groups` for a later `parse_captured`
+    /// call. The inner attributes of the body are still parsed, and attached
+    /// to the item as usual.
+    pub defer_fn_bodies: bool,
+    pub captured_groups: Vec<CapturedGroup>,
+    /// The syntax variant selected by the file being parsed, whose item
+    /// extensions are enabled in addition to those for all files.
+    pub syntax_variant: Option<ast::Name>,
+    /// The self type of the impl whose items are being parsed, used to explain
+    /// misplaced `self` parameters in functions nested in methods. Entry points
+    /// parsing a method body in isolation can set it. Only the parser's own
+    /// diagnostics use it, resolution errors such as "cannot find type" are
+    /// reported after parsing.
+    pub impl_self_ty: Option<P<Ty>>,
+    /// The constructs being parsed, innermost last, used to explain errors at
+    /// the end of the input, and others if `label_enclosing_constructs` is set.
+    contexts: Vec<ParseContext>,
+    /// Where parsing resumes after an error in a statement.
+    pub stmt_sync: StmtSyncPoints,
+    /// The pattern of a `let` statement which failed to parse after it, so
+    /// that the placeholder for the statement still declares its bindings.
+    failed_let_pat: Option<P<Pat>>,
+    /// The parse events recorded so far, if recording is enabled.
+    events: Option<EventBuffer>,
+}
+
+/// A construct the parser is in the middle of.
+#[derive(Clone, Copy)]
+struct ParseContext {
+    /// Describes the construct, e.g. "`match` expression".
+    descr: &'static str,
+    /// The span of the keyword that started the construct.
+    span: Span,
 }
 
 
@@ -384,6 +473,7 @@ impl TokenCursor {
 pub enum TokenType {
     Token(token::Token),
     Keyword(keywords::Keyword),
+    ContextualKeyword(ast::Name),
     Operator,
     Lifetime,
     Ident,
@@ -396,6 +486,7 @@ impl TokenType {
         match *self {
             TokenType::Token(ref t) => format!("`{}`", Parser::token_to_string(t)),
             TokenType::Keyword(kw) => format!("`{}`", kw.name()),
+            TokenType::ContextualKeyword(name) => format!("`{}`", name),
             TokenType::Operator => "an operator".to_string(),
             TokenType::Lifetime => "lifetime".to_string(),
             TokenType::Ident => "identifier".to_string(),
@@ -403,15 +494,40 @@ impl TokenType {
             TokenType::Type => "type".to_string(),
         }
     }
+
+    /// Returns the kind of the expected token, see `Token::kind_name`.
+    pub fn kind_name(&self) -> String {
+        match *self {
+            TokenType::Token(ref t) => t.kind_name(),
+            TokenType::Keyword(kw) => format!("Keyword({})", kw.name()),
+            TokenType::ContextualKeyword(name) => format!("ContextualKeyword({})", name),
+            TokenType::Operator => "Operator".to_string(),
+            TokenType::Lifetime => "Lifetime".to_string(),
+            TokenType::Ident => "Ident".to_string(),
+            TokenType::Path => "Path".to_string(),
+            TokenType::Type => "Type".to_string(),
+        }
+    }
 }
 
 // Returns true if `IDENT t` can start a type - `IDENT::a::b`, `IDENT<u8, u8>`,
 // `IDENT<<u8 as Trait>::AssocTy>`, `IDENT(u8, u8) -> u8`.
-fn can_continue_type_after_ident(t: &token::Token) -> bool {
+pub(super) fn can_continue_type_after_ident(t: &token::Token) -> bool {
     t == &token::ModSep || t == &token::Lt ||
     t == &token::BinOp(token::Shl) || t == &token::OpenDelim(token::Paren)
 }
 
+/// Keywords that can start an item, for suggestions on misspelled keywords.
+const ITEM_KEYWORDS: &'static [&'static str] = &[
+    "const", "enum", "extern", "fn", "impl", "mod", "static", "struct", "trait", "type", "unsafe",
+    "use",
+];
+
+/// Keywords that can start a statement other than an item.
+const STMT_KEYWORDS: &'static [&'static str] = &[
+    "break", "continue", "for", "if", "let", "loop", "match", "return", "while",
+];
+
 /// Information about the path to a module.
 pub struct ModulePath {
     pub name: String,
@@ -489,6 +605,16 @@ impl Error {
     }
 }
 
+/// A prefix operator of an expression, see `parse_prefix_expr`.
+#[derive(Clone, Copy, Debug)]
+enum PrefixOp {
+    Unary(UnOp),
+    /// `~`, which is reported and parsed as `!`.
+    Tilde,
+    AddrOf(Mutability),
+    Box,
+}
+
 #[derive(Debug)]
 pub enum LhsExpr {
     NotYetParsed,
@@ -531,6 +657,61 @@ fn dummy_arg(span: Span) -> Arg {
     Arg { ty: P(ty), pat: pat, id: ast::DUMMY_NODE_ID }
 }
 
+/// The first character of `tok` as a token, which is what `bump_with` consumes
+/// when it splits `tok`.
+fn consumed_part(tok: &token::Token) -> token::Token {
+    match *tok {
+        token::AndAnd => token::BinOp(token::And),
+        token::OrOr => token::BinOp(token::Or),
+        token::BinOp(token::Shl) => token::Lt,
+        token::BinOp(token::Shr) | token::BinOpEq(token::Shr) | token::Ge => token::Gt,
+        _ => tok.clone(),
+    }
+}
+
+/// The parser reads a placeholder as an identifier, so that the code around it
+/// still parses.
+fn placeholder_to_ident(tok: token::Token) -> token::Token {
+    match tok {
+        token::Placeholder(name) => token::Ident(Ident::with_empty_ctxt(name)),
+        tok => tok,
+    }
+}
+
+/// Returns whether `a` and `b` are the same lifetime, or the same trait path
+/// without generic arguments, which are the duplicate bounds worth reporting.
+fn is_same_simple_bound(a: &TyParamBound, b: &TyParamBound) -> bool {
+    match (a, b) {
+        (&RegionTyParamBound(ref a), &RegionTyParamBound(ref b)) => a.ident.name == b.ident.name,
+        (&TraitTyParamBound(ref a, a_modifier), &TraitTyParamBound(ref b, b_modifier)) => {
+            let (a_path, b_path) = (&a.trait_ref.path, &b.trait_ref.path);
+            a_modifier == b_modifier &&
+            a.bound_generic_params.is_empty() && b.bound_generic_params.is_empty() &&
+            a_path.segments.len() == b_path.segments.len() &&
+            a_path.segments.iter().zip(&b_path.segments).all(|(a, b)| {
+                a.identifier.name == b.identifier.name &&
+                a.parameters.is_none() && b.parameters.is_none()
+            })
+        }
+        _ => false,
+    }
+}
+
+/// Whether `pat` is `self`, `mut self`, `&self` or `&mut self`.
+fn is_self_pat(pat: &Pat) -> bool {
+    match pat.node {
+        PatKind::Ident(BindingMode::ByValue(Mutability::Mutable), ref ident, None) => {
+            ident.node.name == keywords::SelfValue.name()
+        }
+        PatKind::Path(None, ref path) => {
+            path.segments.len() == 1 &&
+            path.segments[0].identifier.name == keywords::SelfValue.name()
+        }
+        PatKind::Ref(ref pat, _) => is_self_pat(pat),
+        _ => false,
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 enum TokenExpectType {
     Expect,
@@ -569,6 +750,14 @@ impl<'a> Parser<'a> {
             },
             desugar_doc_comments,
             cfg_mods: true,
+            defer_fn_bodies: false,
+            captured_groups: Vec::new(),
+            syntax_variant: None,
+            impl_self_ty: None,
+            contexts: Vec::new(),
+            stmt_sync: StmtSyncPoints::default(),
+            failed_let_pat: None,
+            events: None,
         };
 
         let tok = parser.next_tok();
@@ -597,6 +786,7 @@ impl<'a> Parser<'a> {
         if next.sp == syntax_pos::DUMMY_SP {
             next.sp = self.prev_span;
         }
+        next.tok = placeholder_to_ident(next.tok);
         next
     }
 
@@ -627,6 +817,159 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Runs `f` to parse the rest of the construct `descr`, whose keyword was
+    /// the previous token, so that errors at the end of the input can point
+    /// at the construct that was left unfinished.
+    fn in_context<T, F>(&mut self, descr: &'static str, f: F) -> PResult<'a, T>
+        where F: FnOnce(&mut Self) -> PResult<'a, T>
+    {
+        self.contexts.push(ParseContext { descr, span: self.prev_span });
+        let result = match f(self) {
+            Ok(value) => Ok(value),
+            Err(mut err) => {
+                self.label_contexts(&mut err);
+                Err(err)
+            }
+        };
+        self.contexts.pop();
+        result
+    }
+
+    /// Runs `f` with `impl_self_ty` set to `self_ty`.
+    fn with_impl_self_ty<T, F>(&mut self, self_ty: Option<P<Ty>>, f: F) -> T
+        where F: FnOnce(&mut Self) -> T
+    {
+        let outer = mem::replace(&mut self.impl_self_ty, self_ty);
+        let result = f(self);
+        self.impl_self_ty = outer;
+        result
+    }
+
+    /// Runs `f`, passing the state of the parser to the session's ICE hook if
+    /// `f` panics other than with a fatal error. The panic then continues with
+    /// its original payload, so the driver still recognizes compiler bugs.
+    /// The parsing functions of the `parse` module use it for every entry
+    /// point, tools driving a parser directly can use it the same way.
+    pub fn with_ice_report<T, F>(&mut self, f: F) -> T
+        where F: FnOnce(&mut Self) -> T
+    {
+        let hook = match self.sess.ice_hook() {
+            Some(hook) => hook,
+            None => return f(self),
+        };
+        let payload = match panic::catch_unwind(AssertUnwindSafe(|| f(self))) {
+            Ok(result) => return result,
+            Err(payload) => payload,
+        };
+        if payload.is::<errors::FatalErrorMarker>() {
+            panic::resume_unwind(payload);
+        }
+        let message = payload.downcast_ref::<&str>().map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned());
+        let report = IceReport {
+            message,
+            token: self.this_token_to_string(),
+            span: self.span,
+            location: self.sess.codemap().span_to_string(self.span),
+            // Unwinding skipped popping the contexts of `in_context`.
+            contexts: self.contexts.iter().map(|context| context.descr.to_string()).collect(),
+        };
+        hook(&report);
+        panic::resume_unwind(payload)
+    }
+
+    /// Starts recording parse events, see the `events` module.
+    pub fn record_events(&mut self) {
+        if self.events.is_none() {
+            self.events = Some(EventBuffer::new());
+        }
+    }
+
+    /// Returns the parse events recorded since recording started or since the
+    /// last call, in order.
+    pub fn take_events(&mut self) -> Vec<ParseEvent> {
+        match self.events {
+            Some(ref mut events) => events.take(),
+            None => Vec::new(),
+        }
+    }
+
+    fn record_event(&mut self, event: ParseEvent) {
+        if let Some(ref mut events) = self.events {
+            events.push(event);
+        }
+    }
+
+    /// Records the tokens of `tree`, a delimited group being consumed as a
+    /// whole.
+    fn record_token_tree(&mut self, tree: &TokenTree) {
+        match *tree {
+            TokenTree::Token(span, ref tok) => {
+                self.record_event(ParseEvent::Token(tok.clone(), span))
+            }
+            TokenTree::Delimited(span, ref delimited) => {
+                self.record_token_tree(&delimited.open_tt(span));
+                for tree in delimited.stream().trees() {
+                    self.record_token_tree(&tree);
+                }
+                self.record_token_tree(&delimited.close_tt(span));
+            }
+        }
+    }
+
+    /// Runs `f` to parse a node of kind `kind`, recording its start and
+    /// finish events around the events of its contents.
+    fn node<T, F>(&mut self, kind: NodeKind, f: F) -> PResult<'a, T>
+        where F: FnOnce(&mut Self) -> PResult<'a, T>
+    {
+        if self.events.is_none() {
+            return f(self);
+        }
+        let start = self.events.as_ref().map_or(0, |events| events.len());
+        let lo = self.span;
+        self.record_event(ParseEvent::StartNode(kind, lo));
+        let result = f(self);
+        let consumed = self.events.as_ref().map_or(false, |events| events.len() > start + 1);
+        if consumed {
+            let span = lo.to(self.prev_span);
+            self.record_event(ParseEvent::FinishNode(kind, span));
+        } else if let Some(ref mut events) = self.events {
+            events.truncate(start);
+        }
+        result
+    }
+
+    /// If the input ended unexpectedly, labels the innermost unfinished
+    /// construct on `err`.
+    fn label_eof_context(&self, err: &mut DiagnosticBuilder) {
+        if self.token != token::Eof {
+            return;
+        }
+        if let Some(&context) = self.contexts.last() {
+            self.label_context(err, context);
+        }
+    }
+
+    /// Labels all the constructs `err` was raised in, if the session asks for
+    /// it. Errors propagated out of a construct are labelled by `in_context`,
+    /// so this is only needed for errors which are recovered from inside one.
+    fn label_contexts(&self, err: &mut DiagnosticBuilder) {
+        if !self.sess.label_enclosing_constructs {
+            return;
+        }
+        for &context in self.contexts.iter().rev() {
+            self.label_context(err, context);
+        }
+    }
+
+    fn label_context(&self, err: &mut DiagnosticBuilder, context: ParseContext) {
+        let labelled = err.span.primary_spans().contains(&context.span) ||
+                       err.span.span_labels().iter().any(|label| label.span == context.span);
+        if !labelled {
+            err.span_label(context.span, format!("while parsing this {}", context.descr));
+        }
+    }
+
     pub fn unexpected_last<T>(&self, t: &token::Token) -> PResult<'a, T> {
         let token_str = Parser::token_to_string(t);
         Err(self.span_fatal(self.prev_span, &format!("unexpected token: `{}`", token_str)))
@@ -649,9 +992,15 @@ impl<'a> Parser<'a> {
             } else {
                 let token_str = Parser::token_to_string(t);
                 let this_token_str = self.this_token_to_string();
-                Err(self.fatal(&format!("expected `{}`, found `{}`",
-                                   token_str,
-                                   this_token_str)))
+                let msg = self.message(messages::EXPECTED_TOKEN,
+                                       &[("expected", &token_str), ("found", &this_token_str)]);
+                let mut err = self.fatal(&msg);
+                err.set_expected_found(ExpectedFound {
+                    expected: vec![t.kind_name()],
+                    found: self.token.kind_name(),
+                    production: self.contexts.last().map(|context| context.descr.to_string()),
+                });
+                Err(err)
             }
         } else {
             self.expect_one_of(unsafe { slice::from_raw_parts(t, 1) }, &[])
@@ -699,20 +1048,22 @@ impl<'a> Parser<'a> {
             let actual = self.this_token_to_string();
             let (msg_exp, (label_sp, label_exp)) = if expected.len() > 1 {
                 let short_expect = if expected.len() > 6 {
-                    format!("{} possible tokens", expected.len())
+                    self.message(messages::POSSIBLE_TOKENS,
+                                 &[("count", &expected.len().to_string())])
                 } else {
                     expect.clone()
                 };
-                (format!("expected one of {}, found `{}`", expect, actual),
+                (self.message(messages::EXPECTED_ONE_OF,
+                              &[("expected", &expect), ("found", &actual)]),
                  (self.sess.codemap().next_point(self.prev_span),
-                  format!("expected one of {} here", short_expect)))
+                  self.message(messages::EXPECTED_ONE_OF_LABEL, &[("expected", &short_expect)])))
             } else if expected.is_empty() {
-                (format!("unexpected token: `{}`", actual),
-                 (self.prev_span, "unexpected token after this".to_string()))
+                (self.message(messages::UNEXPECTED_TOKEN, &[("found", &actual)]),
+                 (self.prev_span, self.message(messages::UNEXPECTED_AFTER_LABEL, &[])))
             } else {
-                (format!("expected {}, found `{}`", expect, actual),
+                (self.message(messages::EXPECTED, &[("expected", &expect), ("found", &actual)]),
                  (self.sess.codemap().next_point(self.prev_span),
-                  format!("expected {} here", expect)))
+                  self.message(messages::EXPECTED_LABEL, &[("expected", &expect)])))
             };
             let mut err = self.fatal(&msg_exp);
             let sp = if self.token == token::Token::Eof {
@@ -741,9 +1092,18 @@ impl<'a> Parser<'a> {
                 }
                 _ => {
                     err.span_label(sp, label_exp);
-                    err.span_label(self.span, "unexpected token");
+                    err.span_label(self.span, self.message(messages::UNEXPECTED_TOKEN_LABEL, &[]));
                 }
             }
+            let mut expected = expected.iter().map(TokenType::kind_name).collect::<Vec<_>>();
+            expected.sort();
+            expected.dedup();
+            err.set_expected_found(ExpectedFound {
+                expected,
+                found: self.token.kind_name(),
+                production: self.contexts.last().map(|context| context.descr.to_string()),
+            });
+            self.label_eof_context(&mut err);
             Err(err)
         }
     }
@@ -769,15 +1129,10 @@ impl<'a> Parser<'a> {
         match self.token {
             token::Ident(i) => {
                 if self.token.is_reserved_ident() {
-                    let mut err = self.struct_span_err(self.span,
-                                                       &format!("expected identifier, found {}",
-                                                                self.this_token_descr()));
-                    if let Some(token_descr) = self.token_descr() {
-                        err.span_label(self.span, format!("expected identifier, found {}",
-                                                          token_descr));
-                    } else {
-                        err.span_label(self.span, "expected identifier");
-                    }
+                    let msg = self.message(messages::EXPECTED_IDENT,
+                                           &[("found", &self.this_token_descr())]);
+                    let mut err = self.struct_span_err(self.span, &msg);
+                    self.label_expected_ident(&mut err);
                     if recover {
                         err.emit();
                     } else {
@@ -791,17 +1146,14 @@ impl<'a> Parser<'a> {
                 Err(if self.prev_token_kind == PrevTokenKind::DocComment {
                         self.span_fatal_err(self.prev_span, Error::UselessDocComment)
                     } else {
-                        let mut err = self.fatal(&format!("expected identifier, found `{}`",
-                                                          self.this_token_to_string()));
-                        if let Some(token_descr) = self.token_descr() {
-                            err.span_label(self.span, format!("expected identifier, found {}",
-                                                              token_descr));
-                        } else {
-                            err.span_label(self.span, "expected identifier");
-                        }
+                        let found = format!("`{}`", self.this_token_to_string());
+                        let msg = self.message(messages::EXPECTED_IDENT, &[("found", &found)]);
+                        let mut err = self.fatal(&msg);
+                        self.label_expected_ident(&mut err);
                         if self.token == token::Underscore {
                             err.note("`_` is a wildcard pattern, not an identifier");
                         }
+                        self.label_eof_context(&mut err);
                         err
                     })
             }
@@ -851,6 +1203,34 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Returns `true` if the current token is the contextual keyword `kw` and
+    /// the rule registered for it accepts it as a keyword in this position.
+    pub fn is_contextual_keyword(&self, kw: ast::Name) -> bool {
+        if !self.token.ident().map_or(false, |ident| ident.name == kw) {
+            return false;
+        }
+        match self.sess.contextual_keyword_rule(kw) {
+            Some(rule) => rule(self),
+            None => false,
+        }
+    }
+
+    pub fn check_contextual_keyword(&mut self, kw: ast::Name) -> bool {
+        self.expected_tokens.push(TokenType::ContextualKeyword(kw));
+        self.is_contextual_keyword(kw)
+    }
+
+    /// If the current token is the contextual keyword `kw` used as a
+    /// keyword, eat it and return true. Otherwise, return false.
+    pub fn eat_contextual_keyword(&mut self, kw: ast::Name) -> bool {
+        if self.check_contextual_keyword(kw) {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+
     /// If the given word is not a keyword, signal an error.
     /// If the next token is not the given word, signal an error.
     /// Otherwise, eat it.
@@ -1081,6 +1461,11 @@ impl<'a> Parser<'a> {
     /// Parse a sequence, including the closing delimiter. The function
     /// f must consume tokens until reaching the next separator or
     /// closing bracket.
+    ///
+    /// The sequence also ends at any other closing delimiter or the end of
+    /// the input, and the token ending it is consumed whether it is `ket` or
+    /// not. See `SeqSep` for how the items are separated, and for the
+    /// recovery from missing separators.
     pub fn parse_seq_to_end<T, F>(&mut self,
                                   ket: &token::Token,
                                   sep: SeqSep,
@@ -1133,10 +1518,15 @@ impl<'a> Parser<'a> {
                             }
                         }
                         e.emit();
-                        // Attempt to keep parsing if it was an omitted separator
+                        // Attempt to keep parsing if it was an omitted separator,
+                        // as long as that makes progress.
+                        let lo = self.span;
                         match f(self) {
                             Ok(t) => {
                                 v.push(t);
+                                if self.span == lo {
+                                    break;
+                                }
                                 continue;
                             },
                             Err(mut e) => {
@@ -1182,6 +1572,34 @@ impl<'a> Parser<'a> {
         Ok(result)
     }
 
+    /// Parse a comma separated sequence like `parse_unspanned_seq`, also
+    /// returning the span of its trailing comma, if any.
+    pub fn parse_unspanned_comma_seq<T, F>(&mut self,
+                                       bra: &token::Token,
+                                       ket: &token::Token,
+                                       f: F)
+                                       -> PResult<'a, (Vec<T>, Option<Span>)> where
+        F: FnMut(&mut Parser<'a>) -> PResult<'a,  T>,
+    {
+        self.expect(bra)?;
+        let result = self.parse_seq_to_before_end(ket, SeqSep::trailing_allowed(token::Comma), f)?;
+        let trailing_comma = self.trailing_comma(&result);
+        if self.token == *ket {
+            self.bump();
+        }
+        Ok((result, trailing_comma))
+    }
+
+    /// Returns the span of the previous token if it is a comma ending the
+    /// non-empty sequence `items`.
+    fn trailing_comma<T>(&self, items: &[T]) -> Option<Span> {
+        if !items.is_empty() && self.prev_token_kind == PrevTokenKind::Comma {
+            Some(self.prev_span)
+        } else {
+            None
+        }
+    }
+
     // NB: Do not use this function unless you actually plan to place the
     // spanned list in the AST.
     pub fn parse_seq<T, F>(&mut self,
@@ -1207,6 +1625,11 @@ impl<'a> Parser<'a> {
             self.bug("attempted to bump the parser past EOF (may be stuck in a loop)");
         }
 
+        if self.events.is_some() && self.token != token::Eof {
+            let (tok, span) = (self.token.clone(), self.span);
+            self.record_event(ParseEvent::Token(tok, span));
+        }
+
         self.prev_span = self.meta_var_span.take().unwrap_or(self.span);
 
         // Record last token kind for possible error recovery.
@@ -1231,6 +1654,11 @@ impl<'a> Parser<'a> {
     /// Advance the parser using provided token as a next one. Use this when
     /// consuming a part of a token. For example a single `<` from `<<`.
     pub fn bump_with(&mut self, next: token::Token, span: Span) {
+        if self.events.is_some() {
+            let consumed = consumed_part(&self.token);
+            let consumed_span = self.span.with_hi(span.lo());
+            self.record_event(ParseEvent::Token(consumed, consumed_span));
+        }
         self.prev_span = self.span.with_hi(span.lo());
         // It would be incorrect to record the kind of the current token, but
         // fortunately for tokens currently using `bump_with`, the
@@ -1241,6 +1669,12 @@ impl<'a> Parser<'a> {
         self.expected_tokens.clear();
     }
 
+    /// Calls `f` with the token `dist` tokens ahead of the current one, without
+    /// consuming anything. `look_ahead(0, ..)` sees the current token.
+    ///
+    /// Lookahead does not leave the innermost delimited group: a delimited
+    /// group ahead is seen as its opening delimiter only, and looking past the
+    /// end of the current group yields its closing delimiter.
     pub fn look_ahead<R, F>(&self, dist: usize, f: F) -> R where
         F: FnOnce(&token::Token) -> R,
     {
@@ -1250,14 +1684,16 @@ impl<'a> Parser<'a> {
 
         f(&match self.token_cursor.frame.tree_cursor.look_ahead(dist - 1) {
             Some(tree) => match tree {
-                TokenTree::Token(_, tok) => tok,
+                TokenTree::Token(_, tok) => placeholder_to_ident(tok),
                 TokenTree::Delimited(_, delimited) => token::OpenDelim(delimited.delim),
             },
             None => token::CloseDelim(self.token_cursor.frame.delim),
         })
     }
 
-    fn look_ahead_span(&self, dist: usize) -> Span {
+    /// Returns the span of the token `dist` tokens ahead of the current one.
+    /// Follows the same rules as `look_ahead`.
+    pub fn look_ahead_span(&self, dist: usize) -> Span {
         if dist == 0 {
             return self.span
         }
@@ -1267,6 +1703,40 @@ impl<'a> Parser<'a> {
             None => self.look_ahead_span(dist - 1),
         }
     }
+
+    /// Returns the token `dist` tokens ahead of the current one together with
+    /// its span, without consuming anything.
+    pub fn look_ahead_token(&self, dist: usize) -> (token::Token, Span) {
+        (self.look_ahead(dist, |t| t.clone()), self.look_ahead_span(dist))
+    }
+
+    /// Returns the current token and up to `n - 1` following tokens, capped at
+    /// `MAX_LOOKAHEAD` tokens in total. Stops early at the end of the current
+    /// delimited group, whose closing delimiter is included.
+    pub fn peek_tokens(&self, n: usize) -> Vec<(token::Token, Span)> {
+        let mut tokens = Vec::new();
+        for dist in 0..cmp::min(n, MAX_LOOKAHEAD) {
+            let (tok, span) = self.look_ahead_token(dist);
+            let at_end = dist > 0 && tok == token::CloseDelim(self.token_cursor.frame.delim);
+            tokens.push((tok, span));
+            if at_end {
+                break;
+            }
+        }
+        tokens
+    }
+
+    /// Is the current token the keyword `kw`? Unlike `check_keyword`, this
+    /// does not record `kw` as an expected token.
+    pub fn peek_keyword(&self, kw: keywords::Keyword) -> bool {
+        self.token.is_keyword(kw)
+    }
+
+    /// Is the token `dist` tokens ahead the keyword `kw`?
+    pub fn look_ahead_keyword(&self, dist: usize, kw: keywords::Keyword) -> bool {
+        self.look_ahead(dist, |t| t.is_keyword(kw))
+    }
+
     pub fn fatal(&self, m: &str) -> DiagnosticBuilder<'a> {
         self.sess.span_diagnostic.struct_span_fatal(self.span, m)
     }
@@ -1307,6 +1777,11 @@ impl<'a> Parser<'a> {
     pub fn span_bug<S: Into<MultiSpan>>(&self, sp: S, m: &str) -> ! {
         self.sess.span_diagnostic.span_bug(sp, m)
     }
+    /// Records an inconsistency left by error recovery, which is only
+    /// reported as a bug if no error is emitted, see `Handler::delay_span_bug`.
+    pub fn delay_span_bug<S: Into<MultiSpan>>(&self, sp: S, m: &str) {
+        self.sess.span_diagnostic.delay_span_bug(sp, m)
+    }
     pub fn abort_if_errors(&self) {
         self.sess.span_diagnostic.abort_if_errors();
     }
@@ -1319,6 +1794,19 @@ impl<'a> Parser<'a> {
         &self.sess.span_diagnostic
     }
 
+    /// Returns the text of a parser message, see the `messages` module.
+    fn message(&self, message: Message, args: &[(&str, &str)]) -> String {
+        self.diagnostic().message(&message, args)
+    }
+
+    fn label_expected_ident(&self, err: &mut DiagnosticBuilder) {
+        let label = match self.token_descr() {
+            Some(token_descr) => self.message(messages::EXPECTED_IDENT, &[("found", token_descr)]),
+            None => self.message(messages::EXPECTED_IDENT_LABEL, &[]),
+        };
+        err.span_label(self.span, label);
+    }
+
     /// Is the current token one of the keywords that signals a bare function
     /// type?
     pub fn token_is_bare_fn_keyword(&mut self) -> bool {
@@ -1392,7 +1880,7 @@ impl<'a> Parser<'a> {
         maybe_whole!(self, NtTraitItem, |x| x);
         let attrs = self.parse_outer_attributes()?;
         let (mut item, tokens) = self.collect_tokens(|this| {
-            this.parse_trait_item_(at_end, attrs)
+            this.node(NodeKind::TraitItem, |this| this.parse_trait_item_(at_end, attrs))
         })?;
         // See `parse_item` for why this clause is here.
         if !item.attrs.iter().any(|attr| attr.style == AttrStyle::Inner) {
@@ -1446,7 +1934,7 @@ impl<'a> Parser<'a> {
                 self.expect(&token::Semi)?
             }
 
-            let mac = respan(lo.to(self.prev_span), Mac_ { path: pth, tts: tts });
+            let mac = self.mk_mac(lo.to(self.prev_span), pth, tts);
             (keywords::Invalid.ident(), ast::TraitItemKind::Macro(mac), ast::Generics::default())
         } else {
             let (constness, unsafety, abi) = self.parse_fn_front_matter()?;
@@ -1479,7 +1967,7 @@ impl<'a> Parser<'a> {
                 token::OpenDelim(token::Brace) => {
                     debug!("parse_trait_methods(): parsing provided method");
                     *at_end = true;
-                    let (inner_attrs, body) = self.parse_inner_attrs_and_block()?;
+                    let (inner_attrs, body) = self.parse_fn_body()?;
                     attrs.extend(inner_attrs.iter().cloned());
                     Some(body)
                 }
@@ -1491,6 +1979,13 @@ impl<'a> Parser<'a> {
             (ident, ast::TraitItemKind::Method(sig, body), generics)
         };
 
+        let span = lo.to(self.prev_span);
+        match node {
+            TraitItemKind::Const(..) => self.check_naming(NameKind::Const, name, span),
+            TraitItemKind::Method(..) => self.check_naming(NameKind::Fn, name, span),
+            TraitItemKind::Type(..) => self.check_naming(NameKind::Type, name, span),
+            TraitItemKind::Macro(..) => {}
+        }
         Ok(TraitItem {
             id: ast::DUMMY_NODE_ID,
             ident: name,
@@ -1513,7 +2008,7 @@ impl<'a> Parser<'a> {
 
     // Parse a type
     pub fn parse_ty(&mut self) -> PResult<'a, P<Ty>> {
-        self.parse_ty_common(true, true)
+        self.node(NodeKind::Ty, |this| this.parse_ty_common(true, true))
     }
 
     /// Parse a type in restricted contexts where `+` is not permitted.
@@ -1522,7 +2017,7 @@ impl<'a> Parser<'a> {
     /// Example 2: `value1 as TYPE + value2`
     ///     `+` is prohibited to avoid interactions with expression grammar.
     fn parse_ty_no_plus(&mut self) -> PResult<'a, P<Ty>> {
-        self.parse_ty_common(false, true)
+        self.node(NodeKind::Ty, |this| this.parse_ty_common(false, true))
     }
 
     fn parse_ty_common(&mut self, allow_plus: bool, allow_qpath_recovery: bool)
@@ -1617,16 +2112,14 @@ impl<'a> Parser<'a> {
             }
         } else if self.eat_keyword(keywords::Impl) {
             // FIXME: figure out priority of `+` in `impl Trait1 + Trait2` (#34511).
-            TyKind::ImplTrait(self.parse_ty_param_bounds()?)
-        } else if self.check_keyword(keywords::Dyn) &&
-                  self.look_ahead(1, |t| t.can_begin_bound() && !can_continue_type_after_ident(t)) {
+            TyKind::ImplTrait(self.parse_ty_bounds()?)
+        } else if self.eat_contextual_keyword(keywords::Dyn.name()) {
             // FIXME: figure out priority of `+` in `dyn Trait1 + Trait2` (#34511).
-            self.bump(); // `dyn`
-            TyKind::TraitObject(self.parse_ty_param_bounds()?, TraitObjectSyntax::Dyn)
+            TyKind::TraitObject(self.parse_ty_bounds()?, TraitObjectSyntax::Dyn)
         } else if self.check(&token::Question) ||
                   self.check_lifetime() && self.look_ahead(1, |t| t == &token::BinOp(token::Plus)) {
             // Bound list (trait object type)
-            TyKind::TraitObject(self.parse_ty_param_bounds_common(allow_plus)?,
+            TyKind::TraitObject(self.parse_ty_param_bounds_common(Vec::new(), allow_plus, true)?,
                                 TraitObjectSyntax::None)
         } else if self.eat_lt() {
             // Qualified path
@@ -1638,7 +2131,7 @@ impl<'a> Parser<'a> {
             if self.eat(&token::Not) {
                 // Macro invocation in type position
                 let (_, tts) = self.expect_delimited_token_tree()?;
-                TyKind::Mac(respan(lo.to(self.prev_span), Mac_ { path: path, tts: tts }))
+                TyKind::Mac(self.mk_mac(lo.to(self.prev_span), path, tts))
             } else {
                 // Just a type path or bound list (trait object type) starting with a trait.
                 //   `Type`
@@ -1650,7 +2143,7 @@ impl<'a> Parser<'a> {
                 }
             }
         } else {
-            let msg = format!("expected type, found {}", self.this_token_descr());
+            let msg = self.message(messages::EXPECTED_TYPE, &[("found", &self.this_token_descr())]);
             return Err(self.fatal(&msg));
         };
 
@@ -1669,8 +2162,7 @@ impl<'a> Parser<'a> {
         let poly_trait_ref = PolyTraitRef::new(generic_params, path, lo.to(self.prev_span));
         let mut bounds = vec![TraitTyParamBound(poly_trait_ref, TraitBoundModifier::None)];
         if parse_plus {
-            self.bump(); // `+`
-            bounds.append(&mut self.parse_ty_param_bounds()?);
+            bounds = self.parse_ty_param_bounds_common(bounds, true, true)?;
         }
         Ok(TyKind::TraitObject(bounds, TraitObjectSyntax::None))
     }
@@ -1789,6 +2281,9 @@ impl<'a> Parser<'a> {
                    require_name);
             let pat = self.parse_pat()?;
 
+            if self.token != token::Colon && is_self_pat(&pat) {
+                return Err(self.misplaced_self_param(pat.span));
+            }
             self.expect(&token::Colon)?;
             pat
         } else {
@@ -1812,6 +2307,17 @@ impl<'a> Parser<'a> {
         })
     }
 
+    /// The error for a `self` parameter of something other than a method.
+    fn misplaced_self_param(&self, span: Span) -> DiagnosticBuilder<'a> {
+        let mut err = self.struct_span_err(span, "unexpected `self` parameter in function");
+        err.span_label(span, "`self` can only be the first parameter of a method");
+        if let Some(ref self_ty) = self.impl_self_ty {
+            err.note(&format!("this function is nested in a method of `impl {}`, \
+                               but is not a method itself", pprust::ty_to_string(self_ty)));
+        }
+        err
+    }
+
     /// Parse a single function argument
     pub fn parse_arg(&mut self) -> PResult<'a, Arg> {
         self.parse_arg_general(true)
@@ -1854,6 +2360,12 @@ impl<'a> Parser<'a> {
                 },
                 _ => { return self.unexpected_last(&self.token); }
             },
+            token::Literal(lit @ token::Integer(_), Some(suf)) |
+            token::Literal(lit @ token::Float(_), Some(suf))
+                if self.sess.preserve_unknown_suffixes &&
+                   !parse::is_known_numeric_suffix(lit, &suf.as_str()) => {
+                LitKind::UnknownSuffix(lit, suf)
+            }
             token::Literal(lit, suf) => {
                 let diag = Some((self.span, &self.sess.span_diagnostic));
                 let (suffix_illegal, result) = parse::lit_token(lit, suf, diag);
@@ -2052,7 +2564,7 @@ impl<'a> Parser<'a> {
                     |p| p.parse_ty())?;
                 self.bump(); // `)`
                 let output = if self.eat(&token::RArrow) {
-                    Some(self.parse_ty_common(false, false)?)
+                    Some(self.node(NodeKind::Ty, |this| this.parse_ty_common(false, false))?)
                 } else {
                     None
                 };
@@ -2141,8 +2653,9 @@ impl<'a> Parser<'a> {
         ExprKind::Binary(binop, lhs, rhs)
     }
 
-    pub fn mk_call(&mut self, f: P<Expr>, args: Vec<P<Expr>>) -> ast::ExprKind {
-        ExprKind::Call(f, args)
+    pub fn mk_call(&mut self, f: P<Expr>, args: Vec<P<Expr>>, trailing_comma: Option<Span>)
+                   -> ast::ExprKind {
+        ExprKind::Call(f, args, trailing_comma)
     }
 
     pub fn mk_index(&mut self, expr: P<Expr>, idx: P<Expr>) -> ast::ExprKind {
@@ -2179,10 +2692,26 @@ impl<'a> Parser<'a> {
         })
     }
 
+    /// Builds a macro invocation, reporting it to the session's include hook
+    /// if it is an `include!`-family macro and keeping the contents the hook
+    /// supplies for it.
+    fn mk_mac(&self, span: Span, path: ast::Path, tts: ThinTokenStream) -> ast::Mac {
+        let mac = respan(span, Mac_ { path: path, tts: tts });
+        if let Some(kind) = parse::IncludeKind::from_path(&mac.node.path) {
+            if let Some(hook) = self.sess.include_hook() {
+                let include = parse::IncludeMacro::new(kind, &mac, self.sess.codemap());
+                if let (Some(src), Some(file)) = (hook(self.sess, &include), include.file) {
+                    self.sess.included_sources.borrow_mut().insert(file, src);
+                }
+            }
+        }
+        mac
+    }
+
     pub fn mk_lit_u32(&mut self, i: u32, attrs: ThinVec<Attribute>) -> P<Expr> {
         let span = &self.span;
         let lv_lit = P(codemap::Spanned {
-            node: LitKind::Int(i as u128, ast::LitIntType::Unsigned(UintTy::U32)),
+            node: LitKind::Int(i as u128, ast::LitIntType::Unsigned(UintTy::U32), None),
             span: *span
         });
 
@@ -2249,13 +2778,14 @@ impl<'a> Parser<'a> {
                         break;
                     }
                 }
+                let comma = self.trailing_comma(&es);
                 self.bump();
 
                 hi = self.prev_span;
                 ex = if es.len() == 1 && !trailing_comma {
                     ExprKind::Paren(es.into_iter().nth(0).unwrap())
                 } else {
-                    ExprKind::Tup(es)
+                    ExprKind::Tup(es, comma)
                 };
             }
             token::OpenDelim(token::Brace) => {
@@ -2311,7 +2841,7 @@ impl<'a> Parser<'a> {
                     return self.parse_lambda_expr(attrs);
                 }
                 if self.eat_keyword(keywords::If) {
-                    return self.parse_if_expr(attrs);
+                    return self.in_context("`if` expression", |this| this.parse_if_expr(attrs));
                 }
                 if self.eat_keyword(keywords::For) {
                     let lo = self.prev_span;
@@ -2346,7 +2876,8 @@ impl<'a> Parser<'a> {
                     return Ok(self.mk_expr(lo.to(hi), ex, attrs));
                 }
                 if self.eat_keyword(keywords::Match) {
-                    return self.parse_match_expr(attrs);
+                    return self.in_context("`match` expression",
+                                           |this| this.parse_match_expr(attrs));
                 }
                 if self.eat_keyword(keywords::Unsafe) {
                     return self.parse_block_expr(
@@ -2401,8 +2932,8 @@ impl<'a> Parser<'a> {
                     if self.eat(&token::Not) {
                         // MACRO INVOCATION expression
                         let (_, tts) = self.expect_delimited_token_tree()?;
-                        let hi = self.prev_span;
-                        return Ok(self.mk_mac_expr(lo.to(hi), Mac_ { path: pth, tts: tts }, attrs));
+                        let mac = self.mk_mac(lo.to(self.prev_span), pth, tts);
+                        return Ok(self.mk_mac_expr(mac.span, mac.node, attrs));
                     }
                     if self.check(&token::OpenDelim(token::Brace)) {
                         // This is a struct literal, unless we're prohibited
@@ -2425,9 +2956,11 @@ impl<'a> Parser<'a> {
                         }
                         Err(mut err) => {
                             self.cancel(&mut err);
-                            let msg = format!("expected expression, found {}",
-                                              self.this_token_descr());
-                            return Err(self.fatal(&msg));
+                            let msg = self.message(messages::EXPECTED_EXPR,
+                                                   &[("found", &self.this_token_descr())]);
+                            let mut err = self.fatal(&msg);
+                            self.label_eof_context(&mut err);
+                            return Err(err);
                         }
                     }
                 }
@@ -2466,7 +2999,12 @@ impl<'a> Parser<'a> {
                         exp_span.to(self.prev_span),
                         "cannot use a comma after the base struct",
                     );
-                    err.span_suggestion_short(self.span, "remove this comma", "".to_owned());
+                    err.span_suggestion_short_with_applicability(
+                        self.span,
+                        "remove this comma",
+                        "".to_owned(),
+                        Applicability::MachineApplicable
+                    );
                     err.note("the base struct must always be the last field");
                     err.emit();
                     self.recover_stmt();
@@ -2495,9 +3033,11 @@ impl<'a> Parser<'a> {
             }
         }
 
+        let trailing_comma = if base.is_none() { self.trailing_comma(&fields) } else { None };
         let span = lo.to(self.span);
         self.expect(&token::CloseDelim(token::Brace))?;
-        return Ok(self.mk_expr(span, ExprKind::Struct(pth, fields, base), attrs));
+        let struct_expr = ExprKind::Struct(pth, fields, base, trailing_comma);
+        return Ok(self.mk_expr(span, struct_expr, attrs));
     }
 
     fn parse_or_use_outer_attributes(&mut self,
@@ -2514,12 +3054,14 @@ impl<'a> Parser<'a> {
     pub fn parse_block_expr(&mut self, lo: Span, blk_mode: BlockCheckMode,
                             outer_attrs: ThinVec<Attribute>)
                             -> PResult<'a, P<Expr>> {
-        self.expect(&token::OpenDelim(token::Brace))?;
+        let (attrs, blk) = self.node(NodeKind::Block, |this| {
+            this.expect(&token::OpenDelim(token::Brace))?;
 
-        let mut attrs = outer_attrs;
-        attrs.extend(self.parse_inner_attributes()?);
+            let mut attrs = outer_attrs;
+            attrs.extend(this.parse_inner_attributes()?);
 
-        let blk = self.parse_block_tail(lo, blk_mode)?;
+            Ok((attrs, this.parse_block_tail(lo, blk_mode)?))
+        })?;
         return Ok(self.mk_expr(blk.span, ExprKind::Block(blk), attrs));
     }
 
@@ -2685,15 +3227,14 @@ impl<'a> Parser<'a> {
             match self.token {
               // expr(...)
               token::OpenDelim(token::Paren) => {
-                let es = self.parse_unspanned_seq(
+                let (es, trailing_comma) = self.parse_unspanned_comma_seq(
                     &token::OpenDelim(token::Paren),
                     &token::CloseDelim(token::Paren),
-                    SeqSep::trailing_allowed(token::Comma),
                     |p| Ok(p.parse_expr()?)
                 )?;
                 hi = self.prev_span;
 
-                let nd = self.mk_call(e, es);
+                let nd = self.mk_call(e, es, trailing_comma);
                 e = self.mk_expr(lo.to(hi), nd, ThinVec::new());
               }
 
@@ -2742,11 +3283,16 @@ impl<'a> Parser<'a> {
                 let frame = mem::replace(&mut self.token_cursor.frame,
                                          self.token_cursor.stack.pop().unwrap());
                 self.span = frame.span;
-                self.bump();
-                TokenTree::Delimited(frame.span, Delimited {
+                let tree = TokenTree::Delimited(frame.span, Delimited {
                     delim: frame.delim,
                     tts: frame.tree_cursor.original_stream().into(),
-                })
+                });
+                // The group is recorded token by token rather than by `bump`.
+                let events = self.events.take();
+                self.bump();
+                self.events = events;
+                self.record_token_tree(&tree);
+                tree
             },
             token::CloseDelim(_) | token::Eof => unreachable!(),
             _ => {
@@ -2767,6 +3313,29 @@ impl<'a> Parser<'a> {
         Ok(tts)
     }
 
+    /// If the current token opens a delimited group, skips past the matching
+    /// closing delimiter and returns the span of the whole group, delimiters
+    /// included. Otherwise, consumes nothing and returns `None`.
+    pub fn skip_to_matching_delimiter(&mut self) -> Option<Span> {
+        match self.token {
+            token::OpenDelim(..) => Some(self.parse_token_tree().span()),
+            _ => None,
+        }
+    }
+
+    /// If the current token opens a delimited group, consumes the group and
+    /// returns the token trees between its delimiters, with their original
+    /// spans. Otherwise, consumes nothing and returns an empty vector.
+    pub fn consume_block_tokens(&mut self) -> Vec<TokenTree> {
+        match self.token {
+            token::OpenDelim(..) => match self.parse_token_tree() {
+                TokenTree::Delimited(_, delimited) => delimited.stream().trees().collect(),
+                TokenTree::Token(..) => unreachable!(),
+            },
+            _ => Vec::new(),
+        }
+    }
+
     pub fn parse_tokens(&mut self) -> TokenStream {
         let mut result = Vec::new();
         loop {
@@ -2782,68 +3351,76 @@ impl<'a> Parser<'a> {
     pub fn parse_prefix_expr(&mut self,
                              already_parsed_attrs: Option<ThinVec<Attribute>>)
                              -> PResult<'a, P<Expr>> {
-        let attrs = self.parse_or_use_outer_attributes(already_parsed_attrs)?;
-        let lo = self.span;
-        // Note: when adding new unary operators, don't forget to adjust Token::can_begin_expr()
-        let (hi, ex) = match self.token {
-            token::Not => {
-                self.bump();
-                let e = self.parse_prefix_expr(None);
-                let (span, e) = self.interpolated_or_expr_span(e)?;
-                (lo.to(span), self.mk_unary(UnOp::Not, e))
-            }
-            // Suggest `!` for bitwise negation when encountering a `~`
-            token::Tilde => {
-                self.bump();
-                let e = self.parse_prefix_expr(None);
-                let (span, e) = self.interpolated_or_expr_span(e)?;
-                let span_of_tilde = lo;
-                let mut err = self.diagnostic().struct_span_err(span_of_tilde,
-                        "`~` can not be used as a unary operator");
-                err.span_label(span_of_tilde, "did you mean `!`?");
-                err.help("use `!` instead of `~` if you meant to perform bitwise negation");
-                err.emit();
-                (lo.to(span), self.mk_unary(UnOp::Not, e))
-            }
-            token::BinOp(token::Minus) => {
-                self.bump();
-                let e = self.parse_prefix_expr(None);
-                let (span, e) = self.interpolated_or_expr_span(e)?;
-                (lo.to(span), self.mk_unary(UnOp::Neg, e))
-            }
-            token::BinOp(token::Star) => {
-                self.bump();
-                let e = self.parse_prefix_expr(None);
-                let (span, e) = self.interpolated_or_expr_span(e)?;
-                (lo.to(span), self.mk_unary(UnOp::Deref, e))
-            }
-            token::BinOp(token::And) | token::AndAnd => {
-                self.expect_and()?;
-                let m = self.parse_mutability();
-                let e = self.parse_prefix_expr(None);
-                let (span, e) = self.interpolated_or_expr_span(e)?;
-                (lo.to(span), ExprKind::AddrOf(m, e))
-            }
-            token::Ident(..) if self.token.is_keyword(keywords::In) => {
-                self.bump();
-                let place = self.parse_expr_res(
-                    Restrictions::NO_STRUCT_LITERAL,
-                    None,
-                )?;
-                let blk = self.parse_block()?;
-                let span = blk.span;
-                let blk_expr = self.mk_expr(span, ExprKind::Block(blk), ThinVec::new());
-                (lo.to(span), ExprKind::InPlace(place, blk_expr))
-            }
-            token::Ident(..) if self.token.is_keyword(keywords::Box) => {
-                self.bump();
-                let e = self.parse_prefix_expr(None);
-                let (span, e) = self.interpolated_or_expr_span(e)?;
-                (lo.to(span), ExprKind::Box(e))
-            }
-            _ => return self.parse_dot_or_call_expr(Some(attrs))
+        // Prefix operators are collected in a loop rather than parsed recursively, so that long
+        // chains of them in generated code cannot overflow the stack.
+        let mut prefixes = Vec::new();
+        let mut attrs = self.parse_or_use_outer_attributes(already_parsed_attrs)?;
+        let mut e = loop {
+            let lo = self.span;
+            // Note: when adding new unary operators, don't forget to adjust Token::can_begin_expr()
+            let op = match self.token {
+                token::Not => {
+                    self.bump();
+                    PrefixOp::Unary(UnOp::Not)
+                }
+                // Suggest `!` for bitwise negation when encountering a `~`
+                token::Tilde => {
+                    self.bump();
+                    PrefixOp::Tilde
+                }
+                token::BinOp(token::Minus) => {
+                    self.bump();
+                    PrefixOp::Unary(UnOp::Neg)
+                }
+                token::BinOp(token::Star) => {
+                    self.bump();
+                    PrefixOp::Unary(UnOp::Deref)
+                }
+                token::BinOp(token::And) | token::AndAnd => {
+                    self.expect_and()?;
+                    PrefixOp::AddrOf(self.parse_mutability())
+                }
+                token::Ident(..) if self.token.is_keyword(keywords::In) => {
+                    self.bump();
+                    let place = self.parse_expr_res(
+                        Restrictions::NO_STRUCT_LITERAL,
+                        None,
+                    )?;
+                    let blk = self.parse_block()?;
+                    let span = blk.span;
+                    let blk_expr = self.mk_expr(span, ExprKind::Block(blk), ThinVec::new());
+                    break self.mk_expr(lo.to(span), ExprKind::InPlace(place, blk_expr), attrs);
+                }
+                token::Ident(..) if self.token.is_keyword(keywords::Box) => {
+                    self.bump();
+                    PrefixOp::Box
+                }
+                _ => break self.parse_dot_or_call_expr(Some(attrs))?,
+            };
+            prefixes.push((lo, op, attrs));
+            attrs = self.parse_or_use_outer_attributes(None)?;
         };
-        return Ok(self.mk_expr(lo.to(hi), ex, attrs));
+
+        // Apply the operators, innermost first.
+        while let Some((lo, op, attrs)) = prefixes.pop() {
+            let (span, operand) = self.interpolated_or_expr_span(Ok(e))?;
+            let ex = match op {
+                PrefixOp::Unary(op) => self.mk_unary(op, operand),
+                PrefixOp::Tilde => {
+                    let span_of_tilde = lo;
+                    let mut err = self.diagnostic().struct_span_err(span_of_tilde,
+                            "`~` can not be used as a unary operator");
+                    err.span_label(span_of_tilde, "did you mean `!`?");
+                    err.help("use `!` instead of `~` if you meant to perform bitwise negation");
+                    err.emit();
+                    self.mk_unary(UnOp::Not, operand)
+                }
+                PrefixOp::AddrOf(m) => ExprKind::AddrOf(m, operand),
+                PrefixOp::Box => ExprKind::Box(operand),
+            };
+            e = self.mk_expr(lo.to(span), ex, attrs);
+        }
+        Ok(e)
     }
 
     /// Parse an associative expression
@@ -3233,18 +3810,32 @@ impl<'a> Parser<'a> {
                              -> PResult<'a, P<Expr>>
     {
         let lo = self.span;
-        let movability = if self.eat_keyword(keywords::Static) {
-            Movability::Static
+        let (movability, static_kw) = if self.eat_keyword(keywords::Static) {
+            (Movability::Static, Some(self.prev_span))
         } else {
-            Movability::Movable
+            (Movability::Movable, None)
         };
-        let capture_clause = if self.eat_keyword(keywords::Move) {
-            CaptureBy::Value
+        let (capture_clause, move_kw) = if self.eat_keyword(keywords::Move) {
+            (CaptureBy::Value, Some(self.prev_span))
         } else {
-            CaptureBy::Ref
+            (CaptureBy::Ref, None)
         };
-        let decl = self.parse_fn_block_decl()?;
-        let decl_hi = self.prev_span;
+        let args_lo = self.span;
+        let inputs = self.parse_fn_block_args()?;
+        let args = args_lo.to(self.prev_span);
+        let output_lo = self.span;
+        let output = self.parse_ret_ty()?;
+        let spans = ClosureSpans {
+            decl: lo.to(self.prev_span),
+            static_kw,
+            move_kw,
+            args,
+            output: match output {
+                FunctionRetTy::Ty(_) => Some(output_lo.to(self.prev_span)),
+                FunctionRetTy::Default(_) => None,
+            },
+        };
+        let decl = P(FnDecl { inputs, output, variadic: false });
         let body = match decl.output {
             FunctionRetTy::Default(_) => {
                 let restrictions = self.restrictions - Restrictions::STMT_EXPR;
@@ -3260,14 +3851,15 @@ impl<'a> Parser<'a> {
 
         Ok(self.mk_expr(
             lo.to(body.span),
-            ExprKind::Closure(capture_clause, movability, decl, body, lo.to(decl_hi)),
+            ExprKind::Closure(capture_clause, movability, decl, body, spans),
             attrs))
     }
 
     // `else` token already eaten
     pub fn parse_else_expr(&mut self) -> PResult<'a, P<Expr>> {
         if self.eat_keyword(keywords::If) {
-            return self.parse_if_expr(ThinVec::new());
+            return self.in_context("`if` expression",
+                                   |this| this.parse_if_expr(ThinVec::new()));
         } else {
             let blk = self.parse_block()?;
             return Ok(self.mk_expr(blk.span, ExprKind::Block(blk), ThinVec::new()));
@@ -3363,6 +3955,7 @@ impl<'a> Parser<'a> {
                 Ok(arm) => arms.push(arm),
                 Err(mut e) => {
                     // Recover by skipping to the end of the block.
+                    self.label_contexts(&mut e);
                     e.emit();
                     self.recover_stmt();
                     let span = lo.to(self.span);
@@ -3405,6 +3998,11 @@ impl<'a> Parser<'a> {
         } else {
             self.eat(&token::Comma);
         }
+        let trailing_comma = if self.prev_token_kind == PrevTokenKind::Comma {
+            Some(self.prev_span)
+        } else {
+            None
+        };
 
         Ok(ast::Arm {
             attrs,
@@ -3412,6 +4010,7 @@ impl<'a> Parser<'a> {
             guard,
             body: expr,
             beginning_vert,
+            trailing_comma,
         })
     }
 
@@ -3434,11 +4033,22 @@ impl<'a> Parser<'a> {
 
     }
 
+    /// Parses an expression subject to the restrictions `r`, like the
+    /// compiler does in the same context: e.g. with `NO_STRUCT_LITERAL` for
+    /// the scrutinee of a `match`, `x {}` is the path `x` and the `{` is left
+    /// unparsed. The restrictions only apply outside of delimiters, so
+    /// `(S {})` is still parsed as a struct literal.
+    pub fn parse_expr_with_restrictions(&mut self, r: Restrictions) -> PResult<'a, P<Expr>> {
+        self.parse_expr_res(r, None)
+    }
+
     /// Parse an expression, subject to the given restrictions
     pub fn parse_expr_res(&mut self, r: Restrictions,
                           already_parsed_attrs: Option<ThinVec<Attribute>>)
                           -> PResult<'a, P<Expr>> {
-        self.with_res(r, |this| this.parse_assoc_expr(already_parsed_attrs))
+        self.with_res(r, |this| {
+            this.node(NodeKind::Expr, |this| this.parse_assoc_expr(already_parsed_attrs))
+        })
     }
 
     /// Parse the RHS of a local variable declaration (e.g. '= 14;')
@@ -3683,6 +4293,10 @@ impl<'a> Parser<'a> {
 
     /// Parse a pattern.
     pub fn parse_pat(&mut self) -> PResult<'a, P<Pat>> {
+        self.node(NodeKind::Pat, |this| this.parse_pat_())
+    }
+
+    fn parse_pat_(&mut self) -> PResult<'a, P<Pat>> {
         maybe_whole!(self, NtPat, |x| x);
 
         let lo = self.span;
@@ -3724,7 +4338,12 @@ impl<'a> Parser<'a> {
                 let binding_mode = if self.eat_keyword(keywords::Ref) {
                     self.diagnostic()
                         .struct_span_err(mutref_span, "the order of `mut` and `ref` is incorrect")
-                        .span_suggestion(mutref_span, "try switching the order", "ref mut".into())
+                        .span_suggestion_with_applicability(
+                            mutref_span,
+                            "try switching the order",
+                            "ref mut".into(),
+                            Applicability::MachineApplicable
+                        )
                         .emit();
                     BindingMode::ByRef(Mutability::Mutable)
                 } else {
@@ -3761,7 +4380,7 @@ impl<'a> Parser<'a> {
                         // Parse macro invocation
                         self.bump();
                         let (_, tts) = self.expect_delimited_token_tree()?;
-                        let mac = respan(lo.to(self.prev_span), Mac_ { path: path, tts: tts });
+                        let mac = self.mk_mac(lo.to(self.prev_span), path, tts);
                         pat = PatKind::Mac(mac);
                     }
                     token::DotDotDot | token::DotDotEq | token::DotDot => {
@@ -3826,7 +4445,8 @@ impl<'a> Parser<'a> {
                     }
                     Err(mut err) => {
                         self.cancel(&mut err);
-                        let msg = format!("expected pattern, found {}", self.this_token_descr());
+                        let msg = self.message(messages::EXPECTED_PAT,
+                                               &[("found", &self.this_token_descr())]);
                         return Err(self.fatal(&msg));
                     }
                 }
@@ -3870,6 +4490,9 @@ impl<'a> Parser<'a> {
     }
 
     /// Parse a local variable declaration
+    ///
+    /// Parses the following grammar:
+    ///     Local = "let" Pat [":" Ty] ["=" Expr]
     fn parse_local(&mut self, attrs: ThinVec<Attribute>) -> PResult<'a, P<Local>> {
         let lo = self.prev_span;
         let pat = self.parse_pat()?;
@@ -3916,11 +4539,13 @@ impl<'a> Parser<'a> {
                 // return to the parser state before parsing the type as the initializer.
                 // let x: <parse_error>;
                 mem::replace(self, snapshot);
+                self.failed_let_pat = Some(pat);
                 return Err(ty_err);
             }
             (Err(err), None) => {  // init error, ty parsed
                 // Couldn't parse the initializer and we're not attempting to recover a failed
                 // parse of the type, return the error.
+                self.failed_let_pat = Some(pat);
                 return Err(err);
             }
         };
@@ -3971,7 +4596,7 @@ impl<'a> Parser<'a> {
     /// Parse a statement. This stops just before trailing semicolons on everything but items.
     /// e.g. a `StmtKind::Semi` parses to a `StmtKind::Expr`, leaving the trailing `;` unconsumed.
     pub fn parse_stmt(&mut self) -> PResult<'a, Option<Stmt>> {
-        Ok(self.parse_stmt_(true))
+        self.node(NodeKind::Stmt, |this| Ok(this.parse_stmt_(true)))
     }
 
     // Eat tokens until we can be relatively sure we reached the end of the
@@ -4050,6 +4675,49 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// The number of delimited groups the current token is nested in. The
+    /// delimiters of a group count as outside of it.
+    fn token_depth(&self) -> usize {
+        match self.token {
+            token::OpenDelim(_) | token::CloseDelim(_) => {
+                self.token_cursor.stack.len().saturating_sub(1)
+            }
+            _ => self.token_cursor.stack.len(),
+        }
+    }
+
+    /// Skips the rest of a statement that failed to parse, up to the next
+    /// point in `stmt_sync` or the `}` closing the block. The statement starts
+    /// at `stmt_lo`, at `depth` (see `token_depth`); sync points in groups the
+    /// error occurred in, e.g. a `;` in an unclosed call, are skipped. Unless
+    /// the block ends right away, at least one token is skipped so that
+    /// recovery always makes progress, even if the statement failed on its
+    /// first token.
+    fn recover_to_stmt_sync_point(&mut self, stmt_lo: Span, depth: usize) {
+        let mut skipped = self.span != stmt_lo;
+        loop {
+            let token_depth = self.token_depth();
+            match self.token {
+                token::Eof => return,
+                token::CloseDelim(_) if token_depth < depth => return,
+                _ if token_depth == depth => {
+                    let at_keyword = self.stmt_sync.keywords.iter()
+                        .any(|&kw| self.token.is_keyword(kw));
+                    if skipped && at_keyword {
+                        return;
+                    }
+                    if self.stmt_sync.tokens.contains(&self.token) {
+                        self.bump();
+                        return;
+                    }
+                }
+                _ => {}
+            }
+            self.bump();
+            skipped = true;
+        }
+    }
+
     fn parse_stmt_(&mut self, macro_legacy_warnings: bool) -> Option<Stmt> {
         self.parse_stmt_without_recovery(macro_legacy_warnings).unwrap_or_else(|mut e| {
             e.emit();
@@ -4068,8 +4736,108 @@ impl<'a> Parser<'a> {
     }
 
     fn is_union_item(&self) -> bool {
-        self.token.is_keyword(keywords::Union) &&
-        self.look_ahead(1, |t| t.is_ident() && !t.is_reserved_ident())
+        self.is_contextual_keyword(keywords::Union.name())
+    }
+
+    /// If the current token is an identifier that looks like a misspelling of
+    /// one of `keywords` (`strcut`), or of `pub` followed by one of them
+    /// (`pubfn`) if `allow_pub` is set, reports it and replaces the token with
+    /// the keyword so that parsing can continue. Returns `true` if the
+    /// misspelling included `pub`, which the caller has to apply.
+    ///
+    /// Only identifiers directly followed by another identifier or a literal
+    /// are considered, as those can never start a valid item or statement,
+    /// and only keywords whose grammar the following tokens fit, so that
+    /// e.g. `foo x` is not turned into a `for` loop missing its `in`.
+    fn recover_misspelled_keyword(&mut self, keywords: &[&[&'static str]], allow_pub: bool)
+                                  -> bool {
+        let ident = match self.token {
+            token::Ident(ident) if !self.token.is_reserved_ident() => ident,
+            _ => return false,
+        };
+        if !self.look_ahead(1, |t| t.is_ident() || t.is_lit()) ||
+           self.sess.contextual_keyword_rule(ident.name).is_some() ||
+           self.sess.variant_item_extension(self.syntax_variant, ident.name).is_some() {
+            return false;
+        }
+
+        let mut candidates = Vec::new();
+        for &keyword in keywords.iter().flat_map(|keywords| keywords.iter()) {
+            if !self.fits_keyword_grammar(keyword) {
+                continue;
+            }
+            candidates.push((Symbol::intern(keyword), keyword, false));
+            if allow_pub {
+                candidates.push((Symbol::intern(&format!("pub{}", keyword)), keyword, true));
+            }
+        }
+        let lookup = ident.name.as_str();
+        let max_dist = cmp::min(2, cmp::max(lookup.len(), 3) / 3);
+        let best = match find_best_match_for_name(candidates.iter().map(|&(ref name, ..)| name),
+                                                  &lookup,
+                                                  Some(max_dist)) {
+            Some(best) => best,
+            None => return false,
+        };
+        let (_, keyword, with_pub) = *candidates.iter().find(|c| c.0 == best).unwrap();
+
+        let suggestion = if with_pub { format!("pub {}", keyword) } else { keyword.to_string() };
+        let mut err = self.struct_span_err(self.span,
+                                           &format!("expected keyword, found `{}`", ident));
+        err.span_suggestion(self.span, "a keyword with a similar name exists", suggestion);
+        err.emit();
+        self.token = token::Ident(ast::Ident::with_empty_ctxt(Symbol::intern(keyword)));
+        with_pub
+    }
+
+    /// Whether the tokens after the current identifier fit the grammar of
+    /// `keyword`, as if the identifier were that keyword.
+    fn fits_keyword_grammar(&self, keyword: &str) -> bool {
+        fn is_ident(t: &token::Token) -> bool {
+            t.is_ident() && !t.is_reserved_ident()
+        }
+        fn is_kw(t: &token::Token, keywords: &[keywords::Keyword]) -> bool {
+            keywords.iter().any(|&kw| t.is_keyword(kw))
+        }
+        fn ends_expr(t: &token::Token) -> bool {
+            *t == token::Dot || AssocOp::from_token(t).is_some()
+        }
+        let brace = token::OpenDelim(token::Brace);
+        let paren = token::OpenDelim(token::Paren);
+        let named = |after: &[token::Token], keywords: &[keywords::Keyword]| {
+            self.look_ahead(1, is_ident) &&
+            self.look_ahead(2, |t| after.contains(t) || is_kw(t, keywords))
+        };
+
+        match keyword {
+            "let" => self.look_ahead(1, |t| is_ident(t) || is_kw(t, &[keywords::Mut,
+                                                                       keywords::Ref])),
+            "fn" => named(&[paren, token::Lt], &[]),
+            "struct" => named(&[brace, paren, token::Lt, token::Semi], &[keywords::Where]),
+            "enum" => named(&[brace, token::Lt], &[keywords::Where]),
+            "trait" => named(&[brace, token::Lt, token::Colon], &[keywords::Where]),
+            "type" => named(&[token::Eq, token::Lt, token::Semi], &[]),
+            "mod" => named(&[brace, token::Semi], &[]),
+            "impl" => named(&[brace, token::Lt, token::ModSep], &[keywords::For]),
+            "use" => named(&[token::ModSep, token::Semi], &[keywords::As]),
+            "static" | "const" => {
+                named(&[token::Colon], &[]) ||
+                self.look_ahead(1, |t| is_kw(t, &[keywords::Fn, keywords::Unsafe])) ||
+                (self.look_ahead(1, |t| t.is_keyword(keywords::Mut)) &&
+                 self.look_ahead(2, is_ident) &&
+                 self.look_ahead(3, |t| *t == token::Colon))
+            }
+            "extern" => self.look_ahead(1, |t| t.is_lit() || t.is_keyword(keywords::Crate)),
+            "unsafe" => self.look_ahead(1, |t| {
+                is_kw(t, &[keywords::Fn, keywords::Impl, keywords::Trait, keywords::Extern])
+            }),
+            "for" => named(&[], &[keywords::In]),
+            "if" | "while" | "match" => self.look_ahead(2, |t| *t == brace || ends_expr(t)),
+            "return" | "break" => self.look_ahead(2, |t| {
+                *t == token::Semi || *t == token::CloseDelim(token::Brace) || ends_expr(t)
+            }),
+            _ => false,
+        }
     }
 
     fn is_crate_vis(&self) -> bool {
@@ -4082,8 +4850,7 @@ impl<'a> Parser<'a> {
 
     fn is_auto_trait_item(&mut self) -> bool {
         // auto trait
-        (self.token.is_keyword(keywords::Auto)
-            && self.look_ahead(1, |t| t.is_keyword(keywords::Trait)))
+        self.is_contextual_keyword(keywords::Auto.name())
         || // unsafe auto trait
         (self.token.is_keyword(keywords::Unsafe) &&
          self.look_ahead(1, |t| t.is_keyword(keywords::Auto)) &&
@@ -4156,6 +4923,8 @@ impl<'a> Parser<'a> {
         let attrs = self.parse_outer_attributes()?;
         let lo = self.span;
 
+        self.recover_misspelled_keyword(&[STMT_KEYWORDS, ITEM_KEYWORDS], false);
+
         Ok(Some(if self.eat_keyword(keywords::Let) {
             Stmt {
                 id: ast::DUMMY_NODE_ID,
@@ -4239,7 +5008,7 @@ impl<'a> Parser<'a> {
             };
 
             if id.name == keywords::Invalid.name() {
-                let mac = respan(lo.to(hi), Mac_ { path: pth, tts: tts });
+                let mac = self.mk_mac(lo.to(hi), pth, tts);
                 let node = if delim == token::Brace ||
                               self.token == token::Semi || self.token == token::Eof {
                     StmtKind::Mac(P((mac, style, attrs.into())))
@@ -4298,7 +5067,9 @@ impl<'a> Parser<'a> {
             // FIXME: Bad copy of attrs
             let old_directory_ownership =
                 mem::replace(&mut self.directory.ownership, DirectoryOwnership::UnownedViaBlock);
-            let item = self.parse_item_(attrs.clone(), false, true)?;
+            let item = self.node(NodeKind::Item, |this| {
+                this.parse_item_(attrs.clone(), false, true)
+            })?;
             self.directory.ownership = old_directory_ownership;
 
             match item {
@@ -4351,6 +5122,10 @@ impl<'a> Parser<'a> {
 
     /// Parse a block. No inner attrs are allowed.
     pub fn parse_block(&mut self) -> PResult<'a, P<Block>> {
+        self.node(NodeKind::Block, |this| this.parse_block_())
+    }
+
+    fn parse_block_(&mut self) -> PResult<'a, P<Block>> {
         maybe_whole!(self, NtBlock, |x| x);
 
         let lo = self.span;
@@ -4394,14 +5169,76 @@ impl<'a> Parser<'a> {
         self.parse_block_tail(lo, BlockCheckMode::Default)
     }
 
+    /// Parse a function body, or capture it if `defer_fn_bodies` is set.
+    fn parse_fn_body(&mut self) -> PResult<'a, (Vec<Attribute>, P<Block>)> {
+        if !self.defer_fn_bodies || !self.check(&token::OpenDelim(token::Brace)) {
+            return self.parse_inner_attrs_and_block();
+        }
+
+        let group = self.capture_group();
+        let inner_attrs = {
+            let mut parser = self.captured_group_parser(&group);
+            parser.expect(&token::OpenDelim(token::Brace))?;
+            parser.parse_inner_attributes()?
+        };
+        let unparsed = self.mk_expr(group.span,
+                                    ExprKind::Unparsed(group.tokens.clone().into()),
+                                    ThinVec::new());
+        let body = P(ast::Block {
+            stmts: vec![Stmt {
+                id: ast::DUMMY_NODE_ID,
+                node: StmtKind::Expr(unparsed),
+                span: group.span,
+            }],
+            id: ast::DUMMY_NODE_ID,
+            rules: BlockCheckMode::Default,
+            span: group.span,
+            recovered: false,
+        });
+        self.captured_groups.push(group);
+        Ok((inner_attrs, body))
+    }
+
+    /// Consume the delimited group starting at the current token without
+    /// parsing its contents.
+    /// Precondition: the current token is an open delimiter.
+    pub fn capture_group(&mut self) -> CapturedGroup {
+        let tt = self.parse_token_tree();
+        CapturedGroup { span: tt.span(), tokens: tt.into() }
+    }
+
+    /// Parse a function body captured by this parser (or any parser for the
+    /// same session) while `defer_fn_bodies` was set. Returns the body's inner
+    /// attributes, which are also attached to the item, along with the block
+    /// itself.
+    pub fn parse_captured(&self, group: &CapturedGroup)
+                          -> PResult<'a, (Vec<Attribute>, P<Block>)> {
+        let mut parser = self.captured_group_parser(group);
+        let body = parser.parse_inner_attrs_and_block()?;
+        parser.expect(&token::Eof)?;
+        Ok(body)
+    }
+
+    fn captured_group_parser(&self, group: &CapturedGroup) -> Parser<'a> {
+        let mut parser = Parser::new(self.sess,
+                                     group.tokens.clone(),
+                                     Some(self.directory.clone()),
+                                     self.recurse_into_file_modules,
+                                     self.desugar_doc_comments);
+        parser.cfg_mods = self.cfg_mods;
+        parser
+    }
+
     /// Parse a block. Inner attrs are allowed.
     fn parse_inner_attrs_and_block(&mut self) -> PResult<'a, (Vec<Attribute>, P<Block>)> {
         maybe_whole!(self, NtBlock, |x| (Vec::new(), x));
 
         let lo = self.span;
-        self.expect(&token::OpenDelim(token::Brace))?;
-        Ok((self.parse_inner_attributes()?,
-            self.parse_block_tail(lo, BlockCheckMode::Default)?))
+        self.node(NodeKind::Block, |this| {
+            this.expect(&token::OpenDelim(token::Brace))?;
+            Ok((this.parse_inner_attributes()?,
+                this.parse_block_tail(lo, BlockCheckMode::Default)?))
+        })
     }
 
     /// Parse the rest of a block expression or function body
@@ -4411,13 +5248,37 @@ impl<'a> Parser<'a> {
         let mut recovered = false;
 
         while !self.eat(&token::CloseDelim(token::Brace)) {
+            let stmt_lo = self.span;
+            let stmt_depth = self.token_depth();
+            self.failed_let_pat = None;
             let stmt = match self.parse_full_stmt(false) {
                 Err(mut err) => {
+                    self.label_contexts(&mut err);
                     err.emit();
-                    self.recover_stmt_(SemiColonMode::Ignore, BlockMode::Ignore);
-                    self.eat(&token::CloseDelim(token::Brace));
+                    self.recover_to_stmt_sync_point(stmt_lo, stmt_depth);
+                    // Keep a placeholder for the statement, so that the block
+                    // still has a statement at that position. A `let`
+                    // statement keeps its bindings, with the error type so
+                    // that neither resolve nor typeck report their uses.
+                    let span = stmt_lo.to(self.prev_span);
+                    let placeholder = self.mk_expr(span, ExprKind::Err, ThinVec::new());
+                    let node = match self.failed_let_pat.take() {
+                        Some(pat) => StmtKind::Local(P(ast::Local {
+                            pat,
+                            ty: Some(P(Ty { node: TyKind::Err, span, id: ast::DUMMY_NODE_ID })),
+                            init: Some(placeholder),
+                            id: ast::DUMMY_NODE_ID,
+                            span,
+                            attrs: ThinVec::new(),
+                        })),
+                        None => StmtKind::Semi(placeholder),
+                    };
+                    stmts.push(Stmt { id: ast::DUMMY_NODE_ID, node, span });
                     recovered = true;
-                    break;
+                    if self.token == token::Eof {
+                        break;
+                    }
+                    continue;
                 }
                 Ok(stmt) => stmt,
             };
@@ -4441,6 +5302,11 @@ impl<'a> Parser<'a> {
 
     /// Parse a statement, including the trailing semicolon.
     pub fn parse_full_stmt(&mut self, macro_legacy_warnings: bool) -> PResult<'a, Option<Stmt>> {
+        self.node(NodeKind::Stmt, |this| this.parse_full_stmt_(macro_legacy_warnings))
+    }
+
+    fn parse_full_stmt_(&mut self, macro_legacy_warnings: bool) -> PResult<'a, Option<Stmt>> {
+        let depth = self.token_depth();
         let mut stmt = match self.parse_stmt_without_recovery(macro_legacy_warnings)? {
             Some(stmt) => stmt,
             None => return Ok(None),
@@ -4455,16 +5321,17 @@ impl<'a> Parser<'a> {
                         self.expect_one_of(&[], &[token::Semi, token::CloseDelim(token::Brace)])
                     {
                         e.emit();
-                        self.recover_stmt();
+                        self.recover_to_stmt_sync_point(stmt.span, depth);
                     }
                 }
             }
-            StmtKind::Local(..) => {
+            StmtKind::Local(ref local) => {
                 // We used to incorrectly allow a macro-expanded let statement to lack a semicolon.
                 if macro_legacy_warnings && self.token != token::Semi {
                     self.warn_missing_semicolon();
-                } else {
-                    self.expect_one_of(&[token::Semi], &[])?;
+                } else if let Err(err) = self.expect_one_of(&[token::Semi], &[]) {
+                    self.failed_let_pat = Some(local.pat.clone());
+                    return Err(err);
                 }
             }
             _ => {}
@@ -4501,56 +5368,115 @@ impl<'a> Parser<'a> {
     // LT_BOUND = LIFETIME (e.g. `'a`)
     // TY_BOUND = TY_BOUND_NOPAREN | (TY_BOUND_NOPAREN)
     // TY_BOUND_NOPAREN = [?] [for<LT_PARAM_DEFS>] SIMPLE_PATH (e.g. `?for<'a: 'b> m::Trait<'a>`)
-    fn parse_ty_param_bounds_common(&mut self, allow_plus: bool) -> PResult<'a, TyParamBounds> {
-        let mut bounds = Vec::new();
+    /// Parses bounds separated by `+`, following the already parsed `bounds`.
+    /// If `recover_redundant_bounds` is set, extra `+`s and bounds written more
+    /// than once are reported as warnings, with suggestions to remove them. In
+    /// types (`in_ty`), so is a trailing `+`, as in `Box<Trait +>`.
+    fn parse_ty_param_bounds_common(&mut self, mut bounds: TyParamBounds,
+                                    allow_plus: bool, in_ty: bool)
+                                    -> PResult<'a, TyParamBounds> {
+        let recover = self.sess.recover_redundant_bounds;
+        let mut last_plus = None;
         loop {
+            if !bounds.is_empty() {
+                if !allow_plus || !self.eat(&token::BinOp(token::Plus)) {
+                    break
+                }
+                last_plus = Some(self.prev_span);
+            }
+            // Do not add `+` to expected tokens.
+            while recover && allow_plus && self.token == token::BinOp(token::Plus) {
+                self.bump();
+                self.diagnostic().struct_span_warn(self.prev_span, "extra `+` in bounds")
+                    .span_suggestion_short_with_applicability(self.prev_span, "remove it",
+                                                              String::new(),
+                                                              Applicability::MachineApplicable)
+                    .emit();
+            }
+
             // This needs to be syncronized with `Token::can_begin_bound`.
             let is_bound_start = self.check_path() || self.check_lifetime() ||
                                  self.check(&token::Question) ||
                                  self.check_keyword(keywords::For) ||
                                  self.check(&token::OpenDelim(token::Paren));
-            if is_bound_start {
-                let has_parens = self.eat(&token::OpenDelim(token::Paren));
-                let question = if self.eat(&token::Question) { Some(self.prev_span) } else { None };
-                if self.token.is_lifetime() {
-                    if let Some(question_span) = question {
-                        self.span_err(question_span,
-                                      "`?` may only modify trait bounds, not lifetime bounds");
+            if !is_bound_start {
+                if let Some(plus) = last_plus {
+                    if recover && in_ty {
+                        self.diagnostic().struct_span_warn(plus, "trailing `+` in type")
+                            .span_suggestion_short_with_applicability(
+                                plus, "remove it", String::new(),
+                                Applicability::MachineApplicable)
+                            .emit();
                     }
-                    bounds.push(RegionTyParamBound(self.expect_lifetime()));
-                } else {
-                    let lo = self.span;
-                    let lifetime_defs = self.parse_late_bound_lifetime_defs()?;
-                    let path = self.parse_path(PathStyle::Type)?;
-                    let poly_trait = PolyTraitRef::new(lifetime_defs, path, lo.to(self.prev_span));
-                    let modifier = if question.is_some() {
-                        TraitBoundModifier::Maybe
-                    } else {
-                        TraitBoundModifier::None
-                    };
-                    bounds.push(TraitTyParamBound(poly_trait, modifier));
                 }
-                if has_parens {
-                    self.expect(&token::CloseDelim(token::Paren))?;
-                    if let Some(&RegionTyParamBound(..)) = bounds.last() {
-                        self.span_err(self.prev_span,
-                                      "parenthesized lifetime bounds are not supported");
-                    }
+                break
+            }
+
+            let bound_lo = self.span;
+            let has_parens = self.eat(&token::OpenDelim(token::Paren));
+            let question = if self.eat(&token::Question) { Some(self.prev_span) } else { None };
+            if self.token.is_lifetime() {
+                if let Some(question_span) = question {
+                    self.span_err(question_span,
+                                  "`?` may only modify trait bounds, not lifetime bounds");
                 }
+                bounds.push(RegionTyParamBound(self.expect_lifetime()));
             } else {
-                break
+                let lo = self.span;
+                let lifetime_defs = self.parse_late_bound_lifetime_defs()?;
+                let path = self.parse_path(PathStyle::Type)?;
+                let poly_trait = PolyTraitRef::new(lifetime_defs, path, lo.to(self.prev_span));
+                let modifier = if question.is_some() {
+                    TraitBoundModifier::Maybe
+                } else {
+                    TraitBoundModifier::None
+                };
+                bounds.push(TraitTyParamBound(poly_trait, modifier));
+            }
+            if has_parens {
+                self.expect(&token::CloseDelim(token::Paren))?;
+                if let Some(&RegionTyParamBound(..)) = bounds.last() {
+                    self.span_err(self.prev_span,
+                                  "parenthesized lifetime bounds are not supported");
+                }
             }
 
-            if !allow_plus || !self.eat(&token::BinOp(token::Plus)) {
-                break
+            let first_span = if !recover {
+                None
+            } else {
+                let (bound, previous) = bounds.split_last().unwrap();
+                previous.iter().find(|previous| is_same_simple_bound(previous, bound))
+                        .map(|previous| match *previous {
+                            RegionTyParamBound(ref lifetime) => lifetime.span,
+                            TraitTyParamBound(ref poly_trait, _) => poly_trait.span,
+                        })
+            };
+            if let Some(first_span) = first_span {
+                let bound_span = bound_lo.to(self.prev_span);
+                let remove_span = last_plus.map_or(bound_span, |plus: Span| plus.to(bound_span));
+                let msg = format!("duplicate bound `{}`",
+                                  pprust::bounds_to_string(&bounds[bounds.len() - 1..]));
+                self.diagnostic().struct_span_warn(bound_span, &msg)
+                    .span_label(first_span, "first bound here")
+                    .span_suggestion_short_with_applicability(remove_span, "remove it",
+                                                              String::new(),
+                                                              Applicability::MachineApplicable)
+                    .emit();
+                bounds.pop();
             }
+            last_plus = None;
         }
 
         return Ok(bounds);
     }
 
     fn parse_ty_param_bounds(&mut self) -> PResult<'a, TyParamBounds> {
-        self.parse_ty_param_bounds_common(true)
+        self.parse_ty_param_bounds_common(Vec::new(), true, false)
+    }
+
+    /// Parses the bounds of a trait object or `impl Trait` type.
+    fn parse_ty_bounds(&mut self) -> PResult<'a, TyParamBounds> {
+        self.parse_ty_param_bounds_common(Vec::new(), true, true)
     }
 
     // Parse bounds of a lifetime parameter `BOUND + BOUND + BOUND`, possibly with trailing `+`.
@@ -4595,6 +5521,24 @@ impl<'a> Parser<'a> {
         })
     }
 
+    /// Matches constparam = `const` IDENT `:` ty
+    fn parse_const_param(&mut self, preceding_attrs: Vec<Attribute>)
+                         -> PResult<'a, ast::ConstParam> {
+        let lo = self.span;
+        self.expect_keyword(keywords::Const)?;
+        let ident = self.parse_ident()?;
+        self.expect(&token::Colon)?;
+        let ty = self.parse_ty()?;
+
+        Ok(ast::ConstParam {
+            attrs: preceding_attrs.into(),
+            ident,
+            id: ast::DUMMY_NODE_ID,
+            ty,
+            span: lo.to(self.prev_span),
+        })
+    }
+
     /// Parses the following grammar:
     ///     TraitItemAssocTy = Ident ["<"...">"] [":" [TyParamBounds]] ["where" ...] ["=" Ty]
     fn parse_trait_item_assoc_ty(&mut self, preceding_attrs: Vec<Attribute>)
@@ -4652,6 +5596,10 @@ impl<'a> Parser<'a> {
                     self.span_err(self.prev_span,
                         "lifetime parameters must be declared prior to type parameters");
                 }
+            } else if self.sess.const_generics && self.check_keyword(keywords::Const) {
+                // Parse const parameter.
+                params.push(ast::GenericParam::Const(self.parse_const_param(attrs)?));
+                seen_ty_param = true;
             } else if self.check_ident() {
                 // Parse type parameter.
                 params.push(ast::GenericParam::Type(self.parse_ty_param(attrs)?));
@@ -5043,29 +5991,20 @@ impl<'a> Parser<'a> {
     }
 
     // parse the |arg, arg| header on a lambda
-    fn parse_fn_block_decl(&mut self) -> PResult<'a, P<FnDecl>> {
-        let inputs_captures = {
-            if self.eat(&token::OrOr) {
-                Vec::new()
-            } else {
-                self.expect(&token::BinOp(token::Or))?;
-                let args = self.parse_seq_to_before_tokens(
-                    &[&token::BinOp(token::Or), &token::OrOr],
-                    SeqSep::trailing_allowed(token::Comma),
-                    TokenExpectType::NoExpect,
-                    |p| p.parse_fn_block_arg()
-                )?;
-                self.expect_or()?;
-                args
-            }
-        };
-        let output = self.parse_ret_ty()?;
-
-        Ok(P(FnDecl {
-            inputs: inputs_captures,
-            output,
-            variadic: false
-        }))
+    fn parse_fn_block_args(&mut self) -> PResult<'a, Vec<Arg>> {
+        if self.eat(&token::OrOr) {
+            Ok(Vec::new())
+        } else {
+            self.expect(&token::BinOp(token::Or))?;
+            let args = self.parse_seq_to_before_tokens(
+                &[&token::BinOp(token::Or), &token::OrOr],
+                SeqSep::trailing_allowed(token::Comma),
+                TokenExpectType::NoExpect,
+                |p| p.parse_fn_block_arg()
+            )?;
+            self.expect_or()?;
+            Ok(args)
+        }
     }
 
     /// Parse the name and optional generic types of a function header.
@@ -5077,6 +6016,22 @@ impl<'a> Parser<'a> {
 
     fn mk_item(&mut self, span: Span, ident: Ident, node: ItemKind, vis: Visibility,
                attrs: Vec<Attribute>) -> P<Item> {
+        match node {
+            ItemKind::Fn(..) => self.check_naming(NameKind::Fn, ident, span),
+            ItemKind::Mod(..) => self.check_naming(NameKind::Mod, ident, span),
+            ItemKind::Struct(..) | ItemKind::Union(..) | ItemKind::Trait(..) |
+            ItemKind::Ty(..) => self.check_naming(NameKind::Type, ident, span),
+            ItemKind::Enum(ref def, _) => {
+                self.check_naming(NameKind::Type, ident, span);
+                for variant in &def.variants {
+                    self.check_naming(NameKind::Variant, variant.node.name, variant.span);
+                }
+            }
+            ItemKind::Const(..) | ItemKind::Static(..) => {
+                self.check_naming(NameKind::Const, ident, span)
+            }
+            _ => {}
+        }
         P(Item {
             ident,
             attrs,
@@ -5088,7 +6043,22 @@ impl<'a> Parser<'a> {
         })
     }
 
+    /// Warns about `ident` if it does not follow the naming convention of
+    /// `kind` and `ParseSess::check_naming` is set.
+    fn check_naming(&self, kind: NameKind, ident: Ident, span: Span) {
+        if !self.sess.check_naming || ident.name == keywords::Invalid.name() ||
+           kind.style().matches(&ident.name.as_str()) {
+            return;
+        }
+        let warning = NamingWarning { kind, name: ident.name, span };
+        self.diagnostic().span_warn(span, &warning.message());
+        self.sess.naming_warnings.borrow_mut().push(warning);
+    }
+
     /// Parse an item-position function declaration.
+    ///
+    /// Parses the following grammar:
+    ///     ItemFn = "fn" Ident [Generics] FnDecl [WhereClause] Block
     fn parse_item_fn(&mut self,
                      unsafety: Unsafety,
                      constness: Spanned<Constness>,
@@ -5097,7 +6067,7 @@ impl<'a> Parser<'a> {
         let (ident, mut generics) = self.parse_fn_header()?;
         let decl = self.parse_fn_decl(false)?;
         generics.where_clause = self.parse_where_clause()?;
-        let (inner_attrs, body) = self.parse_inner_attrs_and_block()?;
+        let (inner_attrs, body) = self.parse_fn_body()?;
         Ok((ident, ItemKind::Fn(decl, unsafety, constness, abi, generics, body), Some(inner_attrs)))
     }
 
@@ -5139,7 +6109,7 @@ impl<'a> Parser<'a> {
         maybe_whole!(self, NtImplItem, |x| x);
         let attrs = self.parse_outer_attributes()?;
         let (mut item, tokens) = self.collect_tokens(|this| {
-            this.parse_impl_item_(at_end, attrs)
+            this.node(NodeKind::ImplItem, |this| this.parse_impl_item_(at_end, attrs))
         })?;
 
         // See `parse_item` for why this clause is here.
@@ -5182,9 +6152,16 @@ impl<'a> Parser<'a> {
             (name, node, generics)
         };
 
+        let span = lo.to(self.prev_span);
+        match node {
+            ast::ImplItemKind::Const(..) => self.check_naming(NameKind::Const, name, span),
+            ast::ImplItemKind::Method(..) => self.check_naming(NameKind::Fn, name, span),
+            ast::ImplItemKind::Type(..) => self.check_naming(NameKind::Type, name, span),
+            ast::ImplItemKind::Macro(..) => {}
+        }
         Ok(ImplItem {
             id: ast::DUMMY_NODE_ID,
-            span: lo.to(self.prev_span),
+            span,
             ident: name,
             vis,
             defaultness,
@@ -5274,17 +6251,19 @@ impl<'a> Parser<'a> {
                 self.expect(&token::Semi)?
             }
 
-            let mac = respan(lo.to(self.prev_span), Mac_ { path: pth, tts: tts });
+            let mac = self.mk_mac(lo.to(self.prev_span), pth, tts);
             Ok((keywords::Invalid.ident(), vec![], ast::Generics::default(),
                 ast::ImplItemKind::Macro(mac)))
         } else {
             let (constness, unsafety, abi) = self.parse_fn_front_matter()?;
             let ident = self.parse_ident()?;
             let mut generics = self.parse_generics()?;
-            let decl = self.parse_fn_decl_with_self(|p| p.parse_arg())?;
+            let decl = self.with_impl_self_ty(None, |this| {
+                this.parse_fn_decl_with_self(|p| p.parse_arg())
+            })?;
             generics.where_clause = self.parse_where_clause()?;
             *at_end = true;
-            let (inner_attrs, body) = self.parse_inner_attrs_and_block()?;
+            let (inner_attrs, body) = self.parse_fn_body()?;
             Ok((ident, inner_attrs, generics, ast::ImplItemKind::Method(ast::MethodSig {
                 abi,
                 unsafety,
@@ -5319,19 +6298,23 @@ impl<'a> Parser<'a> {
             // it's a normal trait
             tps.where_clause = self.parse_where_clause()?;
             self.expect(&token::OpenDelim(token::Brace))?;
-            let mut trait_items = vec![];
-            while !self.eat(&token::CloseDelim(token::Brace)) {
-                let mut at_end = false;
-                match self.parse_trait_item(&mut at_end) {
-                    Ok(item) => trait_items.push(item),
-                    Err(mut e) => {
-                        e.emit();
-                        if !at_end {
-                            self.recover_stmt_(SemiColonMode::Break, BlockMode::Break);
+            let trait_items = self.with_impl_self_ty(None, |this| {
+                let mut trait_items = vec![];
+                while !this.eat(&token::CloseDelim(token::Brace)) {
+                    let mut at_end = false;
+                    match this.parse_trait_item(&mut at_end) {
+                        Ok(item) => trait_items.push(item),
+                        Err(mut e) => {
+                            this.label_contexts(&mut e);
+                            e.emit();
+                            if !at_end {
+                                this.recover_stmt_(SemiColonMode::Break, BlockMode::Break);
+                            }
                         }
                     }
                 }
-            }
+                trait_items
+            });
             Ok((ident, ItemKind::Trait(is_auto, unsafety, tps, bounds, trait_items), None))
         }
     }
@@ -5369,6 +6352,7 @@ impl<'a> Parser<'a> {
             match self.parse_impl_item(&mut at_end) {
                 Ok(impl_item) => impl_items.push(impl_item),
                 Err(mut err) => {
+                    self.label_contexts(&mut err);
                     err.emit();
                     if !at_end {
                         self.recover_stmt_(SemiColonMode::Break, BlockMode::Break);
@@ -5422,7 +6406,10 @@ impl<'a> Parser<'a> {
 
         generics.where_clause = self.parse_where_clause()?;
 
-        let (impl_items, attrs) = self.parse_impl_body()?;
+        let self_ty = ty_second.as_ref().unwrap_or(&ty_first).clone();
+        let (impl_items, attrs) = self.with_impl_self_ty(Some(self_ty), |this| {
+            this.parse_impl_body()
+        })?;
 
         let item_kind = match ty_second {
             Some(ty_second) => {
@@ -5465,6 +6452,7 @@ impl<'a> Parser<'a> {
                 .filter_map(|param| match *param {
                     ast::GenericParam::Lifetime(_) => None,
                     ast::GenericParam::Type(ref t) => Some(t.span),
+                    ast::GenericParam::Const(ref c) => Some(c.span),
                 })
                 .next();
 
@@ -5521,8 +6509,10 @@ impl<'a> Parser<'a> {
             body
         } else {
             let token_str = self.this_token_to_string();
-            return Err(self.fatal(&format!("expected `where`, `{{`, `(`, or `;` after struct \
-                                            name, found `{}`", token_str)))
+            let mut err = self.fatal(&format!("expected `where`, `{{`, `(`, or `;` after struct \
+                                         name, found `{}`", token_str));
+            self.label_eof_context(&mut err);
+            return Err(err);
         };
 
         Ok((class_name, ItemKind::Struct(vdata, generics), None))
@@ -5541,8 +6531,10 @@ impl<'a> Parser<'a> {
             VariantData::Struct(self.parse_record_struct_body()?, ast::DUMMY_NODE_ID)
         } else {
             let token_str = self.this_token_to_string();
-            return Err(self.fatal(&format!("expected `where` or `{{` after union \
-                                            name, found `{}`", token_str)))
+            let mut err = self.fatal(&format!("expected `where` or `{{` after union \
+                                         name, found `{}`", token_str));
+            self.label_eof_context(&mut err);
+            return Err(err);
         };
 
         Ok((class_name, ItemKind::Union(vdata, generics), None))
@@ -5727,16 +6719,7 @@ impl<'a> Parser<'a> {
 
     /// Parse defaultness: `default` or nothing.
     fn parse_defaultness(&mut self) -> Defaultness {
-        // `pub` is included for better error messages
-        if self.check_keyword(keywords::Default) &&
-           self.look_ahead(1, |t| t.is_keyword(keywords::Impl) ||
-                                  t.is_keyword(keywords::Const) ||
-                                  t.is_keyword(keywords::Fn) ||
-                                  t.is_keyword(keywords::Unsafe) ||
-                                  t.is_keyword(keywords::Extern) ||
-                                  t.is_keyword(keywords::Type) ||
-                                  t.is_keyword(keywords::Pub)) {
-            self.bump(); // `default`
+        if self.eat_contextual_keyword(keywords::Default.name()) {
             Defaultness::Default
         } else {
             Defaultness::Final
@@ -5752,7 +6735,8 @@ impl<'a> Parser<'a> {
 
         if !self.eat(term) {
             let token_str = self.this_token_to_string();
-            let mut err = self.fatal(&format!("expected item, found `{}`", token_str));
+            let msg = self.message(messages::EXPECTED_ITEM, &[("found", &token_str)]);
+            let mut err = self.fatal(&msg);
             let msg = "consider removing this semicolon";
             if token_str == ";" {
                 err.span_suggestion_short(self.span, msg, "".to_string());
@@ -5772,6 +6756,8 @@ impl<'a> Parser<'a> {
         })
     }
 
+    /// Parses the following grammar:
+    ///     ItemConst = ("const" | "static" ["mut"]) Ident ":" Ty "=" Expr ";"
     fn parse_item_const(&mut self, m: Option<Mutability>) -> PResult<'a, ItemInfo> {
         let id = self.parse_ident()?;
         self.expect(&token::Colon)?;
@@ -5842,15 +6828,47 @@ impl<'a> Parser<'a> {
 
     fn push_directory(&mut self, id: Ident, attrs: &[Attribute]) {
         if let Some(path) = attr::first_attr_value_str_by_name(attrs, "path") {
-            self.directory.path.push(&path.as_str());
+            let path = path.as_str();
+            match mod_path::normalize_path_attr(&path) {
+                Ok(path) => self.directory.path.push(path),
+                Err(_) => self.directory.path.push(&*path),
+            }
             self.directory.ownership = DirectoryOwnership::Owned { relative: None };
         } else {
             self.directory.path.push(&id.name.as_str());
         }
     }
 
+    /// Returns the path given by the `#[path]` attribute among `attrs`, if any,
+    /// relative to `dir_path`. See `mod_path::normalize_path_attr`.
     pub fn submod_path_from_attr(attrs: &[Attribute], dir_path: &Path) -> Option<PathBuf> {
-        attr::first_attr_value_str_by_name(attrs, "path").map(|d| dir_path.join(&d.as_str()))
+        attr::first_attr_value_str_by_name(attrs, "path").map(|d| {
+            let d = d.as_str();
+            dir_path.join(mod_path::normalize_path_attr(&d).unwrap_or_else(|_| PathBuf::from(&*d)))
+        })
+    }
+
+    /// Checks the `#[path]` attribute among `attrs`, if any, reporting invalid
+    /// values, and warning about backslashes.
+    fn check_path_attr(&self, attrs: &[Attribute]) -> PResult<'a, ()> {
+        let (value, span) = match attrs.iter().find(|attr| attr.check_name("path")) {
+            Some(attr) => match attr.value_str() {
+                Some(value) => (value.as_str(), attr.span),
+                None => return Ok(()),
+            },
+            None => return Ok(()),
+        };
+        if let Err(err) = mod_path::normalize_path_attr(&value) {
+            return Err(self.span_fatal(span, &format!("invalid `#[path]` value: {}", err)));
+        }
+        if value.contains('\\') {
+            self.diagnostic()
+                .struct_span_warn(span, "`#[path]` value uses `\\` as a path separator")
+                .note("`\\` and `/` both separate path components on all platforms")
+                .help("use `/` for portability with other tools")
+                .emit();
+        }
+        Ok(())
     }
 
     /// Returns either a path to a module, or .
@@ -5921,6 +6939,7 @@ impl<'a> Parser<'a> {
                    outer_attrs: &[Attribute],
                    id_sp: Span)
                    -> PResult<'a, ModulePathSuccess> {
+        self.check_path_attr(outer_attrs)?;
         if let Some(path) = Parser::submod_path_from_attr(outer_attrs, &self.directory.path) {
             return Ok(ModulePathSuccess {
                 directory_ownership: match path.file_name().and_then(|s| s.to_str()) {
@@ -6012,8 +7031,10 @@ impl<'a> Parser<'a> {
                     name: String,
                     id_sp: Span)
                     -> PResult<'a, (ast::ItemKind, Vec<Attribute> )> {
+        // Compare canonical paths, so that symbolic links do not hide cycles.
+        let canonical_path = self.sess.codemap().canonicalize(&path);
         let mut included_mod_stack = self.sess.included_mod_stack.borrow_mut();
-        if let Some(i) = included_mod_stack.iter().position(|p| *p == path) {
+        if let Some(i) = included_mod_stack.iter().position(|p| *p == canonical_path) {
             let mut err = String::from("circular modules: ");
             let len = included_mod_stack.len();
             for p in &included_mod_stack[i.. len] {
@@ -6023,15 +7044,37 @@ impl<'a> Parser<'a> {
             err.push_str(&path.to_string_lossy());
             return Err(self.span_fatal(id_sp, &err[..]));
         }
-        included_mod_stack.push(path.clone());
+        included_mod_stack.push(canonical_path);
         drop(included_mod_stack);
 
+        if let Some(policy) = self.sess.mod_path_policy() {
+            if let Err(reason) = policy(&path) {
+                self.sess.included_mod_stack.borrow_mut().pop();
+                let mut err = self.diagnostic().struct_span_fatal(
+                    id_sp, &format!("cannot load the file `{}` for this module", path.display()));
+                err.note(&reason);
+                return Err(err);
+            }
+        }
+
         let mut p0 =
             new_sub_parser_from_file(self.sess, &path, directory_ownership, Some(name), id_sp);
         p0.cfg_mods = self.cfg_mods;
+        p0.defer_fn_bodies = self.defer_fn_bodies;
+        p0.events = self.events.clone();
         let mod_inner_lo = p0.span;
-        let mod_attrs = p0.parse_inner_attributes()?;
-        let m0 = p0.parse_mod_items(&token::Eof, mod_inner_lo)?;
+        let file = self.sess.codemap().span_to_filename(mod_inner_lo);
+        let parse_mod = || -> PResult<'a, _> {
+            let mod_attrs = p0.parse_inner_attributes()?;
+            p0.apply_syntax_variant(&mod_attrs);
+            let m0 = p0.parse_mod_items(&token::Eof, mod_inner_lo)?;
+            Ok((mod_attrs, m0))
+        };
+        let (mod_attrs, m0) = self.sess.time_parse_phase(&file, ParsePhase::Parsing, parse_mod)?;
+        self.captured_groups.extend(p0.captured_groups.drain(..));
+        if p0.events.is_some() {
+            self.events = p0.events.take();
+        }
         self.sess.included_mod_stack.borrow_mut().pop();
         Ok((ast::ItemKind::Mod(m0), mod_attrs))
     }
@@ -6046,6 +7089,7 @@ impl<'a> Parser<'a> {
         generics.where_clause = self.parse_where_clause()?;
         let hi = self.span;
         self.expect(&token::Semi)?;
+        self.check_naming(NameKind::Fn, ident, lo.to(hi));
         Ok(ast::ForeignItem {
             ident,
             attrs,
@@ -6066,6 +7110,7 @@ impl<'a> Parser<'a> {
         let ty = self.parse_ty()?;
         let hi = self.span;
         self.expect(&token::Semi)?;
+        self.check_naming(NameKind::Const, ident, lo.to(hi));
         Ok(ForeignItem {
             ident,
             attrs,
@@ -6084,6 +7129,7 @@ impl<'a> Parser<'a> {
         let ident = self.parse_ident()?;
         let hi = self.span;
         self.expect(&token::Semi)?;
+        self.check_naming(NameKind::Type, ident, lo.to(hi));
         Ok(ast::ForeignItem {
             ident: ident,
             attrs: attrs,
@@ -6145,7 +7191,7 @@ impl<'a> Parser<'a> {
         attrs.extend(self.parse_inner_attributes()?);
 
         let mut foreign_items = vec![];
-        while let Some(item) = self.parse_foreign_item()? {
+        while let Some(item) = self.node(NodeKind::ForeignItem, |p| p.parse_foreign_item())? {
             foreign_items.push(item);
         }
         self.expect(&token::CloseDelim(token::Brace))?;
@@ -6160,6 +7206,9 @@ impl<'a> Parser<'a> {
     }
 
     /// Parse type Foo = Bar;
+    ///
+    /// Parses the following grammar:
+    ///     ItemTy = "type" Ident [Generics] [WhereClause] "=" Ty ";"
     fn parse_item_type(&mut self) -> PResult<'a, ItemInfo> {
         let ident = self.parse_ident()?;
         let mut tps = self.parse_generics()?;
@@ -6294,7 +7343,23 @@ impl<'a> Parser<'a> {
 
         let lo = self.span;
 
-        let visibility = self.parse_visibility(false)?;
+        let mut visibility = self.parse_visibility(false)?;
+
+        if let Some((keyword, extension)) = self.item_extension() {
+            self.bump(); // `keyword`
+            let (ident, item_) = self.parse_item_extension(keyword, &*extension)?;
+            let prev_span = self.prev_span;
+            let item = self.mk_item(lo.to(prev_span), ident, item_, visibility, attrs);
+            return Ok(Some(item));
+        }
+
+        let allow_pub = match visibility {
+            Visibility::Inherited => true,
+            _ => false,
+        };
+        if self.recover_misspelled_keyword(&[ITEM_KEYWORDS], allow_pub) {
+            visibility = Visibility::Public;
+        }
 
         if self.eat_keyword(keywords::Use) {
             // USE ITEM
@@ -6319,10 +7384,9 @@ impl<'a> Parser<'a> {
                 // EXTERN FUNCTION ITEM
                 let fn_span = self.prev_span;
                 let abi = opt_abi.unwrap_or(Abi::C);
-                let (ident, item_, extra_attrs) =
-                    self.parse_item_fn(Unsafety::Normal,
-                                       respan(fn_span, Constness::NotConst),
-                                       abi)?;
+                let (ident, item_, extra_attrs) = self.in_context("function", |this| {
+                    this.parse_item_fn(Unsafety::Normal, respan(fn_span, Constness::NotConst), abi)
+                })?;
                 let prev_span = self.prev_span;
                 let item = self.mk_item(lo.to(prev_span),
                                         ident,
@@ -6362,10 +7426,9 @@ impl<'a> Parser<'a> {
                 // CONST FUNCTION ITEM
                 let unsafety = self.parse_unsafety();
                 self.bump();
-                let (ident, item_, extra_attrs) =
-                    self.parse_item_fn(unsafety,
-                                       respan(const_span, Constness::Const),
-                                       Abi::Rust)?;
+                let (ident, item_, extra_attrs) = self.in_context("function", |this| {
+                    this.parse_item_fn(unsafety, respan(const_span, Constness::Const), Abi::Rust)
+                })?;
                 let prev_span = self.prev_span;
                 let item = self.mk_item(lo.to(prev_span),
                                         ident,
@@ -6405,7 +7468,7 @@ impl<'a> Parser<'a> {
                 IsAuto::Yes
             };
             let (ident, item_, extra_attrs) =
-                self.parse_item_trait(is_auto, Unsafety::Unsafe)?;
+                self.in_context("trait", |this| this.parse_item_trait(is_auto, Unsafety::Unsafe))?;
             let prev_span = self.prev_span;
             let item = self.mk_item(lo.to(prev_span),
                                     ident,
@@ -6417,15 +7480,15 @@ impl<'a> Parser<'a> {
         if self.check_keyword(keywords::Impl) ||
            self.check_keyword(keywords::Unsafe) &&
                 self.look_ahead(1, |t| t.is_keyword(keywords::Impl)) ||
-           self.check_keyword(keywords::Default) &&
-                self.look_ahead(1, |t| t.is_keyword(keywords::Impl)) ||
-           self.check_keyword(keywords::Default) &&
-                self.look_ahead(1, |t| t.is_keyword(keywords::Unsafe)) {
+           self.check_contextual_keyword(keywords::Default.name()) &&
+                self.look_ahead(1, |t| t.is_keyword(keywords::Impl) ||
+                                       t.is_keyword(keywords::Unsafe)) {
             // IMPL ITEM
             let defaultness = self.parse_defaultness();
             let unsafety = self.parse_unsafety();
             self.expect_keyword(keywords::Impl)?;
-            let (ident, item, extra_attrs) = self.parse_item_impl(unsafety, defaultness)?;
+            let (ident, item, extra_attrs) =
+                self.in_context("impl", |this| this.parse_item_impl(unsafety, defaultness))?;
             let span = lo.to(self.prev_span);
             return Ok(Some(self.mk_item(span, ident, item, visibility,
                                         maybe_append(attrs, extra_attrs))));
@@ -6434,10 +7497,10 @@ impl<'a> Parser<'a> {
             // FUNCTION ITEM
             self.bump();
             let fn_span = self.prev_span;
-            let (ident, item_, extra_attrs) =
-                self.parse_item_fn(Unsafety::Normal,
-                                   respan(fn_span, Constness::NotConst),
-                                   Abi::Rust)?;
+            let (ident, item_, extra_attrs) = self.in_context("function", |this| {
+                let constness = respan(fn_span, Constness::NotConst);
+                this.parse_item_fn(Unsafety::Normal, constness, Abi::Rust)
+            })?;
             let prev_span = self.prev_span;
             let item = self.mk_item(lo.to(prev_span),
                                     ident,
@@ -6457,10 +7520,9 @@ impl<'a> Parser<'a> {
             };
             self.expect_keyword(keywords::Fn)?;
             let fn_span = self.prev_span;
-            let (ident, item_, extra_attrs) =
-                self.parse_item_fn(Unsafety::Unsafe,
-                                   respan(fn_span, Constness::NotConst),
-                                   abi)?;
+            let (ident, item_, extra_attrs) = self.in_context("function", |this| {
+                this.parse_item_fn(Unsafety::Unsafe, respan(fn_span, Constness::NotConst), abi)
+            })?;
             let prev_span = self.prev_span;
             let item = self.mk_item(lo.to(prev_span),
                                     ident,
@@ -6494,7 +7556,8 @@ impl<'a> Parser<'a> {
         }
         if self.eat_keyword(keywords::Enum) {
             // ENUM ITEM
-            let (ident, item_, extra_attrs) = self.parse_item_enum()?;
+            let (ident, item_, extra_attrs) =
+                self.in_context("enum", |this| this.parse_item_enum())?;
             let prev_span = self.prev_span;
             let item = self.mk_item(lo.to(prev_span),
                                     ident,
@@ -6504,8 +7567,7 @@ impl<'a> Parser<'a> {
             return Ok(Some(item));
         }
         if self.check_keyword(keywords::Trait)
-            || (self.check_keyword(keywords::Auto)
-                && self.look_ahead(1, |t| t.is_keyword(keywords::Trait)))
+            || self.check_contextual_keyword(keywords::Auto.name())
         {
             let is_auto = if self.eat_keyword(keywords::Trait) {
                 IsAuto::No
@@ -6516,7 +7578,7 @@ impl<'a> Parser<'a> {
             };
             // TRAIT ITEM
             let (ident, item_, extra_attrs) =
-                self.parse_item_trait(is_auto, Unsafety::Normal)?;
+                self.in_context("trait", |this| this.parse_item_trait(is_auto, Unsafety::Normal))?;
             let prev_span = self.prev_span;
             let item = self.mk_item(lo.to(prev_span),
                                     ident,
@@ -6527,7 +7589,8 @@ impl<'a> Parser<'a> {
         }
         if self.eat_keyword(keywords::Struct) {
             // STRUCT ITEM
-            let (ident, item_, extra_attrs) = self.parse_item_struct()?;
+            let (ident, item_, extra_attrs) =
+                self.in_context("struct", |this| this.parse_item_struct())?;
             let prev_span = self.prev_span;
             let item = self.mk_item(lo.to(prev_span),
                                     ident,
@@ -6539,7 +7602,8 @@ impl<'a> Parser<'a> {
         if self.is_union_item() {
             // UNION ITEM
             self.bump();
-            let (ident, item_, extra_attrs) = self.parse_item_union()?;
+            let (ident, item_, extra_attrs) =
+                self.in_context("union", |this| this.parse_item_union())?;
             let prev_span = self.prev_span;
             let item = self.mk_item(lo.to(prev_span),
                                     ident,
@@ -6640,12 +7704,34 @@ impl<'a> Parser<'a> {
             return Ok(Some(self.parse_item_foreign_type(visibility, lo, attrs)?));
         }
 
-        // FIXME #5668: this will occur for a macro invocation:
-        match self.parse_macro_use_or_failure(attrs, true, false, lo, visibility)? {
-            Some(item) => {
-                return Err(self.span_fatal(item.span, "macros cannot expand to foreign items"));
+        // FOREIGN MACRO INVOCATION
+        if self.token.is_path_start() {
+            let prev_span = self.prev_span;
+            let mac_lo = self.span;
+            let pth = self.parse_path(PathStyle::Mod)?;
+            self.expect(&token::Not)?;
+            self.complain_if_pub_macro(&visibility, prev_span);
+
+            // eat a matched-delimiter token tree:
+            let (delim, tts) = self.expect_delimited_token_tree()?;
+            if delim != token::Brace {
+                self.expect(&token::Semi)?;
             }
-            None => Ok(None)
+
+            let mac = self.mk_mac(mac_lo.to(self.prev_span), pth, tts);
+            return Ok(Some(ForeignItem {
+                ident: keywords::Invalid.ident(),
+                attrs,
+                node: ForeignItemKind::Macro(mac),
+                id: ast::DUMMY_NODE_ID,
+                span: lo.to(self.prev_span),
+                vis: visibility,
+            }));
+        }
+
+        match self.parse_macro_use_or_failure(attrs, false, false, lo, visibility)? {
+            Some(_) => unreachable!(),
+            None => Ok(None),
         }
     }
 
@@ -6690,7 +7776,7 @@ impl<'a> Parser<'a> {
             }
 
             let hi = self.prev_span;
-            let mac = respan(mac_lo.to(hi), Mac_ { path: pth, tts: tts });
+            let mac = self.mk_mac(mac_lo.to(hi), pth, tts);
             let item = self.mk_item(lo.to(hi), id, ItemKind::Mac(mac), visibility, attrs);
             return Ok(Some(item));
         }
@@ -6709,6 +7795,59 @@ impl<'a> Parser<'a> {
         Ok(None)
     }
 
+    /// If the current token is a contextual keyword registered as an item
+    /// extension and is followed by an identifier, returns the extension.
+    fn item_extension(&self) -> Option<(ast::Name, Rc<parse::ItemExtension>)> {
+        let ident = match self.token {
+            token::Ident(ident) if !self.token.is_reserved_ident() => ident,
+            _ => return None,
+        };
+        if !self.look_ahead(1, |t| t.is_ident()) {
+            return None;
+        }
+        self.sess.variant_item_extension(self.syntax_variant, ident.name)
+            .map(|extension| (ident.name, extension))
+    }
+
+    /// Parses the remainder of an extension item, the keyword having already
+    /// been eaten. The token trees up to and including the first `;` or
+    /// brace-delimited group are handed to `extension` through a sub-parser.
+    fn parse_item_extension(&mut self,
+                            keyword: ast::Name,
+                            extension: &parse::ItemExtension)
+                            -> PResult<'a, (Ident, ItemKind)> {
+        let mut tts = Vec::new();
+        loop {
+            match self.token {
+                token::Semi | token::OpenDelim(token::Brace) => {
+                    tts.push(self.parse_token_tree());
+                    break;
+                }
+                token::CloseDelim(_) | token::Eof => {
+                    self.expect_one_of(&[], &[token::Semi, token::OpenDelim(token::Brace)])?;
+                }
+                _ => tts.push(self.parse_token_tree()),
+            }
+        }
+
+        let tokens: TokenStream = tts.into_iter().collect();
+        let mut parser = Parser::new(self.sess,
+                                     tokens.clone(),
+                                     Some(self.directory.clone()),
+                                     false,
+                                     self.desugar_doc_comments);
+        let (ident, data) = extension.parse_item(&mut parser)?;
+        if parser.token != token::Eof {
+            return parser.unexpected();
+        }
+
+        Ok((ident, ItemKind::Extension(ast::ExtensionItem {
+            keyword,
+            tokens: tokens.into(),
+            data: ast::ExtensionData(data),
+        })))
+    }
+
     fn collect_tokens<F, R>(&mut self, f: F) -> PResult<'a, (R, TokenStream)>
         where F: FnOnce(&mut Self) -> PResult<'a, R>
     {
@@ -6749,7 +7888,7 @@ impl<'a> Parser<'a> {
         let attrs = self.parse_outer_attributes()?;
 
         let (ret, tokens) = self.collect_tokens(|this| {
-            this.parse_item_(attrs, true, false)
+            this.node(NodeKind::Item, |this| this.parse_item_(attrs, true, false))
         })?;
 
         // Once we've parsed an item and recorded the tokens we got while
@@ -6823,7 +7962,7 @@ impl<'a> Parser<'a> {
                 UseTreeKind::Glob
             } else if self.check(&token::OpenDelim(token::Brace)) {
                 // `use {...};`
-                UseTreeKind::Nested(self.parse_use_tree_list()?)
+                self.parse_use_tree_list()?
             } else {
                 return self.unexpected();
             }
@@ -6843,7 +7982,7 @@ impl<'a> Parser<'a> {
                     UseTreeKind::Glob
                 } else if self.check(&token::OpenDelim(token::Brace)) {
                     // `use path::{...};`
-                    UseTreeKind::Nested(self.parse_use_tree_list()?)
+                    self.parse_use_tree_list()?
                 } else {
                     return self.unexpected();
                 }
@@ -6865,12 +8004,13 @@ impl<'a> Parser<'a> {
     /// Parse UseTreeKind::Nested(list)
     ///
     /// USE_TREE_LIST = Ø | (USE_TREE `,`)* USE_TREE [`,`]
-    fn parse_use_tree_list(&mut self) -> PResult<'a, Vec<(UseTree, ast::NodeId)>> {
-        self.parse_unspanned_seq(&token::OpenDelim(token::Brace),
-                                 &token::CloseDelim(token::Brace),
-                                 SeqSep::trailing_allowed(token::Comma), |this| {
-            Ok((this.parse_use_tree(true)?, ast::DUMMY_NODE_ID))
-        })
+    fn parse_use_tree_list(&mut self) -> PResult<'a, UseTreeKind> {
+        let (items, trailing_comma) =
+            self.parse_unspanned_comma_seq(&token::OpenDelim(token::Brace),
+                                           &token::CloseDelim(token::Brace), |this| {
+                Ok((this.parse_use_tree(true)?, ast::DUMMY_NODE_ID))
+            })?;
+        Ok(UseTreeKind::Nested(items, trailing_comma))
     }
 
     fn parse_rename(&mut self) -> PResult<'a, Option<Ident>> {
@@ -6884,14 +8024,69 @@ impl<'a> Parser<'a> {
     /// Parses a source module as a crate. This is the main
     /// entry point for the parser.
     pub fn parse_crate_mod(&mut self) -> PResult<'a, Crate> {
+        let krate = self.with_ice_report(|this| this.parse_crate_mod_())?;
+        if self.sess.check_duplicate_names {
+            duplicates::report_duplicates(&krate, &self.sess.span_diagnostic);
+        }
+        Ok(krate)
+    }
+
+    fn parse_crate_mod_(&mut self) -> PResult<'a, Crate> {
         let lo = self.span;
+        let attrs = self.parse_inner_attributes()?;
+        self.apply_syntax_variant(&attrs);
         Ok(ast::Crate {
-            attrs: self.parse_inner_attributes()?,
+            attrs,
             module: self.parse_mod_items(&token::Eof, lo)?,
             span: lo.to(self.span),
         })
     }
 
+    /// Selects the syntax variant named by a `#![syntax_variant("...")]`
+    /// among the inner attributes of a file, enabling its item extensions for
+    /// the rest of the file and recording it on the file's `FileMap`.
+    fn apply_syntax_variant(&mut self, attrs: &[Attribute]) {
+        let mut selected: Option<(ast::Name, Span)> = None;
+        for attr in attrs.iter().filter(|attr| attr.path == "syntax_variant") {
+            let variant = attr.value_str().or_else(|| match attr.meta_item_list() {
+                Some(ref list) if list.len() == 1 => match list[0].literal() {
+                    Some(&ast::Lit { node: LitKind::Str(s, _), .. }) => Some(s),
+                    _ => None,
+                },
+                _ => None,
+            });
+            let variant = match variant {
+                Some(variant) => variant,
+                None => {
+                    self.diagnostic()
+                        .struct_span_err(attr.span, "malformed `syntax_variant` attribute")
+                        .help("use `#![syntax_variant(\"name\")]`")
+                        .emit();
+                    continue
+                }
+            };
+            if let Some((_, first_span)) = selected {
+                self.diagnostic()
+                    .struct_span_err(attr.span, "a file can only select one syntax variant")
+                    .span_label(first_span, "variant first selected here")
+                    .emit();
+                continue
+            }
+            if !self.sess.is_known_syntax_variant(variant) {
+                let msg = format!("unknown syntax variant `{}`", variant);
+                self.diagnostic().struct_span_err(attr.span, &msg).emit();
+                continue
+            }
+            selected = Some((variant, attr.span));
+        }
+
+        if let Some((variant, span)) = selected {
+            self.syntax_variant = Some(variant);
+            let file = self.sess.codemap().lookup_char_pos(span.lo()).file;
+            file.syntax_variant.set(Some(variant));
+        }
+    }
+
     pub fn parse_optional_str(&mut self) -> Option<(Symbol, ast::StrStyle, Option<ast::Name>)> {
         let ret = match self.token {
             token::Literal(token::Str_(s), suf) => (s, ast::StrStyle::Cooked, suf),
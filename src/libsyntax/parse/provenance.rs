@@ -0,0 +1,93 @@
+// Copyright 2018 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! How a tree was parsed, for caching layers which store parsed crates.
+//!
+//! The `*_with_provenance` entry points return a `ParseProvenance` alongside
+//! the crate. Before reusing a stored crate, a cache computes the provenance
+//! of the parse it would replace with `ParseProvenance::new` and compares
+//! the two: a stored crate may only be reused if they are equal (and the
+//! source has not changed, which is not covered here).
+//!
+//! Item extensions, contextual keywords and the module path policy (see
+//! `ParseSess::set_mod_path_policy`) registered with the session are not
+//! part of the provenance, as they cannot be compared, so caches must not be
+//! shared between sessions which register different ones.
+
+use codemap::CrlfPolicy;
+use parse::ParseSess;
+use syntax_pos::FileName;
+use version_info::VERSION_INFO;
+
+/// The parser entry point which produced a tree.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, RustcEncodable, RustcDecodable)]
+pub enum EntryPoint {
+    /// `parse_crate_from_file`, which also parses out-of-line modules.
+    CrateFromFile,
+    /// `parse_crate_from_source_str` or `parse_crate_from_str`, which do not.
+    CrateFromSource,
+}
+
+/// The options of the session which change the tree the parser produces.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, RustcEncodable, RustcDecodable)]
+pub struct ParseOptions {
+    pub reject_non_rust_input: bool,
+    pub preserve_unknown_suffixes: bool,
+    pub placeholder_chars: Vec<char>,
+    pub const_generics: bool,
+    pub skip_control_chars: bool,
+    pub allow_frontmatter: bool,
+    pub max_delim_depth: Option<usize>,
+    pub max_tokens: Option<usize>,
+    /// The CRLF policy of the codemap, see `CodeMap::set_crlf_policy`.
+    pub crlf_policy: CrlfPolicy,
+}
+
+/// Metadata describing how a tree was parsed.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, RustcEncodable, RustcDecodable)]
+pub struct ParseProvenance {
+    pub entry_point: EntryPoint,
+    /// The name of the source, e.g. the path of the crate root.
+    pub source_name: FileName,
+    /// The configuration `mod` declarations were resolved in, sorted.
+    pub cfg: Vec<(String, Option<String>)>,
+    pub options: ParseOptions,
+    /// The version of the parser, see `VersionInfo::version`.
+    pub version: Option<String>,
+}
+
+impl ParseProvenance {
+    /// The provenance of a parse of `source_name` from `entry_point` in `sess`.
+    pub fn new(entry_point: EntryPoint, source_name: FileName, sess: &ParseSess)
+               -> ParseProvenance {
+        let mut cfg = sess.config.iter().map(|&(name, value)| {
+            (name.to_string(), value.map(|value| value.to_string()))
+        }).collect::<Vec<_>>();
+        cfg.sort();
+
+        ParseProvenance {
+            entry_point,
+            source_name,
+            cfg,
+            options: ParseOptions {
+                reject_non_rust_input: sess.reject_non_rust_input,
+                preserve_unknown_suffixes: sess.preserve_unknown_suffixes,
+                placeholder_chars: sess.placeholder_chars.clone(),
+                const_generics: sess.const_generics,
+                skip_control_chars: sess.skip_control_chars,
+                allow_frontmatter: sess.allow_frontmatter,
+                max_delim_depth: sess.max_delim_depth,
+                max_tokens: sess.max_tokens,
+                crlf_policy: sess.codemap().crlf_policy(),
+            },
+            version: VERSION_INFO.version.map(String::from),
+        }
+    }
+}
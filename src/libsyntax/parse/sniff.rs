@@ -0,0 +1,103 @@
+// Copyright 2018 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Cheap heuristics for recognizing input that is obviously not Rust source.
+//!
+//! Feeding an HTML page or a binary blob to the lexer produces thousands of
+//! errors. When `ParseSess::reject_non_rust_input` is set, crate parsing
+//! first runs `sniff_non_rust` and reports a single error instead.
+
+use std::fmt;
+
+/// The kind of non-Rust input that was detected.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NonRustInput {
+    /// NUL bytes or a high proportion of control characters.
+    Binary,
+    /// An HTML or XML document.
+    Markup,
+    /// A JSON object or array.
+    Json,
+}
+
+impl fmt::Display for NonRustInput {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            NonRustInput::Binary => "the input looks like binary data",
+            NonRustInput::Markup => "the input looks like an HTML or XML document",
+            NonRustInput::Json => "the input looks like JSON",
+        })
+    }
+}
+
+/// Only this many bytes from the start of the input are examined.
+const SNIFF_LEN: usize = 4096;
+
+/// Returns the kind of input detected if `src` is obviously not Rust source.
+///
+/// This errs on the side of accepting the input: anything it lets through is
+/// simply handed to the lexer as usual.
+pub fn sniff_non_rust(src: &str) -> Option<NonRustInput> {
+    let mut end = ::std::cmp::min(src.len(), SNIFF_LEN);
+    while !src.is_char_boundary(end) {
+        end -= 1;
+    }
+    let head = &src[..end];
+
+    let control = head.chars().filter(|&c| {
+        c.is_control() && c != '\n' && c != '\r' && c != '\t' && c != '\x0c'
+    }).count();
+    if head.contains('\0') || control * 10 > head.chars().count() {
+        return Some(NonRustInput::Binary);
+    }
+
+    let text = head.trim_left_matches('\u{feff}').trim_left();
+    let lower = text.chars().take(16).collect::<String>().to_lowercase();
+    if lower.starts_with("<!doctype") || lower.starts_with("<html") ||
+       lower.starts_with("<?xml") {
+        return Some(NonRustInput::Markup);
+    }
+
+    // Neither `{` nor `[` can start a Rust source file, but they can start
+    // JSON. Require a key or element to follow so that a stray brace still
+    // produces the normal parse error.
+    let mut chars = text.chars();
+    match chars.next() {
+        Some('{') | Some('[') => {
+            match chars.as_str().trim_left().chars().next() {
+                Some('"') | Some('{') | Some('[') => Some(NonRustInput::Json),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_rust() {
+        assert_eq!(sniff_non_rust(""), None);
+        assert_eq!(sniff_non_rust("\u{feff}fn main() {}\n"), None);
+        assert_eq!(sniff_non_rust("#![no_std]\n// <html>\n"), None);
+        assert_eq!(sniff_non_rust("{ }"), None);
+    }
+
+    #[test]
+    fn rejects_obvious_non_rust() {
+        assert_eq!(sniff_non_rust("ELF\0\0\u{1}"), Some(NonRustInput::Binary));
+        assert_eq!(sniff_non_rust("  <!DOCTYPE html>\n<html>"), Some(NonRustInput::Markup));
+        assert_eq!(sniff_non_rust("<?xml version=\"1.0\"?>"), Some(NonRustInput::Markup));
+        assert_eq!(sniff_non_rust("{\n  \"name\": \"x\"\n}"), Some(NonRustInput::Json));
+        assert_eq!(sniff_non_rust("[[1, 2]]"), Some(NonRustInput::Json));
+    }
+}
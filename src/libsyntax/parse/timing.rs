@@ -0,0 +1,130 @@
+// Copyright 2018 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Optional timing of the phases of parsing.
+//!
+//! Timing is off by default. When enabled with
+//! `ParseSess::enable_parse_timings`, the time spent in each phase is
+//! accumulated both in total and per source file, and can be retrieved with
+//! `ParseSess::take_parse_timings`.
+//!
+//! All times are exclusive: the time spent parsing a module does not include
+//! the time spent loading, lexing and parsing the out-of-line modules it
+//! declares, which are attributed to their own files instead.
+
+use syntax_pos::FileName;
+
+use std::ops::AddAssign;
+use std::time::Duration;
+
+/// A phase of parsing.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ParsePhase {
+    /// Reading a source file from disk.
+    FileLoading,
+    /// Producing tokens from source text.
+    Lexing,
+    /// Grouping tokens into token trees, excluding the lexing itself.
+    TokenTrees,
+    /// Parsing token trees into an AST.
+    Parsing,
+}
+
+/// The time spent in each phase.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PhaseTimes {
+    pub file_loading: Duration,
+    pub lexing: Duration,
+    pub token_trees: Duration,
+    pub parsing: Duration,
+}
+
+impl PhaseTimes {
+    pub fn get(&self, phase: ParsePhase) -> Duration {
+        match phase {
+            ParsePhase::FileLoading => self.file_loading,
+            ParsePhase::Lexing => self.lexing,
+            ParsePhase::TokenTrees => self.token_trees,
+            ParsePhase::Parsing => self.parsing,
+        }
+    }
+
+    fn get_mut(&mut self, phase: ParsePhase) -> &mut Duration {
+        match phase {
+            ParsePhase::FileLoading => &mut self.file_loading,
+            ParsePhase::Lexing => &mut self.lexing,
+            ParsePhase::TokenTrees => &mut self.token_trees,
+            ParsePhase::Parsing => &mut self.parsing,
+        }
+    }
+
+    /// The sum of all phases.
+    pub fn total(&self) -> Duration {
+        self.file_loading + self.lexing + self.token_trees + self.parsing
+    }
+}
+
+impl AddAssign for PhaseTimes {
+    fn add_assign(&mut self, other: PhaseTimes) {
+        self.file_loading += other.file_loading;
+        self.lexing += other.lexing;
+        self.token_trees += other.token_trees;
+        self.parsing += other.parsing;
+    }
+}
+
+/// A report of the time spent parsing.
+#[derive(Clone, Debug, Default)]
+pub struct ParseTimings {
+    /// Times summed over all files.
+    pub total: PhaseTimes,
+    /// Times for each file, in the order in which the files were first seen.
+    pub files: Vec<(FileName, PhaseTimes)>,
+    /// For each phase currently being timed, the time spent in phases nested
+    /// within it so far.
+    nested: Vec<Duration>,
+}
+
+impl ParseTimings {
+    /// Records `time` spent in `phase` for `file`.
+    pub fn record(&mut self, file: &FileName, phase: ParsePhase, time: Duration) {
+        *self.total.get_mut(phase) += time;
+        let idx = match self.files.iter().position(|&(ref name, _)| name == file) {
+            Some(idx) => idx,
+            None => {
+                self.files.push((file.clone(), PhaseTimes::default()));
+                self.files.len() - 1
+            }
+        };
+        *self.files[idx].1.get_mut(phase) += time;
+        if let Some(outer) = self.nested.last_mut() {
+            *outer += time;
+        }
+    }
+
+    /// Returns the times recorded for `file`, if any.
+    pub fn file(&self, file: &FileName) -> Option<&PhaseTimes> {
+        self.files.iter().find(|&&(ref name, _)| name == file).map(|&(_, ref times)| times)
+    }
+
+    pub(super) fn start_phase(&mut self) {
+        self.nested.push(Duration::new(0, 0));
+    }
+
+    /// Ends the innermost phase, which took `elapsed` in total, and records
+    /// its exclusive time.
+    pub(super) fn end_phase(&mut self, file: &FileName, phase: ParsePhase, elapsed: Duration) {
+        let nested = self.nested.pop().unwrap_or(Duration::new(0, 0));
+        self.record(file, phase, elapsed.checked_sub(nested).unwrap_or(Duration::new(0, 0)));
+        if let Some(outer) = self.nested.last_mut() {
+            *outer += nested;
+        }
+    }
+}
@@ -91,6 +91,79 @@ impl Lit {
     }
 }
 
+/// The classification of a keyword.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum KeywordKind {
+    /// Identifiers reserved for internal use by the compiler, like `$crate`.
+    Special,
+    /// Keywords used in the language, like `fn`.
+    Strict,
+    /// Keywords reserved for possible future use, like `become`.
+    Reserved,
+    /// Identifiers that are keywords only in certain contexts, like `union`.
+    Weak,
+}
+
+impl KeywordKind {
+    pub fn of(keyword: keywords::Keyword) -> KeywordKind {
+        let token = Ident(keyword.ident());
+        if token.is_special_ident() {
+            KeywordKind::Special
+        } else if token.is_used_keyword() {
+            KeywordKind::Strict
+        } else if token.is_unused_keyword() {
+            KeywordKind::Reserved
+        } else {
+            KeywordKind::Weak
+        }
+    }
+}
+
+/// Returns every keyword with its classification, in declaration order.
+/// The empty identifier used internally for error recovery is not included.
+pub fn all_keywords() -> Vec<(keywords::Keyword, KeywordKind)> {
+    keywords::ALL.iter()
+        .filter(|&&kw| kw != keywords::Invalid)
+        .map(|&kw| (kw, KeywordKind::of(kw)))
+        .collect()
+}
+
+/// Returns every punctuation token, i.e. operators and delimiters, with its
+/// source text. Invisible delimiters are not included. `DotEq` is, although
+/// the lexer never produces it, because procedural macros can.
+pub fn punctuation_tokens() -> Vec<(Token, &'static str)> {
+    vec![
+        (Eq, "="), (Lt, "<"), (Le, "<="), (EqEq, "=="), (Ne, "!="), (Ge, ">="), (Gt, ">"),
+        (AndAnd, "&&"), (OrOr, "||"), (Not, "!"), (Tilde, "~"),
+        (BinOp(Plus), "+"), (BinOp(Minus), "-"), (BinOp(Star), "*"), (BinOp(Slash), "/"),
+        (BinOp(Percent), "%"), (BinOp(Caret), "^"), (BinOp(And), "&"), (BinOp(Or), "|"),
+        (BinOp(Shl), "<<"), (BinOp(Shr), ">>"),
+        (BinOpEq(Plus), "+="), (BinOpEq(Minus), "-="), (BinOpEq(Star), "*="),
+        (BinOpEq(Slash), "/="), (BinOpEq(Percent), "%="), (BinOpEq(Caret), "^="),
+        (BinOpEq(And), "&="), (BinOpEq(Or), "|="), (BinOpEq(Shl), "<<="), (BinOpEq(Shr), ">>="),
+        (At, "@"), (Dot, "."), (DotDot, ".."), (DotDotDot, "..."), (DotDotEq, "..="),
+        (DotEq, ".="),
+        (Comma, ","), (Semi, ";"), (Colon, ":"), (ModSep, "::"), (RArrow, "->"),
+        (LArrow, "<-"), (FatArrow, "=>"), (Pound, "#"), (Dollar, "$"), (Question, "?"),
+        (Underscore, "_"),
+        (OpenDelim(Paren), "("), (CloseDelim(Paren), ")"),
+        (OpenDelim(Bracket), "["), (CloseDelim(Bracket), "]"),
+        (OpenDelim(Brace), "{"), (CloseDelim(Brace), "}"),
+    ]
+}
+
+/// The kinds of literal tokens, each with its name and an example.
+pub const LITERAL_KINDS: &'static [(&'static str, &'static str)] = &[
+    ("byte", "b'a'"),
+    ("char", "'a'"),
+    ("integer", "1u8"),
+    ("float", "1.5e3f64"),
+    ("string", "\"a\""),
+    ("raw string", "r#\"a\"#"),
+    ("byte string", "b\"a\""),
+    ("raw byte string", "br#\"a\"#"),
+];
+
 fn ident_can_begin_expr(ident: ast::Ident) -> bool {
     let ident_token: Token = Ident(ident);
 
@@ -176,6 +249,11 @@ pub enum Token {
     Ident(ast::Ident),
     Underscore,
     Lifetime(ast::Ident),
+    /// A placeholder inserted into the source by an editor, e.g. at the cursor
+    /// to request completions. Only lexed when enabled with
+    /// `ParseSess::placeholder_chars`. The parser reads it as an identifier
+    /// with the same name.
+    Placeholder(ast::Name),
 
     // The `LazyTokenStream` is a pure function of the `Nonterminal`,
     // and so the `LazyTokenStream` can be ignored by Eq, Hash, etc.
@@ -202,6 +280,43 @@ impl Token {
         Token::Interpolated(Rc::new((nt, LazyTokenStream::new())))
     }
 
+    /// Returns an adapter that formats the token as its canonical source
+    /// text, e.g. `1u8`, `'a` or `/// doc`, for use in user-facing messages.
+    /// Interpolated tokens are pretty-printed.
+    pub fn display(&self) -> TokenDisplay {
+        TokenDisplay(self)
+    }
+
+    /// Returns the kind of the token without its contents, for machine-readable
+    /// descriptions: the variant name for punctuation, like `Semi` or
+    /// `BinOp(Plus)`, `Keyword(fn)` for reserved identifiers, and `Ident`,
+    /// `Lifetime`, `Literal` and so on for the other tokens.
+    pub fn kind_name(&self) -> String {
+        match *self {
+            Ident(ident) if self.is_reserved_ident() => format!("Keyword({})", ident.name),
+            Ident(..) => "Ident".to_string(),
+            Lifetime(..) => "Lifetime".to_string(),
+            Literal(..) => "Literal".to_string(),
+            Placeholder(..) => "Placeholder".to_string(),
+            Interpolated(..) => "Interpolated".to_string(),
+            DocComment(..) => "DocComment".to_string(),
+            Shebang(..) => "Shebang".to_string(),
+            _ => format!("{:?}", self),
+        }
+    }
+
+    /// Returns the length in characters of the token's canonical source text.
+    /// This is exact for tokens lexed from source, other than whitespace and
+    /// comments; interpolated tokens are measured by pretty-printing them.
+    /// Invisible delimiters and `Eof` have a length of zero.
+    pub fn approx_len(&self) -> usize {
+        match *self {
+            OpenDelim(NoDelim) | CloseDelim(NoDelim) | Eof => 0,
+            Ident(ident) | Lifetime(ident) => ident.name.as_str().chars().count(),
+            _ => pprust::token_to_string(self).chars().count(),
+        }
+    }
+
     /// Returns `true` if the token starts with '>'.
     pub fn is_like_gt(&self) -> bool {
         match *self {
@@ -442,11 +557,40 @@ impl Token {
             DotDotEq | Comma | Semi | ModSep | RArrow | LArrow | FatArrow | Pound | Dollar |
             Question | OpenDelim(..) | CloseDelim(..) | Underscore => return None,
 
-            Literal(..) | Ident(..) | Lifetime(..) | Interpolated(..) | DocComment(..) |
-            Whitespace | Comment | Shebang(..) | Eof => return None,
+            Literal(..) | Ident(..) | Lifetime(..) | Placeholder(..) | Interpolated(..) |
+            DocComment(..) | Whitespace | Comment | Shebang(..) | Eof => return None,
         })
     }
 
+    /// Splits a multi-character operator into single-character tokens, undoing
+    /// `glue`. Any other token is returned as is.
+    pub fn unglue(&self) -> Vec<Token> {
+        match *self {
+            EqEq => vec![Eq, Eq],
+            Le => vec![Lt, Eq],
+            Ne => vec![Not, Eq],
+            Ge => vec![Gt, Eq],
+            AndAnd => vec![BinOp(And), BinOp(And)],
+            OrOr => vec![BinOp(Or), BinOp(Or)],
+            BinOp(Shl) => vec![Lt, Lt],
+            BinOp(Shr) => vec![Gt, Gt],
+            BinOpEq(op) => {
+                let mut parts = BinOp(op).unglue();
+                parts.push(Eq);
+                parts
+            }
+            DotDot => vec![Dot, Dot],
+            DotDotDot => vec![Dot, Dot, Dot],
+            DotEq => vec![Dot, Eq],
+            DotDotEq => vec![Dot, Dot, Eq],
+            ModSep => vec![Colon, Colon],
+            RArrow => vec![BinOp(Minus), Gt],
+            LArrow => vec![Lt, BinOp(Minus)],
+            FatArrow => vec![Eq, Gt],
+            _ => vec![self.clone()],
+        }
+    }
+
     /// Returns tokens that are likely to be typed accidentally instead of the current token.
     /// Enables better error recovery when the wrong token is found.
     pub fn similar_tokens(&self) -> Option<Vec<Token>> {
@@ -519,6 +663,15 @@ impl Token {
     }
 }
 
+/// Formats a token as its canonical source text, see `Token::display`.
+pub struct TokenDisplay<'a>(&'a Token);
+
+impl<'a> fmt::Display for TokenDisplay<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&pprust::token_to_string(self.0))
+    }
+}
+
 #[derive(Clone, RustcEncodable, RustcDecodable, PartialEq, Eq, Hash)]
 /// For interpolation during macro expansion.
 pub enum Nonterminal {
@@ -572,7 +725,7 @@ impl fmt::Debug for Nonterminal {
 pub fn is_op(tok: &Token) -> bool {
     match *tok {
         OpenDelim(..) | CloseDelim(..) | Literal(..) | DocComment(..) |
-        Ident(..) | Underscore | Lifetime(..) | Interpolated(..) |
+        Ident(..) | Underscore | Lifetime(..) | Placeholder(..) | Interpolated(..) |
         Whitespace | Comment | Shebang(..) | Eof => false,
         _ => true,
     }
@@ -0,0 +1,68 @@
+// Copyright 2018 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A flat table of the tokens of a file, for editors which need to find the
+//! token at a cursor position without walking nested token trees.
+//!
+//! Tokens are numbered in source order, which is the pre-order of the token
+//! trees with both delimiters of a `Delimited` counted as tokens. Whitespace
+//! and comments are not tokens.
+
+use std::u32;
+
+const NO_TOKEN: u32 = u32::MAX;
+
+/// The start and end byte offsets of the tokens of a file, built by
+/// `parse::filemap_to_stream_with_offsets`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TokenOffsets {
+    tokens: Vec<(u32, u32)>,
+    // The index of the token covering each byte of the file, or `NO_TOKEN`.
+    // This costs four bytes per byte of source, but makes `token_at` O(1).
+    by_offset: Vec<u32>,
+}
+
+impl TokenOffsets {
+    /// `tokens` are the offsets of the tokens in source order and `len` is
+    /// the length of the file in bytes.
+    pub fn new(tokens: Vec<(u32, u32)>, len: usize) -> TokenOffsets {
+        let mut by_offset = vec![NO_TOKEN; len];
+        for (index, &(start, end)) in tokens.iter().enumerate() {
+            let end = (end as usize).min(len);
+            for slot in &mut by_offset[(start as usize).min(end)..end] {
+                *slot = index as u32;
+            }
+        }
+        TokenOffsets { tokens, by_offset }
+    }
+
+    /// The number of tokens.
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// The start and end offsets of the token with the given index.
+    pub fn token(&self, index: usize) -> Option<(usize, usize)> {
+        self.tokens.get(index).map(|&(start, end)| (start as usize, end as usize))
+    }
+
+    /// The index of the token containing the byte at `offset`, or `None` if
+    /// the byte is part of whitespace or a comment.
+    pub fn token_at(&self, offset: usize) -> Option<usize> {
+        match self.by_offset.get(offset) {
+            Some(&index) if index != NO_TOKEN => Some(index as usize),
+            _ => None,
+        }
+    }
+}
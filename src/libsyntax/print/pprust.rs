@@ -55,6 +55,20 @@ pub struct NoAnn;
 
 impl PpAnn for NoAnn {}
 
+/// How items with `#[cfg]` attributes are printed, for producing views of
+/// the source under a given configuration.
+#[derive(Clone)]
+pub enum CfgMode {
+    /// Print all items as they are.
+    Keep,
+    /// Print all items, preceding each cfg-gated item with a comment telling
+    /// whether it is active under the configuration.
+    Annotate(ast::CrateConfig),
+    /// Omit the items that are inactive under the configuration, and the
+    /// `#[cfg]` attributes of the active ones.
+    Strip(ast::CrateConfig),
+}
+
 pub struct State<'a> {
     pub s: pp::Printer<'a>,
     cm: Option<&'a CodeMap>,
@@ -63,17 +77,26 @@ pub struct State<'a> {
     cur_cmnt: usize,
     boxes: Vec<pp::Breaks>,
     ann: &'a (PpAnn+'a),
+    cfg_mode: CfgMode,
+    /// Whether to print the canonical form, see `to_canonical_string`.
+    canonical: bool,
+    /// If set, the source text of nodes not overlapping these spans is copied
+    /// from the codemap, see `set_verbatim_source`.
+    dirty_spans: Option<Vec<syntax_pos::Span>>,
 }
 
-fn rust_printer<'a>(writer: Box<Write+'a>, ann: &'a PpAnn) -> State<'a> {
+fn rust_printer<'a>(writer: Box<Write+'a>, ann: &'a PpAnn, columns: usize) -> State<'a> {
     State {
-        s: pp::mk_printer(writer, DEFAULT_COLUMNS),
+        s: pp::mk_printer(writer, columns),
         cm: None,
         comments: None,
         literals: vec![].into_iter().peekable(),
         cur_cmnt: 0,
         boxes: Vec::new(),
         ann,
+        cfg_mode: CfgMode::Keep,
+        canonical: false,
+        dirty_spans: None,
     }
 }
 
@@ -81,6 +104,10 @@ pub const INDENT_UNIT: usize = 4;
 
 pub const DEFAULT_COLUMNS: usize = 78;
 
+/// The line width of the canonical form. Unlike `DEFAULT_COLUMNS`, this is
+/// never going to change.
+pub const CANONICAL_COLUMNS: usize = 100;
+
 /// Requires you to pass an input filename and reader so that
 /// it can scan the input text for comments and literals to
 /// copy forward.
@@ -151,17 +178,70 @@ impl<'a> State<'a> {
             cur_cmnt: 0,
             boxes: Vec::new(),
             ann,
+            cfg_mode: CfgMode::Keep,
+            canonical: false,
+            dirty_spans: None,
         }
     }
 }
 
+/// Returns whether all `#[cfg]` attributes among `attrs` match `config`.
+fn cfg_attrs_active(attrs: &[ast::Attribute], config: &ast::CrateConfig) -> bool {
+    attrs.iter().filter(|attr| attr.path == "cfg").all(|attr| {
+        match attr.meta_item_list() {
+            Some(ref list) if list.len() == 1 => {
+                list[0].meta_item().map_or(false, |mi| attr::cfg_matches_config(mi, config))
+            }
+            _ => false,
+        }
+    })
+}
+
 pub fn to_string<F>(f: F) -> String where
     F: FnOnce(&mut State) -> io::Result<()>,
 {
     let mut wr = Vec::new();
     {
         let ann = NoAnn;
-        let mut printer = rust_printer(Box::new(&mut wr), &ann);
+        let mut printer = rust_printer(Box::new(&mut wr), &ann, DEFAULT_COLUMNS);
+        f(&mut printer).unwrap();
+        printer.s.eof().unwrap();
+    }
+    String::from_utf8(wr).unwrap()
+}
+
+/// Prints like `to_string`, but in a canonical form meant for hashing, which
+/// is kept stable across releases: e.g. `to_canonical_string(|s| s.print_item(item))`.
+///
+/// The canonical form is printed with the fixed line width `CANONICAL_COLUMNS`,
+/// contains no comments, and prints literals from their values rather than
+/// their source text, so raw strings are printed as ordinary strings and
+/// integers in decimal.
+pub fn to_canonical_string<F>(f: F) -> String where
+    F: FnOnce(&mut State) -> io::Result<()>,
+{
+    let mut wr = Vec::new();
+    {
+        let ann = NoAnn;
+        let mut printer = rust_printer(Box::new(&mut wr), &ann, CANONICAL_COLUMNS);
+        printer.canonical = true;
+        f(&mut printer).unwrap();
+        printer.s.eof().unwrap();
+    }
+    String::from_utf8(wr).unwrap()
+}
+
+/// Prints like `to_string`, but copies the source text of the nodes which do not
+/// overlap `dirty_spans` from `cm`, see `State::set_verbatim_source`.
+pub fn to_string_with_source<F>(cm: &CodeMap, dirty_spans: Vec<syntax_pos::Span>, f: F) -> String
+    where F: FnOnce(&mut State) -> io::Result<()>,
+{
+    let mut wr = Vec::new();
+    {
+        let ann = NoAnn;
+        let mut printer = rust_printer(Box::new(&mut wr), &ann, DEFAULT_COLUMNS);
+        printer.cm = Some(cm);
+        printer.set_verbatim_source(dirty_spans);
         f(&mut printer).unwrap();
         printer.s.eof().unwrap();
     }
@@ -253,6 +333,7 @@ pub fn token_to_string(tok: &Token) -> String {
         token::Ident(s)             => s.to_string(),
         token::Lifetime(s)          => s.to_string(),
         token::Underscore           => "_".to_string(),
+        token::Placeholder(s)       => s.to_string(),
 
         /* Other */
         token::DocComment(s)        => s.to_string(),
@@ -433,6 +514,9 @@ pub trait PrintState<'a> {
     fn cur_lit(&mut self) -> Option<&comments::Literal>;
     fn bump_lit(&mut self) -> Option<comments::Literal>;
 
+    /// Whether to print the canonical form, see `to_canonical_string`.
+    fn canonical(&self) -> bool { false }
+
     fn word_space(&mut self, w: &str) -> io::Result<()> {
         self.writer().word(w)?;
         self.writer().space()
@@ -602,6 +686,9 @@ pub trait PrintState<'a> {
             return self.writer().word(&ltrl.lit);
         }
         match lit.node {
+            ast::LitKind::Str(st, _) if self.canonical() => {
+                self.print_string(&st.as_str(), ast::StrStyle::Cooked)
+            }
             ast::LitKind::Str(st, style) => self.print_string(&st.as_str(), style),
             ast::LitKind::Byte(byte) => {
                 let mut res = String::from("b'");
@@ -615,7 +702,15 @@ pub trait PrintState<'a> {
                 res.push('\'');
                 self.writer().word(&res[..])
             }
-            ast::LitKind::Int(i, t) => {
+            ast::LitKind::Int(_, t, Some(text)) if !self.canonical() => {
+                let suffix = match t {
+                    ast::LitIntType::Signed(st) => st.ty_to_string(),
+                    ast::LitIntType::Unsigned(ut) => ut.ty_to_string(),
+                    ast::LitIntType::Unsuffixed => "",
+                };
+                self.writer().word(&format!("{}{}", text, suffix))
+            }
+            ast::LitKind::Int(i, t, _) => {
                 match t {
                     ast::LitIntType::Signed(st) => {
                         self.writer().word(&st.val_to_string(i as i128))
@@ -628,10 +723,19 @@ pub trait PrintState<'a> {
                     }
                 }
             }
-            ast::LitKind::Float(ref f, t) => {
+            ast::LitKind::Float(_, t, Some(text)) if !self.canonical() => {
+                self.writer().word(&format!("{}{}", text, t.ty_to_string()))
+            }
+            ast::LitKind::Float(ref f, t, _) => {
                 self.writer().word(&format!("{}{}", &f, t.ty_to_string()))
             }
-            ast::LitKind::FloatUnsuffixed(ref f) => self.writer().word(&f.as_str()),
+            ast::LitKind::FloatUnsuffixed(_, Some(text)) if !self.canonical() => {
+                self.writer().word(&text.as_str())
+            }
+            ast::LitKind::FloatUnsuffixed(ref f, _) => self.writer().word(&f.as_str()),
+            ast::LitKind::UnknownSuffix(lit, suf) => {
+                self.writer().word(&token_to_string(&token::Literal(lit, Some(suf))))
+            }
             ast::LitKind::Bool(val) => {
                 if val { self.writer().word("true") } else { self.writer().word("false") }
             }
@@ -865,9 +969,90 @@ impl<'a> PrintState<'a> for State<'a> {
     fn bump_lit(&mut self) -> Option<comments::Literal> {
         self.literals.next()
     }
+
+    fn canonical(&self) -> bool {
+        self.canonical
+    }
 }
 
 impl<'a> State<'a> {
+    pub fn set_cfg_mode(&mut self, cfg_mode: CfgMode) {
+        self.cfg_mode = cfg_mode;
+    }
+
+    /// Makes the printer copy the source text of items, trait and impl items,
+    /// expressions, types and patterns from the codemap, unless their span
+    /// overlaps or touches one of `dirty_spans`, or their source text is not
+    /// available. This keeps the output of refactoring tools close to the
+    /// original source: only the modified nodes and the nodes containing them
+    /// are pretty-printed. Nodes with dummy spans or spans from macro
+    /// expansions are always pretty-printed.
+    ///
+    /// The printer must have been created with a codemap.
+    pub fn set_verbatim_source(&mut self, dirty_spans: Vec<syntax_pos::Span>) {
+        assert!(self.cm.is_some(), "printing verbatim source requires a codemap");
+        self.dirty_spans = Some(dirty_spans);
+    }
+
+    /// Prints the source text of `span` and returns `true` if the node with the
+    /// span can be printed verbatim, see `set_verbatim_source`.
+    fn print_verbatim(&mut self, span: syntax_pos::Span) -> io::Result<bool> {
+        let snippet = match (self.cm, &self.dirty_spans) {
+            (Some(cm), &Some(ref dirty_spans)) => {
+                if span.is_dummy() || span.ctxt() != SyntaxContext::empty() ||
+                   dirty_spans.iter().any(|d| d.lo() <= span.hi() && span.lo() <= d.hi()) {
+                    return Ok(false);
+                }
+                match cm.span_to_snippet(span) {
+                    Ok(snippet) => snippet,
+                    Err(_) => return Ok(false),
+                }
+            }
+            _ => return Ok(false),
+        };
+        self.s.word(&snippet)?;
+        // The comments in the snippet have been printed with it.
+        while let Some(cmnt) = self.next_comment() {
+            if cmnt.pos >= span.hi() {
+                break;
+            }
+            self.cur_cmnt += 1;
+        }
+        Ok(true)
+    }
+
+    /// Returns whether the item with `attrs` is omitted under the cfg mode.
+    fn is_cfg_stripped(&self, attrs: &[ast::Attribute]) -> bool {
+        match self.cfg_mode {
+            CfgMode::Strip(ref config) => !cfg_attrs_active(attrs, config),
+            _ => false,
+        }
+    }
+
+    /// Prints the outer attributes of an item, taking the cfg mode into
+    /// account.
+    fn print_item_attributes(&mut self, attrs: &[ast::Attribute]) -> io::Result<()> {
+        if !attrs.iter().any(|attr| attr.path == "cfg") {
+            return self.print_outer_attributes(attrs);
+        }
+        let (active, strip) = match self.cfg_mode {
+            CfgMode::Keep => (None, false),
+            CfgMode::Annotate(ref config) => (Some(cfg_attrs_active(attrs, config)), false),
+            CfgMode::Strip(_) => (None, true),
+        };
+        if let Some(active) = active {
+            self.hardbreak_if_not_bol()?;
+            self.writer().word(if active { "// cfg: active" } else { "// cfg: inactive" })?;
+            self.writer().hardbreak()?;
+        }
+        if strip {
+            let attrs: Vec<_> = attrs.iter().filter(|attr| attr.path != "cfg").cloned().collect();
+            self.print_outer_attributes(&attrs)
+        } else {
+            self.print_outer_attributes(attrs)
+        }
+    }
+
     pub fn cbox(&mut self, u: usize) -> io::Result<()> {
         self.boxes.push(pp::Breaks::Consistent);
         self.s.cbox(u)
@@ -1006,6 +1191,9 @@ impl<'a> State<'a> {
 
     pub fn print_type(&mut self, ty: &ast::Ty) -> io::Result<()> {
         self.maybe_print_comment(ty.span.lo())?;
+        if self.print_verbatim(ty.span)? {
+            return Ok(());
+        }
         self.ibox(0)?;
         match ty.node {
             ast::TyKind::Slice(ref ty) => {
@@ -1079,7 +1267,7 @@ impl<'a> State<'a> {
                 self.s.word("_")?;
             }
             ast::TyKind::Err => {
-                self.s.word("?")?;
+                self.s.word("<error>")?;
             }
             ast::TyKind::ImplicitSelf => {
                 self.s.word("Self")?;
@@ -1093,9 +1281,12 @@ impl<'a> State<'a> {
 
     pub fn print_foreign_item(&mut self,
                               item: &ast::ForeignItem) -> io::Result<()> {
+        if self.is_cfg_stripped(&item.attrs) {
+            return Ok(());
+        }
         self.hardbreak_if_not_bol()?;
         self.maybe_print_comment(item.span.lo())?;
-        self.print_outer_attributes(&item.attrs)?;
+        self.print_item_attributes(&item.attrs)?;
         match item.node {
             ast::ForeignItemKind::Fn(ref decl, ref generics) => {
                 self.head("")?;
@@ -1126,6 +1317,10 @@ impl<'a> State<'a> {
                 self.end()?; // end the head-ibox
                 self.end() // end the outer cbox
             }
+            ast::ForeignItemKind::Macro(ref m) => {
+                self.print_mac(m, token::Paren)?;
+                self.s.word(";")
+            }
         }
     }
 
@@ -1169,9 +1364,15 @@ impl<'a> State<'a> {
 
     /// Pretty-print an item
     pub fn print_item(&mut self, item: &ast::Item) -> io::Result<()> {
+        if self.is_cfg_stripped(&item.attrs) {
+            return Ok(());
+        }
         self.hardbreak_if_not_bol()?;
         self.maybe_print_comment(item.span.lo())?;
-        self.print_outer_attributes(&item.attrs)?;
+        self.print_item_attributes(&item.attrs)?;
+        if self.print_verbatim(item.span)? {
+            return Ok(());
+        }
         self.ann.pre(self, NodeItem(item))?;
         match item.node {
             ast::ItemKind::ExternCrate(ref optional_path) => {
@@ -1403,6 +1604,16 @@ impl<'a> State<'a> {
                 self.s.word(";")?;
                 self.end()?;
             }
+            ast::ItemKind::Extension(ref ext) => {
+                self.s.word(&visibility_qualified(&item.vis, &ext.keyword.as_str()))?;
+                self.s.word(" ")?;
+                self.cbox(INDENT_UNIT)?;
+                self.print_tts(ext.tokens.clone().into())?;
+                self.end()?;
+            }
+            ast::ItemKind::Err => {
+                self.s.word("<error>")?;
+            }
         }
         self.ann.post(self, NodeItem(item))
     }
@@ -1563,10 +1774,16 @@ impl<'a> State<'a> {
 
     pub fn print_trait_item(&mut self, ti: &ast::TraitItem)
                             -> io::Result<()> {
+        if self.is_cfg_stripped(&ti.attrs) {
+            return Ok(());
+        }
         self.ann.pre(self, NodeSubItem(ti.id))?;
         self.hardbreak_if_not_bol()?;
         self.maybe_print_comment(ti.span.lo())?;
-        self.print_outer_attributes(&ti.attrs)?;
+        self.print_item_attributes(&ti.attrs)?;
+        if self.print_verbatim(ti.span)? {
+            return self.ann.post(self, NodeSubItem(ti.id));
+        }
         match ti.node {
             ast::TraitItemKind::Const(ref ty, ref default) => {
                 self.print_associated_const(ti.ident, ty,
@@ -1605,10 +1822,16 @@ impl<'a> State<'a> {
     }
 
     pub fn print_impl_item(&mut self, ii: &ast::ImplItem) -> io::Result<()> {
+        if self.is_cfg_stripped(&ii.attrs) {
+            return Ok(());
+        }
         self.ann.pre(self, NodeSubItem(ii.id))?;
         self.hardbreak_if_not_bol()?;
         self.maybe_print_comment(ii.span.lo())?;
-        self.print_outer_attributes(&ii.attrs)?;
+        self.print_item_attributes(&ii.attrs)?;
+        if self.print_verbatim(ii.span)? {
+            return self.ann.post(self, NodeSubItem(ii.id));
+        }
         self.print_defaultness(ii.defaultness)?;
         match ii.node {
             ast::ImplItemKind::Const(ref ty, ref expr) => {
@@ -2044,6 +2267,9 @@ impl<'a> State<'a> {
         } else {
             self.print_outer_attributes(attrs)?;
         }
+        if self.print_verbatim(expr.span)? {
+            return Ok(());
+        }
 
         self.ibox(INDENT_UNIT)?;
         self.ann.pre(self, NodeExpr(expr))?;
@@ -2061,13 +2287,13 @@ impl<'a> State<'a> {
             ast::ExprKind::Repeat(ref element, ref count) => {
                 self.print_expr_repeat(element, count, attrs)?;
             }
-            ast::ExprKind::Struct(ref path, ref fields, ref wth) => {
+            ast::ExprKind::Struct(ref path, ref fields, ref wth, _) => {
                 self.print_expr_struct(path, &fields[..], wth, attrs)?;
             }
-            ast::ExprKind::Tup(ref exprs) => {
+            ast::ExprKind::Tup(ref exprs, _) => {
                 self.print_expr_tup(&exprs[..], attrs)?;
             }
-            ast::ExprKind::Call(ref func, ref args) => {
+            ast::ExprKind::Call(ref func, ref args, _) => {
                 self.print_expr_call(func, &args[..])?;
             }
             ast::ExprKind::MethodCall(ref segment, ref args) => {
@@ -2220,8 +2446,14 @@ impl<'a> State<'a> {
                 // Here we use a fake precedence value so that any child with lower precedence than
                 // a "normal" binop gets parenthesized.  (`LOr` is the lowest-precedence binop.)
                 let fake_prec = AssocOp::LOr.precedence() as i8;
+                // Casts are always parenthesized so that the end of the cast type is never
+                // ambiguous, as is done for `as` followed by `<` in `print_expr_binary`.
+                let operand_prec = |e: &ast::Expr| match e.node {
+                    ast::ExprKind::Cast(..) => parser::PREC_FORCE_PAREN,
+                    _ => fake_prec,
+                };
                 if let Some(ref e) = *start {
-                    self.print_expr_maybe_paren(e, fake_prec)?;
+                    self.print_expr_maybe_paren(e, operand_prec(e))?;
                 }
                 if limits == ast::RangeLimits::HalfOpen {
                     self.s.word("..")?;
@@ -2229,7 +2461,7 @@ impl<'a> State<'a> {
                     self.s.word("..=")?;
                 }
                 if let Some(ref e) = *end {
-                    self.print_expr_maybe_paren(e, fake_prec)?;
+                    self.print_expr_maybe_paren(e, operand_prec(e))?;
                 }
             }
             ast::ExprKind::Path(None, ref path) => {
@@ -2354,6 +2586,12 @@ impl<'a> State<'a> {
                 self.s.space()?;
                 self.print_block_with_attrs(blk, attrs)?
             }
+            ast::ExprKind::Err => {
+                self.s.word("<error>")?
+            }
+            ast::ExprKind::Unparsed(ref tts) => {
+                self.print_tts(tts.clone().into())?
+            }
         }
         self.ann.post(self, NodeExpr(expr))?;
         self.end()
@@ -2522,6 +2760,9 @@ impl<'a> State<'a> {
 
     pub fn print_pat(&mut self, pat: &ast::Pat) -> io::Result<()> {
         self.maybe_print_comment(pat.span.lo())?;
+        if self.print_verbatim(pat.span)? {
+            return Ok(());
+        }
         self.ann.pre(self, NodePat(pat))?;
         /* Pat isn't normalized, but the beauty of it
          is that it doesn't matter */
@@ -2652,6 +2893,7 @@ impl<'a> State<'a> {
                 self.s.word("]")?;
             }
             PatKind::Mac(ref m) => self.print_mac(m, token::Paren)?,
+            PatKind::Err => self.s.word("<error>")?,
         }
         self.ann.post(self, NodePat(pat))
     }
@@ -2869,6 +3111,7 @@ impl<'a> State<'a> {
                     s.print_lifetime_bounds(&lifetime_def.lifetime, &lifetime_def.bounds)
                 },
                 ast::GenericParam::Type(ref ty_param) => s.print_ty_param(ty_param),
+                ast::GenericParam::Const(ref const_param) => s.print_const_param(const_param),
             }
         })?;
 
@@ -2890,6 +3133,14 @@ impl<'a> State<'a> {
         }
     }
 
+    pub fn print_const_param(&mut self, param: &ast::ConstParam) -> io::Result<()> {
+        self.print_outer_attributes_inline(&param.attrs)?;
+        self.word_nbsp("const")?;
+        self.print_ident(param.ident)?;
+        self.word_space(":")?;
+        self.print_type(&param.ty)
+    }
+
     pub fn print_where_clause(&mut self, where_clause: &ast::WhereClause)
                               -> io::Result<()> {
         if where_clause.predicates.is_empty() {
@@ -2952,7 +3203,7 @@ impl<'a> State<'a> {
                 }
                 self.s.word("*")?;
             }
-            ast::UseTreeKind::Nested(ref items) => {
+            ast::UseTreeKind::Nested(ref items, _) => {
                 if tree.prefix.segments.is_empty() {
                     self.s.word("{")?;
                 } else {
@@ -3160,6 +3411,9 @@ mod tests {
     use ast;
     use codemap;
     use syntax_pos;
+    use util::parser_testing::{assert_roundtrip_str, string_to_crate, string_to_expr};
+
+    use std::path::PathBuf;
 
     #[test]
     fn test_fun_to_string() {
@@ -3192,4 +3446,70 @@ mod tests {
         let varstr = variant_to_string(&var);
         assert_eq!(varstr, "principal_skinner");
     }
+
+    #[test]
+    fn test_range_cast_operands() {
+        let e = string_to_expr("(a as usize)..(b as usize)".to_string());
+        assert_eq!(expr_to_string(&e), "(a as usize)..(b as usize)");
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        assert_roundtrip_str("fn f() { let _ = (x as usize)..y; let _ = a..b as u8; }");
+        assert_roundtrip_str("fn f() { if (x as u8) < y { (1 + 2) * 3; } }");
+        assert_roundtrip_str("fn f((a,): (u8,), (b, ..): (u8, u8)) { let (.., c) = (1, 2); }");
+        assert_roundtrip_str("fn f() { match x { S(a, ..) | T { b, .. } => {} 1...2 => {} } }");
+        assert_roundtrip_str("struct S<T: Clone + 'static>(T) where T: Copy;");
+        assert_roundtrip_str("impl<'a> Tr for &'a (Fn() -> u8 + Send) { type A = [u8; 4]; }");
+        assert_roundtrip_str("macro_rules! m { ($e:expr) => { $e }; }");
+    }
+
+    #[test]
+    fn test_cfg_modes() {
+        let krate = string_to_crate("#[cfg(unix)] fn a() {} #[cfg(not(unix))] fn b() {} \
+                                     #[inline] #[cfg(any(unix, windows))] fn c() {}".to_string());
+        let mut config = ast::CrateConfig::new();
+        config.insert((Symbol::intern("unix"), None));
+        let print = |mode: CfgMode| to_string(|s| {
+            s.set_cfg_mode(mode);
+            s.print_mod(&krate.module, &[])
+        });
+
+        assert_eq!(print(CfgMode::Strip(config.clone())),
+                   "fn a() { }\n#[inline]\nfn c() { }");
+        assert_eq!(print(CfgMode::Annotate(config)),
+                   "// cfg: active\n#[cfg(unix)]\nfn a() { }\n\
+                    // cfg: inactive\n#[cfg(not(unix))]\nfn b() { }\n\
+                    // cfg: active\n#[inline]\n#[cfg(any(unix, windows))]\nfn c() { }");
+    }
+
+    #[test]
+    fn test_canonical_string() {
+        let a = string_to_expr("f(r#\"a\"#, 0x1F) // comment".to_string());
+        let b = string_to_expr("f(\n    \"a\",\n    31,\n)".to_string());
+        let print = |e: &ast::Expr| to_canonical_string(|s| s.print_expr(e));
+        assert_eq!(print(&a), "f(\"a\", 31)");
+        assert_eq!(print(&a), print(&b));
+    }
+
+    #[test]
+    fn test_verbatim_source() {
+        let sess = ParseSess::new(codemap::FilePathMapping::empty());
+        let src = "fn f() {\n    let  x = 1 +  2;\n    g( x );\n}";
+        let item = parse::parse_item_from_source_str(PathBuf::from("f.rs").into(),
+                                                     src.to_string(), &sess)
+            .unwrap().unwrap();
+        let print = |dirty_spans| {
+            to_string_with_source(sess.codemap(), dirty_spans, |s| s.print_item(&item))
+        };
+        assert_eq!(print(vec![]), src);
+
+        let lo = src.find("g( x )").unwrap() as u32;
+        let dirty = syntax_pos::Span::new(BytePos(lo), BytePos(lo + 6),
+                                          syntax_pos::NO_EXPANSION);
+        let printed = print(vec![dirty]);
+        assert!(printed.starts_with("fn f() {"));
+        assert!(printed.contains("let x = 1 +  2;"));
+        assert!(printed.contains("g(x);"));
+    }
 }
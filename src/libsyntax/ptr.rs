@@ -153,6 +153,22 @@ impl<T> P<[T]> {
     pub fn into_vec(self) -> Vec<T> {
         self.ptr.into_vec()
     }
+
+    /// Modifies each element in place, without reallocating.
+    pub fn map_in_place<F>(mut self, mut f: F) -> P<[T]> where F: FnMut(&mut T) {
+        for x in self.ptr.iter_mut() {
+            f(x);
+        }
+        self
+    }
+
+    /// Modifies the elements as a `Vec`, for example to add or remove some.
+    /// The allocation is reused unless the length changes.
+    pub fn map_vec<F>(self, f: F) -> P<[T]> where F: FnOnce(&mut Vec<T>) {
+        let mut v = self.into_vec();
+        f(&mut v);
+        P::from_vec(v)
+    }
 }
 
 impl<T> Default for P<[T]> {
@@ -174,6 +190,12 @@ impl<T> From<Vec<T>> for P<[T]> {
     }
 }
 
+impl<'a, T: Clone> From<&'a [T]> for P<[T]> {
+    fn from(v: &[T]) -> Self {
+        P::from_vec(v.to_vec())
+    }
+}
+
 impl<T> Into<Vec<T>> for P<[T]> {
     fn into(self) -> Vec<T> {
         self.into_vec()
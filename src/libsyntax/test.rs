@@ -40,19 +40,25 @@ use ast::{self, Ident};
 use ptr::P;
 use symbol::{self, Symbol, keywords};
 use util::small_vector::SmallVector;
+use visit::{self, Visitor};
 
-enum ShouldPanic {
+#[derive(Clone, Debug, PartialEq)]
+pub enum ShouldPanic {
     No,
+    /// `#[should_panic]`, with the expected message if there is one.
     Yes(Option<Symbol>),
 }
 
-struct Test {
-    span: Span,
-    path: Vec<Ident> ,
-    bench: bool,
-    ignore: bool,
-    should_panic: ShouldPanic,
-    allow_fail: bool,
+/// A test or bench function.
+#[derive(Clone, Debug)]
+pub struct Test {
+    pub span: Span,
+    /// The path of the function from the crate root, its own name included.
+    pub path: Vec<Ident>,
+    pub bench: bool,
+    pub ignore: bool,
+    pub should_panic: ShouldPanic,
+    pub allow_fail: bool,
 }
 
 struct TestCtxt<'a> {
@@ -90,6 +96,56 @@ pub fn modify_for_testing(sess: &ParseSess,
     }
 }
 
+/// Collects the test and bench functions of `krate`, as the test harness
+/// would, without modifying the crate. Like the harness, only functions in
+/// modules are looked at, not those nested in other items. Functions which
+/// are not valid tests are reported to `span_diagnostic` and left out.
+///
+/// Tests generated by macros are only found if the crate has been expanded.
+pub fn find_tests(krate: &ast::Crate, span_diagnostic: &errors::Handler) -> Vec<Test> {
+    let mut collector = TestCollector {
+        diag: span_diagnostic,
+        path: Vec::new(),
+        tests: Vec::new(),
+    };
+    visit::walk_crate(&mut collector, krate);
+    collector.tests
+}
+
+struct TestCollector<'a> {
+    diag: &'a errors::Handler,
+    path: Vec<Ident>,
+    tests: Vec<Test>,
+}
+
+impl<'a, 'ast> Visitor<'ast> for TestCollector<'a> {
+    fn visit_item(&mut self, i: &'ast ast::Item) {
+        let ident = i.ident;
+        if ident.name != keywords::Invalid.name() {
+            self.path.push(ident);
+        }
+
+        if is_test_fn(self.diag, i) || is_bench_fn(self.diag, i) {
+            match i.node {
+                ast::ItemKind::Fn(_, ast::Unsafety::Unsafe, _, _, _, _) => {
+                    self.diag.span_err(i.span, "unsafe functions cannot be used for tests");
+                }
+                _ => {
+                    let test = describe_test(self.diag, self.path.clone(), i);
+                    self.tests.push(test);
+                }
+            }
+        }
+        if let ast::ItemKind::Mod(ref module) = i.node {
+            visit::walk_mod(self, module);
+        }
+
+        if ident.name != keywords::Invalid.name() {
+            self.path.pop();
+        }
+    }
+}
+
 struct TestHarnessGenerator<'a> {
     cx: TestCtxt<'a>,
     tests: Vec<Ident>,
@@ -119,22 +175,15 @@ impl<'a> fold::Folder for TestHarnessGenerator<'a> {
         }
         debug!("current path: {}", path_name_i(&self.cx.path));
 
-        if is_test_fn(&self.cx, &i) || is_bench_fn(&self.cx, &i) {
+        let diag = self.cx.span_diagnostic;
+        if is_test_fn(diag, &i) || is_bench_fn(diag, &i) {
             match i.node {
                 ast::ItemKind::Fn(_, ast::Unsafety::Unsafe, _, _, _, _) => {
-                    let diag = self.cx.span_diagnostic;
                     diag.span_fatal(i.span, "unsafe functions cannot be used for tests").raise();
                 }
                 _ => {
                     debug!("this is a test function");
-                    let test = Test {
-                        span: i.span,
-                        path: self.cx.path.clone(),
-                        bench: is_bench_fn(&self.cx, &i),
-                        ignore: is_ignored(&i),
-                        should_panic: should_panic(&i, &self.cx),
-                        allow_fail: is_allowed_fail(&i),
-                    };
+                    let test = describe_test(diag, self.cx.path.clone(), &i);
                     self.cx.testfns.push(test);
                     self.tests.push(i.ident);
                 }
@@ -315,7 +364,7 @@ enum HasTestSignature {
     NotEvenAFunction,
 }
 
-fn is_test_fn(cx: &TestCtxt, i: &ast::Item) -> bool {
+fn is_test_fn(diag: &errors::Handler, i: &ast::Item) -> bool {
     let has_test_attr = attr::contains_name(&i.attrs, "test");
 
     fn has_test_signature(i: &ast::Item) -> HasTestSignature {
@@ -339,7 +388,6 @@ fn is_test_fn(cx: &TestCtxt, i: &ast::Item) -> bool {
     }
 
     if has_test_attr {
-        let diag = cx.span_diagnostic;
         match has_test_signature(i) {
             Yes => {},
             No => diag.span_err(i.span, "functions used as tests must have signature fn() -> ()"),
@@ -351,7 +399,7 @@ fn is_test_fn(cx: &TestCtxt, i: &ast::Item) -> bool {
     has_test_attr && has_test_signature(i) == Yes
 }
 
-fn is_bench_fn(cx: &TestCtxt, i: &ast::Item) -> bool {
+fn is_bench_fn(diag: &errors::Handler, i: &ast::Item) -> bool {
     let has_bench_attr = attr::contains_name(&i.attrs, "bench");
 
     fn has_test_signature(i: &ast::Item) -> bool {
@@ -377,7 +425,6 @@ fn is_bench_fn(cx: &TestCtxt, i: &ast::Item) -> bool {
     }
 
     if has_bench_attr && !has_test_signature(i) {
-        let diag = cx.span_diagnostic;
         diag.span_err(i.span, "functions used as benches must have signature \
                       `fn(&mut Bencher) -> ()`");
     }
@@ -393,10 +440,9 @@ fn is_allowed_fail(i: &ast::Item) -> bool {
     attr::contains_name(&i.attrs, "allow_fail")
 }
 
-fn should_panic(i: &ast::Item, cx: &TestCtxt) -> ShouldPanic {
+fn should_panic(i: &ast::Item, sd: &errors::Handler) -> ShouldPanic {
     match attr::find_by_name(&i.attrs, "should_panic") {
         Some(attr) => {
-            let sd = cx.span_diagnostic;
             if attr.is_value_str() {
                 sd.struct_span_warn(
                     attr.span(),
@@ -436,6 +482,17 @@ fn should_panic(i: &ast::Item, cx: &TestCtxt) -> ShouldPanic {
     }
 }
 
+fn describe_test(diag: &errors::Handler, path: Vec<Ident>, i: &ast::Item) -> Test {
+    Test {
+        span: i.span,
+        path,
+        bench: is_bench_fn(diag, i),
+        ignore: is_ignored(i),
+        should_panic: should_panic(i, diag),
+        allow_fail: is_allowed_fail(i),
+    }
+}
+
 /*
 
 We're going to be building a module that looks more or less like:
@@ -712,3 +769,33 @@ fn mk_test_desc_and_fn_rec(cx: &TestCtxt, test: &Test) -> P<ast::Expr> {
                     vec![field("desc", desc_expr),
                          field("testfn", testfn_expr)])
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codemap::FilePathMapping;
+    use parse;
+    use std::path::PathBuf;
+
+    #[test]
+    fn find_tests_in_modules() {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let source = "#[test] fn a() {} \
+                      mod m { \
+                          #[test] #[ignore] #[should_panic(expected = \"boom\")] fn b() {} \
+                          #[bench] fn c(b: &mut Bencher) {} \
+                          fn d() { #[test] fn nested() {} } \
+                      } \
+                      #[test] fn e(x: u8) {}".to_string();
+        let krate = parse::parse_crate_from_source_str(PathBuf::from("tests").into(), source,
+                                                       &sess).unwrap();
+        let tests = find_tests(&krate, &sess.span_diagnostic);
+        assert_eq!(tests.iter().map(|test| path_name_i(&test.path)).collect::<Vec<_>>(),
+                   ["a", "m::b", "m::c"]);
+        assert!(tests[1].ignore);
+        assert_eq!(tests[1].should_panic, ShouldPanic::Yes(Some(Symbol::intern("boom"))));
+        assert!(tests[2].bench);
+        // `e` takes an argument.
+        assert_eq!(sess.span_diagnostic.err_count(), 1);
+    }
+}
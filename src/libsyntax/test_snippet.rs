@@ -10,7 +10,7 @@
 
 use codemap::{CodeMap, FilePathMapping};
 use errors::Handler;
-use errors::emitter::EmitterWriter;
+use errors::emitter::{EmitterWriter, DEFAULT_TAB_WIDTH};
 use std::io;
 use std::io::prelude::*;
 use std::rc::Rc;
@@ -46,10 +46,21 @@ impl<T: Write> Write for Shared<T> {
 }
 
 fn test_harness(file_text: &str, span_labels: Vec<SpanLabel>, expected_output: &str) {
+    test_harness_with_tabs(file_text, span_labels, expected_output, DEFAULT_TAB_WIDTH, false)
+}
+
+fn test_harness_with_tabs(file_text: &str,
+                          span_labels: Vec<SpanLabel>,
+                          expected_output: &str,
+                          tab_width: usize,
+                          replace_tabs: bool) {
     let output = Arc::new(Mutex::new(Vec::new()));
 
     let code_map = Rc::new(CodeMap::new(FilePathMapping::empty()));
-    code_map.new_filemap_and_lines(Path::new("test.rs"), &file_text);
+    let fm = code_map.new_filemap_and_lines(Path::new("test.rs"), &file_text);
+    for (i, c) in file_text.char_indices().filter(|&(_, c)| c == '\t') {
+        fm.record_width(BytePos(i as u32), c);
+    }
 
     let primary_span = make_span(&file_text, &span_labels[0].start, &span_labels[0].end);
     let mut msp = MultiSpan::from_span(primary_span);
@@ -62,7 +73,7 @@ fn test_harness(file_text: &str, span_labels: Vec<SpanLabel>, expected_output: &
 
     let emitter = EmitterWriter::new(Box::new(Shared { data: output.clone() }),
                                      Some(code_map.clone()),
-                                     false);
+                                     false).with_tab_width(tab_width, replace_tabs);
     let handler = Handler::with_emitter(true, false, Box::new(emitter));
     handler.span_err(msp, "foo");
 
@@ -1106,3 +1117,30 @@ error: foo
 "#);
 }
 
+#[test]
+fn replaced_tabs() {
+    test_harness_with_tabs("\nfn foo() {\n\t\tlet x = 1;\n}\n",
+    vec![
+        SpanLabel {
+            start: Position {
+                string: "x",
+                count: 1,
+            },
+            end: Position {
+                string: "x",
+                count: 1,
+            },
+            label: "",
+        },
+    ],
+    r#"
+error: foo
+ --> test.rs:3:7
+  |
+3 |     let x = 1;
+  |         ^
+
+"#,
+    2,
+    true);
+}
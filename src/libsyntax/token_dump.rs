@@ -0,0 +1,426 @@
+// Copyright 2018 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A stable textual form of token trees, for snapshotting token streams in
+//! tests and diffing them, unlike the `Debug` output which changes with the
+//! definitions of the types.
+//!
+//! Every token is written on its own line, as a kind followed by its quoted
+//! text, and the trees of a delimited group are indented between a `group`
+//! line and an `end` line:
+//!
+//! ```text
+//! ident "f"
+//! group paren @1..7
+//!   ident "x"
+//!   punct "+"
+//!   lit integer "1" suffix "u8"
+//! end
+//! ```
+//!
+//! The optional `@lo..hi` at the end of a line is the span of the token or
+//! group as positions in the `CodeMap`. Hygiene information and whether
+//! operators are joint are not recorded. Quoted text uses the escapes of
+//! `char::escape_default`.
+
+use ast::Ident;
+use parse::token::{self, DelimToken, Token};
+use print::pprust::token_to_string;
+use symbol::Symbol;
+use syntax_pos::{BytePos, Span, DUMMY_SP, NO_EXPANSION};
+use tokenstream::{Delimited, TokenStream, TokenTree};
+
+use std::{char, fmt};
+
+/// An error in a dump given to `parse_dump`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DumpError {
+    /// The line of the error, starting at 1.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for DumpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// Dumps a token tree, see the module documentation for the format.
+pub fn dump_tree(tree: &TokenTree, with_spans: bool) -> String {
+    let mut out = String::new();
+    dump_into(tree, with_spans, 0, &mut out);
+    out
+}
+
+/// Dumps the trees of a token stream.
+pub fn dump_stream(stream: &TokenStream, with_spans: bool) -> String {
+    let mut out = String::new();
+    for tree in stream.trees() {
+        dump_into(&tree, with_spans, 0, &mut out);
+    }
+    out
+}
+
+/// Parses the output of `dump_stream` or `dump_tree` back into a token
+/// stream. Trees without a span get `DUMMY_SP`.
+pub fn parse_dump(dump: &str) -> Result<TokenStream, DumpError> {
+    let mut trees = Vec::new();
+    let mut groups: Vec<(DelimToken, Span, Vec<TokenTree>)> = Vec::new();
+    let mut lines = 0;
+    for (index, line) in dump.lines().enumerate() {
+        lines = index + 1;
+        let error = |message| DumpError { line: index + 1, message };
+        let tree = match parse_line(line).map_err(&error)? {
+            Line::Blank => continue,
+            Line::Group(delim, span) => {
+                groups.push((delim, span, Vec::new()));
+                continue;
+            }
+            Line::End => match groups.pop() {
+                Some((delim, span, tts)) => TokenTree::Delimited(span, Delimited {
+                    delim,
+                    tts: tts.into_iter().collect::<TokenStream>().into(),
+                }),
+                None => return Err(error("`end` without a group".to_string())),
+            },
+            Line::Token(span, tok) => TokenTree::Token(span, tok),
+        };
+        match groups.last_mut() {
+            Some(&mut (_, _, ref mut tts)) => tts.push(tree),
+            None => trees.push(tree),
+        }
+    }
+    if !groups.is_empty() {
+        return Err(DumpError { line: lines, message: "unclosed group".to_string() });
+    }
+    Ok(trees.into_iter().collect())
+}
+
+fn dump_into(tree: &TokenTree, with_spans: bool, depth: usize, out: &mut String) {
+    for _ in 0..depth {
+        out.push_str("  ");
+    }
+    match *tree {
+        TokenTree::Token(_, ref tok) => out.push_str(&dump_token(tok)),
+        TokenTree::Delimited(_, ref delimited) => {
+            out.push_str("group ");
+            out.push_str(delim_name(delimited.delim));
+        }
+    }
+    if with_spans {
+        let span = tree.span();
+        out.push_str(&format!(" @{}..{}", span.lo().0, span.hi().0));
+    }
+    out.push('\n');
+
+    if let TokenTree::Delimited(_, ref delimited) = *tree {
+        for tree in delimited.stream().trees() {
+            dump_into(&tree, with_spans, depth + 1, out);
+        }
+        for _ in 0..depth {
+            out.push_str("  ");
+        }
+        out.push_str("end\n");
+    }
+}
+
+fn dump_token(tok: &Token) -> String {
+    match *tok {
+        token::Literal(lit, suffix) => {
+            let (kind, text) = match lit {
+                token::Byte(text) => ("byte".to_string(), text),
+                token::Char(text) => ("char".to_string(), text),
+                token::Integer(text) => ("integer".to_string(), text),
+                token::Float(text) => ("float".to_string(), text),
+                token::Str_(text) => ("str".to_string(), text),
+                token::StrRaw(text, n) => (format!("str_raw {}", n), text),
+                token::ByteStr(text) => ("byte_str".to_string(), text),
+                token::ByteStrRaw(text, n) => (format!("byte_str_raw {}", n), text),
+            };
+            let mut out = format!("lit {} {}", kind, quote(&text.as_str()));
+            if let Some(suffix) = suffix {
+                out.push_str(&format!(" suffix {}", quote(&suffix.as_str())));
+            }
+            out
+        }
+        token::Ident(ident) => format!("ident {}", quote(&ident.name.as_str())),
+        token::Lifetime(ident) => format!("lifetime {}", quote(&ident.name.as_str())),
+        token::Placeholder(name) => format!("placeholder {}", quote(&name.as_str())),
+        token::DocComment(name) => format!("doc_comment {}", quote(&name.as_str())),
+        token::Shebang(name) => format!("shebang {}", quote(&name.as_str())),
+        token::Interpolated(..) => format!("interpolated {}", quote(&token_to_string(tok))),
+        token::OpenDelim(delim) => format!("open {}", delim_name(delim)),
+        token::CloseDelim(delim) => format!("close {}", delim_name(delim)),
+        token::Underscore => "underscore".to_string(),
+        token::Whitespace => "whitespace".to_string(),
+        token::Comment => "comment".to_string(),
+        token::Eof => "eof".to_string(),
+        _ => format!("punct {}", quote(&token_to_string(tok))),
+    }
+}
+
+fn delim_name(delim: DelimToken) -> &'static str {
+    match delim {
+        token::Paren => "paren",
+        token::Bracket => "bracket",
+        token::Brace => "brace",
+        token::NoDelim => "none",
+    }
+}
+
+fn quote(text: &str) -> String {
+    let mut out = String::from("\"");
+    out.extend(text.chars().flat_map(|c| c.escape_default()));
+    out.push('"');
+    out
+}
+
+/// A parsed line of a dump.
+enum Line {
+    Blank,
+    Group(DelimToken, Span),
+    End,
+    Token(Span, Token),
+}
+
+fn parse_line(line: &str) -> Result<Line, String> {
+    let mut fields = Fields { rest: line };
+    let kind = match fields.word() {
+        Some(kind) => kind,
+        None => {
+            fields.finish()?;
+            return Ok(Line::Blank);
+        }
+    };
+    let line = match kind {
+        "group" => {
+            let delim = parse_delim(fields.word())?;
+            Line::Group(delim, fields.span()?)
+        }
+        "end" => Line::End,
+        _ => {
+            let tok = parse_token(kind, &mut fields)?;
+            Line::Token(fields.span()?, tok)
+        }
+    };
+    fields.finish()?;
+    Ok(line)
+}
+
+fn parse_token(kind: &str, fields: &mut Fields) -> Result<Token, String> {
+    Ok(match kind {
+        "lit" => {
+            let lit_kind = fields.word().ok_or("expected a literal kind")?;
+            let raw = if lit_kind.ends_with("_raw") {
+                let hashes = fields.word().ok_or("expected the number of `#`s")?;
+                hashes.parse().map_err(|_| format!("invalid number of `#`s: `{}`", hashes))?
+            } else {
+                0
+            };
+            let text = Symbol::intern(&fields.string()?);
+            let lit = match lit_kind {
+                "byte" => token::Byte(text),
+                "char" => token::Char(text),
+                "integer" => token::Integer(text),
+                "float" => token::Float(text),
+                "str" => token::Str_(text),
+                "str_raw" => token::StrRaw(text, raw),
+                "byte_str" => token::ByteStr(text),
+                "byte_str_raw" => token::ByteStrRaw(text, raw),
+                _ => return Err(format!("unknown literal kind `{}`", lit_kind)),
+            };
+            let suffix = if fields.eat("suffix") {
+                Some(Symbol::intern(&fields.string()?))
+            } else {
+                None
+            };
+            token::Literal(lit, suffix)
+        }
+        "ident" => token::Ident(Ident::from_str(&fields.string()?)),
+        "lifetime" => token::Lifetime(Ident::from_str(&fields.string()?)),
+        "placeholder" => token::Placeholder(Symbol::intern(&fields.string()?)),
+        "doc_comment" => token::DocComment(Symbol::intern(&fields.string()?)),
+        "shebang" => token::Shebang(Symbol::intern(&fields.string()?)),
+        "interpolated" => return Err("interpolated tokens cannot be parsed".to_string()),
+        "open" => token::OpenDelim(parse_delim(fields.word())?),
+        "close" => token::CloseDelim(parse_delim(fields.word())?),
+        "underscore" => token::Underscore,
+        "whitespace" => token::Whitespace,
+        "comment" => token::Comment,
+        "eof" => token::Eof,
+        "punct" => {
+            let text = fields.string()?;
+            match token::punctuation_tokens().into_iter().find(|&(_, s)| s == text) {
+                Some((tok, _)) => tok,
+                None => return Err(format!("unknown punctuation `{}`", text)),
+            }
+        }
+        _ => return Err(format!("unknown token kind `{}`", kind)),
+    })
+}
+
+fn parse_delim(name: Option<&str>) -> Result<DelimToken, String> {
+    Ok(match name {
+        Some("paren") => token::Paren,
+        Some("bracket") => token::Bracket,
+        Some("brace") => token::Brace,
+        Some("none") => token::NoDelim,
+        Some(name) => return Err(format!("unknown delimiter `{}`", name)),
+        None => return Err("expected a delimiter".to_string()),
+    })
+}
+
+/// The tokens which are dumped as `punct`.
+/// The unparsed rest of a line.
+struct Fields<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Fields<'a> {
+    fn skip_whitespace(&mut self) {
+        self.rest = self.rest.trim_left();
+    }
+
+    fn word(&mut self) -> Option<&'a str> {
+        self.skip_whitespace();
+        let len = self.rest.find(char::is_whitespace).unwrap_or(self.rest.len());
+        if len == 0 || self.rest.starts_with('"') || self.rest.starts_with('@') {
+            return None;
+        }
+        let (word, rest) = self.rest.split_at(len);
+        self.rest = rest;
+        Some(word)
+    }
+
+    fn eat(&mut self, word: &str) -> bool {
+        let rest = self.rest;
+        if self.word() == Some(word) {
+            true
+        } else {
+            self.rest = rest;
+            false
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.skip_whitespace();
+        let mut chars = self.rest.char_indices();
+        if chars.next().map(|(_, c)| c) != Some('"') {
+            return Err("expected a quoted string".to_string());
+        }
+        let mut text = String::new();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.rest = &self.rest[i + 1..];
+                    return Ok(text);
+                }
+                '\\' => text.push(match chars.next().map(|(_, c)| c) {
+                    Some('t') => '\t',
+                    Some('r') => '\r',
+                    Some('n') => '\n',
+                    Some('0') => '\0',
+                    Some(c @ '\\') | Some(c @ '\'') | Some(c @ '"') => c,
+                    Some('u') => {
+                        let escape: String = chars.by_ref().map(|(_, c)| c)
+                                                  .take_while(|&c| c != '}').collect();
+                        let code = if escape.starts_with('{') {
+                            u32::from_str_radix(&escape[1..], 16).ok()
+                        } else {
+                            None
+                        };
+                        match code.and_then(char::from_u32) {
+                            Some(c) => c,
+                            None => return Err(format!("invalid escape `\\u{}}}`", escape)),
+                        }
+                    }
+                    _ => return Err("invalid escape".to_string()),
+                }),
+                c => text.push(c),
+            }
+        }
+        Err("unterminated string".to_string())
+    }
+
+    fn span(&mut self) -> Result<Span, String> {
+        self.skip_whitespace();
+        if !self.rest.starts_with('@') {
+            return Ok(DUMMY_SP);
+        }
+        let len = self.rest.find(char::is_whitespace).unwrap_or(self.rest.len());
+        let (span, rest) = self.rest.split_at(len);
+        self.rest = rest;
+        let mut bounds = span[1..].splitn(2, "..").map(|bound| bound.parse::<u32>());
+        match (bounds.next(), bounds.next()) {
+            (Some(Ok(lo)), Some(Ok(hi))) if lo <= hi => {
+                Ok(Span::new(BytePos(lo), BytePos(hi), NO_EXPANSION))
+            }
+            _ => Err(format!("invalid span `{}`", span)),
+        }
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.rest.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("unexpected `{}`", self.rest))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokenstream;
+    use util::parser_testing::string_to_stream;
+
+    #[test]
+    fn dump_format() {
+        let stream = string_to_stream("f(x + 1u8, 'a', \"\\n\")".to_string());
+        assert_eq!(stream.dump(false), "ident \"f\"\n\
+                                        group paren\n  \
+                                          ident \"x\"\n  \
+                                          punct \"+\"\n  \
+                                          lit integer \"1\" suffix \"u8\"\n  \
+                                          punct \",\"\n  \
+                                          lit char \"a\"\n  \
+                                          punct \",\"\n  \
+                                          lit str \"\\\\n\"\n\
+                                        end\n");
+        assert_eq!(stream.trees().next().unwrap().dump(true), "ident \"f\" @0..1\n");
+    }
+
+    #[test]
+    fn dump_round_trip() {
+        let src = "/// Docs.\n\
+                   fn f<'a>(x: &'a [u8; 2]) -> Vec<u8> where u8: Copy {\n\
+                   let _ = r#\"raw\"# .. b\"\\x00\" ..= br\"b\" + \"é\t\" + b'\\'';\n\
+                   x >>= 1.5e3f32 => $y?;\n\
+                   }";
+        let stream = string_to_stream(src.to_string());
+        assert_eq!(parse_dump(&stream.dump(true)).unwrap(), stream);
+        let unspanned = parse_dump(&stream.dump(false)).unwrap();
+        assert!(tokenstream::eq_normalized(&unspanned, &stream));
+        assert_eq!(unspanned.dump(false), stream.dump(false));
+    }
+
+    #[test]
+    fn dump_errors() {
+        let error = |dump: &str| parse_dump(dump).err().unwrap().to_string();
+        assert_eq!(error("ident \"x\"\ngroup paren\n"), "line 2: unclosed group");
+        assert_eq!(error("end"), "line 1: `end` without a group");
+        assert_eq!(error("punct \"+-\""), "line 1: unknown punctuation `+-`");
+        assert_eq!(error("ident x"), "line 1: expected a quoted string");
+        assert_eq!(error("ident \"x\" @3"), "line 1: invalid span `@3`");
+        assert_eq!(error("\n\nident \"x\" y"), "line 3: unexpected `y`");
+    }
+}
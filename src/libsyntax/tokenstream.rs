@@ -28,6 +28,7 @@ use ext::tt::{macro_parser, quoted};
 use parse::Directory;
 use parse::token::{self, Token};
 use print::pprust;
+use token_dump;
 use serialize::{Decoder, Decodable, Encoder, Encodable};
 use util::RcSlice;
 
@@ -151,6 +152,12 @@ impl TokenTree {
     pub fn joint(self) -> TokenStream {
         TokenStream { kind: TokenStreamKind::JointTree(self) }
     }
+
+    /// Renders the tree in the stable format of `token_dump`, with or without
+    /// spans. `token_dump::parse_dump` parses the result.
+    pub fn dump(&self, with_spans: bool) -> String {
+        token_dump::dump_tree(self, with_spans)
+    }
 }
 
 /// # Token Streams
@@ -211,6 +218,11 @@ impl TokenStream {
         TokenStream { kind: TokenStreamKind::Empty }
     }
 
+    /// Renders the trees of the stream like `TokenTree::dump`.
+    pub fn dump(&self, with_spans: bool) -> String {
+        token_dump::dump_stream(self, with_spans)
+    }
+
     pub fn is_empty(&self) -> bool {
         match self.kind {
             TokenStreamKind::Empty => true,
@@ -304,6 +316,67 @@ impl TokenStream {
     }
 }
 
+/// Returns `stream` in a canonical form, for comparing token streams built in
+/// different ways: multi-character operators are split into single-character
+/// tokens, and the contents of `NoDelim` groups are spliced into the
+/// surrounding stream.
+pub fn normalize(stream: &TokenStream) -> TokenStream {
+    let mut trees = Vec::new();
+    normalize_into(stream, &mut trees);
+    trees.into_iter().collect()
+}
+
+fn normalize_into(stream: &TokenStream, trees: &mut Vec<TokenTree>) {
+    for tree in stream.trees() {
+        match tree {
+            TokenTree::Token(span, tok) => {
+                let parts = tok.unglue();
+                let len = parts.len() as u32;
+                let split_span = span != DUMMY_SP && span.hi() - span.lo() == BytePos(len);
+                for (i, part) in parts.into_iter().enumerate() {
+                    let span = if split_span && len > 1 {
+                        let lo = span.lo() + BytePos(i as u32);
+                        span.with_lo(lo).with_hi(lo + BytePos(1))
+                    } else {
+                        span
+                    };
+                    trees.push(TokenTree::Token(span, part));
+                }
+            }
+            TokenTree::Delimited(_, ref delimited) if delimited.delim == token::NoDelim => {
+                normalize_into(&delimited.stream(), trees);
+            }
+            TokenTree::Delimited(span, delimited) => {
+                trees.push(TokenTree::Delimited(span, Delimited {
+                    delim: delimited.delim,
+                    tts: normalize(&delimited.stream()).into(),
+                }));
+            }
+        }
+    }
+}
+
+/// Checks whether `a` and `b` are equal after normalization, regardless of
+/// span information. Unlike `TokenStream::eq_unspanned`, this also tells
+/// streams of different lengths apart.
+pub fn eq_normalized(a: &TokenStream, b: &TokenStream) -> bool {
+    trees_eq_unspanned(&normalize(a), &normalize(b))
+}
+
+fn trees_eq_unspanned(a: &TokenStream, b: &TokenStream) -> bool {
+    let (mut a, mut b) = (a.trees(), b.trees());
+    loop {
+        match (a.next(), b.next()) {
+            (None, None) => return true,
+            (Some(TokenTree::Token(_, ref tok)), Some(TokenTree::Token(_, ref tok2)))
+                if tok == tok2 => {}
+            (Some(TokenTree::Delimited(_, ref dl)), Some(TokenTree::Delimited(_, ref dl2)))
+                if dl.delim == dl2.delim && trees_eq_unspanned(&dl.stream(), &dl2.stream()) => {}
+            _ => return false,
+        }
+    }
+}
+
 pub struct TokenStreamBuilder(Vec<TokenStream>);
 
 impl TokenStreamBuilder {
@@ -600,7 +673,7 @@ mod tests {
     use super::*;
     use syntax::ast::Ident;
     use syntax_pos::{Span, BytePos, NO_EXPANSION};
-    use parse::token::Token;
+    use parse::token::{self, Token};
     use util::parser_testing::string_to_stream;
 
     fn string_to_ts(string: &str) -> TokenStream {
@@ -687,4 +760,23 @@ mod tests {
         assert_eq!(stream.trees().count(), 1);
     }
 
+    #[test]
+    fn test_normalize() {
+        let stream = string_to_ts("a >>= b::c");
+        let normalized = normalize(&stream);
+        assert_eq!(normalized.trees().count(), 8);
+        assert_eq!(normalized.trees().nth(2), Some(TokenTree::Token(sp(3, 4), Token::Gt)));
+        assert_eq!(normalized.trees().nth(3), Some(TokenTree::Token(sp(4, 5), Token::Eq)));
+        assert!(eq_normalized(&stream, &string_to_ts("a > > = b : : c")));
+        assert!(!eq_normalized(&stream, &string_to_ts("a >>= b")));
+
+        let grouped = TokenTree::Delimited(sp(0, 5), Delimited {
+            delim: token::NoDelim,
+            tts: string_to_ts("x + y").into(),
+        });
+        let stream = TokenStream::concat(vec![grouped.into(), string_to_ts("* (z)")]);
+        assert!(eq_normalized(&stream, &string_to_ts("x + y * (z)")));
+        assert!(!eq_normalized(&stream, &string_to_ts("x + y * (z w)")));
+    }
+
 }
@@ -0,0 +1,162 @@
+// Copyright 2018 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The paths of the items of a crate, like `parse::lexer::StringReader`, for
+//! indexers and documentation tools.
+//!
+//! Paths are relative to the crate root. Out-of-line modules are already
+//! part of the parsed crate, so they are treated like inline modules. Items
+//! nested in functions are put below the function, enum variants and trait
+//! items below their enum or trait. Impls and their items, `use` items and
+//! unexpanded macro invocations have no path.
+
+use ast::*;
+use symbol::keywords;
+use syntax_pos::Span;
+use visit::{self, Visitor};
+
+use std::collections::HashMap;
+
+pub struct ItemPaths {
+    paths: Vec<(String, Span)>,
+    by_path: HashMap<String, usize>,
+    by_span: HashMap<Span, usize>,
+}
+
+impl ItemPaths {
+    pub fn of(krate: &Crate) -> ItemPaths {
+        let mut collector = PathCollector { segments: Vec::new(), paths: Vec::new() };
+        visit::walk_crate(&mut collector, krate);
+
+        let mut item_paths = ItemPaths {
+            paths: collector.paths,
+            by_path: HashMap::new(),
+            by_span: HashMap::new(),
+        };
+        for (index, &(ref path, span)) in item_paths.paths.iter().enumerate() {
+            item_paths.by_path.entry(path.clone()).or_insert(index);
+            item_paths.by_span.entry(span).or_insert(index);
+        }
+        item_paths
+    }
+
+    /// All items with their paths, in source order. Items with the same path
+    /// in different namespaces, like a tuple struct and a function, are all
+    /// included.
+    pub fn paths(&self) -> &[(String, Span)] {
+        &self.paths
+    }
+
+    /// The span of the first item with the given path.
+    pub fn span(&self, path: &str) -> Option<Span> {
+        self.by_path.get(path).map(|&index| self.paths[index].1)
+    }
+
+    /// The path of the item with exactly the given span.
+    pub fn path(&self, span: Span) -> Option<&str> {
+        self.by_span.get(&span).map(|&index| &self.paths[index].0[..])
+    }
+
+    /// The path of the innermost item containing `span`, e.g. to find the
+    /// item an identifier is used in.
+    pub fn enclosing_path(&self, span: Span) -> Option<&str> {
+        self.paths.iter()
+            .filter(|&&(_, item_span)| item_span.contains(span))
+            .min_by_key(|&&(_, item_span)| item_span.hi() - item_span.lo())
+            .map(|&(ref path, _)| &path[..])
+    }
+}
+
+struct PathCollector {
+    segments: Vec<Ident>,
+    paths: Vec<(String, Span)>,
+}
+
+impl PathCollector {
+    fn record(&mut self, ident: Ident, span: Span) {
+        let mut path = String::new();
+        for segment in self.segments.iter().chain(Some(&ident)) {
+            if !path.is_empty() {
+                path.push_str("::");
+            }
+            path.push_str(&segment.name.as_str());
+        }
+        self.paths.push((path, span));
+    }
+}
+
+impl<'a> Visitor<'a> for PathCollector {
+    fn visit_item(&mut self, item: &'a Item) {
+        match item.node {
+            ItemKind::Impl(..) | ItemKind::Use(..) | ItemKind::Mac(..) => return,
+            ItemKind::ForeignMod(..) | ItemKind::GlobalAsm(..) => {}
+            _ if item.ident.name == keywords::Invalid.name() => {}
+            _ => {
+                self.record(item.ident, item.span);
+                self.segments.push(item.ident);
+                visit::walk_item(self, item);
+                self.segments.pop();
+                return;
+            }
+        }
+        visit::walk_item(self, item);
+    }
+
+    fn visit_foreign_item(&mut self, item: &'a ForeignItem) {
+        self.record(item.ident, item.span);
+    }
+
+    fn visit_trait_item(&mut self, item: &'a TraitItem) {
+        self.record(item.ident, item.span);
+        self.segments.push(item.ident);
+        visit::walk_trait_item(self, item);
+        self.segments.pop();
+    }
+
+    fn visit_variant(&mut self, variant: &'a Variant, _: &'a Generics, _: NodeId) {
+        self.record(variant.node.name, variant.span);
+    }
+
+    fn visit_mac(&mut self, _mac: &'a Mac) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syntax_pos::BytePos;
+    use util::parser_testing::string_to_crate;
+
+    #[test]
+    fn item_paths() {
+        let src = "mod parse {\n\
+                       pub mod lexer { pub struct StringReader; }\n\
+                       enum Delim { Paren, Brace }\n\
+                       fn f() { struct Local; }\n\
+                   }\n\
+                   trait T { fn m(&self); }\n\
+                   impl T for u8 { fn m(&self) {} }\n\
+                   extern { fn ext(); }\n\
+                   use parse::lexer;\n\
+                   foo!();";
+        let krate = string_to_crate(src.to_string());
+        let item_paths = ItemPaths::of(&krate);
+        let paths = item_paths.paths().iter().map(|&(ref path, _)| &path[..]).collect::<Vec<_>>();
+        assert_eq!(paths, ["parse", "parse::lexer", "parse::lexer::StringReader",
+                           "parse::Delim", "parse::Delim::Paren", "parse::Delim::Brace",
+                           "parse::f", "parse::f::Local", "T", "T::m", "ext"]);
+
+        let span = item_paths.span("parse::lexer::StringReader").unwrap();
+        assert_eq!(item_paths.path(span), Some("parse::lexer::StringReader"));
+        assert_eq!(item_paths.span("parse::StringReader"), None);
+        let inner = span.with_lo(span.lo() + BytePos(4));
+        assert_eq!(item_paths.path(inner), None);
+        assert_eq!(item_paths.enclosing_path(inner), Some("parse::lexer::StringReader"));
+    }
+}
@@ -278,6 +278,7 @@ pub enum ExprPrecedence {
     Block,
     Catch,
     Struct,
+    Err,
 }
 
 impl PartialOrd for ExprPrecedence {
@@ -348,7 +349,8 @@ impl ExprPrecedence {
             ExprPrecedence::Match |
             ExprPrecedence::Block |
             ExprPrecedence::Catch |
-            ExprPrecedence::Struct => PREC_PAREN,
+            ExprPrecedence::Struct |
+            ExprPrecedence::Err => PREC_PAREN,
         }
     }
 }
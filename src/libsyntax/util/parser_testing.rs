@@ -10,10 +10,13 @@
 
 use ast::{self, Ident};
 use codemap::FilePathMapping;
-use parse::{ParseSess, PResult, filemap_to_stream};
+use fold::{self, Folder};
+use parse::{ParseSess, PResult, try_filemap_to_stream};
 use parse::{lexer, new_parser_from_source_str};
 use parse::parser::Parser;
+use print::pprust;
 use ptr::P;
+use syntax_pos::{Span, DUMMY_SP};
 use tokenstream::TokenStream;
 use std::iter::Peekable;
 use std::path::PathBuf;
@@ -21,8 +24,8 @@ use std::path::PathBuf;
 /// Map a string to tts, using a made-up filename:
 pub fn string_to_stream(source_str: String) -> TokenStream {
     let ps = ParseSess::new(FilePathMapping::empty());
-    filemap_to_stream(&ps, ps.codemap()
-                             .new_filemap(PathBuf::from("bogofile").into(), source_str), None)
+    let filemap = ps.codemap().new_filemap(PathBuf::from("bogofile").into(), source_str);
+    panictry!(try_filemap_to_stream(&ps, filemap, None))
 }
 
 /// Map string to parser (via tts)
@@ -85,6 +88,66 @@ pub fn strs_to_idents(ids: Vec<&str> ) -> Vec<Ident> {
     ids.iter().map(|u| Ident::from_str(*u)).collect()
 }
 
+/// Erases everything that legitimately differs between an AST and the AST
+/// obtained by reparsing its pretty-printed form: spans, cached tokens and
+/// explicit parentheses.
+struct RoundtripNormalizer;
+
+impl Folder for RoundtripNormalizer {
+    fn fold_item_simple(&mut self, i: ast::Item) -> ast::Item {
+        ast::Item { tokens: None, ..fold::noop_fold_item_simple(i, self) }
+    }
+
+    fn fold_expr(&mut self, e: P<ast::Expr>) -> P<ast::Expr> {
+        e.map(|e| fold::noop_fold_expr(e, self)).and_then(|e| match e.node {
+            ast::ExprKind::Paren(inner) => inner,
+            node => P(ast::Expr { node, ..e }),
+        })
+    }
+
+    fn fold_ty(&mut self, t: P<ast::Ty>) -> P<ast::Ty> {
+        fold::noop_fold_ty(t, self).and_then(|t| match t.node {
+            ast::TyKind::Paren(inner) => inner,
+            node => P(ast::Ty { node, ..t }),
+        })
+    }
+
+    fn fold_mac(&mut self, mac: ast::Mac) -> ast::Mac {
+        fold::noop_fold_mac(mac, self)
+    }
+
+    fn new_span(&mut self, _sp: Span) -> Span {
+        DUMMY_SP
+    }
+}
+
+fn normalize_item(item: P<ast::Item>) -> P<ast::Item> {
+    RoundtripNormalizer.fold_item(item).expect_one("expected exactly one item")
+}
+
+/// Pretty-print `item`, parse the result and check that it yields the same
+/// AST, modulo spans and parentheses. Panics with both ASTs otherwise.
+pub fn assert_roundtrip(item: P<ast::Item>) {
+    let printed = pprust::item_to_string(&item);
+    let reparsed = match string_to_item(printed.clone()) {
+        Some(item) => item,
+        None => panic!("`{}` did not parse as an item", printed),
+    };
+    let (expected, found) = (normalize_item(item), normalize_item(reparsed));
+    if expected != found {
+        panic!("pretty-printed item `{}` does not round-trip:\n\
+                expected: {:?}\n   found: {:?}", printed, expected, found);
+    }
+}
+
+/// Like `assert_roundtrip`, but starting from source text.
+pub fn assert_roundtrip_str(source_str: &str) {
+    match string_to_item(source_str.to_string()) {
+        Some(item) => assert_roundtrip(item),
+        None => panic!("`{}` did not parse as an item", source_str),
+    }
+}
+
 /// Does the given string match the pattern? whitespace in the first string
 /// may be deleted or replaced with other whitespace to match the pattern.
 /// This function is relatively Unicode-ignorant; fortunately, the careful design
@@ -0,0 +1,165 @@
+// Copyright 2018 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Rewriting every span in a tree, e.g. to splice a parsed snippet into a
+//! synthesized file, or to compare trees parsed in different sessions.
+//!
+//! The token streams cached in items are dropped rather than rewritten, like
+//! in `splice::import_crate`.
+
+use ast::{self, Block, Crate, Expr, Generics, Pat, Path, Ty};
+use fold::{self, Folder};
+use ptr::P;
+use syntax_pos::{BytePos, Span, DUMMY_SP};
+use tokenstream::TokenStream;
+use util::small_vector::SmallVector;
+
+/// AST nodes whose spans can be rewritten.
+pub trait FoldSpans: Sized {
+    fn fold_spans<F: Folder>(self, folder: &mut F) -> Self;
+}
+
+macro_rules! impl_fold_spans {
+    ($($ty:ty => $fold:ident),*) => { $(
+        impl FoldSpans for $ty {
+            fn fold_spans<F: Folder>(self, folder: &mut F) -> Self {
+                folder.$fold(self)
+            }
+        }
+    )* }
+}
+
+impl_fold_spans! {
+    Crate => fold_crate,
+    P<Expr> => fold_expr,
+    P<Pat> => fold_pat,
+    P<Ty> => fold_ty,
+    P<Block> => fold_block,
+    Path => fold_path,
+    Generics => fold_generics,
+    TokenStream => fold_tts
+}
+
+impl FoldSpans for P<ast::Item> {
+    fn fold_spans<F: Folder>(self, folder: &mut F) -> Self {
+        folder.fold_item(self).expect_one("expected one item")
+    }
+}
+
+impl FoldSpans for ast::Stmt {
+    fn fold_spans<F: Folder>(self, folder: &mut F) -> Self {
+        folder.fold_stmt(self).expect_one("expected one statement")
+    }
+}
+
+/// Replaces every span in `node` with the result of `f`.
+pub fn map_spans<T: FoldSpans, F: FnMut(Span) -> Span>(node: T, f: F) -> T {
+    node.fold_spans(&mut SpanRewriter { f })
+}
+
+/// Replaces every span in `node` with a dummy span, keeping the syntax
+/// contexts.
+pub fn zero_spans<T: FoldSpans>(node: T) -> T {
+    map_spans(node, |span| DUMMY_SP.with_ctxt(span.ctxt()))
+}
+
+/// Moves every span in `node` starting at or after `old_base` by
+/// `new_base - old_base`, e.g. from the file a snippet was parsed in to the
+/// position the snippet is spliced at. Dummy spans and spans before
+/// `old_base` are left alone.
+pub fn rebase_spans<T: FoldSpans>(node: T, old_base: BytePos, new_base: BytePos) -> T {
+    map_spans(node, |span| {
+        if span.is_dummy() || span.lo() < old_base {
+            return span;
+        }
+        let data = span.data();
+        Span::new(data.lo - old_base + new_base, data.hi - old_base + new_base, data.ctxt)
+    })
+}
+
+struct SpanRewriter<F> {
+    f: F,
+}
+
+impl<F: FnMut(Span) -> Span> Folder for SpanRewriter<F> {
+    fn fold_item_simple(&mut self, item: ast::Item) -> ast::Item {
+        fold::noop_fold_item_simple(ast::Item { tokens: None, ..item }, self)
+    }
+
+    fn fold_trait_item(&mut self, item: ast::TraitItem) -> SmallVector<ast::TraitItem> {
+        fold::noop_fold_trait_item(ast::TraitItem { tokens: None, ..item }, self)
+    }
+
+    fn fold_impl_item(&mut self, item: ast::ImplItem) -> SmallVector<ast::ImplItem> {
+        fold::noop_fold_impl_item(ast::ImplItem { tokens: None, ..item }, self)
+    }
+
+    fn fold_mac(&mut self, mac: ast::Mac) -> ast::Mac {
+        fold::noop_fold_mac(mac, self)
+    }
+
+    fn new_span(&mut self, span: Span) -> Span {
+        (self.f)(span)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codemap::FilePathMapping;
+    use parse::{self, ParseSess};
+    use std::path::PathBuf;
+    use util::parser_testing::string_to_expr;
+
+    #[test]
+    fn zero_spans_of_expr() {
+        let expr = zero_spans(string_to_expr("f(a + 1, b!(c))".to_string()));
+        assert!(expr.span.is_dummy());
+        match expr.node {
+            ast::ExprKind::Call(ref callee, ref args, _) => {
+                assert!(callee.span.is_dummy());
+                assert!(args.iter().all(|arg| arg.span.is_dummy()));
+            }
+            _ => panic!("expected a call, found {:?}", expr),
+        }
+    }
+
+    #[test]
+    fn zero_spans_of_match() {
+        let expr = zero_spans(string_to_expr("match x { 0 => a, _ => b, }".to_string()));
+        match expr.node {
+            ast::ExprKind::Match(ref scrutinee, ref arms) => {
+                assert!(scrutinee.span.is_dummy());
+                assert_eq!(arms.len(), 2);
+                assert!(arms.iter().all(|arm| arm.trailing_comma.map_or(false, |c| c.is_dummy())));
+            }
+            _ => panic!("expected a match, found {:?}", expr),
+        }
+    }
+
+    #[test]
+    fn rebase_spans_of_item() {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let item = parse::parse_item_from_source_str(PathBuf::from("item").into(),
+                                                     "fn f() { g() }".to_string(), &sess)
+            .unwrap().unwrap();
+        assert!(item.tokens.is_some());
+        let base = sess.codemap().lookup_byte_offset(item.span.lo()).fm.start_pos;
+        let lo = item.span.lo();
+
+        let item = rebase_spans(item, base, base + BytePos(100));
+        assert_eq!(item.span.lo(), lo + BytePos(100));
+        assert!(item.tokens.is_none());
+        match item.node {
+            ast::ItemKind::Fn(.., ref body) => assert_eq!(body.span.lo(), lo + BytePos(107)),
+            _ => panic!("expected a function, found {:?}", item),
+        }
+    }
+}
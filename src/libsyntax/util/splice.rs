@@ -0,0 +1,263 @@
+// Copyright 2018 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Stitching crates together, and taking them apart by module.
+//!
+//! These work on unexpanded crates, as produced by the parser. Node IDs are
+//! left as they are, so crates which already went through expansion need to
+//! be renumbered afterwards.
+
+use ast::{self, Crate, Ident, Item, ItemKind, Mod};
+use codemap::CodeMap;
+use fold::{self, Folder};
+use ptr::P;
+use syntax_pos::{BytePos, MultiByteChar, Span, DUMMY_SP};
+
+use std::collections::HashMap;
+use std::mem;
+
+/// Appends the items of `other` to the root module of `krate`, and returns the
+/// crate attributes of `other` for the caller to merge as it sees fit.
+///
+/// The appended items keep their spans, so both crates must have been parsed
+/// with the same codemap, or `other` moved to the codemap of `krate` with
+/// `import_crate`.
+pub fn append_crate(krate: &mut Crate, other: Crate) -> Vec<ast::Attribute> {
+    krate.module.items.extend(other.module.items);
+    other.attrs
+}
+
+/// Appends `items` to the module at `path` in `krate`, the crate root if
+/// `path` is empty. Only inline modules, and modules whose files were loaded
+/// while parsing, can be found. If there is no such module, the items are
+/// handed back.
+pub fn append_to_module(krate: &mut Crate, path: &[Ident], items: Vec<P<Item>>)
+                        -> Result<(), Vec<P<Item>>> {
+    match find_module_mut(&mut krate.module, path) {
+        Some(module) => {
+            module.items.extend(items);
+            Ok(())
+        }
+        None => Err(items),
+    }
+}
+
+fn find_module_mut<'a>(module: &'a mut Mod, path: &[Ident]) -> Option<&'a mut Mod> {
+    let (first, rest) = match path.split_first() {
+        Some(split) => split,
+        None => return Some(module),
+    };
+    for item in &mut module.items {
+        if item.ident.name != first.name {
+            continue
+        }
+        if let ItemKind::Mod(ref mut submodule) = item.node {
+            return find_module_mut(submodule, rest);
+        }
+    }
+    None
+}
+
+/// A module taken out of a crate by `split_modules`.
+pub struct SplitModule {
+    /// The path of the module from the crate root, empty for the root itself.
+    pub path: Vec<Ident>,
+    /// The attributes of the module item, or the crate attributes for the root.
+    pub attrs: Vec<ast::Attribute>,
+    /// The visibility of the module item, public for the root.
+    pub vis: ast::Visibility,
+    /// The span of the module item, or of the crate for the root.
+    pub span: Span,
+    /// The module, without its submodules.
+    pub module: Mod,
+}
+
+/// Splits `krate` into its modules. The crate root comes first, and each
+/// module is followed by its submodules, in the order of their items.
+pub fn split_modules(krate: Crate) -> Vec<SplitModule> {
+    let mut modules = Vec::new();
+    split_module(SplitModule {
+        path: Vec::new(),
+        attrs: krate.attrs,
+        vis: ast::Visibility::Public,
+        span: krate.span,
+        module: krate.module,
+    }, &mut modules);
+    modules
+}
+
+fn split_module(mut split: SplitModule, modules: &mut Vec<SplitModule>) {
+    let mut submodules = Vec::new();
+    for item in mem::replace(&mut split.module.items, Vec::new()) {
+        let is_mod = match item.node {
+            ItemKind::Mod(..) => true,
+            _ => false,
+        };
+        if is_mod {
+            submodules.push(item);
+        } else {
+            split.module.items.push(item);
+        }
+    }
+
+    let path = split.path.clone();
+    modules.push(split);
+    for item in submodules {
+        let Item { ident, attrs, vis, span, node, .. } = item.into_inner();
+        if let ItemKind::Mod(module) = node {
+            let mut path = path.clone();
+            path.push(ident);
+            split_module(SplitModule { path, attrs, vis, span, module }, modules);
+        }
+    }
+}
+
+/// Moves `krate`, parsed with the codemap `from`, to the codemap `to`, so that
+/// it can be combined with crates parsed with `to`. The files the spans of
+/// `krate` point into are added to `to`, and the spans are adjusted to point
+/// into the added files. Spans into files whose source is not available, like
+/// files imported from other crates, are replaced with dummy spans.
+///
+/// The token streams kept by items for procedural macros are dropped, rather
+/// than adjusted.
+pub fn import_crate(krate: Crate, from: &CodeMap, to: &CodeMap) -> Crate {
+    SpanImporter { from, to, files: HashMap::new() }.fold_crate(krate)
+}
+
+struct SpanImporter<'a> {
+    from: &'a CodeMap,
+    to: &'a CodeMap,
+    /// The start positions of the imported files in `from` and `to`, by their
+    /// index in `from`.
+    files: HashMap<usize, Option<(BytePos, BytePos)>>,
+}
+
+impl<'a> SpanImporter<'a> {
+    fn import_file(&mut self, idx: usize) -> Option<(BytePos, BytePos)> {
+        if let Some(&offsets) = self.files.get(&idx) {
+            return offsets;
+        }
+
+        let fm = self.from.files()[idx].clone();
+        let to = self.to;
+        let offsets = fm.src.as_ref().map(|src| {
+            let name = fm.unmapped_path.clone().unwrap_or_else(|| fm.name.clone());
+            let imported = to.new_filemap(name, (**src).clone());
+            let (from_start, to_start) = (fm.start_pos, imported.start_pos);
+            imported.lines.borrow_mut().extend(fm.lines.borrow().iter().map(|&pos| {
+                pos - from_start + to_start
+            }));
+            imported.multibyte_chars.borrow_mut().extend(fm.multibyte_chars.borrow().iter().map(
+                |mbc| MultiByteChar { pos: mbc.pos - from_start + to_start, bytes: mbc.bytes }
+            ));
+            imported.non_narrow_chars.borrow_mut().extend(fm.non_narrow_chars.borrow().iter().map(
+                |&nnc| nnc - from_start + to_start
+            ));
+            imported.syntax_variant.set(fm.syntax_variant.get());
+            *imported.frontmatter.borrow_mut() = fm.frontmatter.borrow().clone().map(|mut block| {
+                let data = block.span.data();
+                block.span = Span::new(data.lo - from_start + to_start,
+                                       data.hi - from_start + to_start, data.ctxt);
+                block
+            });
+            (from_start, to_start)
+        });
+        self.files.insert(idx, offsets);
+        offsets
+    }
+}
+
+impl<'a> Folder for SpanImporter<'a> {
+    fn fold_item_simple(&mut self, item: Item) -> Item {
+        Item { tokens: None, ..fold::noop_fold_item_simple(item, self) }
+    }
+
+    fn fold_mac(&mut self, mac: ast::Mac) -> ast::Mac {
+        fold::noop_fold_mac(mac, self)
+    }
+
+    fn new_span(&mut self, span: Span) -> Span {
+        if span.is_dummy() {
+            return span;
+        }
+        let data = span.data();
+        let idx = self.from.lookup_filemap_idx(data.lo);
+        match self.import_file(idx) {
+            Some((from_start, to_start)) => {
+                Span::new(data.lo - from_start + to_start, data.hi - from_start + to_start,
+                          data.ctxt)
+            }
+            None => DUMMY_SP.with_ctxt(data.ctxt),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codemap::FilePathMapping;
+    use parse::{self, ParseSess};
+    use std::path::PathBuf;
+    use util::parser_testing::string_to_crate;
+
+    fn module_names(krate: &Crate) -> Vec<String> {
+        split_modules(krate.clone()).iter().map(|split| {
+            split.path.iter().map(|ident| ident.to_string()).collect::<Vec<_>>().join("::")
+        }).collect()
+    }
+
+    #[test]
+    fn split() {
+        let krate = string_to_crate("#![a] fn f() {} mod m { pub mod n {} struct S; } \
+                                     mod o {}".to_string());
+        assert_eq!(module_names(&krate), ["", "m", "m::n", "o"]);
+
+        let modules = split_modules(krate);
+        assert_eq!(modules[0].attrs.len(), 1);
+        assert_eq!(modules[0].module.items.len(), 1);
+        assert_eq!(modules[1].module.items.len(), 1);
+        assert_eq!(modules[2].vis, ast::Visibility::Public);
+    }
+
+    #[test]
+    fn append() {
+        let mut krate = string_to_crate("mod m { mod n {} }".to_string());
+        let other = string_to_crate("#![b] fn g() {}".to_string());
+        assert_eq!(append_crate(&mut krate, other).len(), 1);
+        assert_eq!(krate.module.items.len(), 2);
+
+        let item = string_to_crate("fn h() {}".to_string()).module.items;
+        let path = [Ident::from_str("m"), Ident::from_str("n")];
+        assert!(append_to_module(&mut krate, &path, item).is_ok());
+        let modules = split_modules(krate.clone());
+        assert_eq!(modules[2].path, path);
+        assert_eq!(modules[2].module.items[0].ident.to_string(), "h");
+
+        let missing = [Ident::from_str("x")];
+        assert_eq!(append_to_module(&mut krate, &missing, Vec::new()).unwrap_err().len(), 0);
+    }
+
+    #[test]
+    fn import() {
+        let from = ParseSess::new(FilePathMapping::empty());
+        let to = ParseSess::new(FilePathMapping::empty());
+        parse::parse_crate_from_source_str(PathBuf::from("first").into(),
+                                           "fn f() {}".to_string(), &to).unwrap();
+        let krate = parse::parse_crate_from_source_str(PathBuf::from("second").into(),
+                                                       "fn g() { 1 }".to_string(), &from)
+                                                       .unwrap();
+
+        let krate = import_crate(krate, from.codemap(), to.codemap());
+        assert_eq!(to.codemap().files().len(), 2);
+        let item = &krate.module.items[0];
+        assert_eq!(to.codemap().span_to_snippet(item.span).unwrap(), "fn g() { 1 }");
+        assert_eq!(to.codemap().lookup_char_pos(item.span.hi()).col.0, 12);
+    }
+}
@@ -0,0 +1,219 @@
+// Copyright 2018 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! String literal discovery and rewriting.
+//!
+//! This module finds every string literal in a crate, including the ones
+//! that only exist as tokens inside macro invocations, and records their
+//! unescaped values. A `StrLitRewriter` can then be used to splice new
+//! values back into the original source text.
+
+use ast::{self, StrStyle};
+use codemap::CodeMap;
+use parse::{self, token};
+use symbol::Symbol;
+use syntax_pos::{FileMap, Span};
+use tokenstream::{TokenStream, TokenTree};
+use visit::{self, Visitor};
+
+/// A string literal found in the source.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StrLit {
+    /// The span of the literal, including quotes and any raw-string hashes.
+    pub span: Span,
+    /// The unescaped value of the literal.
+    pub value: Symbol,
+    pub style: StrStyle,
+    /// Whether the literal was found in the token trees of a macro invocation
+    /// rather than in a parsed expression.
+    pub in_macro: bool,
+}
+
+/// Returns the unescaped value of a string literal token, or `None` if the
+/// token is not a (non-byte) string literal.
+pub fn str_lit_token_value(lit: &token::Lit) -> Option<(Symbol, StrStyle)> {
+    match *lit {
+        token::Str_(s) => {
+            Some((Symbol::intern(&parse::str_lit(&s.as_str(), None)), StrStyle::Cooked))
+        }
+        token::StrRaw(s, n) => {
+            Some((Symbol::intern(&parse::raw_str_lit(&s.as_str())), StrStyle::Raw(n)))
+        }
+        _ => None,
+    }
+}
+
+/// Collects all string literals appearing in token trees.
+pub fn str_lits_in_tts(tts: TokenStream) -> Vec<StrLit> {
+    let mut collector = StrLitCollector { lits: Vec::new(), include_attrs: false };
+    collector.visit_tts(tts);
+    collector.lits
+}
+
+/// Collects all string literals in `krate`, in source order of traversal.
+///
+/// Literals inside macro invocations are found by walking the invocation's
+/// token trees, so this is most useful on an unexpanded crate. Literals in
+/// attributes (including doc comments) are skipped unless `include_attrs`
+/// is set.
+pub fn str_lits_in_crate(krate: &ast::Crate, include_attrs: bool) -> Vec<StrLit> {
+    let mut collector = StrLitCollector { lits: Vec::new(), include_attrs };
+    visit::walk_crate(&mut collector, krate);
+    collector.lits
+}
+
+/// If `mac` is a `concat!` invocation whose arguments are all string
+/// literals, returns the concatenated value.
+pub fn concat_str_value(mac: &ast::Mac) -> Option<String> {
+    if mac.node.path != "concat" {
+        return None;
+    }
+
+    let mut value = String::new();
+    let mut expect_lit = true;
+    for tt in mac.node.stream().trees() {
+        match tt {
+            TokenTree::Token(_, token::Literal(ref lit, None)) if expect_lit => {
+                match str_lit_token_value(lit) {
+                    Some((s, _)) => value.push_str(&s.as_str()),
+                    None => return None,
+                }
+            }
+            TokenTree::Token(_, token::Comma) if !expect_lit => {}
+            _ => return None,
+        }
+        expect_lit = !expect_lit;
+    }
+    Some(value)
+}
+
+struct StrLitCollector {
+    lits: Vec<StrLit>,
+    include_attrs: bool,
+}
+
+impl<'ast> Visitor<'ast> for StrLitCollector {
+    fn visit_expr(&mut self, e: &'ast ast::Expr) {
+        if let ast::ExprKind::Lit(ref lit) = e.node {
+            if let ast::LitKind::Str(value, style) = lit.node {
+                self.lits.push(StrLit { span: lit.span, value, style, in_macro: false });
+            }
+        }
+        visit::walk_expr(self, e);
+    }
+
+    fn visit_tt(&mut self, tt: TokenTree) {
+        if let TokenTree::Token(span, token::Literal(ref lit, _)) = tt {
+            if let Some((value, style)) = str_lit_token_value(lit) {
+                self.lits.push(StrLit { span, value, style, in_macro: true });
+            }
+        }
+        visit::walk_tt(self, tt);
+    }
+
+    fn visit_attribute(&mut self, attr: &'ast ast::Attribute) {
+        if self.include_attrs {
+            visit::walk_attribute(self, attr);
+        }
+    }
+
+    fn visit_mac(&mut self, mac: &'ast ast::Mac) {
+        self.visit_tts(mac.node.stream());
+    }
+}
+
+/// Accumulates replacement values for string literals and produces the
+/// rewritten source of a file.
+pub struct StrLitRewriter {
+    replacements: Vec<(Span, String)>,
+}
+
+impl StrLitRewriter {
+    pub fn new() -> StrLitRewriter {
+        StrLitRewriter { replacements: Vec::new() }
+    }
+
+    /// Schedules `lit` to be replaced by a cooked string literal holding `value`.
+    pub fn replace(&mut self, lit: &StrLit, value: &str) {
+        let quoted = format!("\"{}\"", parse::escape_default(value));
+        self.replacements.push((lit.span, quoted));
+    }
+
+    /// Returns the source of `filemap` with every scheduled replacement that
+    /// falls inside it applied, or `None` if the source is not available.
+    pub fn rewrite(&self, cm: &CodeMap, filemap: &FileMap) -> Option<String> {
+        let src = match filemap.src {
+            Some(ref src) => src,
+            None => return None,
+        };
+
+        let mut replacements = self.replacements.iter()
+            .filter(|&&(span, _)| {
+                span.lo() >= filemap.start_pos && span.hi() <= filemap.end_pos
+            })
+            .collect::<Vec<_>>();
+        replacements.sort_by_key(|&&(span, _)| span.lo());
+
+        let mut out = String::with_capacity(src.len());
+        let mut last = 0;
+        for &&(span, ref text) in &replacements {
+            let lo = cm.lookup_byte_offset(span.lo()).pos.to_usize();
+            let hi = cm.lookup_byte_offset(span.hi()).pos.to_usize();
+            if lo < last {
+                // Overlapping replacement; keep the first one.
+                continue;
+            }
+            out.push_str(&src[last..lo]);
+            out.push_str(text);
+            last = hi;
+        }
+        out.push_str(&src[last..]);
+        Some(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codemap::FilePathMapping;
+    use parse::{self, ParseSess};
+    use std::path::PathBuf;
+
+    #[test]
+    fn finds_lits_in_exprs_and_macros() {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let krate = parse::parse_crate_from_source_str(
+            PathBuf::from("lits.rs").into(),
+            "#[doc = \"skip\"] fn f() { let _ = \"a\\tb\"; println!(r\"c\", \"d\"); }".to_string(),
+            &sess).unwrap();
+
+        let lits = str_lits_in_crate(&krate, false);
+        let values = lits.iter().map(|l| (l.value.to_string(), l.in_macro)).collect::<Vec<_>>();
+        assert_eq!(values, vec![("a\tb".to_string(), false),
+                                ("c".to_string(), true),
+                                ("d".to_string(), true)]);
+        assert_eq!(str_lits_in_crate(&krate, true).len(), 4);
+    }
+
+    #[test]
+    fn rewrites_selected_lits() {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let src = "fn f() { g(\"one\", \"two\"); }";
+        let krate = parse::parse_crate_from_source_str(
+            PathBuf::from("lits.rs").into(), src.to_string(), &sess).unwrap();
+
+        let lits = str_lits_in_crate(&krate, false);
+        let mut rewriter = StrLitRewriter::new();
+        rewriter.replace(&lits[1], "deux\n");
+        let fm = sess.codemap().files()[0].clone();
+        assert_eq!(rewriter.rewrite(sess.codemap(), &fm).unwrap(),
+                   "fn f() { g(\"one\", \"deux\\n\"); }");
+    }
+}
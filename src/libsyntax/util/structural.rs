@@ -0,0 +1,127 @@
+// Copyright 2018 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Comparing and hashing AST nodes by their structure.
+//!
+//! The AST types implement `PartialEq` and `Hash` including their spans and
+//! node IDs, so the same code parsed twice never compares equal. Wrapping a
+//! node in `Structural` makes it compare and hash by its canonical source
+//! instead, e.g. to use expressions as keys in memoization tables.
+//!
+//! The canonical source is produced by the pretty printer, which does not
+//! print everything in the tree, like hygiene information. Nodes which only
+//! differ in such details have the same key, so `Structural` is suited to
+//! caches which may share results between them, but not to telling whether
+//! two trees are identical.
+
+use ast;
+use print::pprust;
+use ptr::P;
+
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+
+/// AST nodes which can be wrapped in `Structural`.
+pub trait StructuralKey {
+    /// Returns a key which is the same for two nodes with the same structure
+    /// and names, disregarding spans, node IDs and hygiene. Nodes which print
+    /// the same have the same key even if their trees differ, see the module
+    /// documentation.
+    fn structural_key(&self) -> String;
+}
+
+impl StructuralKey for ast::Expr {
+    fn structural_key(&self) -> String {
+        pprust::to_canonical_string(|s| s.print_expr(self))
+    }
+}
+
+impl StructuralKey for ast::Ty {
+    fn structural_key(&self) -> String {
+        pprust::to_canonical_string(|s| s.print_type(self))
+    }
+}
+
+impl StructuralKey for ast::Pat {
+    fn structural_key(&self) -> String {
+        pprust::to_canonical_string(|s| s.print_pat(self))
+    }
+}
+
+impl<T: StructuralKey> StructuralKey for P<T> {
+    fn structural_key(&self) -> String {
+        (**self).structural_key()
+    }
+}
+
+/// An AST node which compares and hashes by its structure, see
+/// `StructuralKey`. The key is computed once, when the node is wrapped, and
+/// takes as much memory as the printed node.
+#[derive(Clone, Debug)]
+pub struct Structural<T> {
+    node: T,
+    key: String,
+}
+
+impl<T: StructuralKey> Structural<T> {
+    pub fn new(node: T) -> Structural<T> {
+        let key = node.structural_key();
+        Structural { node, key }
+    }
+}
+
+impl<T> Structural<T> {
+    pub fn into_inner(self) -> T {
+        self.node
+    }
+}
+
+impl<T> Deref for Structural<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.node
+    }
+}
+
+impl<T> PartialEq for Structural<T> {
+    fn eq(&self, other: &Structural<T>) -> bool {
+        self.key == other.key
+    }
+}
+
+impl<T> Eq for Structural<T> {}
+
+impl<T> Hash for Structural<T> {
+    fn hash<H: Hasher>(&self, hasher: &mut H) {
+        self.key.hash(hasher)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use util::parser_testing::{string_to_expr, string_to_pat};
+
+    use std::collections::HashMap;
+
+    #[test]
+    fn structural_keys() {
+        let mut memo = HashMap::new();
+        memo.insert(Structural::new(string_to_expr("a + f(1)".to_string())), 1);
+        assert_eq!(memo.get(&Structural::new(string_to_expr("  a+f( 1 )".to_string()))),
+                   Some(&1));
+        assert!(memo.get(&Structural::new(string_to_expr("a + f(2)".to_string()))).is_none());
+
+        let pat = Structural::new(string_to_pat("Some(x)".to_string()));
+        assert!(pat == Structural::new(string_to_pat("Some( x )".to_string())));
+        assert!(pat != Structural::new(string_to_pat("Some(y)".to_string())));
+    }
+}
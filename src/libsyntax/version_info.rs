@@ -0,0 +1,107 @@
+// Copyright 2018 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A description of the grammar this crate supports, for tools which can be
+//! linked against several versions of it and need to find out at runtime
+//! what the parser can handle.
+
+use feature_gate;
+use parse::token;
+
+/// A language feature, see `feature_gate`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FeatureInfo {
+    pub name: &'static str,
+    /// The version the feature was added in if it is unstable, or the
+    /// version it was stabilized in.
+    pub since: &'static str,
+    pub issue: Option<u32>,
+}
+
+#[derive(Debug)]
+pub struct VersionInfo {
+    /// The version of the compiler this crate was built with, e.g.
+    /// `1.25.0-nightly`, when it is known at build time.
+    pub version: Option<&'static str>,
+    /// The release channel, `stable`, `beta`, `nightly` or `dev`, when it is
+    /// known at build time.
+    pub release_channel: Option<&'static str>,
+    /// The editions of the language the parser accepts.
+    pub editions: &'static [&'static str],
+    /// The kinds of literals the lexer accepts, each with its name and an
+    /// example, see `token::LITERAL_KINDS`.
+    pub literal_kinds: &'static [(&'static str, &'static str)],
+    /// The suffixes integer literals can have.
+    pub integer_suffixes: &'static [&'static str],
+    /// The suffixes float literals can have.
+    pub float_suffixes: &'static [&'static str],
+}
+
+pub static VERSION_INFO: VersionInfo = VersionInfo {
+    version: option_env!("CFG_VERSION"),
+    release_channel: option_env!("CFG_RELEASE_CHANNEL"),
+    editions: &["2015"],
+    literal_kinds: token::LITERAL_KINDS,
+    integer_suffixes: &["isize", "i8", "i16", "i32", "i64", "i128",
+                        "usize", "u8", "u16", "u32", "u64", "u128"],
+    float_suffixes: &["f32", "f64"],
+};
+
+impl VersionInfo {
+    /// The unstable features, whose syntax is only accepted in crates which
+    /// enable them with `#![feature]`.
+    pub fn gated_features(&self) -> Vec<FeatureInfo> {
+        feature_gate::active_lang_features().into_iter().map(|(name, since, issue)| {
+            FeatureInfo { name, since, issue }
+        }).collect()
+    }
+
+    /// The features which have been stabilized.
+    pub fn accepted_features(&self) -> Vec<FeatureInfo> {
+        feature_gate::accepted_lang_features().into_iter().map(|(name, since, issue)| {
+            FeatureInfo { name, since, issue }
+        }).collect()
+    }
+
+    /// Whether the language feature `name` is known, stable or not.
+    pub fn supports_feature(&self, name: &str) -> bool {
+        feature_gate::is_lang_feature(name)
+    }
+
+    pub fn supports_edition(&self, edition: &str) -> bool {
+        self.editions.contains(&edition)
+    }
+
+    /// Whether the lexer accepts the kind of literal `name`, like `"raw string"`.
+    pub fn supports_literal(&self, name: &str) -> bool {
+        self.literal_kinds.iter().any(|&(kind, _)| kind == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_info() {
+        assert!(VERSION_INFO.supports_edition("2015"));
+        assert!(!VERSION_INFO.supports_edition("2018"));
+        assert!(VERSION_INFO.supports_literal("raw byte string"));
+        assert!(!VERSION_INFO.supports_literal("c string"));
+        assert!(VERSION_INFO.integer_suffixes.contains(&"u128"));
+
+        let asm = VERSION_INFO.gated_features().into_iter().find(|feature| feature.name == "asm");
+        assert_eq!(asm, Some(FeatureInfo { name: "asm", since: "1.0.0", issue: Some(29722) }));
+        assert!(VERSION_INFO.accepted_features().iter().any(|feature| feature.name == "if_let"));
+        assert!(VERSION_INFO.supports_feature("asm"));
+        assert!(VERSION_INFO.supports_feature("if_let"));
+        assert!(!VERSION_INFO.supports_feature("no_such_feature"));
+    }
+}
@@ -150,6 +150,12 @@ pub trait Visitor<'ast>: Sized {
     fn visit_fn_ret_ty(&mut self, ret_ty: &'ast FunctionRetTy) {
         walk_fn_ret_ty(self, ret_ty)
     }
+    /// Whether the placeholders left where code failed to parse, like
+    /// `ExprKind::Err`, are skipped instead of being passed to `visit_expr`,
+    /// `visit_pat`, `visit_ty` and `visit_item`.
+    fn skip_err_nodes(&self) -> bool {
+        false
+    }
 }
 
 #[macro_export]
@@ -166,6 +172,23 @@ macro_rules! walk_list {
     }
 }
 
+macro_rules! visit_unless_err {
+    ($visitor: expr, $method: ident, $node: expr) => {{
+        let node = $node;
+        if !(node.is_err() && $visitor.skip_err_nodes()) {
+            $visitor.$method(node)
+        }
+    }}
+}
+
+macro_rules! walk_list_unless_err {
+    ($visitor: expr, $method: ident, $list: expr) => {
+        for elem in $list {
+            visit_unless_err!($visitor, $method, elem)
+        }
+    }
+}
+
 pub fn walk_ident<'a, V: Visitor<'a>>(visitor: &mut V, span: Span, ident: Ident) {
     visitor.visit_name(span, ident.name);
 }
@@ -176,16 +199,16 @@ pub fn walk_crate<'a, V: Visitor<'a>>(visitor: &mut V, krate: &'a Crate) {
 }
 
 pub fn walk_mod<'a, V: Visitor<'a>>(visitor: &mut V, module: &'a Mod) {
-    walk_list!(visitor, visit_item, &module.items);
+    walk_list_unless_err!(visitor, visit_item, &module.items);
 }
 
 pub fn walk_local<'a, V: Visitor<'a>>(visitor: &mut V, local: &'a Local) {
     for attr in local.attrs.iter() {
         visitor.visit_attribute(attr);
     }
-    visitor.visit_pat(&local.pat);
-    walk_list!(visitor, visit_ty, &local.ty);
-    walk_list!(visitor, visit_expr, &local.init);
+    visit_unless_err!(visitor, visit_pat, &local.pat);
+    walk_list_unless_err!(visitor, visit_ty, &local.ty);
+    walk_list_unless_err!(visitor, visit_expr, &local.init);
 }
 
 pub fn walk_label<'a, V: Visitor<'a>>(visitor: &mut V, label: &'a Label) {
@@ -223,8 +246,8 @@ pub fn walk_item<'a, V: Visitor<'a>>(visitor: &mut V, item: &'a Item) {
         }
         ItemKind::Static(ref typ, _, ref expr) |
         ItemKind::Const(ref typ, ref expr) => {
-            visitor.visit_ty(typ);
-            visitor.visit_expr(expr);
+            visit_unless_err!(visitor, visit_ty, typ);
+            visit_unless_err!(visitor, visit_expr, expr);
         }
         ItemKind::Fn(ref declaration, unsafety, constness, abi, ref generics, ref body) => {
             visitor.visit_generics(generics);
@@ -242,7 +265,7 @@ pub fn walk_item<'a, V: Visitor<'a>>(visitor: &mut V, item: &'a Item) {
         }
         ItemKind::GlobalAsm(ref ga) => visitor.visit_global_asm(ga),
         ItemKind::Ty(ref typ, ref type_parameters) => {
-            visitor.visit_ty(typ);
+            visit_unless_err!(visitor, visit_ty, typ);
             visitor.visit_generics(type_parameters)
         }
         ItemKind::Enum(ref enum_definition, ref type_parameters) => {
@@ -256,7 +279,7 @@ pub fn walk_item<'a, V: Visitor<'a>>(visitor: &mut V, item: &'a Item) {
                  ref impl_items) => {
             visitor.visit_generics(type_parameters);
             walk_list!(visitor, visit_trait_ref, opt_trait_reference);
-            visitor.visit_ty(typ);
+            visit_unless_err!(visitor, visit_ty, typ);
             walk_list!(visitor, visit_impl_item, impl_items);
         }
         ItemKind::Struct(ref struct_definition, ref generics) |
@@ -276,6 +299,8 @@ pub fn walk_item<'a, V: Visitor<'a>>(visitor: &mut V, item: &'a Item) {
         }
         ItemKind::Mac(ref mac) => visitor.visit_mac(mac),
         ItemKind::MacroDef(ref ts) => visitor.visit_mac_def(ts, item.id),
+        ItemKind::Extension(ref ext) => visitor.visit_tts(ext.tokens.clone().into()),
+        ItemKind::Err => {}
     }
     walk_list!(visitor, visit_attribute, &item.attrs);
 }
@@ -296,25 +321,25 @@ pub fn walk_variant<'a, V>(visitor: &mut V,
     visitor.visit_ident(variant.span, variant.node.name);
     visitor.visit_variant_data(&variant.node.data, variant.node.name,
                              generics, item_id, variant.span);
-    walk_list!(visitor, visit_expr, &variant.node.disr_expr);
+    walk_list_unless_err!(visitor, visit_expr, &variant.node.disr_expr);
     walk_list!(visitor, visit_attribute, &variant.node.attrs);
 }
 
 pub fn walk_ty<'a, V: Visitor<'a>>(visitor: &mut V, typ: &'a Ty) {
     match typ.node {
         TyKind::Slice(ref ty) | TyKind::Paren(ref ty) => {
-            visitor.visit_ty(ty)
+            visit_unless_err!(visitor, visit_ty, ty)
         }
         TyKind::Ptr(ref mutable_type) => {
-            visitor.visit_ty(&mutable_type.ty)
+            visit_unless_err!(visitor, visit_ty, &mutable_type.ty)
         }
         TyKind::Rptr(ref opt_lifetime, ref mutable_type) => {
             walk_list!(visitor, visit_lifetime, opt_lifetime);
-            visitor.visit_ty(&mutable_type.ty)
+            visit_unless_err!(visitor, visit_ty, &mutable_type.ty)
         }
         TyKind::Never => {},
         TyKind::Tup(ref tuple_element_types) => {
-            walk_list!(visitor, visit_ty, tuple_element_types);
+            walk_list_unless_err!(visitor, visit_ty, tuple_element_types);
         }
         TyKind::BareFn(ref function_declaration) => {
             walk_fn_decl(visitor, &function_declaration.decl);
@@ -322,20 +347,20 @@ pub fn walk_ty<'a, V: Visitor<'a>>(visitor: &mut V, typ: &'a Ty) {
         }
         TyKind::Path(ref maybe_qself, ref path) => {
             if let Some(ref qself) = *maybe_qself {
-                visitor.visit_ty(&qself.ty);
+                visit_unless_err!(visitor, visit_ty, &qself.ty);
             }
             visitor.visit_path(path, typ.id);
         }
         TyKind::Array(ref ty, ref expression) => {
-            visitor.visit_ty(ty);
-            visitor.visit_expr(expression)
+            visit_unless_err!(visitor, visit_ty, ty);
+            visit_unless_err!(visitor, visit_expr, expression)
         }
         TyKind::TraitObject(ref bounds, ..) |
         TyKind::ImplTrait(ref bounds) => {
             walk_list!(visitor, visit_ty_param_bound, bounds);
         }
         TyKind::Typeof(ref expression) => {
-            visitor.visit_expr(expression)
+            visit_unless_err!(visitor, visit_expr, expression)
         }
         TyKind::Infer | TyKind::ImplicitSelf | TyKind::Err => {}
         TyKind::Mac(ref mac) => {
@@ -360,7 +385,7 @@ pub fn walk_use_tree<'a, V: Visitor<'a>>(
             visitor.visit_ident(use_tree.span, ident);
         }
         UseTreeKind::Glob => {},
-        UseTreeKind::Nested(ref use_trees) => {
+        UseTreeKind::Nested(ref use_trees, _) => {
             for &(ref nested_tree, nested_id) in use_trees {
                 visitor.visit_use_tree(nested_tree, nested_id, true);
             }
@@ -384,13 +409,13 @@ pub fn walk_path_parameters<'a, V>(visitor: &mut V,
 {
     match *path_parameters {
         PathParameters::AngleBracketed(ref data) => {
-            walk_list!(visitor, visit_ty, &data.types);
+            walk_list_unless_err!(visitor, visit_ty, &data.types);
             walk_list!(visitor, visit_lifetime, &data.lifetimes);
             walk_list!(visitor, visit_assoc_type_binding, &data.bindings);
         }
         PathParameters::Parenthesized(ref data) => {
-            walk_list!(visitor, visit_ty, &data.inputs);
-            walk_list!(visitor, visit_ty, &data.output);
+            walk_list_unless_err!(visitor, visit_ty, &data.inputs);
+            walk_list_unless_err!(visitor, visit_ty, &data.output);
         }
     }
 }
@@ -398,18 +423,18 @@ pub fn walk_path_parameters<'a, V>(visitor: &mut V,
 pub fn walk_assoc_type_binding<'a, V: Visitor<'a>>(visitor: &mut V,
                                                    type_binding: &'a TypeBinding) {
     visitor.visit_ident(type_binding.span, type_binding.ident);
-    visitor.visit_ty(&type_binding.ty);
+    visit_unless_err!(visitor, visit_ty, &type_binding.ty);
 }
 
 pub fn walk_pat<'a, V: Visitor<'a>>(visitor: &mut V, pattern: &'a Pat) {
     match pattern.node {
         PatKind::TupleStruct(ref path, ref children, _) => {
             visitor.visit_path(path, pattern.id);
-            walk_list!(visitor, visit_pat, children);
+            walk_list_unless_err!(visitor, visit_pat, children);
         }
         PatKind::Path(ref opt_qself, ref path) => {
             if let Some(ref qself) = *opt_qself {
-                visitor.visit_ty(&qself.ty);
+                visit_unless_err!(visitor, visit_ty, &qself.ty);
             }
             visitor.visit_path(path, pattern.id)
         }
@@ -418,32 +443,33 @@ pub fn walk_pat<'a, V: Visitor<'a>>(visitor: &mut V, pattern: &'a Pat) {
             for field in fields {
                 walk_list!(visitor, visit_attribute, field.node.attrs.iter());
                 visitor.visit_ident(field.span, field.node.ident);
-                visitor.visit_pat(&field.node.pat)
+                visit_unless_err!(visitor, visit_pat, &field.node.pat)
             }
         }
         PatKind::Tuple(ref tuple_elements, _) => {
-            walk_list!(visitor, visit_pat, tuple_elements);
+            walk_list_unless_err!(visitor, visit_pat, tuple_elements);
         }
         PatKind::Box(ref subpattern) |
         PatKind::Ref(ref subpattern, _) => {
-            visitor.visit_pat(subpattern)
+            visit_unless_err!(visitor, visit_pat, subpattern)
         }
         PatKind::Ident(_, ref pth1, ref optional_subpattern) => {
             visitor.visit_ident(pth1.span, pth1.node);
-            walk_list!(visitor, visit_pat, optional_subpattern);
+            walk_list_unless_err!(visitor, visit_pat, optional_subpattern);
         }
-        PatKind::Lit(ref expression) => visitor.visit_expr(expression),
+        PatKind::Lit(ref expression) => visit_unless_err!(visitor, visit_expr, expression),
         PatKind::Range(ref lower_bound, ref upper_bound, _) => {
-            visitor.visit_expr(lower_bound);
-            visitor.visit_expr(upper_bound);
+            visit_unless_err!(visitor, visit_expr, lower_bound);
+            visit_unless_err!(visitor, visit_expr, upper_bound);
         }
         PatKind::Wild => (),
         PatKind::Slice(ref prepatterns, ref slice_pattern, ref postpatterns) => {
-            walk_list!(visitor, visit_pat, prepatterns);
-            walk_list!(visitor, visit_pat, slice_pattern);
-            walk_list!(visitor, visit_pat, postpatterns);
+            walk_list_unless_err!(visitor, visit_pat, prepatterns);
+            walk_list_unless_err!(visitor, visit_pat, slice_pattern);
+            walk_list_unless_err!(visitor, visit_pat, postpatterns);
         }
         PatKind::Mac(ref mac) => visitor.visit_mac(mac),
+        PatKind::Err => {}
     }
 }
 
@@ -456,8 +482,9 @@ pub fn walk_foreign_item<'a, V: Visitor<'a>>(visitor: &mut V, foreign_item: &'a
             walk_fn_decl(visitor, function_declaration);
             visitor.visit_generics(generics)
         }
-        ForeignItemKind::Static(ref typ, _) => visitor.visit_ty(typ),
+        ForeignItemKind::Static(ref typ, _) => visit_unless_err!(visitor, visit_ty, typ),
         ForeignItemKind::Ty => (),
+        ForeignItemKind::Macro(ref mac) => visitor.visit_mac(mac),
     }
 
     walk_list!(visitor, visit_attribute, &foreign_item.attrs);
@@ -488,9 +515,14 @@ pub fn walk_generic_param<'a, V: Visitor<'a>>(visitor: &mut V, param: &'a Generi
         GenericParam::Type(ref t) => {
             visitor.visit_ident(t.span, t.ident);
             walk_list!(visitor, visit_ty_param_bound, &t.bounds);
-            walk_list!(visitor, visit_ty, &t.default);
+            walk_list_unless_err!(visitor, visit_ty, &t.default);
             walk_list!(visitor, visit_attribute, &*t.attrs);
         }
+        GenericParam::Const(ref c) => {
+            visitor.visit_ident(c.span, c.ident);
+            visit_unless_err!(visitor, visit_ty, &c.ty);
+            walk_list!(visitor, visit_attribute, &*c.attrs);
+        }
     }
 }
 
@@ -505,7 +537,7 @@ pub fn walk_where_predicate<'a, V: Visitor<'a>>(visitor: &mut V, predicate: &'a
                                                            ref bounds,
                                                            ref bound_generic_params,
                                                            ..}) => {
-            visitor.visit_ty(bounded_ty);
+            visit_unless_err!(visitor, visit_ty, bounded_ty);
             walk_list!(visitor, visit_ty_param_bound, bounds);
             walk_list!(visitor, visit_generic_param, bound_generic_params);
         }
@@ -518,22 +550,22 @@ pub fn walk_where_predicate<'a, V: Visitor<'a>>(visitor: &mut V, predicate: &'a
         WherePredicate::EqPredicate(WhereEqPredicate{ref lhs_ty,
                                                      ref rhs_ty,
                                                      ..}) => {
-            visitor.visit_ty(lhs_ty);
-            visitor.visit_ty(rhs_ty);
+            visit_unless_err!(visitor, visit_ty, lhs_ty);
+            visit_unless_err!(visitor, visit_ty, rhs_ty);
         }
     }
 }
 
 pub fn walk_fn_ret_ty<'a, V: Visitor<'a>>(visitor: &mut V, ret_ty: &'a FunctionRetTy) {
     if let FunctionRetTy::Ty(ref output_ty) = *ret_ty {
-        visitor.visit_ty(output_ty)
+        visit_unless_err!(visitor, visit_ty, output_ty)
     }
 }
 
 pub fn walk_fn_decl<'a, V: Visitor<'a>>(visitor: &mut V, function_declaration: &'a FnDecl) {
     for argument in &function_declaration.inputs {
-        visitor.visit_pat(&argument.pat);
-        visitor.visit_ty(&argument.ty)
+        visit_unless_err!(visitor, visit_pat, &argument.pat);
+        visit_unless_err!(visitor, visit_ty, &argument.ty)
     }
     visitor.visit_fn_ret_ty(&function_declaration.output)
 }
@@ -552,7 +584,7 @@ pub fn walk_fn<'a, V>(visitor: &mut V, kind: FnKind<'a>, declaration: &'a FnDecl
         }
         FnKind::Closure(body) => {
             walk_fn_decl(visitor, declaration);
-            visitor.visit_expr(body);
+            visit_unless_err!(visitor, visit_expr, body);
         }
     }
 }
@@ -563,8 +595,8 @@ pub fn walk_trait_item<'a, V: Visitor<'a>>(visitor: &mut V, trait_item: &'a Trai
     visitor.visit_generics(&trait_item.generics);
     match trait_item.node {
         TraitItemKind::Const(ref ty, ref default) => {
-            visitor.visit_ty(ty);
-            walk_list!(visitor, visit_expr, default);
+            visit_unless_err!(visitor, visit_ty, ty);
+            walk_list_unless_err!(visitor, visit_expr, default);
         }
         TraitItemKind::Method(ref sig, None) => {
             walk_fn_decl(visitor, &sig.decl);
@@ -575,7 +607,7 @@ pub fn walk_trait_item<'a, V: Visitor<'a>>(visitor: &mut V, trait_item: &'a Trai
         }
         TraitItemKind::Type(ref bounds, ref default) => {
             walk_list!(visitor, visit_ty_param_bound, bounds);
-            walk_list!(visitor, visit_ty, default);
+            walk_list_unless_err!(visitor, visit_ty, default);
         }
         TraitItemKind::Macro(ref mac) => {
             visitor.visit_mac(mac);
@@ -590,15 +622,15 @@ pub fn walk_impl_item<'a, V: Visitor<'a>>(visitor: &mut V, impl_item: &'a ImplIt
     visitor.visit_generics(&impl_item.generics);
     match impl_item.node {
         ImplItemKind::Const(ref ty, ref expr) => {
-            visitor.visit_ty(ty);
-            visitor.visit_expr(expr);
+            visit_unless_err!(visitor, visit_ty, ty);
+            visit_unless_err!(visitor, visit_expr, expr);
         }
         ImplItemKind::Method(ref sig, ref body) => {
             visitor.visit_fn(FnKind::Method(impl_item.ident, sig, Some(&impl_item.vis), body),
                              &sig.decl, impl_item.span, impl_item.id);
         }
         ImplItemKind::Type(ref ty) => {
-            visitor.visit_ty(ty);
+            visit_unless_err!(visitor, visit_ty, ty);
         }
         ImplItemKind::Macro(ref mac) => {
             visitor.visit_mac(mac);
@@ -615,7 +647,7 @@ pub fn walk_struct_field<'a, V: Visitor<'a>>(visitor: &mut V, struct_field: &'a
     if let Some(ident) = struct_field.ident {
         visitor.visit_ident(struct_field.span, ident);
     }
-    visitor.visit_ty(&struct_field.ty);
+    visit_unless_err!(visitor, visit_ty, &struct_field.ty);
     walk_list!(visitor, visit_attribute, &struct_field.attrs);
 }
 
@@ -626,9 +658,9 @@ pub fn walk_block<'a, V: Visitor<'a>>(visitor: &mut V, block: &'a Block) {
 pub fn walk_stmt<'a, V: Visitor<'a>>(visitor: &mut V, statement: &'a Stmt) {
     match statement.node {
         StmtKind::Local(ref local) => visitor.visit_local(local),
-        StmtKind::Item(ref item) => visitor.visit_item(item),
+        StmtKind::Item(ref item) => visit_unless_err!(visitor, visit_item, item),
         StmtKind::Expr(ref expression) | StmtKind::Semi(ref expression) => {
-            visitor.visit_expr(expression)
+            visit_unless_err!(visitor, visit_expr, expression)
         }
         StmtKind::Mac(ref mac) => {
             let (ref mac, _, ref attrs) = **mac;
@@ -650,77 +682,77 @@ pub fn walk_expr<'a, V: Visitor<'a>>(visitor: &mut V, expression: &'a Expr) {
     }
     match expression.node {
         ExprKind::Box(ref subexpression) => {
-            visitor.visit_expr(subexpression)
+            visit_unless_err!(visitor, visit_expr, subexpression)
         }
         ExprKind::InPlace(ref place, ref subexpression) => {
-            visitor.visit_expr(place);
-            visitor.visit_expr(subexpression)
+            visit_unless_err!(visitor, visit_expr, place);
+            visit_unless_err!(visitor, visit_expr, subexpression)
         }
         ExprKind::Array(ref subexpressions) => {
-            walk_list!(visitor, visit_expr, subexpressions);
+            walk_list_unless_err!(visitor, visit_expr, subexpressions);
         }
         ExprKind::Repeat(ref element, ref count) => {
-            visitor.visit_expr(element);
-            visitor.visit_expr(count)
+            visit_unless_err!(visitor, visit_expr, element);
+            visit_unless_err!(visitor, visit_expr, count)
         }
-        ExprKind::Struct(ref path, ref fields, ref optional_base) => {
+        ExprKind::Struct(ref path, ref fields, ref optional_base, _) => {
             visitor.visit_path(path, expression.id);
             for field in fields {
                 walk_list!(visitor, visit_attribute, field.attrs.iter());
                 visitor.visit_ident(field.ident.span, field.ident.node);
-                visitor.visit_expr(&field.expr)
+                visit_unless_err!(visitor, visit_expr, &field.expr)
             }
-            walk_list!(visitor, visit_expr, optional_base);
+            walk_list_unless_err!(visitor, visit_expr, optional_base);
         }
-        ExprKind::Tup(ref subexpressions) => {
-            walk_list!(visitor, visit_expr, subexpressions);
+        ExprKind::Tup(ref subexpressions, _) => {
+            walk_list_unless_err!(visitor, visit_expr, subexpressions);
         }
-        ExprKind::Call(ref callee_expression, ref arguments) => {
-            visitor.visit_expr(callee_expression);
-            walk_list!(visitor, visit_expr, arguments);
+        ExprKind::Call(ref callee_expression, ref arguments, _) => {
+            visit_unless_err!(visitor, visit_expr, callee_expression);
+            walk_list_unless_err!(visitor, visit_expr, arguments);
         }
         ExprKind::MethodCall(ref segment, ref arguments) => {
             visitor.visit_path_segment(expression.span, segment);
-            walk_list!(visitor, visit_expr, arguments);
+            walk_list_unless_err!(visitor, visit_expr, arguments);
         }
         ExprKind::Binary(_, ref left_expression, ref right_expression) => {
-            visitor.visit_expr(left_expression);
-            visitor.visit_expr(right_expression)
+            visit_unless_err!(visitor, visit_expr, left_expression);
+            visit_unless_err!(visitor, visit_expr, right_expression)
         }
         ExprKind::AddrOf(_, ref subexpression) | ExprKind::Unary(_, ref subexpression) => {
-            visitor.visit_expr(subexpression)
+            visit_unless_err!(visitor, visit_expr, subexpression)
         }
         ExprKind::Lit(_) => {}
         ExprKind::Cast(ref subexpression, ref typ) | ExprKind::Type(ref subexpression, ref typ) => {
-            visitor.visit_expr(subexpression);
-            visitor.visit_ty(typ)
+            visit_unless_err!(visitor, visit_expr, subexpression);
+            visit_unless_err!(visitor, visit_ty, typ)
         }
         ExprKind::If(ref head_expression, ref if_block, ref optional_else) => {
-            visitor.visit_expr(head_expression);
+            visit_unless_err!(visitor, visit_expr, head_expression);
             visitor.visit_block(if_block);
-            walk_list!(visitor, visit_expr, optional_else);
+            walk_list_unless_err!(visitor, visit_expr, optional_else);
         }
         ExprKind::While(ref subexpression, ref block, ref opt_label) => {
             walk_list!(visitor, visit_label, opt_label);
-            visitor.visit_expr(subexpression);
+            visit_unless_err!(visitor, visit_expr, subexpression);
             visitor.visit_block(block);
         }
         ExprKind::IfLet(ref pattern, ref subexpression, ref if_block, ref optional_else) => {
-            visitor.visit_pat(pattern);
-            visitor.visit_expr(subexpression);
+            visit_unless_err!(visitor, visit_pat, pattern);
+            visit_unless_err!(visitor, visit_expr, subexpression);
             visitor.visit_block(if_block);
-            walk_list!(visitor, visit_expr, optional_else);
+            walk_list_unless_err!(visitor, visit_expr, optional_else);
         }
         ExprKind::WhileLet(ref pattern, ref subexpression, ref block, ref opt_label) => {
             walk_list!(visitor, visit_label, opt_label);
-            visitor.visit_pat(pattern);
-            visitor.visit_expr(subexpression);
+            visit_unless_err!(visitor, visit_pat, pattern);
+            visit_unless_err!(visitor, visit_expr, subexpression);
             visitor.visit_block(block);
         }
         ExprKind::ForLoop(ref pattern, ref subexpression, ref block, ref opt_label) => {
             walk_list!(visitor, visit_label, opt_label);
-            visitor.visit_pat(pattern);
-            visitor.visit_expr(subexpression);
+            visit_unless_err!(visitor, visit_pat, pattern);
+            visit_unless_err!(visitor, visit_expr, subexpression);
             visitor.visit_block(block);
         }
         ExprKind::Loop(ref block, ref opt_label) => {
@@ -728,10 +760,10 @@ pub fn walk_expr<'a, V: Visitor<'a>>(visitor: &mut V, expression: &'a Expr) {
             visitor.visit_block(block);
         }
         ExprKind::Match(ref subexpression, ref arms) => {
-            visitor.visit_expr(subexpression);
+            visit_unless_err!(visitor, visit_expr, subexpression);
             walk_list!(visitor, visit_arm, arms);
         }
-        ExprKind::Closure(_, _, ref function_declaration, ref body, _decl_span) => {
+        ExprKind::Closure(_, _, ref function_declaration, ref body, _spans) => {
             visitor.visit_fn(FnKind::Closure(body),
                              function_declaration,
                              expression.span,
@@ -739,74 +771,76 @@ pub fn walk_expr<'a, V: Visitor<'a>>(visitor: &mut V, expression: &'a Expr) {
         }
         ExprKind::Block(ref block) => visitor.visit_block(block),
         ExprKind::Assign(ref left_hand_expression, ref right_hand_expression) => {
-            visitor.visit_expr(left_hand_expression);
-            visitor.visit_expr(right_hand_expression);
+            visit_unless_err!(visitor, visit_expr, left_hand_expression);
+            visit_unless_err!(visitor, visit_expr, right_hand_expression);
         }
         ExprKind::AssignOp(_, ref left_expression, ref right_expression) => {
-            visitor.visit_expr(left_expression);
-            visitor.visit_expr(right_expression);
+            visit_unless_err!(visitor, visit_expr, left_expression);
+            visit_unless_err!(visitor, visit_expr, right_expression);
         }
         ExprKind::Field(ref subexpression, ref ident) => {
-            visitor.visit_expr(subexpression);
+            visit_unless_err!(visitor, visit_expr, subexpression);
             visitor.visit_ident(ident.span, ident.node);
         }
         ExprKind::TupField(ref subexpression, _) => {
-            visitor.visit_expr(subexpression);
+            visit_unless_err!(visitor, visit_expr, subexpression);
         }
         ExprKind::Index(ref main_expression, ref index_expression) => {
-            visitor.visit_expr(main_expression);
-            visitor.visit_expr(index_expression)
+            visit_unless_err!(visitor, visit_expr, main_expression);
+            visit_unless_err!(visitor, visit_expr, index_expression)
         }
         ExprKind::Range(ref start, ref end, _) => {
-            walk_list!(visitor, visit_expr, start);
-            walk_list!(visitor, visit_expr, end);
+            walk_list_unless_err!(visitor, visit_expr, start);
+            walk_list_unless_err!(visitor, visit_expr, end);
         }
         ExprKind::Path(ref maybe_qself, ref path) => {
             if let Some(ref qself) = *maybe_qself {
-                visitor.visit_ty(&qself.ty);
+                visit_unless_err!(visitor, visit_ty, &qself.ty);
             }
             visitor.visit_path(path, expression.id)
         }
         ExprKind::Break(ref opt_label, ref opt_expr) => {
             walk_list!(visitor, visit_label, opt_label);
-            walk_list!(visitor, visit_expr, opt_expr);
+            walk_list_unless_err!(visitor, visit_expr, opt_expr);
         }
         ExprKind::Continue(ref opt_label) => {
             walk_list!(visitor, visit_label, opt_label);
         }
         ExprKind::Ret(ref optional_expression) => {
-            walk_list!(visitor, visit_expr, optional_expression);
+            walk_list_unless_err!(visitor, visit_expr, optional_expression);
         }
         ExprKind::Mac(ref mac) => visitor.visit_mac(mac),
         ExprKind::Paren(ref subexpression) => {
-            visitor.visit_expr(subexpression)
+            visit_unless_err!(visitor, visit_expr, subexpression)
         }
         ExprKind::InlineAsm(ref ia) => {
             for &(_, ref input) in &ia.inputs {
-                visitor.visit_expr(input)
+                visit_unless_err!(visitor, visit_expr, input)
             }
             for output in &ia.outputs {
-                visitor.visit_expr(&output.expr)
+                visit_unless_err!(visitor, visit_expr, &output.expr)
             }
         }
         ExprKind::Yield(ref optional_expression) => {
-            walk_list!(visitor, visit_expr, optional_expression);
+            walk_list_unless_err!(visitor, visit_expr, optional_expression);
         }
         ExprKind::Try(ref subexpression) => {
-            visitor.visit_expr(subexpression)
+            visit_unless_err!(visitor, visit_expr, subexpression)
         }
         ExprKind::Catch(ref body) => {
             visitor.visit_block(body)
         }
+        ExprKind::Err => {}
+        ExprKind::Unparsed(ref tts) => visitor.visit_tts(tts.clone().into()),
     }
 
     visitor.visit_expr_post(expression)
 }
 
 pub fn walk_arm<'a, V: Visitor<'a>>(visitor: &mut V, arm: &'a Arm) {
-    walk_list!(visitor, visit_pat, &arm.pats);
-    walk_list!(visitor, visit_expr, &arm.guard);
-    visitor.visit_expr(&arm.body);
+    walk_list_unless_err!(visitor, visit_pat, &arm.pats);
+    walk_list_unless_err!(visitor, visit_expr, &arm.guard);
+    visit_unless_err!(visitor, visit_expr, &arm.body);
     walk_list!(visitor, visit_attribute, &arm.attrs);
 }
 
@@ -10,43 +10,17 @@
 
 // Inline assembly support.
 //
-use self::State::*;
-
 use syntax::ast;
 use syntax::ext::base;
 use syntax::ext::base::*;
 use syntax::feature_gate;
-use syntax::parse::{self, token};
+use syntax::parse::asm::parse_asm_args;
 use syntax::ptr::P;
 use syntax::symbol::Symbol;
 use syntax::ast::AsmDialect;
 use syntax_pos::Span;
 use syntax::tokenstream;
 
-enum State {
-    Asm,
-    Outputs,
-    Inputs,
-    Clobbers,
-    Options,
-    StateNone,
-}
-
-impl State {
-    fn next(&self) -> State {
-        match *self {
-            Asm => Outputs,
-            Outputs => Inputs,
-            Inputs => Clobbers,
-            Clobbers => Options,
-            Options => StateNone,
-            StateNone => StateNone,
-        }
-    }
-}
-
-const OPTIONS: &'static [&'static str] = &["volatile", "alignstack", "intel"];
-
 pub fn expand_asm<'cx>(cx: &'cx mut ExtCtxt,
                        sp: Span,
                        tts: &[tokenstream::TokenTree])
@@ -60,182 +34,57 @@ pub fn expand_asm<'cx>(cx: &'cx mut ExtCtxt,
         return DummyResult::expr(sp);
     }
 
-    // Split the tts before the first colon, to avoid `asm!("x": y)`  being
-    // parsed as `asm!(z)` with `z = "x": y` which is type ascription.
-    let first_colon = tts.iter()
-        .position(|tt| {
-            match *tt {
-                tokenstream::TokenTree::Token(_, token::Colon) |
-                tokenstream::TokenTree::Token(_, token::ModSep) => true,
-                _ => false,
-            }
-        })
-        .unwrap_or(tts.len());
-    let mut p = cx.new_parser_from_tts(&tts[first_colon..]);
-    let mut asm = Symbol::intern("");
-    let mut asm_str_style = None;
-    let mut outputs = Vec::new();
-    let mut inputs = Vec::new();
-    let mut clobs = Vec::new();
+    let args = match panictry!(parse_asm_args(cx.parse_sess, sp, tts)) {
+        Some(args) => args,
+        // let compilation continue
+        None => return DummyResult::expr(sp),
+    };
+    args.validate(&cx.parse_sess.span_diagnostic);
+
+    let (asm, asm_str_style) = match expr_to_string(cx,
+                                                    args.template,
+                                                    "inline assembly must be a string literal") {
+        Some((s, st)) => (s, st),
+        // let compilation continue
+        None => return DummyResult::expr(sp),
+    };
+
+    let outputs = args.outputs.into_iter().map(|out| {
+        // Expands a read+write operand into two operands.
+        //
+        // Use '+' modifier when you want the same expression
+        // to be both an input and an output at the same time.
+        // It's the opposite of '=&' which means that the memory
+        // cannot be shared with any other operand (usually when
+        // a register is clobbered early.)
+        let constraint = out.constraint.node.as_str();
+        let is_rw = constraint.starts_with('+');
+        ast::InlineAsmOutput {
+            constraint: if is_rw {
+                Symbol::intern(&format!("={}", &constraint[1..]))
+            } else {
+                out.constraint.node
+            },
+            expr: out.expr,
+            is_rw,
+            is_indirect: constraint.contains("*"),
+        }
+    }).collect();
+    let inputs = args.inputs.into_iter().map(|input| (input.constraint.node, input.expr)).collect();
+    let clobbers = args.clobbers.into_iter().map(|clobber| clobber.node).collect();
+
     let mut volatile = false;
     let mut alignstack = false;
     let mut dialect = AsmDialect::Att;
-
-    let mut state = Asm;
-
-    'statement: loop {
-        match state {
-            Asm => {
-                if asm_str_style.is_some() {
-                    // If we already have a string with instructions,
-                    // ending up in Asm state again is an error.
-                    cx.span_err(sp, "malformed inline assembly");
-                    return DummyResult::expr(sp);
-                }
-                // Nested parser, stop before the first colon (see above).
-                let mut p2 = cx.new_parser_from_tts(&tts[..first_colon]);
-                let (s, style) = match expr_to_string(cx,
-                                                      panictry!(p2.parse_expr()),
-                                                      "inline assembly must be a string literal") {
-                    Some((s, st)) => (s, st),
-                    // let compilation continue
-                    None => return DummyResult::expr(sp),
-                };
-
-                // This is most likely malformed.
-                if p2.token != token::Eof {
-                    let mut extra_tts = panictry!(p2.parse_all_token_trees());
-                    extra_tts.extend(tts[first_colon..].iter().cloned());
-                    p = parse::stream_to_parser(cx.parse_sess, extra_tts.into_iter().collect());
-                }
-
-                asm = s;
-                asm_str_style = Some(style);
-            }
-            Outputs => {
-                while p.token != token::Eof && p.token != token::Colon && p.token != token::ModSep {
-
-                    if !outputs.is_empty() {
-                        p.eat(&token::Comma);
-                    }
-
-                    let (constraint, _str_style) = panictry!(p.parse_str());
-
-                    let span = p.prev_span;
-
-                    panictry!(p.expect(&token::OpenDelim(token::Paren)));
-                    let out = panictry!(p.parse_expr());
-                    panictry!(p.expect(&token::CloseDelim(token::Paren)));
-
-                    // Expands a read+write operand into two operands.
-                    //
-                    // Use '+' modifier when you want the same expression
-                    // to be both an input and an output at the same time.
-                    // It's the opposite of '=&' which means that the memory
-                    // cannot be shared with any other operand (usually when
-                    // a register is clobbered early.)
-                    let constraint_str = constraint.as_str();
-                    let mut ch = constraint_str.chars();
-                    let output = match ch.next() {
-                        Some('=') => None,
-                        Some('+') => {
-                            Some(Symbol::intern(&format!("={}", ch.as_str())))
-                        }
-                        _ => {
-                            cx.span_err(span, "output operand constraint lacks '=' or '+'");
-                            None
-                        }
-                    };
-
-                    let is_rw = output.is_some();
-                    let is_indirect = constraint_str.contains("*");
-                    outputs.push(ast::InlineAsmOutput {
-                        constraint: output.unwrap_or(constraint),
-                        expr: out,
-                        is_rw,
-                        is_indirect,
-                    });
-                }
-            }
-            Inputs => {
-                while p.token != token::Eof && p.token != token::Colon && p.token != token::ModSep {
-
-                    if !inputs.is_empty() {
-                        p.eat(&token::Comma);
-                    }
-
-                    let (constraint, _str_style) = panictry!(p.parse_str());
-
-                    if constraint.as_str().starts_with("=") {
-                        cx.span_err(p.prev_span, "input operand constraint contains '='");
-                    } else if constraint.as_str().starts_with("+") {
-                        cx.span_err(p.prev_span, "input operand constraint contains '+'");
-                    }
-
-                    panictry!(p.expect(&token::OpenDelim(token::Paren)));
-                    let input = panictry!(p.parse_expr());
-                    panictry!(p.expect(&token::CloseDelim(token::Paren)));
-
-                    inputs.push((constraint, input));
-                }
-            }
-            Clobbers => {
-                while p.token != token::Eof && p.token != token::Colon && p.token != token::ModSep {
-
-                    if !clobs.is_empty() {
-                        p.eat(&token::Comma);
-                    }
-
-                    let (s, _str_style) = panictry!(p.parse_str());
-
-                    if OPTIONS.iter().any(|&opt| s == opt) {
-                        cx.span_warn(p.prev_span, "expected a clobber, found an option");
-                    } else if s.as_str().starts_with("{") || s.as_str().ends_with("}") {
-                        cx.span_err(p.prev_span, "clobber should not be surrounded by braces");
-                    }
-
-                    clobs.push(s);
-                }
-            }
-            Options => {
-                let (option, _str_style) = panictry!(p.parse_str());
-
-                if option == "volatile" {
-                    // Indicates that the inline assembly has side effects
-                    // and must not be optimized out along with its outputs.
-                    volatile = true;
-                } else if option == "alignstack" {
-                    alignstack = true;
-                } else if option == "intel" {
-                    dialect = AsmDialect::Intel;
-                } else {
-                    cx.span_warn(p.prev_span, "unrecognized option");
-                }
-
-                if p.token == token::Comma {
-                    p.eat(&token::Comma);
-                }
-            }
-            StateNone => (),
-        }
-
-        loop {
-            // MOD_SEP is a double colon '::' without space in between.
-            // When encountered, the state must be advanced twice.
-            match (&p.token, state.next(), state.next().next()) {
-                (&token::Colon, StateNone, _) |
-                (&token::ModSep, _, StateNone) => {
-                    p.bump();
-                    break 'statement;
-                }
-                (&token::Colon, st, _) |
-                (&token::ModSep, _, st) => {
-                    p.bump();
-                    state = st;
-                }
-                (&token::Eof, ..) => break 'statement,
-                _ => break,
-            }
+    for option in args.options {
+        if option.node == "volatile" {
+            // Indicates that the inline assembly has side effects
+            // and must not be optimized out along with its outputs.
+            volatile = true;
+        } else if option.node == "alignstack" {
+            alignstack = true;
+        } else if option.node == "intel" {
+            dialect = AsmDialect::Intel;
         }
     }
 
@@ -243,10 +92,10 @@ pub fn expand_asm<'cx>(cx: &'cx mut ExtCtxt,
         id: ast::DUMMY_NODE_ID,
         node: ast::ExprKind::InlineAsm(P(ast::InlineAsm {
             asm,
-            asm_str_style: asm_str_style.unwrap(),
+            asm_str_style,
             outputs,
             inputs,
-            clobbers: clobs,
+            clobbers,
             volatile,
             alignstack,
             dialect,
@@ -11,6 +11,8 @@
 use syntax::ast;
 use syntax::ext::base;
 use syntax::ext::build::AstBuilder;
+use syntax::parse::token;
+use syntax::print::pprust;
 use syntax::symbol::Symbol;
 use syntax_pos;
 use syntax::tokenstream;
@@ -31,21 +33,25 @@ pub fn expand_syntax_ext(cx: &mut base::ExtCtxt,
             ast::ExprKind::Lit(ref lit) => {
                 match lit.node {
                     ast::LitKind::Str(ref s, _) |
-                    ast::LitKind::Float(ref s, _) |
-                    ast::LitKind::FloatUnsuffixed(ref s) => {
+                    ast::LitKind::Float(ref s, ..) |
+                    ast::LitKind::FloatUnsuffixed(ref s, _) => {
                         accumulator.push_str(&s.as_str());
                     }
                     ast::LitKind::Char(c) => {
                         accumulator.push(c);
                     }
-                    ast::LitKind::Int(i, ast::LitIntType::Unsigned(_)) |
-                    ast::LitKind::Int(i, ast::LitIntType::Signed(_)) |
-                    ast::LitKind::Int(i, ast::LitIntType::Unsuffixed) => {
+                    ast::LitKind::Int(i, ast::LitIntType::Unsigned(_), _) |
+                    ast::LitKind::Int(i, ast::LitIntType::Signed(_), _) |
+                    ast::LitKind::Int(i, ast::LitIntType::Unsuffixed, _) => {
                         accumulator.push_str(&format!("{}", i));
                     }
                     ast::LitKind::Bool(b) => {
                         accumulator.push_str(&format!("{}", b));
                     }
+                    ast::LitKind::UnknownSuffix(lit, suffix) => {
+                        let token = token::Literal(lit, Some(suffix));
+                        accumulator.push_str(&pprust::token_to_string(&token));
+                    }
                     ast::LitKind::Byte(..) |
                     ast::LitKind::ByteStr(..) => {
                         cx.span_err(e.span, "cannot concatenate a byte string literal");
@@ -22,6 +22,7 @@ use syntax::ast;
 use syntax::ext::base;
 use syntax::ext::base::*;
 use syntax::feature_gate;
+use syntax::parse::asm::parse_global_asm_args;
 use syntax::ptr::P;
 use syntax::symbol::Symbol;
 use syntax_pos::Span;
@@ -43,9 +44,9 @@ pub fn expand_global_asm<'cx>(cx: &'cx mut ExtCtxt,
         return DummyResult::any(sp);
     }
 
-    let mut p = cx.new_parser_from_tts(tts);
+    let args = panictry!(parse_global_asm_args(cx.parse_sess, tts));
     let (asm, _) = match expr_to_string(cx,
-                                        panictry!(p.parse_expr()),
+                                        args.template,
                                         "inline assembly must be a string literal") {
         Some((s, st)) => (s, st),
         None => return DummyResult::any(sp),
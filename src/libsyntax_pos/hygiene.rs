@@ -44,7 +44,7 @@ struct MarkData {
     expn_info: Option<ExpnInfo>,
 }
 
-#[derive(Copy, Clone, PartialEq, Eq)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub enum MarkKind {
     Modern,
     Builtin,
@@ -106,6 +106,16 @@ impl Mark {
         HygieneData::with(|data| data.marks[self.0 as usize].kind = kind)
     }
 
+    /// Returns all the marks with an expansion record, in the order they were
+    /// created.
+    pub fn all_with_expn_info() -> Vec<(Mark, ExpnInfo)> {
+        HygieneData::with(|data| {
+            data.marks.iter().enumerate().filter_map(|(i, mark)| {
+                mark.expn_info.clone().map(|info| (Mark(i as u32), info))
+            }).collect()
+        })
+    }
+
     pub fn is_descendant_of(mut self, ancestor: Mark) -> bool {
         HygieneData::with(|data| {
             while self != ancestor {
@@ -419,6 +429,87 @@ impl NameAndSpan {
     }
 }
 
+/// Builds and registers the record of an expansion, for macro expansion
+/// engines outside of this crate which need to mint spans whose macro
+/// backtraces are rendered like those of the built-in expansions:
+///
+/// ```ignore
+/// let mark = ExpnBuilder::new(call_site, ExpnFormat::MacroBang(name))
+///     .def_site(def_site)
+///     .register();
+/// let span = span.apply_mark(mark);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ExpnBuilder {
+    parent: Mark,
+    kind: MarkKind,
+    info: ExpnInfo,
+}
+
+impl ExpnBuilder {
+    /// Starts the record of an expansion invoked at `call_site`. By default it
+    /// has no definition site, allows nothing internally, is a legacy
+    /// (`macro_rules!`-like) expansion, and is nested in the root expansion.
+    pub fn new(call_site: Span, format: ExpnFormat) -> ExpnBuilder {
+        ExpnBuilder {
+            parent: Mark::root(),
+            kind: MarkKind::Legacy,
+            info: ExpnInfo {
+                call_site,
+                callee: NameAndSpan {
+                    format,
+                    allow_internal_unstable: false,
+                    allow_internal_unsafe: false,
+                    span: None,
+                },
+            },
+        }
+    }
+
+    /// Sets the span of the macro definition.
+    pub fn def_site(mut self, span: Span) -> ExpnBuilder {
+        self.info.callee.span = Some(span);
+        self
+    }
+
+    pub fn allow_internal_unstable(mut self, allow: bool) -> ExpnBuilder {
+        self.info.callee.allow_internal_unstable = allow;
+        self
+    }
+
+    pub fn allow_internal_unsafe(mut self, allow: bool) -> ExpnBuilder {
+        self.info.callee.allow_internal_unsafe = allow;
+        self
+    }
+
+    /// Sets the expansion this one happens in, e.g. the expansion of the macro
+    /// whose output invoked this one.
+    pub fn parent(mut self, parent: Mark) -> ExpnBuilder {
+        self.parent = parent;
+        self
+    }
+
+    /// Sets the hygiene of the expansion, `MarkKind::Modern` for macros 2.0.
+    pub fn kind(mut self, kind: MarkKind) -> ExpnBuilder {
+        self.kind = kind;
+        self
+    }
+
+    pub fn info(&self) -> &ExpnInfo {
+        &self.info
+    }
+
+    /// Registers the expansion under a fresh mark, and returns the mark. The
+    /// spans of the code produced by the expansion are marked with
+    /// `Span::apply_mark`, and the record is looked up with `Mark::expn_info`.
+    pub fn register(self) -> Mark {
+        let mark = Mark::fresh(self.parent);
+        mark.set_kind(self.kind);
+        mark.set_expn_info(self.info);
+        mark
+    }
+}
+
 /// The source of expansion.
 #[derive(Clone, Hash, Debug, PartialEq, Eq, RustcEncodable, RustcDecodable)]
 pub enum ExpnFormat {
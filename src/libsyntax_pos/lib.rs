@@ -31,9 +31,11 @@ use std::cell::{Cell, RefCell};
 use std::cmp::{self, Ordering};
 use std::fmt;
 use std::hash::{Hasher, Hash};
+use std::mem;
 use std::ops::{Add, Sub};
 use std::path::PathBuf;
 use std::rc::Rc;
+use std::u32;
 
 use rustc_data_structures::stable_hasher::StableHasher;
 
@@ -48,9 +50,10 @@ extern crate unicode_width;
 
 pub mod hygiene;
 pub use hygiene::{SyntaxContext, ExpnInfo, ExpnFormat, NameAndSpan, CompilerDesugaringKind};
+pub use hygiene::ExpnBuilder;
 
 mod span_encoding;
-pub use span_encoding::{Span, DUMMY_SP};
+pub use span_encoding::{Span, DUMMY_SP, span_interner_memory_usage};
 
 pub mod symbol;
 
@@ -216,6 +219,11 @@ impl Span {
         self.data().with_ctxt(ctxt)
     }
 
+    /// Returns the span marked as produced by the expansion `mark`.
+    pub fn apply_mark(self, mark: hygiene::Mark) -> Span {
+        self.with_ctxt(self.ctxt().apply_mark(mark))
+    }
+
     /// Returns `self` if `self` is not the dummy span, and `other` otherwise.
     pub fn substitute_dummy(self, other: Span) -> Span {
         if self.source_equal(&DUMMY_SP) { other } else { self }
@@ -545,7 +553,7 @@ impl From<Vec<Span>> for MultiSpan {
 pub const NO_EXPANSION: SyntaxContext = SyntaxContext::empty();
 
 /// Identifies an offset of a multi-byte character in a FileMap
-#[derive(Copy, Clone, RustcEncodable, RustcDecodable, Eq, PartialEq)]
+#[derive(Copy, Clone, RustcEncodable, RustcDecodable, Eq, PartialEq, Debug)]
 pub struct MultiByteChar {
     /// The absolute offset of the character in the CodeMap
     pub pos: BytePos,
@@ -554,7 +562,7 @@ pub struct MultiByteChar {
 }
 
 /// Identifies an offset of a non-narrow character in a FileMap
-#[derive(Copy, Clone, RustcEncodable, RustcDecodable, Eq, PartialEq)]
+#[derive(Copy, Clone, RustcEncodable, RustcDecodable, Eq, PartialEq, Debug)]
 pub enum NonNarrowChar {
     /// Represents a zero-width character
     ZeroWidth(BytePos),
@@ -583,7 +591,7 @@ impl NonNarrowChar {
         }
     }
 
-    /// Returns the width of the character, 0 (zero-width) or 2 (wide)
+    /// Returns the width of the character, 0 (zero-width), 2 (wide) or 4 (tab)
     pub fn width(&self) -> usize {
         match *self {
             NonNarrowChar::ZeroWidth(_) => 0,
@@ -646,6 +654,25 @@ impl ExternalSource {
     }
 }
 
+/// A metadata block at the top of a file, like a manifest embedded in a
+/// script. It is delimited by lines of three or more dashes, and the opening
+/// line may name the format of the block:
+///
+/// ```text
+/// ---cargo
+/// [dependencies]
+/// ---
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frontmatter {
+    /// The text after the opening dashes, e.g. `cargo`, if any.
+    pub info: Option<String>,
+    /// The raw text between the delimiter lines.
+    pub text: String,
+    /// The span of the block, including the delimiter lines.
+    pub span: Span,
+}
+
 /// A single source in the CodeMap.
 #[derive(Clone)]
 pub struct FileMap {
@@ -679,6 +706,20 @@ pub struct FileMap {
     pub non_narrow_chars: RefCell<Vec<NonNarrowChar>>,
     /// A hash of the filename, used for speeding up the incr. comp. hashing.
     pub name_hash: u128,
+    /// The syntax variant the file opted into with `#![syntax_variant(...)]`,
+    /// if any. This is only known once the file has been parsed.
+    pub syntax_variant: Cell<Option<symbol::Symbol>>,
+    /// The frontmatter block at the top of the file, if there is one and the
+    /// file was lexed with frontmatter enabled.
+    pub frontmatter: RefCell<Option<Frontmatter>>,
+    /// The indices of the lines which ended in CRLF in the source as it was
+    /// read, whether or not the CRs were removed from `src`. This is empty for
+    /// files imported from other crates.
+    pub crlf_lines: Vec<usize>,
+    /// The canonical path of the file, with symbolic links resolved, if it was
+    /// loaded from the file system and the file loader could resolve it. Two
+    /// files with the same canonical path are the same file.
+    pub canonical_path: Option<PathBuf>,
 }
 
 impl Encodable for FileMap {
@@ -811,6 +852,10 @@ impl Decodable for FileMap {
                 multibyte_chars: RefCell::new(multibyte_chars),
                 non_narrow_chars: RefCell::new(non_narrow_chars),
                 name_hash,
+                syntax_variant: Cell::new(None),
+                frontmatter: RefCell::new(None),
+                crlf_lines: Vec::new(),
+                canonical_path: None,
             })
         })
     }
@@ -827,8 +872,13 @@ impl FileMap {
                name_was_remapped: bool,
                unmapped_path: FileName,
                mut src: String,
-               start_pos: BytePos) -> FileMap {
+               start_pos: BytePos,
+               normalize_crlf: bool) -> FileMap {
         remove_bom(&mut src);
+        let crlf_lines = find_crlf_lines(&src);
+        if normalize_crlf && !crlf_lines.is_empty() {
+            src = src.replace("\r\n", "\n");
+        }
 
         let src_hash = {
             let mut hasher: StableHasher<u128> = StableHasher::new();
@@ -856,6 +906,10 @@ impl FileMap {
             multibyte_chars: RefCell::new(Vec::new()),
             non_narrow_chars: RefCell::new(Vec::new()),
             name_hash,
+            syntax_variant: Cell::new(None),
+            frontmatter: RefCell::new(None),
+            crlf_lines,
+            canonical_path: None,
         }
     }
 
@@ -964,6 +1018,49 @@ impl FileMap {
         }
     }
 
+    /// Returns the multi-byte characters between the positions `lo` and `hi`.
+    pub fn multibyte_chars_between(&self, lo: BytePos, hi: BytePos) -> Vec<MultiByteChar> {
+        table_range(&self.multibyte_chars.borrow(), lo, hi, |mbc| mbc.pos).to_vec()
+    }
+
+    /// Returns the characters between the positions `lo` and `hi` which are
+    /// not displayed one column wide.
+    pub fn non_narrow_chars_between(&self, lo: BytePos, hi: BytePos) -> Vec<NonNarrowChar> {
+        table_range(&self.non_narrow_chars.borrow(), lo, hi, |nnc| nnc.pos()).to_vec()
+    }
+
+    /// Returns the number of characters between the positions `lo` and `hi`,
+    /// which must be character boundaries with `lo <= hi`.
+    pub fn char_count(&self, lo: BytePos, hi: BytePos) -> usize {
+        let multibyte_chars = self.multibyte_chars.borrow();
+        let extra_bytes: usize = table_range(&multibyte_chars, lo, hi, |mbc| mbc.pos)
+            .iter()
+            .map(|mbc| mbc.bytes - 1)
+            .sum();
+        (hi - lo).to_usize() - extra_bytes
+    }
+
+    /// Returns the number of columns the source between the positions `lo` and
+    /// `hi` takes up when displayed: tabs take up 4 columns, wide characters
+    /// such as CJK characters and most emoji take up 2, and zero-width and
+    /// control characters take up none.
+    pub fn display_width(&self, lo: BytePos, hi: BytePos) -> usize {
+        let non_narrow_chars = self.non_narrow_chars.borrow();
+        let non_narrow = table_range(&non_narrow_chars, lo, hi, |nnc| nnc.pos());
+        let non_narrow_width: usize = non_narrow.iter().map(|nnc| nnc.width()).sum();
+        self.char_count(lo, hi) - non_narrow.len() + non_narrow_width
+    }
+
+    /// Returns the 0-based column at which the character at `pos` is displayed
+    /// within its line, for underlining the right columns in diagnostics.
+    pub fn display_col(&self, pos: BytePos) -> usize {
+        let line_start = match self.lookup_line(pos) {
+            Some(line) => self.lines.borrow()[line],
+            None => self.start_pos,
+        };
+        self.display_width(line_start, pos)
+    }
+
     pub fn is_real_file(&self) -> bool {
         self.name.is_real()
     }
@@ -979,6 +1076,20 @@ impl FileMap {
         self.lines.borrow().len()
     }
 
+    /// Returns an estimate of the heap memory used by this filemap's source
+    /// text and position tables, in bytes.
+    pub fn memory_usage(&self) -> usize {
+        let src = self.src.as_ref().map_or(0, |src| src.capacity());
+        let external_src = match *self.external_src.borrow() {
+            ExternalSource::Present(ref src) => src.capacity(),
+            _ => 0,
+        };
+        src + external_src +
+            self.lines.borrow().capacity() * mem::size_of::<BytePos>() +
+            self.multibyte_chars.borrow().capacity() * mem::size_of::<MultiByteChar>() +
+            self.non_narrow_chars.borrow().capacity() * mem::size_of::<NonNarrowChar>()
+    }
+
     /// Find the line containing the given position. The return value is the
     /// index into the `lines` array of this FileMap, not the 1-based line
     /// number. If the filemap is empty or the position is located before the
@@ -1018,6 +1129,16 @@ impl FileMap {
     }
 }
 
+/// Returns the entries of `table`, which is sorted by position, positioned
+/// between `lo` and `hi`.
+fn table_range<T, F>(table: &[T], lo: BytePos, hi: BytePos, pos: F) -> &[T]
+    where F: Fn(&T) -> BytePos
+{
+    let start = table.binary_search_by_key(&lo, &pos).unwrap_or_else(|i| i);
+    let end = table.binary_search_by_key(&hi, &pos).unwrap_or_else(|i| i);
+    &table[start..cmp::max(start, end)]
+}
+
 /// Remove utf-8 BOM if any.
 fn remove_bom(src: &mut String) {
     if src.starts_with("\u{feff}") {
@@ -1025,6 +1146,17 @@ fn remove_bom(src: &mut String) {
     }
 }
 
+/// Returns the indices of the lines of `src` which end in CRLF.
+fn find_crlf_lines(src: &str) -> Vec<usize> {
+    let mut crlf_lines = Vec::new();
+    for (line, text) in src.split('\n').enumerate() {
+        if text.ends_with('\r') {
+            crlf_lines.push(line);
+        }
+    }
+    crlf_lines
+}
+
 // _____________________________________________________________________________
 // Pos, BytePos, CharPos
 //
@@ -1053,6 +1185,44 @@ impl Pos for BytePos {
     fn to_usize(&self) -> usize { let BytePos(n) = *self; n as usize }
 }
 
+// `from_usize` and the operators wrap around silently when a position goes
+// past `BytePos::MAX`, which is possible with sources approaching 4 GiB. The
+// methods below check for that instead.
+impl BytePos {
+    pub const MAX: BytePos = BytePos(u32::MAX);
+
+    /// The position `n`, or `None` if it is past `BytePos::MAX`.
+    pub fn try_from_usize(n: usize) -> Option<BytePos> {
+        if n > u32::MAX as usize {
+            None
+        } else {
+            Some(BytePos(n as u32))
+        }
+    }
+
+    pub fn checked_add(self, rhs: BytePos) -> Option<BytePos> {
+        self.0.checked_add(rhs.0).map(BytePos)
+    }
+
+    pub fn checked_sub(self, rhs: BytePos) -> Option<BytePos> {
+        self.0.checked_sub(rhs.0).map(BytePos)
+    }
+
+    /// The position `n` bytes after this one, or `None` if it is past
+    /// `BytePos::MAX`.
+    pub fn checked_add_usize(self, n: usize) -> Option<BytePos> {
+        BytePos::try_from_usize(n).and_then(|n| self.checked_add(n))
+    }
+
+    pub fn saturating_add(self, rhs: BytePos) -> BytePos {
+        BytePos(self.0.saturating_add(rhs.0))
+    }
+
+    pub fn saturating_sub(self, rhs: BytePos) -> BytePos {
+        BytePos(self.0.saturating_sub(rhs.0))
+    }
+}
+
 impl Add for BytePos {
     type Output = BytePos;
 
@@ -1086,6 +1256,20 @@ impl Pos for CharPos {
     fn to_usize(&self) -> usize { let CharPos(n) = *self; n }
 }
 
+impl CharPos {
+    pub fn checked_add(self, rhs: CharPos) -> Option<CharPos> {
+        self.0.checked_add(rhs.0).map(CharPos)
+    }
+
+    pub fn checked_sub(self, rhs: CharPos) -> Option<CharPos> {
+        self.0.checked_sub(rhs.0).map(CharPos)
+    }
+
+    pub fn saturating_sub(self, rhs: CharPos) -> CharPos {
+        CharPos(self.0.saturating_sub(rhs.0))
+    }
+}
+
 impl Add for CharPos {
     type Output = CharPos;
 
@@ -1185,7 +1369,10 @@ pub enum SpanSnippetError {
     IllFormedSpan(Span),
     DistinctSources(DistinctSources),
     MalformedForCodemap(MalformedCodemapPositions),
-    SourceNotAvailable { filename: FileName }
+    SourceNotAvailable { filename: FileName },
+    /// The span was produced by macro expansion and the caller asked for
+    /// such spans to be rejected.
+    FromExpansion(Span),
 }
 
 #[derive(Clone, PartialEq, Eq, Debug)]
@@ -1214,7 +1401,9 @@ fn lookup_line(lines: &[BytePos], pos: BytePos) -> isize {
 
 #[cfg(test)]
 mod tests {
-    use super::{lookup_line, BytePos};
+    use super::{lookup_line, BytePos, CharPos, ExpnBuilder, ExpnFormat, Span, NO_EXPANSION};
+    use hygiene::Mark;
+    use symbol::Symbol;
 
     #[test]
     fn test_lookup_line() {
@@ -1232,4 +1421,39 @@ mod tests {
         assert_eq!(lookup_line(lines, BytePos(28)), 2);
         assert_eq!(lookup_line(lines, BytePos(29)), 2);
     }
+    #[test]
+    fn checked_positions() {
+        assert_eq!(BytePos::try_from_usize(7), Some(BytePos(7)));
+        assert_eq!(BytePos::try_from_usize(u32::max_value() as usize),
+                   Some(BytePos::MAX));
+        assert_eq!(BytePos(3).checked_add(BytePos(4)), Some(BytePos(7)));
+        assert_eq!(BytePos::MAX.checked_add(BytePos(1)), None);
+        assert_eq!(BytePos(3).checked_sub(BytePos(4)), None);
+        assert_eq!(BytePos::MAX.checked_add_usize(0), Some(BytePos::MAX));
+        assert_eq!(BytePos(1).checked_add_usize(u32::max_value() as usize), None);
+        assert_eq!(BytePos::MAX.saturating_add(BytePos(1)), BytePos::MAX);
+        assert_eq!(BytePos(3).saturating_sub(BytePos(4)), BytePos(0));
+
+        assert_eq!(CharPos(3).checked_sub(CharPos(4)), None);
+        assert_eq!(CharPos(3).saturating_sub(CharPos(4)), CharPos(0));
+        assert_eq!(CharPos(3).checked_add(CharPos(4)), Some(CharPos(7)));
+    }
+
+    #[test]
+    fn expansion_records() {
+        let call_site = Span::new(BytePos(10), BytePos(20), NO_EXPANSION);
+        let def_site = Span::new(BytePos(0), BytePos(5), NO_EXPANSION);
+        let mark = ExpnBuilder::new(call_site, ExpnFormat::MacroBang(Symbol::intern("m")))
+            .def_site(def_site)
+            .register();
+        assert_eq!(mark.expn_info().unwrap().callee.span, Some(def_site));
+        assert!(Mark::all_with_expn_info().iter().any(|&(m, _)| m == mark));
+
+        let span = Span::new(BytePos(1), BytePos(2), NO_EXPANSION).apply_mark(mark);
+        assert_eq!(span.ctxt().outer(), mark);
+        let backtrace = span.macro_backtrace();
+        assert_eq!(backtrace.len(), 1);
+        assert_eq!(backtrace[0].macro_decl_name, "m!");
+        assert_eq!(backtrace[0].call_site, call_site);
+    }
 }
@@ -19,6 +19,7 @@ use hygiene::SyntaxContext;
 
 use rustc_data_structures::fx::FxHashMap;
 use std::cell::RefCell;
+use std::mem;
 
 /// A compressed span.
 /// Contains either fields of `SpanData` inline if they are small, or index into span interner.
@@ -46,6 +47,14 @@ impl Span {
     pub fn data(self) -> SpanData {
         decode(self)
     }
+
+    /// Returns whether this is `DUMMY_SP`, possibly with a syntax context,
+    /// like the spans given to code synthesized by expansion.
+    #[inline]
+    pub fn is_dummy(self) -> bool {
+        let data = self.data();
+        data.lo.0 == 0 && data.hi.0 == 0
+    }
 }
 
 // Tags
@@ -115,26 +124,50 @@ fn decode(span: Span) -> SpanData {
 struct SpanInterner {
     spans: FxHashMap<SpanData, u32>,
     span_data: Vec<SpanData>,
+    // The span interned last. Generated code tends to give the same span to
+    // many nodes in a row, so this saves most of the lookups in `spans`.
+    last: Option<(SpanData, u32)>,
 }
 
 impl SpanInterner {
     fn intern(&mut self, span_data: &SpanData) -> u32 {
-        if let Some(index) = self.spans.get(span_data) {
-            return *index;
+        if let Some((last, index)) = self.last {
+            if last == *span_data {
+                return index;
+            }
         }
 
-        let index = self.spans.len() as u32;
-        self.span_data.push(*span_data);
-        self.spans.insert(*span_data, index);
+        let index = match self.spans.get(span_data) {
+            Some(index) => *index,
+            None => {
+                let index = self.spans.len() as u32;
+                self.span_data.push(*span_data);
+                self.spans.insert(*span_data, index);
+                index
+            }
+        };
+        self.last = Some((*span_data, index));
         index
     }
 
+    fn memory_usage(&self) -> usize {
+        self.span_data.capacity() * mem::size_of::<SpanData>() +
+            self.spans.capacity() * (mem::size_of::<SpanData>() + mem::size_of::<u32>())
+    }
+
     #[inline]
     fn get(&self, index: u32) -> &SpanData {
         &self.span_data[index as usize]
     }
 }
 
+/// Returns an estimate of the heap memory used by the spans interned on the
+/// current thread, in bytes. Spans which are small enough and have no syntax
+/// context are stored inline, and use no memory besides the `Span` itself.
+pub fn span_interner_memory_usage() -> usize {
+    with_span_interner(|interner| interner.memory_usage())
+}
+
 // If an interner exists in TLS, return it. Otherwise, prepare a fresh one.
 #[inline]
 fn with_span_interner<T, F: FnOnce(&mut SpanInterner) -> T>(f: F) -> T {
@@ -143,3 +176,29 @@ fn with_span_interner<T, F: FnOnce(&mut SpanInterner) -> T>(f: F) -> T {
     });
     INTERNER.with(|interner| f(&mut *interner.borrow_mut()))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn span_interner() {
+        let mut i = SpanInterner::default();
+        let a = SpanData { lo: BytePos(0), hi: BytePos(1000), ctxt: SyntaxContext(0) };
+        let b = SpanData { lo: BytePos(0), hi: BytePos(0), ctxt: SyntaxContext(1) };
+        assert_eq!(i.intern(&a), 0);
+        assert_eq!(i.intern(&a), 0);
+        assert_eq!(i.intern(&b), 1);
+        // earlier spans are still found after the last one changed:
+        assert_eq!(i.intern(&a), 0);
+        assert_eq!(*i.get(1), b);
+        assert!(i.memory_usage() >= 2 * mem::size_of::<SpanData>());
+    }
+
+    #[test]
+    fn dummy_spans() {
+        assert!(DUMMY_SP.is_dummy());
+        assert!(DUMMY_SP.with_ctxt(SyntaxContext(1)).is_dummy());
+        assert!(!Span::new(BytePos(0), BytePos(1), SyntaxContext(0)).is_dummy());
+    }
+}
@@ -18,6 +18,7 @@ use serialize::{Decodable, Decoder, Encodable, Encoder};
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fmt;
+use std::mem;
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash)]
 pub struct Ident {
@@ -210,6 +211,16 @@ impl Interner {
         Symbol(!0 - self.gensyms.len() as u32 + 1)
     }
 
+    /// Returns an estimate of the heap memory used by the interner, in bytes.
+    pub fn memory_usage(&self) -> usize {
+        let strings = self.strings.iter().map(|string| string.len()).sum::<usize>();
+        // Each string is stored twice, as an element of `strings` and as a key of `names`.
+        2 * strings +
+            self.strings.capacity() * mem::size_of::<Box<str>>() +
+            self.names.capacity() * (mem::size_of::<Box<str>>() + mem::size_of::<Symbol>()) +
+            self.gensyms.capacity() * mem::size_of::<Symbol>()
+    }
+
     fn is_gensymed(&mut self, symbol: Symbol) -> bool {
         symbol.0 as usize >= self.strings.len()
     }
@@ -244,6 +255,9 @@ macro_rules! declare_keywords {(
                 ident: Ident::with_empty_ctxt(super::Symbol($index))
             };
         )*
+
+        /// All keywords, in the order in which they are declared.
+        pub const ALL: &'static [Keyword] = &[$($konst,)*];
     }
 
     impl Interner {
@@ -330,6 +344,12 @@ declare_keywords! {
     (60, Union,          "union")
 }
 
+/// Returns an estimate of the heap memory used by the current thread's
+/// interner, in bytes.
+pub fn interner_memory_usage() -> usize {
+    with_interner(|interner| interner.memory_usage())
+}
+
 // If an interner exists in TLS, return it. Otherwise, prepare a fresh one.
 fn with_interner<T, F: FnOnce(&mut Interner) -> T>(f: F) -> T {
     thread_local!(static INTERNER: RefCell<Interner> = {
@@ -9,11 +9,11 @@
 // except according to those terms.
 
 #[derive(Copy(Bad))]
-//~^ ERROR expected one of `)`, `,`, or `::`, found `(`
+//~^ ERROR traits in `derive` cannot have arguments
 struct Test1;
 
 #[derive(Copy="bad")]
-//~^ ERROR expected one of `)`, `,`, or `::`, found `=`
+//~^ ERROR traits in `derive` cannot have arguments
 struct Test2;
 
 #[derive()]
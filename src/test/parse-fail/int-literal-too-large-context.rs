@@ -0,0 +1,23 @@
+// Copyright 2018 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// compile-flags: -Z parse-only
+
+fn main() {
+    let _ = 340282366920938463463374607431768211456;
+    //~^ ERROR int literal is too large
+    //~| NOTE does not fit in any integer type
+    //~| NOTE the maximum value of `u128` is `340282366920938463463374607431768211455`
+    let _ = 0x1_0000_0000_0000_0000_0000_0000_0000_0000u8;
+    //~^ ERROR int literal is too large
+    //~| NOTE does not fit in `u8`
+    //~| NOTE the maximum value of `u8` is `0xff`
+    //~| NOTE no integer type can hold this value
+}
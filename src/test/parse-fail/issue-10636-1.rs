@@ -13,3 +13,4 @@
 struct Obj { //~ NOTE: unclosed delimiter
     member: usize
 ) //~ ERROR: incorrect close delimiter
+//~^ NOTE incorrect close delimiter
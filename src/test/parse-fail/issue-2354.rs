@@ -10,7 +10,7 @@
 
 // compile-flags: -Z parse-only
 
-fn foo() { //~ HELP did you mean to close this delimiter?
+fn foo() { //~ NOTE unclosed delimiter
   match Some(x) {
       Some(y) => { panic!(); }
       None => { panic!(); }
@@ -94,7 +94,7 @@ fn iter_exprs(depth: usize, f: &mut FnMut(P<Expr>)) {
                 iter_exprs(depth - 1, &mut |e| g(ExprKind::InPlace(e, make_x())));
                 iter_exprs(depth - 1, &mut |e| g(ExprKind::InPlace(make_x(), e)));
             },
-            2 => iter_exprs(depth - 1, &mut |e| g(ExprKind::Call(e, vec![]))),
+            2 => iter_exprs(depth - 1, &mut |e| g(ExprKind::Call(e, vec![], None))),
             3 => {
                 let seg = PathSegment {
                     identifier: Ident::from_str("x"),
@@ -146,7 +146,7 @@ fn iter_exprs(depth: usize, f: &mut FnMut(P<Expr>)) {
                                           Movability::Movable,
                                           decl.clone(),
                                           e,
-                                          DUMMY_SP)));
+                                          ClosureSpans::from_args(DUMMY_SP))));
             },
             10 => {
                 iter_exprs(depth - 1, &mut |e| g(ExprKind::Assign(e, make_x())));
@@ -179,7 +179,7 @@ fn iter_exprs(depth: usize, f: &mut FnMut(P<Expr>)) {
                     span: DUMMY_SP,
                     segments: vec![seg],
                 };
-                g(ExprKind::Struct(path, vec![], Some(make_x())));
+                g(ExprKind::Struct(path, vec![], Some(make_x()), None));
             },
             16 => {
                 iter_exprs(depth - 1, &mut |e| g(ExprKind::Try(e)));
@@ -0,0 +1,23 @@
+// Copyright 2018 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Test that each typo in a block gives one error: parsing resumes at the next
+// statement instead of skipping the rest of the block, and the recovery does
+// not report follow-up errors, also for uses of the bindings of a broken `let`.
+
+fn main() {
+    let mut total = 0;
+    total += ; //~ ERROR expected expression, found `;`
+    total = std::cmp::max(total +, 1); //~ ERROR expected expression, found `,`
+    total += 1;
+    println!("{}", total);
+    let doubled = total * ; //~ ERROR expected expression, found `;`
+    println!("{}", doubled);
+}
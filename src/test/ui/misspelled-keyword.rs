@@ -0,0 +1,19 @@
+// Copyright 2018 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// compile-flags: -Z parse-only
+
+// Test that misspelled keywords are only corrected where the tokens after them
+// fit the keyword: `fo x` is neither a `for` loop nor a function.
+
+fn main() {
+    retrun 1; //~ ERROR expected keyword, found `retrun`
+    fo x; //~ ERROR expected one of
+}